@@ -80,8 +80,17 @@ async fn run_command_under_sandbox(
 
     let mut child = match sandbox_type {
         SandboxType::Seatbelt => {
-            spawn_command_under_seatbelt(command, &config.sandbox_policy, cwd, stdio_policy, env)
-                .await?
+            spawn_command_under_seatbelt(
+                command,
+                &config.sandbox_policy,
+                config.seatbelt_base_policy.as_deref(),
+                cwd,
+                stdio_policy,
+                env,
+                &[],
+                None,
+            )
+            .await?
         }
         SandboxType::Landlock => {
             #[expect(clippy::expect_used)]
@@ -95,6 +104,8 @@ async fn run_command_under_sandbox(
                 cwd,
                 stdio_policy,
                 env,
+                &[],
+                None,
             )
             .await?
         }
@@ -2,6 +2,7 @@ pub mod debug_sandbox;
 mod exit_status;
 pub mod login;
 pub mod proto;
+pub mod sandbox_test;
 
 use clap::Parser;
 use codex_common::CliConfigOverrides;
@@ -33,3 +34,9 @@ pub struct LandlockCommand {
     #[arg(trailing_var_arg = true)]
     pub command: Vec<String>,
 }
+
+#[derive(Debug, Parser)]
+pub struct SandboxTestCommand {
+    #[clap(skip)]
+    pub config_overrides: CliConfigOverrides,
+}
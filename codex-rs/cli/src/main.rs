@@ -6,6 +6,7 @@ use codex_arg0::arg0_dispatch_or_else;
 use codex_chatgpt::apply_command::ApplyCommand;
 use codex_chatgpt::apply_command::run_apply_command;
 use codex_cli::LandlockCommand;
+use codex_cli::SandboxTestCommand;
 use codex_cli::SeatbeltCommand;
 use codex_cli::login::run_login_status;
 use codex_cli::login::run_login_with_api_key;
@@ -69,6 +70,9 @@ enum Subcommand {
     /// Internal debugging commands.
     Debug(DebugArgs),
 
+    /// Check that the sandbox blocks what the configured sandbox policy forbids.
+    SandboxTest(SandboxTestCommand),
+
     /// Apply the latest diff produced by Codex agent as a `git apply` to your local working tree.
     #[clap(visible_alias = "a")]
     Apply(ApplyCommand),
@@ -205,6 +209,11 @@ async fn cli_main(codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()
                 .await?;
             }
         },
+        Some(Subcommand::SandboxTest(mut sandbox_test_cli)) => {
+            prepend_config_flags(&mut sandbox_test_cli.config_overrides, cli.config_overrides);
+            codex_cli::sandbox_test::run_sandbox_test(sandbox_test_cli, codex_linux_sandbox_exe)
+                .await?;
+        }
         Some(Subcommand::Apply(mut apply_cli)) => {
             prepend_config_flags(&mut apply_cli.config_overrides, cli.config_overrides);
             run_apply_command(apply_cli, None).await?;
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use codex_common::summarize_sandbox_policy;
+use codex_core::config::Config;
+use codex_core::config::ConfigOverrides;
+use codex_core::exec_env::create_env;
+use codex_core::sandbox_self_test::ProbeOutcome;
+use codex_core::sandbox_self_test::run_sandbox_self_test;
+
+use crate::SandboxTestCommand;
+
+/// Runs the sandbox self-test under the configured sandbox policy and prints
+/// one line per probe. Exits with status 1 if any probe got through.
+pub async fn run_sandbox_test(
+    command: SandboxTestCommand,
+    codex_linux_sandbox_exe: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let config = Config::load_with_cli_overrides(
+        command
+            .config_overrides
+            .parse_overrides()
+            .map_err(anyhow::Error::msg)?,
+        ConfigOverrides {
+            codex_linux_sandbox_exe,
+            ..Default::default()
+        },
+    )?;
+    let env = create_env(&config.shell_environment_policy);
+
+    println!(
+        "Sandbox policy: {}",
+        summarize_sandbox_policy(&config.sandbox_policy)
+    );
+    let reports = run_sandbox_self_test(
+        &config.sandbox_policy,
+        &config.cwd,
+        env,
+        &config.codex_linux_sandbox_exe,
+    )
+    .await;
+    for report in &reports {
+        println!(
+            "{:<30} {:<50} {}",
+            report.name, report.description, report.outcome
+        );
+    }
+
+    if reports
+        .iter()
+        .any(|report| report.outcome == ProbeOutcome::Escaped)
+    {
+        eprintln!("The sandbox did not block everything the policy forbids.");
+        std::process::exit(1);
+    }
+    Ok(())
+}
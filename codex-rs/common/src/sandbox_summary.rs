@@ -1,3 +1,4 @@
+use codex_core::protocol::RunAsUser;
 use codex_core::protocol::SandboxPolicy;
 
 pub fn summarize_sandbox_policy(sandbox_policy: &SandboxPolicy) -> String {
@@ -9,6 +10,12 @@ pub fn summarize_sandbox_policy(sandbox_policy: &SandboxPolicy) -> String {
             network_access,
             exclude_tmpdir_env_var,
             exclude_slash_tmp,
+            denied_programs,
+            scratch_dir,
+            run_as,
+            network_loopback_only,
+            audit_only,
+            ..
         } => {
             let mut summary = "workspace-write".to_string();
 
@@ -23,12 +30,24 @@ pub fn summarize_sandbox_policy(sandbox_policy: &SandboxPolicy) -> String {
             writable_entries.extend(
                 writable_roots
                     .iter()
+                    .chain(scratch_dir)
                     .map(|p| p.to_string_lossy().to_string()),
             );
 
             summary.push_str(&format!(" [{}]", writable_entries.join(", ")));
             if *network_access {
                 summary.push_str(" (network access enabled)");
+            } else if *network_loopback_only {
+                summary.push_str(" (loopback network only)");
+            }
+            if !denied_programs.is_empty() {
+                summary.push_str(&format!(" (denied: {})", denied_programs.join(", ")));
+            }
+            if let Some(RunAsUser { uid, gid }) = run_as {
+                summary.push_str(&format!(" (as uid {uid}, gid {gid})"));
+            }
+            if *audit_only {
+                summary.push_str(" (audit only)");
             }
             summary
         }
@@ -1,7 +1,10 @@
 use crate::codex::Session;
 use crate::codex::TurnContext;
+use crate::config_types::GitIgnoredWrites;
+use crate::git_info::git_ignored_paths;
 use crate::protocol::FileChange;
 use crate::protocol::ReviewDecision;
+use crate::rollout::IgnoredWriteRecord;
 use crate::safety::SafetyCheck;
 use crate::safety::assess_patch_safety;
 use codex_apply_patch::ApplyPatchAction;
@@ -46,6 +49,39 @@ pub(crate) async fn apply_patch(
     call_id: &str,
     action: ApplyPatchAction,
 ) -> InternalApplyPatchInvocation {
+    if turn_context.git_ignored_writes != GitIgnoredWrites::Off {
+        let ignored = git_ignored_paths(&turn_context.cwd, &patch_target_paths(&action)).await;
+        if !ignored.is_empty() {
+            let blocked = turn_context.git_ignored_writes == GitIgnoredWrites::Block;
+            let listed = ignored
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            sess.record_ignored_write(IgnoredWriteRecord {
+                call_id: call_id.to_owned(),
+                paths: ignored,
+                blocked,
+            })
+            .await;
+            if blocked {
+                return ResponseInputItem::FunctionCallOutput {
+                    call_id: call_id.to_owned(),
+                    output: FunctionCallOutputPayload {
+                        content: format!("patch rejected: writes to git-ignored paths: {listed}"),
+                        success: Some(false),
+                    },
+                }
+                .into();
+            }
+            sess.notify_background_event(
+                sub_id,
+                format!("patch writes to git-ignored paths: {listed}"),
+            )
+            .await;
+        }
+    }
+
     match assess_patch_safety(
         &action,
         turn_context.approval_policy,
@@ -99,6 +135,22 @@ pub(crate) async fn apply_patch(
     }
 }
 
+/// Every path a patch writes to, including the destinations of moves.
+fn patch_target_paths(action: &ApplyPatchAction) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for (path, change) in action.changes() {
+        paths.push(path.clone());
+        if let ApplyPatchFileChange::Update {
+            move_path: Some(move_path),
+            ..
+        } = change
+        {
+            paths.push(move_path.clone());
+        }
+    }
+    paths
+}
+
 pub(crate) fn convert_apply_patch_to_protocol(
     action: &ApplyPatchAction,
 ) -> HashMap<PathBuf, FileChange> {
@@ -84,6 +84,35 @@ pub fn try_parse_word_only_commands_sequence(tree: &Tree, src: &str) -> Option<V
     Some(commands)
 }
 
+/// Every simple command in the script as its words, including commands in
+/// substitutions, subshells and control flow. Unlike
+/// [`try_parse_word_only_commands_sequence`] nothing is rejected: quotes and
+/// backslashes are dropped from each word and expansions are kept as
+/// written, so the words are only an approximation of what would run.
+pub fn all_commands_best_effort(tree: &Tree, src: &str) -> Vec<Vec<String>> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let mut stack = vec![root];
+    let mut commands = Vec::new();
+    while let Some(node) = stack.pop() {
+        if node.kind() == "command" {
+            let mut words_cursor = node.walk();
+            let words = node
+                .named_children(&mut words_cursor)
+                .filter(|child| !child.kind().ends_with("redirect"))
+                .filter(|child| child.kind() != "variable_assignment")
+                .filter_map(|child| child.utf8_text(src.as_bytes()).ok())
+                .map(|word| word.replace(['\'', '"', '\\'], ""))
+                .collect();
+            commands.push(words);
+        }
+        for child in node.children(&mut cursor) {
+            stack.push(child);
+        }
+    }
+    commands
+}
+
 fn parse_plain_command_from_node(cmd: tree_sitter::Node, src: &str) -> Option<Vec<String>> {
     if cmd.kind() != "command" {
         return None;
@@ -211,6 +240,18 @@ mod tests {
         assert!(parse_seq("FOO=bar ls").is_none());
     }
 
+    #[test]
+    fn best_effort_finds_commands_anywhere() {
+        let src = "FOO=1 'cu'rl -s x > out; echo $(wget y) | (env sh)";
+        let tree = try_parse_bash(src).unwrap();
+        let mut programs: Vec<String> = all_commands_best_effort(&tree, src)
+            .into_iter()
+            .filter_map(|words| words.into_iter().next())
+            .collect();
+        programs.sort();
+        assert_eq!(programs, vec!["curl", "echo", "env", "wget"]);
+    }
+
     #[test]
     fn rejects_trailing_operator_parse_error() {
         assert!(parse_seq("ls &&").is_none());
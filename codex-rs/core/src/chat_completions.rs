@@ -1,11 +1,10 @@
 use std::time::Duration;
 
 use bytes::Bytes;
-use eventsource_stream::Eventsource;
 use futures::Stream;
 use futures::StreamExt;
 use futures::TryStreamExt;
-use reqwest::StatusCode;
+use serde::Deserialize;
 use serde_json::json;
 use std::pin::Pin;
 use std::task::Context;
@@ -16,14 +15,23 @@ use tracing::debug;
 use tracing::trace;
 
 use crate::ModelProviderInfo;
+use crate::client::MAX_SSE_EVENT_BYTES;
+use crate::client::cap_sse_events;
+use crate::client::send_with_timeout;
 use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
 use crate::client_common::ResponseStream;
 use crate::error::CodexErr;
 use crate::error::Result;
+use crate::error::RetryDecision;
+use crate::error::retry_decision;
+use crate::model_capabilities::ModelCapabilities;
 use crate::model_family::ModelFamily;
+use crate::openai_model_info::sampling_params;
 use crate::openai_tools::create_tools_json_for_chat_completions_api;
-use crate::util::backoff;
+use crate::protocol::TokenUsage;
+use crate::util::parse_retry_after;
+use codex_protocol::config_types::ReasoningEffort as ReasoningEffortConfig;
 use codex_protocol::models::ContentItem;
 use codex_protocol::models::ReasoningItemContent;
 use codex_protocol::models::ResponseItem;
@@ -32,6 +40,7 @@ use codex_protocol::models::ResponseItem;
 pub(crate) async fn stream_chat_completions(
     prompt: &Prompt,
     model_family: &ModelFamily,
+    effort: ReasoningEffortConfig,
     client: &reqwest::Client,
     provider: &ModelProviderInfo,
 ) -> Result<ResponseStream> {
@@ -268,12 +277,25 @@ pub(crate) async fn stream_chat_completions(
     }
 
     let tools_json = create_tools_json_for_chat_completions_api(&prompt.tools)?;
-    let payload = json!({
-        "model": model_family.slug,
+    let mut payload = json!({
+        "model": provider.wire_model(&model_family.slug),
         "messages": messages,
         "stream": true,
         "tools": tools_json,
     });
+    if ModelCapabilities::for_family(model_family).supports_reasoning {
+        payload["reasoning_effort"] = json!(provider.reasoning_effort.unwrap_or(effort));
+    }
+    let (temperature, top_p) = sampling_params(model_family);
+    if let Some(temperature) = temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    if let Some(top_p) = top_p {
+        payload["top_p"] = json!(top_p);
+    }
+    if provider.stream_include_usage {
+        payload["stream_options"] = json!({"include_usage": true});
+    }
 
     debug!(
         "POST to {}: {}",
@@ -283,55 +305,72 @@ pub(crate) async fn stream_chat_completions(
 
     let mut attempt = 0;
     let max_retries = provider.request_max_retries();
+    let mut retry_backoff = provider.retry_backoff();
     loop {
         attempt += 1;
 
         let req_builder = provider.create_request_builder(client, &None).await?;
 
-        let res = req_builder
+        let req_builder = req_builder
             .header(reqwest::header::ACCEPT, "text/event-stream")
-            .json(&payload)
-            .send()
-            .await;
+            .json(&payload);
+        let res = match send_with_timeout(req_builder, provider.request_timeout()).await {
+            Ok(res) => res,
+            Err(err) => {
+                let delay = retry_backoff.delay(attempt);
+                if attempt > max_retries
+                    || retry_decision(&err, false) == RetryDecision::DoNotRetry
+                    || !retry_backoff.may_retry_after(delay)
+                {
+                    return Err(err);
+                }
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+        };
 
         match res {
             Ok(resp) if resp.status().is_success() => {
                 let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(1600);
                 let stream = resp.bytes_stream().map_err(CodexErr::Reqwest);
-                tokio::spawn(process_chat_sse(
+                let producer = tokio::spawn(process_chat_sse(
                     stream,
                     tx_event,
                     provider.stream_idle_timeout(),
+                    provider.stream_include_usage,
                 ));
-                return Ok(ResponseStream { rx_event });
+                return Ok(ResponseStream::new(rx_event, producer.abort_handle()));
             }
             Ok(res) => {
                 let status = res.status();
-                if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()) {
+                if RetryDecision::for_status(status) == RetryDecision::DoNotRetry {
                     let body = (res.text().await).unwrap_or_default();
                     return Err(CodexErr::UnexpectedStatus(status, body));
                 }
 
-                if attempt > max_retries {
-                    return Err(CodexErr::RetryLimit(status));
-                }
-
-                let retry_after_secs = res
+                let retry_after = res
                     .headers()
                     .get(reqwest::header::RETRY_AFTER)
                     .and_then(|v| v.to_str().ok())
-                    .and_then(|s| s.parse::<u64>().ok());
+                    .and_then(parse_retry_after);
 
-                let delay = retry_after_secs
-                    .map(|s| Duration::from_millis(s * 1_000))
-                    .unwrap_or_else(|| backoff(attempt));
+                let delay = match retry_after {
+                    Some(retry_after) => retry_backoff.cap(retry_after),
+                    None => retry_backoff.delay(attempt),
+                };
+                if attempt > max_retries || !retry_backoff.may_retry_after(delay) {
+                    return Err(CodexErr::RetryLimit(status));
+                }
                 tokio::time::sleep(delay).await;
             }
             Err(e) => {
-                if attempt > max_retries {
+                let delay = retry_backoff.delay(attempt);
+                if attempt > max_retries
+                    || RetryDecision::for_reqwest_error(&e) == RetryDecision::DoNotRetry
+                    || !retry_backoff.may_retry_after(delay)
+                {
                     return Err(e.into());
                 }
-                let delay = backoff(attempt);
                 tokio::time::sleep(delay).await;
             }
         }
@@ -341,49 +380,43 @@ pub(crate) async fn stream_chat_completions(
 /// Lightweight SSE processor for the Chat Completions streaming format. The
 /// output is mapped onto Codex's internal [`ResponseEvent`] so that the rest
 /// of the pipeline can stay agnostic of the underlying wire format.
+///
+/// With `include_usage`, the provider sends the token usage in a chunk of
+/// its own after the one with `finish_reason`, so `Completed` waits for the
+/// end of the stream.
 async fn process_chat_sse<S>(
     stream: S,
     tx_event: mpsc::Sender<Result<ResponseEvent>>,
     idle_timeout: Duration,
+    include_usage: bool,
 ) where
     S: Stream<Item = Result<Bytes>> + Unpin,
 {
-    let mut stream = stream.eventsource();
-
-    // State to accumulate a function call across streaming chunks.
-    // OpenAI may split the `arguments` string over multiple `delta` events
-    // until the chunk whose `finish_reason` is `tool_calls` is emitted. We
-    // keep collecting the pieces here and forward a single
-    // `ResponseItem::FunctionCall` once the call is complete.
-    #[derive(Default)]
-    struct FunctionCallState {
-        name: Option<String>,
-        arguments: String,
-        call_id: Option<String>,
-        active: bool,
-    }
+    let mut stream = SseData::new(cap_sse_events(stream, MAX_SSE_EVENT_BYTES));
 
-    let mut fn_call_state = FunctionCallState::default();
+    let mut tool_calls = ToolCallAccumulator::default();
     let mut assistant_text = String::new();
     let mut reasoning_text = String::new();
+    let mut token_usage: Option<TokenUsage> = None;
 
     loop {
-        let sse = match timeout(idle_timeout, stream.next()).await {
-            Ok(Some(Ok(ev))) => ev,
+        let data = match timeout(idle_timeout, stream.next()).await {
+            Ok(Some(Ok(data))) => data,
             Ok(Some(Err(e))) => {
-                let _ = tx_event
-                    .send(Err(CodexErr::Stream(e.to_string(), None)))
-                    .await;
+                let _ = tx_event.send(Err(e)).await;
                 return;
             }
             Ok(None) => {
-                // Stream closed gracefully – emit Completed with dummy id.
-                let _ = tx_event
-                    .send(Ok(ResponseEvent::Completed {
-                        response_id: String::new(),
-                        token_usage: None,
-                    }))
-                    .await;
+                // Stream closed gracefully without a `[DONE]` sentinel (some
+                // providers omit it) – treat it exactly like `[DONE]`.
+                finish_chat_stream(
+                    &tx_event,
+                    &mut assistant_text,
+                    &mut reasoning_text,
+                    &mut tool_calls,
+                    token_usage,
+                )
+                .await;
                 return;
             }
             Err(_) => {
@@ -398,48 +431,33 @@ async fn process_chat_sse<S>(
         };
 
         // OpenAI Chat streaming sends a literal string "[DONE]" when finished.
-        if sse.data.trim() == "[DONE]" {
-            // Emit any finalized items before closing so downstream consumers receive
-            // terminal events for both assistant content and raw reasoning.
-            if !assistant_text.is_empty() {
-                let item = ResponseItem::Message {
-                    role: "assistant".to_string(),
-                    content: vec![ContentItem::OutputText {
-                        text: std::mem::take(&mut assistant_text),
-                    }],
-                    id: None,
-                };
-                let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
-            }
-
-            if !reasoning_text.is_empty() {
-                let item = ResponseItem::Reasoning {
-                    id: String::new(),
-                    summary: Vec::new(),
-                    content: Some(vec![ReasoningItemContent::ReasoningText {
-                        text: std::mem::take(&mut reasoning_text),
-                    }]),
-                    encrypted_content: None,
-                };
-                let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
-            }
-
-            let _ = tx_event
-                .send(Ok(ResponseEvent::Completed {
-                    response_id: String::new(),
-                    token_usage: None,
-                }))
-                .await;
+        // Anything after it (e.g. a trailing event) is ignored.
+        if is_done_sentinel(&data) {
+            finish_chat_stream(
+                &tx_event,
+                &mut assistant_text,
+                &mut reasoning_text,
+                &mut tool_calls,
+                token_usage,
+            )
+            .await;
             return;
         }
 
         // Parse JSON chunk
-        let chunk: serde_json::Value = match serde_json::from_str(&sse.data) {
+        let chunk: serde_json::Value = match serde_json::from_str(&data) {
             Ok(v) => v,
             Err(_) => continue,
         };
         trace!("chat_completions received SSE chunk: {chunk:?}");
 
+        if let Some(usage) = chunk.get("usage").filter(|usage| !usage.is_null()) {
+            match serde_json::from_value::<ChatCompletionUsage>(usage.clone()) {
+                Ok(usage) => token_usage = Some(usage.into()),
+                Err(e) => debug!("ignoring malformed usage {usage}: {e}"),
+            }
+        }
+
         let choice_opt = chunk.get("choices").and_then(|c| c.get(0));
 
         if let Some(choice) = choice_opt {
@@ -516,37 +534,20 @@ async fn process_chat_sse<S>(
             }
 
             // Handle streaming function / tool calls.
-            if let Some(tool_calls) = choice
+            if let Some(deltas) = choice
                 .get("delta")
                 .and_then(|d| d.get("tool_calls"))
                 .and_then(|tc| tc.as_array())
-                && let Some(tool_call) = tool_calls.first()
             {
-                // Mark that we have an active function call in progress.
-                fn_call_state.active = true;
-
-                // Extract call_id if present.
-                if let Some(id) = tool_call.get("id").and_then(|v| v.as_str()) {
-                    fn_call_state.call_id.get_or_insert_with(|| id.to_string());
-                }
-
-                // Extract function details if present.
-                if let Some(function) = tool_call.get("function") {
-                    if let Some(name) = function.get("name").and_then(|n| n.as_str()) {
-                        fn_call_state.name.get_or_insert_with(|| name.to_string());
-                    }
-
-                    if let Some(args_fragment) = function.get("arguments").and_then(|a| a.as_str())
-                    {
-                        fn_call_state.arguments.push_str(args_fragment);
-                    }
+                for delta in deltas {
+                    tool_calls.push_delta(delta);
                 }
             }
 
             // Emit end-of-turn when finish_reason signals completion.
             if let Some(finish_reason) = choice.get("finish_reason").and_then(|v| v.as_str()) {
                 match finish_reason {
-                    "tool_calls" if fn_call_state.active => {
+                    "tool_calls" if !tool_calls.is_empty() => {
                         // First, flush the terminal raw reasoning so UIs can finalize
                         // the reasoning stream before any exec/tool events begin.
                         if !reasoning_text.is_empty() {
@@ -561,15 +562,10 @@ async fn process_chat_sse<S>(
                             let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
                         }
 
-                        // Then emit the FunctionCall response item.
-                        let item = ResponseItem::FunctionCall {
-                            id: None,
-                            name: fn_call_state.name.clone().unwrap_or_else(|| "".to_string()),
-                            arguments: fn_call_state.arguments.clone(),
-                            call_id: fn_call_state.call_id.clone().unwrap_or_else(String::new),
-                        };
-
-                        let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
+                        // Then emit the FunctionCall response items.
+                        for item in tool_calls.take_items() {
+                            let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
+                        }
                     }
                     "stop" => {
                         // Regular turn without tool-call. Emit the final assistant message
@@ -596,27 +592,321 @@ async fn process_chat_sse<S>(
                             };
                             let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
                         }
+                        // Some providers report `stop` even after streaming
+                        // tool calls; do not drop them.
+                        for item in tool_calls.take_items() {
+                            let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
+                        }
                     }
                     _ => {}
                 }
 
+                // The usage is still to come; `[DONE]` or the end of the
+                // stream completes the turn instead.
+                if include_usage {
+                    continue;
+                }
+
                 // Emit Completed regardless of reason so the agent can advance.
                 let _ = tx_event
                     .send(Ok(ResponseEvent::Completed {
                         response_id: String::new(),
-                        token_usage: None,
+                        token_usage,
                     }))
                     .await;
 
-                // Prepare for potential next turn (should not happen in same stream).
-                // fn_call_state = FunctionCallState::default();
-
                 return; // End processing for this SSE stream.
             }
         }
     }
 }
 
+/// The `data` of each event in an SSE byte stream.
+///
+/// Servers split events across reads wherever they like, including in the
+/// middle of a JSON string or a multi-byte character. Bytes are therefore
+/// buffered until the blank line that ends an event, and only complete events
+/// are decoded. Events without `data` (e.g. keep-alive comments) are skipped,
+/// and an unterminated event at the end of the stream is dropped, as the SSE
+/// spec requires.
+struct SseData<S> {
+    inner: S,
+    buffer: Vec<u8>,
+    /// How far `buffer` was searched for the end of the event.
+    scanned: usize,
+    /// Where the line containing `scanned` starts.
+    line_start: usize,
+    exhausted: bool,
+}
+
+impl<S> SseData<S> {
+    fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            scanned: 0,
+            line_start: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Removes the next complete event, including its terminating blank line,
+    /// from the buffer.
+    fn take_event(&mut self) -> Option<Vec<u8>> {
+        while self.scanned < self.buffer.len() {
+            let end_of_line = self.scanned;
+            match self.buffer[end_of_line] {
+                b'\n' => self.scanned += 1,
+                b'\r' => match self.buffer.get(end_of_line + 1) {
+                    Some(b'\n') => self.scanned += 2,
+                    Some(_) => self.scanned += 1,
+                    // Wait for the next byte unless no more are coming: it
+                    // decides whether this is one line break or two.
+                    None if self.exhausted => self.scanned += 1,
+                    None => return None,
+                },
+                _ => {
+                    self.scanned += 1;
+                    continue;
+                }
+            }
+            if end_of_line == self.line_start {
+                let event = self.buffer.drain(..self.scanned).collect();
+                self.scanned = 0;
+                self.line_start = 0;
+                return Some(event);
+            }
+            self.line_start = self.scanned;
+        }
+        None
+    }
+}
+
+/// The `data` lines of one event, joined with newlines, or `None` if it has
+/// none.
+fn event_data(event: &[u8]) -> Option<String> {
+    let event = String::from_utf8_lossy(event);
+    let mut data: Option<String> = None;
+    for line in event.split(['\n', '\r']) {
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        if field != "data" {
+            // Also skips comments, whose field name is empty.
+            continue;
+        }
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match &mut data {
+            Some(data) => {
+                data.push('\n');
+                data.push_str(value);
+            }
+            None => data = Some(value.to_string()),
+        }
+    }
+    data
+}
+
+impl<S> Stream for SseData<S>
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    type Item = Result<String>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            while let Some(event) = this.take_event() {
+                if let Some(data) = event_data(&event) {
+                    return Poll::Ready(Some(Ok(data)));
+                }
+            }
+            if this.exhausted {
+                return Poll::Ready(None);
+            }
+            match std::task::ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(Ok(chunk)) => this.buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => this.exhausted = true,
+            }
+        }
+    }
+}
+
+/// Whether an SSE `data` payload is the end-of-stream sentinel. Providers
+/// disagree on spacing and casing, so `[DONE]`, ` [DONE] ` and `[done]` all
+/// count.
+fn is_done_sentinel(data: &str) -> bool {
+    data.trim().eq_ignore_ascii_case("[DONE]")
+}
+
+/// Emits any finalized items before closing so downstream consumers receive
+/// terminal events for both assistant content and raw reasoning, followed by
+/// `Completed`.
+async fn finish_chat_stream(
+    tx_event: &mpsc::Sender<Result<ResponseEvent>>,
+    assistant_text: &mut String,
+    reasoning_text: &mut String,
+    tool_calls: &mut ToolCallAccumulator,
+    token_usage: Option<TokenUsage>,
+) {
+    if !assistant_text.is_empty() {
+        let item = ResponseItem::Message {
+            role: "assistant".to_string(),
+            content: vec![ContentItem::OutputText {
+                text: std::mem::take(assistant_text),
+            }],
+            id: None,
+        };
+        let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
+    }
+
+    if !reasoning_text.is_empty() {
+        let item = ResponseItem::Reasoning {
+            id: String::new(),
+            summary: Vec::new(),
+            content: Some(vec![ReasoningItemContent::ReasoningText {
+                text: std::mem::take(reasoning_text),
+            }]),
+            encrypted_content: None,
+        };
+        let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
+    }
+
+    for item in tool_calls.take_items() {
+        let _ = tx_event.send(Ok(ResponseEvent::OutputItemDone(item))).await;
+    }
+
+    let _ = tx_event
+        .send(Ok(ResponseEvent::Completed {
+            response_id: String::new(),
+            token_usage,
+        }))
+        .await;
+}
+
+/// The `usage` of a Chat Completions response, sent with
+/// `stream_options.include_usage` (and by some providers unasked).
+#[derive(Debug, Deserialize)]
+struct ChatCompletionUsage {
+    prompt_tokens: u64,
+    prompt_tokens_details: Option<PromptTokensDetails>,
+    completion_tokens: u64,
+    completion_tokens_details: Option<CompletionTokensDetails>,
+    total_tokens: u64,
+}
+
+impl From<ChatCompletionUsage> for TokenUsage {
+    fn from(val: ChatCompletionUsage) -> Self {
+        TokenUsage {
+            input_tokens: val.prompt_tokens,
+            cached_input_tokens: val
+                .prompt_tokens_details
+                .map(|d| d.cached_tokens)
+                .unwrap_or(0),
+            output_tokens: val.completion_tokens,
+            reasoning_output_tokens: val
+                .completion_tokens_details
+                .map(|d| d.reasoning_tokens)
+                .unwrap_or(0),
+            total_tokens: val.total_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionTokensDetails {
+    #[serde(default)]
+    reasoning_tokens: u64,
+}
+
+/// Function calls being streamed. OpenAI splits each call's `arguments`
+/// over many `delta.tool_calls` entries until the chunk whose
+/// `finish_reason` is `tool_calls`; parallel calls are interleaved and told
+/// apart by `index`. The `id` and `name` usually only arrive with a call's
+/// first fragment.
+#[derive(Debug, Default)]
+struct ToolCallAccumulator {
+    /// In the order their first fragment arrived.
+    calls: Vec<PendingToolCall>,
+}
+
+#[derive(Debug, Default)]
+struct PendingToolCall {
+    index: Option<u64>,
+    call_id: Option<String>,
+    name: Option<String>,
+    arguments: String,
+}
+
+impl ToolCallAccumulator {
+    fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Adds one `delta.tool_calls[]` entry to the call it belongs to: the
+    /// one with the same `index`, else the same `id`. Providers that send
+    /// neither only ever stream one call at a time, so the fragment belongs
+    /// to the latest call.
+    fn push_delta(&mut self, delta: &serde_json::Value) {
+        let index = delta.get("index").and_then(serde_json::Value::as_u64);
+        let id = delta
+            .get("id")
+            .and_then(|v| v.as_str())
+            .filter(|id| !id.is_empty());
+        let position = match (index, id) {
+            (Some(index), _) => self.calls.iter().position(|c| c.index == Some(index)),
+            (None, Some(id)) => self
+                .calls
+                .iter()
+                .position(|c| c.call_id.as_deref() == Some(id)),
+            (None, None) => self.calls.len().checked_sub(1),
+        };
+        let call = match position {
+            Some(position) => &mut self.calls[position],
+            None => {
+                self.calls.push(PendingToolCall {
+                    index,
+                    ..Default::default()
+                });
+                let last = self.calls.len() - 1;
+                &mut self.calls[last]
+            }
+        };
+
+        if let Some(id) = id {
+            call.call_id.get_or_insert_with(|| id.to_string());
+        }
+        if let Some(function) = delta.get("function") {
+            if let Some(name) = function.get("name").and_then(|n| n.as_str())
+                && !name.is_empty()
+            {
+                call.name.get_or_insert_with(|| name.to_string());
+            }
+            if let Some(fragment) = function.get("arguments").and_then(|a| a.as_str()) {
+                call.arguments.push_str(fragment);
+            }
+        }
+    }
+
+    /// The completed calls, leaving the accumulator empty.
+    fn take_items(&mut self) -> Vec<ResponseItem> {
+        std::mem::take(&mut self.calls)
+            .into_iter()
+            .map(|call| ResponseItem::FunctionCall {
+                id: None,
+                name: call.name.unwrap_or_default(),
+                arguments: call.arguments,
+                call_id: call.call_id.unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
 /// Optional client-side aggregation helper
 ///
 /// Stream adapter that merges the incremental `OutputItemDone` chunks coming from
@@ -860,3 +1150,65 @@ impl<S> AggregatedChatStream<S> {
         Self::new(inner, AggregateMode::Streaming)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::expect_used)]
+
+    use super::*;
+    use futures::stream;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn reassembles_events_delivered_one_byte_at_a_time() {
+        let body = concat!(
+            ": keep-alive\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"h\\u00e9llo \"}}]}\r\n\r\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"wörld\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+        );
+        let chunks: Vec<Result<Bytes>> = body
+            .bytes()
+            .map(|byte| Ok(Bytes::copy_from_slice(&[byte])))
+            .collect();
+
+        let (tx, mut rx) = mpsc::channel::<Result<ResponseEvent>>(16);
+        process_chat_sse(stream::iter(chunks), tx, Duration::from_secs(5), false).await;
+
+        let mut deltas = Vec::new();
+        let mut messages = Vec::new();
+        let mut completed = false;
+        while let Some(event) = rx.recv().await {
+            match event.expect("no stream error") {
+                ResponseEvent::OutputTextDelta(delta) => deltas.push(delta),
+                ResponseEvent::OutputItemDone(ResponseItem::Message { content, .. }) => {
+                    messages.push(content)
+                }
+                ResponseEvent::Completed { .. } => completed = true,
+                other => panic!("unexpected event {other:?}"),
+            }
+        }
+        assert_eq!(deltas, vec!["héllo ".to_string(), "wörld".to_string()]);
+        assert_eq!(
+            messages,
+            vec![vec![ContentItem::OutputText {
+                text: "héllo wörld".to_string()
+            }]]
+        );
+        assert!(completed);
+    }
+
+    #[tokio::test]
+    async fn only_complete_events_are_parsed() {
+        let chunks: Vec<Result<Bytes>> = vec![
+            Ok(Bytes::from_static(b"data: first\r")),
+            Ok(Bytes::from_static(b"\ndata: line\n")),
+            Ok(Bytes::from_static(b"\ndata: second\r\rdata: unterminated")),
+        ];
+        let data: Vec<String> = SseData::new(stream::iter(chunks))
+            .map(|data| data.expect("no stream error"))
+            .collect()
+            .await;
+        assert_eq!(data, vec!["first\nline".to_string(), "second".to_string()]);
+    }
+}
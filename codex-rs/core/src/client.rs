@@ -1,4 +1,3 @@
-use std::io::BufRead;
 use std::path::Path;
 use std::sync::OnceLock;
 use std::time::Duration;
@@ -16,7 +15,7 @@ use serde::Serialize;
 use serde_json::Value;
 use tokio::sync::mpsc;
 use tokio::time::timeout;
-use tokio_util::io::ReaderStream;
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 use tracing::trace;
 use tracing::warn;
@@ -33,20 +32,30 @@ use crate::config::Config;
 use crate::default_client::create_client;
 use crate::error::CodexErr;
 use crate::error::Result;
+use crate::error::RetryDecision;
 use crate::error::UsageLimitReachedError;
+use crate::error::retry_decision;
 use crate::flags::CODEX_RS_SSE_FIXTURE;
+use crate::flags::CODEX_RS_SSE_RECORD;
+use crate::model_capabilities::ModelCapabilities;
 use crate::model_family::ModelFamily;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::model_provider_info::WireApi;
-use crate::openai_model_info::get_model_info;
+use crate::openai_model_info::sampling_params;
 use crate::openai_tools::create_tools_json_for_responses_api;
 use crate::protocol::TokenUsage;
-use crate::util::backoff;
+use crate::sse_fixture::fixture_stream;
+use crate::sse_fixture::record_sse;
+use crate::util::parse_retry_after;
 use codex_protocol::config_types::ReasoningEffort as ReasoningEffortConfig;
 use codex_protocol::config_types::ReasoningSummary as ReasoningSummaryConfig;
 use codex_protocol::models::ResponseItem;
 use std::sync::Arc;
 
+/// Largest single SSE event we are willing to buffer. Events are parsed as
+/// bytes arrive, but each one has to be held in memory until it is complete.
+pub(crate) const MAX_SSE_EVENT_BYTES: usize = 16 * 1024 * 1024;
+
 #[derive(Debug, Deserialize)]
 struct ErrorResponse {
     error: Error,
@@ -98,9 +107,9 @@ impl ModelClient {
     }
 
     pub fn get_model_context_window(&self) -> Option<u64> {
-        self.config
-            .model_context_window
-            .or_else(|| get_model_info(&self.config.model_family).map(|info| info.context_window))
+        self.config.model_context_window.or_else(|| {
+            ModelCapabilities::for_family(&self.config.model_family).known_context_window()
+        })
     }
 
     /// Dispatches to either the Responses or Chat implementation depending on
@@ -114,6 +123,7 @@ impl ModelClient {
                 let response_stream = stream_chat_completions(
                     prompt,
                     &self.config.model_family,
+                    self.effort,
                     &self.client,
                     &self.provider,
                 )
@@ -132,7 +142,7 @@ impl ModelClient {
                 // `ResponseStream` by forwarding events through a channel.
                 let (tx, rx) = mpsc::channel::<Result<ResponseEvent>>(16);
 
-                tokio::spawn(async move {
+                let producer = tokio::spawn(async move {
                     use futures::StreamExt;
                     while let Some(ev) = aggregated.next().await {
                         // Exit early if receiver hung up.
@@ -142,11 +152,28 @@ impl ModelClient {
                     }
                 });
 
-                Ok(ResponseStream { rx_event: rx })
+                Ok(ResponseStream::new(rx, producer.abort_handle()))
             }
         }
     }
 
+    /// Like [`ModelClient::stream`], but bound to `cancel`: cancelling before
+    /// the provider starts responding abandons the request, and cancelling
+    /// afterwards drops the connection. Either way the caller sees
+    /// [`CodexErr::Interrupted`].
+    pub async fn stream_with_cancellation(
+        &self,
+        prompt: &Prompt,
+        cancel: &CancellationToken,
+    ) -> Result<ResponseStream> {
+        let stream = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => return Err(CodexErr::Interrupted),
+            stream = self.stream(prompt) => stream?,
+        };
+        Ok(stream.cancel_on(cancel.clone()))
+    }
+
     /// Implementation for the OpenAI *Responses* experimental API.
     async fn stream_responses(&self, prompt: &Prompt) -> Result<ResponseStream> {
         if let Some(path) = &*CODEX_RS_SSE_FIXTURE {
@@ -161,7 +188,7 @@ impl ModelClient {
         let tools_json = create_tools_json_for_responses_api(&prompt.tools)?;
         let reasoning = create_reasoning_param_for_request(
             &self.config.model_family,
-            self.effort,
+            self.provider.reasoning_effort.unwrap_or(self.effort),
             self.summary,
         );
 
@@ -186,8 +213,10 @@ impl ModelClient {
             None
         };
 
+        let (temperature, top_p) = sampling_params(&self.config.model_family);
+
         let payload = ResponsesApiRequest {
-            model: &self.config.model,
+            model: self.provider.wire_model(&self.config.model),
             instructions: &full_instructions,
             input: &input_with_instructions,
             tools: &tools_json,
@@ -199,10 +228,13 @@ impl ModelClient {
             include,
             prompt_cache_key: Some(self.conversation_id.to_string()),
             text,
+            temperature,
+            top_p,
         };
 
         let mut attempt = 0;
         let max_retries = self.provider.request_max_retries();
+        let mut retry_backoff = self.provider.retry_backoff();
 
         loop {
             attempt += 1;
@@ -236,7 +268,20 @@ impl ModelClient {
                 req_builder = req_builder.header("chatgpt-account-id", account_id);
             }
 
-            let res = req_builder.send().await;
+            let res = match send_with_timeout(req_builder, self.provider.request_timeout()).await {
+                Ok(res) => res,
+                Err(err) => {
+                    let delay = retry_backoff.delay(attempt);
+                    if attempt > max_retries
+                        || retry_decision(&err, false) == RetryDecision::DoNotRetry
+                        || !retry_backoff.may_retry_after(delay)
+                    {
+                        return Err(err);
+                    }
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            };
             if let Ok(resp) = &res {
                 trace!(
                     "Response status: {}, request-id: {}",
@@ -253,30 +298,35 @@ impl ModelClient {
                     let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(1600);
 
                     // spawn task to process SSE
-                    let stream = resp.bytes_stream().map_err(CodexErr::Reqwest);
-                    tokio::spawn(process_sse(
+                    let stream = record_sse(
+                        resp.bytes_stream().map_err(CodexErr::Reqwest),
+                        CODEX_RS_SSE_RECORD.map(Path::new),
+                    );
+                    let producer = tokio::spawn(process_sse(
                         stream,
                         tx_event,
                         self.provider.stream_idle_timeout(),
                     ));
 
-                    return Ok(ResponseStream { rx_event });
+                    return Ok(ResponseStream::new(rx_event, producer.abort_handle()));
                 }
                 Ok(res) => {
                     let status = res.status();
 
                     // Pull out Retry‑After header if present.
-                    let retry_after_secs = res
+                    let retry_after = res
                         .headers()
                         .get(reqwest::header::RETRY_AFTER)
                         .and_then(|v| v.to_str().ok())
-                        .and_then(|s| s.parse::<u64>().ok());
+                        .and_then(parse_retry_after);
 
+                    // A 401 is only worth retrying with the refreshed credentials.
+                    let mut refreshed = false;
                     if status == StatusCode::UNAUTHORIZED
                         && let Some(manager) = auth_manager.as_ref()
                         && manager.auth().is_some()
                     {
-                        let _ = manager.refresh_token().await;
+                        refreshed = matches!(manager.refresh_token().await, Ok(Some(_)));
                     }
 
                     // The OpenAI Responses endpoint returns structured JSON bodies even for 4xx/5xx
@@ -286,9 +336,7 @@ impl ModelClient {
                     // exact error message (e.g. "Unknown parameter: 'input[0].metadata'"). The body is
                     // small and this branch only runs on error paths so the extra allocation is
                     // negligible.
-                    if !(status == StatusCode::TOO_MANY_REQUESTS
-                        || status == StatusCode::UNAUTHORIZED
-                        || status.is_server_error())
+                    if RetryDecision::for_status(status) == RetryDecision::DoNotRetry && !refreshed
                     {
                         // Surface the error body to callers. Use `unwrap_or_default` per Clippy.
                         let body = res.text().await.unwrap_or_default();
@@ -316,24 +364,27 @@ impl ModelClient {
                         }
                     }
 
-                    if attempt > max_retries {
+                    let delay = match retry_after {
+                        Some(retry_after) => retry_backoff.cap(retry_after),
+                        None => retry_backoff.delay(attempt),
+                    };
+                    if attempt > max_retries || !retry_backoff.may_retry_after(delay) {
                         if status == StatusCode::INTERNAL_SERVER_ERROR {
                             return Err(CodexErr::InternalServerError);
                         }
 
                         return Err(CodexErr::RetryLimit(status));
                     }
-
-                    let delay = retry_after_secs
-                        .map(|s| Duration::from_millis(s * 1_000))
-                        .unwrap_or_else(|| backoff(attempt));
                     tokio::time::sleep(delay).await;
                 }
                 Err(e) => {
-                    if attempt > max_retries {
+                    let delay = retry_backoff.delay(attempt);
+                    if attempt > max_retries
+                        || RetryDecision::for_reqwest_error(&e) == RetryDecision::DoNotRetry
+                        || !retry_backoff.may_retry_after(delay)
+                    {
                         return Err(e.into());
                     }
-                    let delay = backoff(attempt);
                     tokio::time::sleep(delay).await;
                 }
             }
@@ -424,6 +475,20 @@ struct ResponseCompletedOutputTokensDetails {
     reasoning_tokens: u64,
 }
 
+/// Sends `req_builder`, giving up with [`CodexErr::RequestTimeout`] when the
+/// provider has not produced response headers within `request_timeout`.
+pub(crate) async fn send_with_timeout(
+    req_builder: reqwest::RequestBuilder,
+    request_timeout: Option<Duration>,
+) -> Result<std::result::Result<reqwest::Response, reqwest::Error>> {
+    match request_timeout {
+        Some(request_timeout) => timeout(request_timeout, req_builder.send())
+            .await
+            .map_err(|_| CodexErr::RequestTimeout(request_timeout)),
+        None => Ok(req_builder.send().await),
+    }
+}
+
 async fn process_sse<S>(
     stream: S,
     tx_event: mpsc::Sender<Result<ResponseEvent>>,
@@ -431,7 +496,7 @@ async fn process_sse<S>(
 ) where
     S: Stream<Item = Result<Bytes>> + Unpin,
 {
-    let mut stream = stream.eventsource();
+    let mut stream = cap_sse_events(stream, MAX_SSE_EVENT_BYTES).eventsource();
 
     // If the stream stays completely silent for an extended period treat it as disconnected.
     // The response id returned from the "complete" message.
@@ -626,30 +691,56 @@ async fn process_sse<S>(
     }
 }
 
-/// used in tests to stream from a text SSE file
+/// Fails `stream` once a single SSE event (the bytes up to the next blank
+/// line) grows beyond `max_event_bytes`, so a misbehaving provider cannot make
+/// the parser buffer an endless event.
+pub(crate) fn cap_sse_events<S>(
+    stream: S,
+    max_event_bytes: usize,
+) -> impl Stream<Item = Result<Bytes>> + Unpin
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    let mut event_bytes = 0usize;
+    let mut at_line_start = true;
+    stream.map(move |chunk| {
+        let chunk = chunk?;
+        for &byte in chunk.iter() {
+            match byte {
+                b'\n' => {
+                    if at_line_start {
+                        event_bytes = 0;
+                    }
+                    at_line_start = true;
+                }
+                b'\r' => {}
+                _ => at_line_start = false,
+            }
+            event_bytes += 1;
+            if event_bytes > max_event_bytes {
+                return Err(CodexErr::Stream(
+                    format!("SSE event exceeded {max_event_bytes} bytes"),
+                    None,
+                ));
+            }
+        }
+        Ok(chunk)
+    })
+}
+
+/// used in tests to stream from a (possibly timed) text SSE file
 async fn stream_from_fixture(
     path: impl AsRef<Path>,
     provider: ModelProviderInfo,
 ) -> Result<ResponseStream> {
     let (tx_event, rx_event) = mpsc::channel::<Result<ResponseEvent>>(1600);
-    let f = std::fs::File::open(path.as_ref())?;
-    let lines = std::io::BufReader::new(f).lines();
-
-    // insert \n\n after each line for proper SSE parsing
-    let mut content = String::new();
-    for line in lines {
-        content.push_str(&line?);
-        content.push_str("\n\n");
-    }
-
-    let rdr = std::io::Cursor::new(content);
-    let stream = ReaderStream::new(rdr).map_err(CodexErr::Io);
-    tokio::spawn(process_sse(
+    let stream = fixture_stream(path)?;
+    let producer = tokio::spawn(process_sse(
         stream,
         tx_event,
         provider.stream_idle_timeout(),
     ));
-    Ok(ResponseStream { rx_event })
+    Ok(ResponseStream::new(rx_event, producer.abort_handle()))
 }
 
 fn rate_limit_regex() -> &'static Regex {
@@ -755,6 +846,56 @@ mod tests {
     // Tests from `implement-test-for-responses-api-sse-parser`
     // ────────────────────────────
 
+    #[tokio::test]
+    async fn cap_sse_events_rejects_an_oversized_event() {
+        let chunks: Vec<Result<Bytes>> = vec![
+            Ok(Bytes::from_static(b"data: short\r\n\r\n")),
+            Ok(Bytes::from_static(b"data: 0123456789")),
+            Ok(Bytes::from_static(b"0123456789\n\n")),
+        ];
+        let capped: Vec<Result<Bytes>> = cap_sse_events(stream::iter(chunks), 24).collect().await;
+        assert_eq!(capped.len(), 3);
+        assert!(capped[0].is_ok());
+        assert!(capped[1].is_ok());
+        assert!(matches!(&capped[2], Err(CodexErr::Stream(msg, None)) if msg.contains("24 bytes")));
+    }
+
+    #[tokio::test]
+    async fn parses_a_large_body_incrementally() {
+        let delta = json!({
+            "type": "response.output_text.delta",
+            "delta": "x".repeat(256),
+        });
+        let mut events = vec![delta; 20_000];
+        events.push(json!({
+            "type": "response.completed",
+            "response": { "id": "resp_large" }
+        }));
+        let provider = ModelProviderInfo {
+            name: "test".to_string(),
+            base_url: Some("https://test.com".to_string()),
+            env_key: Some("TEST_API_KEY".to_string()),
+            env_key_instructions: None,
+            wire_api: WireApi::Responses,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: Some(0),
+            stream_max_retries: Some(0),
+            stream_idle_timeout_ms: Some(5_000),
+            requires_openai_auth: false,
+            ..Default::default()
+        };
+
+        // Several megabytes in total, far more than any one event.
+        let out = run_sse(events, provider).await;
+        assert_eq!(out.len(), 20_001);
+        assert!(matches!(
+            out.last(),
+            Some(ResponseEvent::Completed { response_id, .. }) if response_id == "resp_large"
+        ));
+    }
+
     #[tokio::test]
     async fn parses_items_and_completed() {
         let item1 = json!({
@@ -800,6 +941,7 @@ mod tests {
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
             requires_openai_auth: false,
+            ..Default::default()
         };
 
         let events = collect_events(
@@ -860,6 +1002,7 @@ mod tests {
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
             requires_openai_auth: false,
+            ..Default::default()
         };
 
         let events = collect_events(&[sse1.as_bytes()], provider).await;
@@ -876,6 +1019,59 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn timed_fixture_replays_its_delays_against_the_idle_timeout() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let fixture = dir.path().join("timed.sse");
+        std::fs::write(
+            &fixture,
+            concat!(
+                "event: response.created\n",
+                "data: {\"type\":\"response.created\",\"response\":{}}\n",
+                "@+300 event: response.completed\n",
+                "data: {\"type\":\"response.completed\",\"response\":{\"id\":\"resp1\"}}\n",
+            ),
+        )
+        .expect("write fixture");
+        let provider = |idle_timeout_ms| ModelProviderInfo {
+            name: "test".to_string(),
+            base_url: Some("https://test.com".to_string()),
+            env_key: Some("TEST_API_KEY".to_string()),
+            env_key_instructions: None,
+            wire_api: WireApi::Responses,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: Some(0),
+            stream_max_retries: Some(0),
+            stream_idle_timeout_ms: Some(idle_timeout_ms),
+            requires_openai_auth: false,
+            ..Default::default()
+        };
+
+        // The pause before `response.completed` outlasts a short idle timeout...
+        let events: Vec<Result<ResponseEvent>> = stream_from_fixture(&fixture, provider(50))
+            .await
+            .expect("fixture stream")
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], Ok(ResponseEvent::Created)));
+        match &events[1] {
+            Err(CodexErr::Stream(msg, _)) => assert_eq!(msg, "idle timeout waiting for SSE"),
+            other => panic!("unexpected second event: {other:?}"),
+        }
+
+        // ...but not a generous one.
+        let events: Vec<Result<ResponseEvent>> = stream_from_fixture(&fixture, provider(5_000))
+            .await
+            .expect("fixture stream")
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], Ok(ResponseEvent::Completed { .. })));
+    }
+
     #[tokio::test]
     async fn error_when_error_event() {
         let raw_error = r#"{"type":"response.failed","sequence_number":3,"response":{"id":"resp_689bcf18d7f08194bf3440ba62fe05d803fee0cdac429894","object":"response","created_at":1755041560,"status":"failed","background":false,"error":{"code":"rate_limit_exceeded","message":"Rate limit reached for gpt-5 in organization org-AAA on tokens per min (TPM): Limit 30000, Used 22999, Requested 12528. Please try again in 11.054s. Visit https://platform.openai.com/account/rate-limits to learn more."}, "usage":null,"user":null,"metadata":{}}}"#;
@@ -894,6 +1090,7 @@ mod tests {
             stream_max_retries: Some(0),
             stream_idle_timeout_ms: Some(1000),
             requires_openai_auth: false,
+            ..Default::default()
         };
 
         let events = collect_events(&[sse1.as_bytes()], provider).await;
@@ -999,6 +1196,7 @@ mod tests {
                 stream_max_retries: Some(0),
                 stream_idle_timeout_ms: Some(1000),
                 requires_openai_auth: false,
+                ..Default::default()
             };
 
             let out = run_sse(evs, provider).await;
@@ -1,3 +1,4 @@
+use crate::error::CodexErr;
 use crate::error::Result;
 use crate::model_family::ModelFamily;
 use crate::openai_tools::OpenAiTool;
@@ -14,6 +15,9 @@ use std::pin::Pin;
 use std::task::Context;
 use std::task::Poll;
 use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::sync::WaitForCancellationFutureOwned;
 
 /// The `instructions` field in the payload sent to a model should always start
 /// with this content.
@@ -132,6 +136,12 @@ pub(crate) struct ResponsesApiRequest<'a> {
     pub(crate) prompt_cache_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) text: Option<TextControls>,
+    /// Omitted for models that do not accept it; see
+    /// [`crate::openai_model_info::sampling_params`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) top_p: Option<f32>,
 }
 
 pub(crate) fn create_reasoning_param_for_request(
@@ -155,17 +165,62 @@ pub(crate) fn create_text_param_for_request(
 }
 
 pub struct ResponseStream {
-    pub(crate) rx_event: mpsc::Receiver<Result<ResponseEvent>>,
+    rx_event: mpsc::Receiver<Result<ResponseEvent>>,
+    /// The task reading the response body into `rx_event`. Aborting it drops
+    /// the connection to the provider.
+    producer: AbortHandle,
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+    interrupted: bool,
+}
+
+impl ResponseStream {
+    pub(crate) fn new(
+        rx_event: mpsc::Receiver<Result<ResponseEvent>>,
+        producer: AbortHandle,
+    ) -> Self {
+        Self {
+            rx_event,
+            producer,
+            cancelled: None,
+            interrupted: false,
+        }
+    }
+
+    /// Once `cancel` fires, drops the connection to the provider and ends the
+    /// stream with [`CodexErr::Interrupted`], so an aborted turn stops
+    /// paying for the rest of the response.
+    pub(crate) fn cancel_on(mut self, cancel: CancellationToken) -> Self {
+        self.cancelled = Some(Box::pin(cancel.cancelled_owned()));
+        self
+    }
 }
 
 impl Stream for ResponseStream {
     type Item = Result<ResponseEvent>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.interrupted {
+            return Poll::Ready(None);
+        }
+        if let Some(cancelled) = self.cancelled.as_mut()
+            && cancelled.as_mut().poll(cx).is_ready()
+        {
+            self.cancelled = None;
+            self.interrupted = true;
+            self.producer.abort();
+            return Poll::Ready(Some(Err(CodexErr::Interrupted)));
+        }
         self.rx_event.poll_recv(cx)
     }
 }
 
+impl Drop for ResponseStream {
+    fn drop(&mut self) {
+        // Nobody will read the rest of the response; stop downloading it.
+        self.producer.abort();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::model_family::find_family_for_model;
@@ -202,6 +257,8 @@ mod tests {
             text: Some(TextControls {
                 verbosity: Some(OpenAiVerbosity::Low),
             }),
+            temperature: None,
+            top_p: None,
         };
 
         let v = serde_json::to_value(&req).expect("json");
@@ -230,6 +287,8 @@ mod tests {
             include: vec![],
             prompt_cache_key: None,
             text: None,
+            temperature: None,
+            top_p: None,
         };
 
         let v = serde_json::to_value(&req).expect("json");
@@ -1,12 +1,14 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::num::NonZeroU64;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
 use std::sync::atomic::AtomicU64;
 use std::time::Duration;
+use std::time::SystemTime;
 
 use crate::AuthManager;
 use crate::event_mapping::map_response_item_to_event_messages;
@@ -26,6 +28,7 @@ use serde::Serialize;
 use serde_json;
 use tokio::sync::oneshot;
 use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
@@ -42,30 +45,46 @@ use crate::client::ModelClient;
 use crate::client_common::Prompt;
 use crate::client_common::ResponseEvent;
 use crate::config::Config;
+use crate::config_types::GitIgnoredWrites;
 use crate::config_types::ShellEnvironmentPolicy;
+use crate::config_types::UnsandboxedCommand;
 use crate::conversation_history::ConversationHistory;
 use crate::conversation_manager::InitialHistory;
 use crate::environment_context::EnvironmentContext;
 use crate::error::CodexErr;
 use crate::error::Result as CodexResult;
+use crate::error::RetryDecision;
 use crate::error::SandboxErr;
 use crate::error::get_error_message_ui;
+use crate::error::retry_decision;
+use crate::exec::ArgvLimits;
+use crate::exec::CommandRewriter;
+use crate::exec::ExecOutcome;
 use crate::exec::ExecParams;
 use crate::exec::ExecToolCallOutput;
+use crate::exec::OutputEncoding;
 use crate::exec::SandboxType;
 use crate::exec::StdoutStream;
 use crate::exec::StreamOutput;
-use crate::exec::process_exec_tool_call;
+use crate::exec::Timeout;
+use crate::exec::effective_sandbox_type;
+use crate::exec::init_max_concurrent_exec;
+use crate::exec::rewrite_command;
+use crate::exec_backend::ExecBackend;
 use crate::exec_command::EXEC_COMMAND_TOOL_NAME;
 use crate::exec_command::ExecCommandParams;
 use crate::exec_command::ExecSessionManager;
 use crate::exec_command::WRITE_STDIN_TOOL_NAME;
 use crate::exec_command::WriteStdinParams;
 use crate::exec_env::create_env;
+use crate::exec_summary::OutputSummarizers;
+use crate::landlock::unenforced_exclusions_warning;
 use crate::mcp_connection_manager::McpConnectionManager;
 use crate::mcp_tool_call::handle_mcp_tool_call;
+use crate::model_capabilities::ModelCapabilities;
 use crate::model_family::find_family_for_model;
-use crate::openai_model_info::get_model_info;
+use crate::model_family::resolve_model_alias;
+use crate::model_registry::lookup_context_window;
 use crate::openai_tools::ApplyPatchToolArgs;
 use crate::openai_tools::ToolsConfig;
 use crate::openai_tools::ToolsConfigParams;
@@ -102,16 +121,21 @@ use crate::protocol::TaskCompleteEvent;
 use crate::protocol::TokenUsageInfo;
 use crate::protocol::TurnDiffEvent;
 use crate::protocol::WebSearchBeginEvent;
+use crate::rollout::CommandRewrite;
+use crate::rollout::ExecSandboxRecord;
+use crate::rollout::ExecTimingRecord;
+use crate::rollout::IgnoredWriteRecord;
 use crate::rollout::RolloutRecorder;
 use crate::rollout::RolloutRecorderParams;
+use crate::rollout::SandboxMeta;
 use crate::safety::SafetyCheck;
 use crate::safety::assess_command_safety;
 use crate::safety::assess_safety_for_untrusted_command;
+use crate::safety::get_platform_sandbox;
 use crate::shell;
 use crate::turn_diff_tracker::TurnDiffTracker;
 use crate::user_instructions::UserInstructions;
 use crate::user_notification::UserNotification;
-use crate::util::backoff;
 use codex_protocol::config_types::ReasoningEffort as ReasoningEffortConfig;
 use codex_protocol::config_types::ReasoningSummary as ReasoningSummaryConfig;
 use codex_protocol::custom_prompts::CustomPrompt;
@@ -162,6 +186,9 @@ pub(crate) const MODEL_FORMAT_MAX_LINES: usize = 256; // lines
 pub(crate) const MODEL_FORMAT_HEAD_LINES: usize = MODEL_FORMAT_MAX_LINES / 2;
 pub(crate) const MODEL_FORMAT_TAIL_LINES: usize = MODEL_FORMAT_MAX_LINES - MODEL_FORMAT_HEAD_LINES; // 128
 pub(crate) const MODEL_FORMAT_HEAD_BYTES: usize = MODEL_FORMAT_MAX_BYTES / 2;
+/// Sent to the model instead of the output of a successful command that
+/// printed nothing.
+pub(crate) const NO_OUTPUT_MESSAGE: &str = "(command completed successfully with no output)";
 
 impl Codex {
     /// Spawn a new [`Codex`] and initialize the session.
@@ -169,12 +196,19 @@ impl Codex {
         config: Config,
         auth_manager: Arc<AuthManager>,
         conversation_history: InitialHistory,
+        exec_backend: Arc<dyn ExecBackend>,
+        command_rewriter: Option<CommandRewriter>,
     ) -> CodexResult<CodexSpawnOk> {
         let (tx_sub, rx_sub) = async_channel::bounded(SUBMISSION_CHANNEL_CAPACITY);
         let (tx_event, rx_event) = async_channel::unbounded();
 
         let user_instructions = get_user_instructions(&config).await;
 
+        let mut config = config;
+        if config.fetch_model_info && config.model_context_window.is_none() {
+            config.model_context_window =
+                lookup_context_window(&config, &auth_manager.auth()).await;
+        }
         let config = Arc::new(config);
 
         let configure_session = ConfigureSession {
@@ -197,6 +231,8 @@ impl Codex {
             auth_manager.clone(),
             tx_event.clone(),
             conversation_history.clone(),
+            exec_backend,
+            command_rewriter,
         )
         .await
         .map_err(|e| {
@@ -289,8 +325,16 @@ pub(crate) struct Session {
     rollout: Mutex<Option<RolloutRecorder>>,
     state: Mutex<State>,
     codex_linux_sandbox_exe: Option<PathBuf>,
+    /// Runs shell tool calls; replaced with a fake in tests.
+    exec_backend: Arc<dyn ExecBackend>,
+    /// Applied to every shell tool call before it runs.
+    command_rewriter: Option<CommandRewriter>,
+    /// See [`StdoutStream::max_bytes_per_sec`].
+    output_stream_max_bytes_per_sec: Option<NonZeroU64>,
     user_shell: shell::Shell,
     show_raw_agent_reasoning: bool,
+    /// Summarize long output of known commands for the model.
+    output_summarizers: OutputSummarizers,
 }
 
 /// The context needed for a single turn of the conversation.
@@ -307,6 +351,18 @@ pub(crate) struct TurnContext {
     pub(crate) sandbox_policy: SandboxPolicy,
     pub(crate) shell_environment_policy: ShellEnvironmentPolicy,
     pub(crate) tools_config: ToolsConfig,
+    pub(crate) git_ignored_writes: GitIgnoredWrites,
+    pub(crate) unsandboxed_commands: Vec<UnsandboxedCommand>,
+    pub(crate) denied_programs: Vec<String>,
+    pub(crate) output_encoding: OutputEncoding,
+    /// Commands prepended to every shell command when it is wrapped in the
+    /// user's shell; see [`maybe_translate_shell_command`].
+    pub(crate) exec_prelude: Vec<String>,
+    /// Log each stderr line of shell commands as it is read; see
+    /// [`ExecParams::trace_stderr`].
+    pub(crate) trace_exec_stderr: bool,
+    pub(crate) argv_limits: ArgvLimits,
+    pub(crate) seatbelt_base_policy: Option<String>,
 }
 
 impl TurnContext {
@@ -361,6 +417,8 @@ impl Session {
         auth_manager: Arc<AuthManager>,
         tx_event: Sender<Event>,
         initial_history: InitialHistory,
+        exec_backend: Arc<dyn ExecBackend>,
+        command_rewriter: Option<CommandRewriter>,
     ) -> anyhow::Result<(Arc<Self>, TurnContext)> {
         let ConfigureSession {
             provider,
@@ -375,6 +433,7 @@ impl Session {
             cwd,
         } = configure_session;
         debug!("Configuring session: model={model}; provider={provider:?}");
+        let max_concurrent_exec = init_max_concurrent_exec(config.max_concurrent_exec);
         if !cwd.is_absolute() {
             return Err(anyhow::anyhow!("cwd is not absolute: {cwd:?}"));
         }
@@ -391,6 +450,17 @@ impl Session {
                 resumed_history.conversation_id,
                 RolloutRecorderParams::resume(resumed_history.rollout_path.clone()),
             ),
+            InitialHistory::Branched(branched_history) => {
+                let conversation_id = ConversationId::default();
+                (
+                    conversation_id,
+                    RolloutRecorderParams::branch(
+                        conversation_id,
+                        user_instructions.clone(),
+                        branched_history.parent.clone(),
+                    ),
+                )
+            }
         };
 
         // Error messages to dispatch after SessionConfigured is sent.
@@ -449,6 +519,28 @@ impl Session {
             }
         }
 
+        if max_concurrent_exec != config.max_concurrent_exec.max(1) {
+            let message = format!(
+                "max_concurrent_exec = {} is ignored: an earlier session in this process already set it to {max_concurrent_exec}",
+                config.max_concurrent_exec
+            );
+            warn!("{message}");
+            post_session_configured_error_events.push(Event {
+                id: INITIAL_SUBMIT_ID.to_owned(),
+                msg: EventMsg::BackgroundEvent(BackgroundEventEvent { message }),
+            });
+        }
+
+        if get_platform_sandbox() == Some(SandboxType::LinuxSeccomp)
+            && let Some(message) = unenforced_exclusions_warning(&sandbox_policy)
+        {
+            warn!("{message}");
+            post_session_configured_error_events.push(Event {
+                id: INITIAL_SUBMIT_ID.to_owned(),
+                msg: EventMsg::BackgroundEvent(BackgroundEventEvent { message }),
+            });
+        }
+
         // Now that the conversation id is final (may have been updated by resume),
         // construct the model client.
         let client = ModelClient::new(
@@ -476,6 +568,14 @@ impl Session {
             approval_policy,
             sandbox_policy,
             shell_environment_policy: config.shell_environment_policy.clone(),
+            git_ignored_writes: config.git_ignored_writes,
+            unsandboxed_commands: config.unsandboxed_commands.clone(),
+            denied_programs: config.denied_programs.clone(),
+            output_encoding: config.output_encoding,
+            exec_prelude: config.exec_prelude.clone(),
+            trace_exec_stderr: config.trace_exec_stderr,
+            argv_limits: config.argv_limits,
+            seatbelt_base_policy: config.seatbelt_base_policy.clone(),
             cwd,
         };
 
@@ -488,8 +588,12 @@ impl Session {
             state: Mutex::new(state),
             rollout: Mutex::new(Some(rollout_recorder)),
             codex_linux_sandbox_exe: config.codex_linux_sandbox_exe.clone(),
+            exec_backend,
+            command_rewriter,
+            output_stream_max_bytes_per_sec: config.output_stream_max_bytes_per_sec,
             user_shell: default_shell,
             show_raw_agent_reasoning: config.show_raw_agent_reasoning,
+            output_summarizers: OutputSummarizers::default(),
         });
 
         // Dispatch the SessionConfiguredEvent first and then report any errors.
@@ -500,6 +604,9 @@ impl Session {
             InitialHistory::Resumed(resumed_history) => {
                 Some(sess.build_initial_messages(&resumed_history.history))
             }
+            InitialHistory::Branched(branched_history) => {
+                Some(sess.build_initial_messages(&branched_history.history))
+            }
         };
 
         let events = std::iter::once(Event {
@@ -556,6 +663,15 @@ impl Session {
                 self.record_initial_history_from_items(resumed_history.history)
                     .await;
             }
+            // Branching before the first item leaves nothing to replay, so
+            // seed the branch the same way a brand new session is seeded.
+            InitialHistory::Branched(branched_history) if branched_history.history.is_empty() => {
+                self.record_initial_history_new(turn_context).await;
+            }
+            InitialHistory::Branched(branched_history) => {
+                self.record_initial_history_from_items(branched_history.history)
+                    .await;
+            }
         }
     }
 
@@ -716,6 +832,48 @@ impl Session {
         }
     }
 
+    /// Notes in the rollout which sandbox a shell command runs under.
+    async fn record_exec_sandbox(&self, record: ExecSandboxRecord) {
+        let recorder = {
+            let guard = self.rollout.lock_unchecked();
+            guard.as_ref().cloned()
+        };
+
+        if let Some(rec) = recorder
+            && let Err(e) = rec.record_exec_sandbox(record).await
+        {
+            error!("failed to record exec sandbox: {e:#}");
+        }
+    }
+
+    /// Notes in the rollout when a shell command started and finished.
+    async fn record_exec_timing(&self, record: ExecTimingRecord) {
+        let recorder = {
+            let guard = self.rollout.lock_unchecked();
+            guard.as_ref().cloned()
+        };
+
+        if let Some(rec) = recorder
+            && let Err(e) = rec.record_exec_timing(record).await
+        {
+            error!("failed to record exec timing: {e:#}");
+        }
+    }
+
+    /// Notes in the rollout that a patch targeted git-ignored paths.
+    pub(crate) async fn record_ignored_write(&self, record: IgnoredWriteRecord) {
+        let recorder = {
+            let guard = self.rollout.lock_unchecked();
+            guard.as_ref().cloned()
+        };
+
+        if let Some(rec) = recorder
+            && let Err(e) = rec.record_ignored_write(record).await
+        {
+            error!("failed to record ignored write: {e:#}");
+        }
+    }
+
     async fn on_exec_command_begin(
         &self,
         turn_diff_tracker: &mut TurnDiffTracker,
@@ -727,6 +885,7 @@ impl Session {
             command_for_display,
             cwd,
             apply_patch,
+            ..
         } = exec_command_context;
         let msg = match apply_patch {
             Some(ApplyPatchCommandContext {
@@ -772,6 +931,8 @@ impl Session {
             aggregated_output,
             duration,
             exit_code,
+            success,
+            ..
         } = output;
         // Send full stdout/stderr to clients; do not truncate.
         let stdout = stdout.text.clone();
@@ -784,7 +945,7 @@ impl Session {
                 call_id: call_id.to_string(),
                 stdout,
                 stderr,
-                success: *exit_code == 0,
+                success: *success,
             })
         } else {
             EventMsg::ExecCommandEnd(ExecCommandEndEvent {
@@ -832,17 +993,42 @@ impl Session {
         let sub_id = begin_ctx.sub_id.clone();
         let call_id = begin_ctx.call_id.clone();
 
+        let mut params = exec_args.params;
+        // Rewrite here rather than in `process_exec_tool_call` so the rollout
+        // records the command that actually runs. `apply_patch` invocations
+        // are Codex's own and left alone.
+        if !is_apply_patch && params.command_rewriter.is_none() {
+            params.command_rewriter = self.command_rewriter.clone();
+        }
+        let command_rewrite = rewrite_command(&mut params).map(|original| CommandRewrite {
+            original,
+            rewritten: params.command.clone(),
+        });
+
+        let sandbox_type = effective_sandbox_type(&params, exec_args.sandbox_type);
+        self.record_exec_sandbox(ExecSandboxRecord {
+            call_id: call_id.clone(),
+            sandbox: SandboxMeta::new(sandbox_type, exec_args.sandbox_policy, &params.cwd),
+            unsandboxed_command: sandbox_type != exec_args.sandbox_type,
+            exec_prelude: begin_ctx.exec_prelude_applied,
+            command_rewrite,
+        })
+        .await;
         self.on_exec_command_begin(turn_diff_tracker, begin_ctx.clone())
             .await;
 
-        let result = process_exec_tool_call(
-            exec_args.params,
-            exec_args.sandbox_type,
-            exec_args.sandbox_policy,
-            exec_args.codex_linux_sandbox_exe,
-            exec_args.stdout_stream,
-        )
-        .await;
+        params.call_id.get_or_insert_with(|| call_id.clone());
+        let started_at = SystemTime::now();
+        let result = self
+            .exec_backend
+            .exec(
+                params,
+                exec_args.sandbox_type,
+                exec_args.sandbox_policy,
+                exec_args.codex_linux_sandbox_exe,
+                exec_args.stdout_stream,
+            )
+            .await;
 
         let output_stderr;
         let borrowed: &ExecToolCallOutput = match &result {
@@ -850,10 +1036,13 @@ impl Session {
             Err(e) => {
                 output_stderr = ExecToolCallOutput {
                     exit_code: -1,
-                    stdout: StreamOutput::new(String::new()),
                     stderr: StreamOutput::new(get_error_message_ui(e)),
                     aggregated_output: StreamOutput::new(get_error_message_ui(e)),
-                    duration: Duration::default(),
+                    call_id: call_id.clone(),
+                    success: false,
+                    started_at,
+                    finished_at: SystemTime::now(),
+                    ..Default::default()
                 };
                 &output_stderr
             }
@@ -866,6 +1055,27 @@ impl Session {
             is_apply_patch,
         )
         .await;
+        if let Some(audited) = &borrowed.audited_syscalls
+            && !audited.is_empty()
+        {
+            let mut would_deny: Vec<String> = Vec::new();
+            for syscall in audited.iter().map(ToString::to_string) {
+                if !would_deny.contains(&syscall) {
+                    would_deny.push(syscall);
+                }
+            }
+            self.notify_background_event(
+                &sub_id,
+                format!("sandbox audit: would have denied {}", would_deny.join(", ")),
+            )
+            .await;
+        }
+        self.record_exec_timing(ExecTimingRecord::new(
+            call_id,
+            borrowed.started_at,
+            borrowed.finished_at,
+        ))
+        .await;
 
         result
     }
@@ -873,7 +1083,7 @@ impl Session {
     /// Helper that emits a BackgroundEvent with the given message. This keeps
     /// the call‑sites terse so adding more diagnostics does not clutter the
     /// core agent logic.
-    async fn notify_background_event(&self, sub_id: &str, message: impl Into<String>) {
+    pub(crate) async fn notify_background_event(&self, sub_id: &str, message: impl Into<String>) {
         let event = Event {
             id: sub_id.to_string(),
             msg: EventMsg::BackgroundEvent(BackgroundEventEvent {
@@ -986,6 +1196,8 @@ pub(crate) struct ExecCommandContext {
     pub(crate) command_for_display: Vec<String>,
     pub(crate) cwd: PathBuf,
     pub(crate) apply_patch: Option<ApplyPatchCommandContext>,
+    /// Whether the session's `exec_prelude` was prepended to the command.
+    pub(crate) exec_prelude_applied: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -999,6 +1211,8 @@ pub(crate) struct AgentTask {
     sess: Arc<Session>,
     sub_id: String,
     handle: AbortHandle,
+    /// Cancelled on abort so in-flight model requests drop their connection.
+    cancel: CancellationToken,
 }
 
 impl AgentTask {
@@ -1008,17 +1222,20 @@ impl AgentTask {
         sub_id: String,
         input: Vec<InputItem>,
     ) -> Self {
+        let cancel = CancellationToken::new();
         let handle = {
             let sess = sess.clone();
             let sub_id = sub_id.clone();
             let tc = Arc::clone(&turn_context);
-            tokio::spawn(async move { run_task(sess, tc.as_ref(), sub_id, input).await })
+            let cancel = cancel.clone();
+            tokio::spawn(async move { run_task(sess, tc.as_ref(), sub_id, input, cancel).await })
                 .abort_handle()
         };
         Self {
             sess,
             sub_id,
             handle,
+            cancel,
         }
     }
 
@@ -1029,12 +1246,22 @@ impl AgentTask {
         input: Vec<InputItem>,
         compact_instructions: String,
     ) -> Self {
+        let cancel = CancellationToken::new();
         let handle = {
             let sess = sess.clone();
             let sub_id = sub_id.clone();
             let tc = Arc::clone(&turn_context);
+            let cancel = cancel.clone();
             tokio::spawn(async move {
-                run_compact_task(sess, tc.as_ref(), sub_id, input, compact_instructions).await
+                run_compact_task(
+                    sess,
+                    tc.as_ref(),
+                    sub_id,
+                    input,
+                    compact_instructions,
+                    cancel,
+                )
+                .await
             })
             .abort_handle()
         };
@@ -1042,12 +1269,14 @@ impl AgentTask {
             sess,
             sub_id,
             handle,
+            cancel,
         }
     }
 
     fn abort(self, reason: TurnAbortReason) {
         // TOCTOU?
         if !self.handle.is_finished() {
+            self.cancel.cancel();
             self.handle.abort();
             let event = Event {
                 id: self.sub_id,
@@ -1090,6 +1319,7 @@ async fn submission_loop(
 
                 // Effective model + family
                 let (effective_model, effective_family) = if let Some(m) = model {
+                    let m = resolve_model_alias(&m, &config.model_aliases).to_string();
                     let fam =
                         find_family_for_model(&m).unwrap_or_else(|| config.model_family.clone());
                     (m, fam)
@@ -1107,8 +1337,10 @@ async fn submission_loop(
                 let mut updated_config = (*config).clone();
                 updated_config.model = effective_model.clone();
                 updated_config.model_family = effective_family.clone();
-                if let Some(model_info) = get_model_info(&effective_family) {
-                    updated_config.model_context_window = Some(model_info.context_window);
+                if let Some(context_window) =
+                    ModelCapabilities::for_family(&effective_family).known_context_window()
+                {
+                    updated_config.model_context_window = Some(context_window);
                 }
 
                 let client = ModelClient::new(
@@ -1145,6 +1377,14 @@ async fn submission_loop(
                     approval_policy: new_approval_policy,
                     sandbox_policy: new_sandbox_policy.clone(),
                     shell_environment_policy: prev.shell_environment_policy.clone(),
+                    git_ignored_writes: prev.git_ignored_writes,
+                    unsandboxed_commands: prev.unsandboxed_commands.clone(),
+                    denied_programs: prev.denied_programs.clone(),
+                    output_encoding: prev.output_encoding,
+                    exec_prelude: prev.exec_prelude.clone(),
+                    trace_exec_stderr: prev.trace_exec_stderr,
+                    argv_limits: prev.argv_limits,
+                    seatbelt_base_policy: prev.seatbelt_base_policy.clone(),
                     cwd: new_cwd.clone(),
                 };
 
@@ -1186,6 +1426,7 @@ async fn submission_loop(
                     let auth_manager = turn_context.client.get_auth_manager();
 
                     // Derive a model family for the requested model; fall back to the session's.
+                    let model = resolve_model_alias(&model, &config.model_aliases).to_string();
                     let model_family = find_family_for_model(&model)
                         .unwrap_or_else(|| config.model_family.clone());
 
@@ -1193,8 +1434,10 @@ async fn submission_loop(
                     let mut per_turn_config = (*config).clone();
                     per_turn_config.model = model.clone();
                     per_turn_config.model_family = model_family.clone();
-                    if let Some(model_info) = get_model_info(&model_family) {
-                        per_turn_config.model_context_window = Some(model_info.context_window);
+                    if let Some(context_window) =
+                        ModelCapabilities::for_family(&model_family).known_context_window()
+                    {
+                        per_turn_config.model_context_window = Some(context_window);
                     }
 
                     // Build a new client with per‑turn reasoning settings.
@@ -1226,6 +1469,14 @@ async fn submission_loop(
                         approval_policy,
                         sandbox_policy,
                         shell_environment_policy: turn_context.shell_environment_policy.clone(),
+                        git_ignored_writes: turn_context.git_ignored_writes,
+                        unsandboxed_commands: turn_context.unsandboxed_commands.clone(),
+                        denied_programs: turn_context.denied_programs.clone(),
+                        output_encoding: turn_context.output_encoding,
+                        exec_prelude: turn_context.exec_prelude.clone(),
+                        trace_exec_stderr: turn_context.trace_exec_stderr,
+                        argv_limits: turn_context.argv_limits,
+                        seatbelt_base_policy: turn_context.seatbelt_base_policy.clone(),
                         cwd,
                     };
                     // TODO: record the new environment context in the conversation history
@@ -1419,6 +1670,7 @@ async fn run_task(
     turn_context: &TurnContext,
     sub_id: String,
     input: Vec<InputItem>,
+    cancel: CancellationToken,
 ) {
     if input.is_empty() {
         return;
@@ -1479,6 +1731,7 @@ async fn run_task(
             &mut turn_diff_tracker,
             sub_id.clone(),
             turn_input,
+            &cancel,
         )
         .await
         {
@@ -1623,6 +1876,7 @@ async fn run_turn(
     turn_diff_tracker: &mut TurnDiffTracker,
     sub_id: String,
     input: Vec<ResponseItem>,
+    cancel: &CancellationToken,
 ) -> CodexResult<Vec<ProcessedResponseItem>> {
     let tools = get_openai_tools(
         &turn_context.tools_config,
@@ -1636,23 +1890,36 @@ async fn run_turn(
     };
 
     let mut retries = 0;
+    let mut retry_backoff = turn_context.client.get_provider().retry_backoff();
     loop {
-        match try_run_turn(sess, turn_context, turn_diff_tracker, &sub_id, &prompt).await {
+        let mut emitted_tool_call = false;
+        match try_run_turn(
+            sess,
+            turn_context,
+            turn_diff_tracker,
+            &sub_id,
+            &prompt,
+            cancel,
+            &mut emitted_tool_call,
+        )
+        .await
+        {
             Ok(output) => return Ok(output),
-            Err(CodexErr::Interrupted) => return Err(CodexErr::Interrupted),
-            Err(CodexErr::EnvVar(var)) => return Err(CodexErr::EnvVar(var)),
-            Err(e @ (CodexErr::UsageLimitReached(_) | CodexErr::UsageNotIncluded)) => {
+            Err(e) if retry_decision(&e, emitted_tool_call) == RetryDecision::DoNotRetry => {
+                if emitted_tool_call {
+                    warn!("not retrying turn after a tool call already ran: {e}");
+                }
                 return Err(e);
             }
             Err(e) => {
                 // Use the configured provider-specific stream retry budget.
                 let max_retries = turn_context.client.get_provider().stream_max_retries();
-                if retries < max_retries {
+                let delay = match e {
+                    CodexErr::Stream(_, Some(delay)) => delay,
+                    _ => retry_backoff.delay(retries + 1),
+                };
+                if retries < max_retries && retry_backoff.may_retry_after(delay) {
                     retries += 1;
-                    let delay = match e {
-                        CodexErr::Stream(_, Some(delay)) => delay,
-                        _ => backoff(retries),
-                    };
                     warn!(
                         "stream disconnected - retrying turn ({retries}/{max_retries} in {delay:?})...",
                     );
@@ -1693,6 +1960,8 @@ async fn try_run_turn(
     turn_diff_tracker: &mut TurnDiffTracker,
     sub_id: &str,
     prompt: &Prompt,
+    cancel: &CancellationToken,
+    emitted_tool_call: &mut bool,
 ) -> CodexResult<Vec<ProcessedResponseItem>> {
     // call_ids that are part of this response.
     let completed_call_ids = prompt
@@ -1749,7 +2018,11 @@ async fn try_run_turn(
         })
     };
 
-    let mut stream = turn_context.client.clone().stream(&prompt).await?;
+    let mut stream = turn_context
+        .client
+        .clone()
+        .stream_with_cancellation(&prompt, cancel)
+        .await?;
 
     let mut output = Vec::new();
 
@@ -1779,6 +2052,14 @@ async fn try_run_turn(
         match event {
             ResponseEvent::Created => {}
             ResponseEvent::OutputItemDone(item) => {
+                // Set before the call runs: once it has started, retrying the
+                // turn would run it again.
+                *emitted_tool_call |= matches!(
+                    item,
+                    ResponseItem::FunctionCall { .. }
+                        | ResponseItem::LocalShellCall { .. }
+                        | ResponseItem::CustomToolCall { .. }
+                );
                 let response = handle_response_item(
                     sess,
                     turn_context,
@@ -1874,6 +2155,7 @@ async fn run_compact_task(
     sub_id: String,
     input: Vec<InputItem>,
     compact_instructions: String,
+    cancel: CancellationToken,
 ) {
     let model_context_window = turn_context.client.get_model_context_window();
     let start_event = Event {
@@ -1898,17 +2180,22 @@ async fn run_compact_task(
 
     let max_retries = turn_context.client.get_provider().stream_max_retries();
     let mut retries = 0;
+    let mut retry_backoff = turn_context.client.get_provider().retry_backoff();
 
     loop {
-        let attempt_result = drain_to_completed(&sess, turn_context, &sub_id, &prompt).await;
+        let attempt_result =
+            drain_to_completed(&sess, turn_context, &sub_id, &prompt, &cancel).await;
 
         match attempt_result {
             Ok(()) => break,
             Err(CodexErr::Interrupted) => return,
             Err(e) => {
-                if retries < max_retries {
+                let delay = retry_backoff.delay(retries + 1);
+                if retries < max_retries
+                    && retry_decision(&e, false) == RetryDecision::Retry
+                    && retry_backoff.may_retry_after(delay)
+                {
                     retries += 1;
-                    let delay = backoff(retries);
                     sess.notify_stream_error(
                         &sub_id,
                         format!(
@@ -2144,10 +2431,7 @@ async fn handle_function_call(
             let exec_params = ExecParams {
                 command: vec!["apply_patch".to_string(), args.input.clone()],
                 cwd: turn_context.cwd.clone(),
-                timeout_ms: None,
-                env: HashMap::new(),
-                with_escalated_permissions: None,
-                justification: None,
+                ..Default::default()
             };
             handle_container_exec_with_params(
                 exec_params,
@@ -2248,10 +2532,7 @@ async fn handle_custom_tool_call(
             let exec_params = ExecParams {
                 command: vec!["apply_patch".to_string(), input.clone()],
                 cwd: turn_context.cwd.clone(),
-                timeout_ms: None,
-                env: HashMap::new(),
-                with_escalated_permissions: None,
-                justification: None,
+                ..Default::default()
             };
             let resp = handle_container_exec_with_params(
                 exec_params,
@@ -2289,10 +2570,17 @@ fn to_exec_params(params: ShellToolCallParams, turn_context: &TurnContext) -> Ex
     ExecParams {
         command: params.command,
         cwd: turn_context.resolve_path(params.workdir.clone()),
-        timeout_ms: params.timeout_ms,
+        timeout: Timeout::from_ms(params.timeout_ms),
         env: create_env(&turn_context.shell_environment_policy),
         with_escalated_permissions: params.with_escalated_permissions,
         justification: params.justification,
+        unsandboxed_commands: turn_context.unsandboxed_commands.clone(),
+        denied_programs: turn_context.denied_programs.clone(),
+        output_encoding: turn_context.output_encoding,
+        trace_stderr: turn_context.trace_exec_stderr,
+        argv_limits: turn_context.argv_limits,
+        seatbelt_base_policy: turn_context.seatbelt_base_policy.clone(),
+        ..Default::default()
     }
 }
 
@@ -2338,22 +2626,27 @@ fn should_translate_shell_command(
         )
 }
 
+/// Wraps the command in the user's shell when needed, prepending the
+/// session's `exec_prelude` if the shell supports it. Also returns whether
+/// the prelude was applied.
 fn maybe_translate_shell_command(
     params: ExecParams,
     sess: &Session,
     turn_context: &TurnContext,
-) -> ExecParams {
+) -> (ExecParams, bool) {
     let should_translate =
         should_translate_shell_command(&sess.user_shell, &turn_context.shell_environment_policy);
 
     if should_translate
-        && let Some(command) = sess
-            .user_shell
-            .format_default_shell_invocation(params.command.clone())
+        && let Some((command, exec_prelude_applied)) =
+            sess.user_shell.format_shell_invocation_with_prelude(
+                params.command.clone(),
+                &turn_context.exec_prelude,
+            )
     {
-        return ExecParams { command, ..params };
+        return (ExecParams { command, ..params }, exec_prelude_applied);
     }
-    params
+    (params, false)
 }
 
 async fn handle_container_exec_with_params(
@@ -2418,10 +2711,10 @@ async fn handle_container_exec_with_params(
                     patch.clone(),
                 ],
                 cwd: cwd.clone(),
-                timeout_ms: params.timeout_ms,
-                env: HashMap::new(),
+                timeout: params.timeout,
                 with_escalated_permissions: params.with_escalated_permissions,
                 justification: params.justification.clone(),
+                ..Default::default()
             };
             let safety = if *user_explicitly_approved_this_action {
                 SafetyCheck::AutoApprove {
@@ -2500,6 +2793,7 @@ async fn handle_container_exec_with_params(
         }
     };
 
+    let (params, exec_prelude_applied) = maybe_translate_shell_command(params, sess, turn_context);
     let exec_command_context = ExecCommandContext {
         sub_id: sub_id.clone(),
         call_id: call_id.clone(),
@@ -2514,9 +2808,9 @@ async fn handle_container_exec_with_params(
                 changes: convert_apply_patch_to_protocol(&action),
             },
         ),
+        exec_prelude_applied,
     };
 
-    let params = maybe_translate_shell_command(params, sess, turn_context);
     let output_result = sess
         .run_exec_with_events(
             turn_diff_tracker,
@@ -2533,6 +2827,7 @@ async fn handle_container_exec_with_params(
                         sub_id: sub_id.clone(),
                         call_id: call_id.clone(),
                         tx_event: sess.tx_event.clone(),
+                        max_bytes_per_sec: sess.output_stream_max_bytes_per_sec,
                     })
                 },
             },
@@ -2541,10 +2836,8 @@ async fn handle_container_exec_with_params(
 
     match output_result {
         Ok(output) => {
-            let ExecToolCallOutput { exit_code, .. } = &output;
-
-            let is_success = *exit_code == 0;
-            let content = format_exec_output(&output);
+            let is_success = output.success;
+            let content = format_exec_output(&output, &params.command, &sess.output_summarizers);
             ResponseInputItem::FunctionCallOutput {
                 call_id: call_id.clone(),
                 output: FunctionCallOutputPayload {
@@ -2606,18 +2899,75 @@ async fn handle_sandbox_error(
     }
 
     // similarly, if the command timed out, we can simply return this failure to the model
-    if matches!(error, SandboxErr::Timeout) {
+    if matches!(error, SandboxErr::Timeout { .. }) {
         return ResponseInputItem::FunctionCallOutput {
             call_id,
             output: FunctionCallOutputPayload {
                 content: format!(
                     "command timed out after {} milliseconds",
-                    params.timeout_duration().as_millis()
+                    params.timeout_duration().unwrap_or_default().as_millis()
                 ),
                 success: Some(false),
             },
         };
     }
+    if matches!(error, SandboxErr::IdleTimeout { .. }) {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!(
+                    "command was killed after producing no output for {} milliseconds",
+                    params.idle_timeout_ms.unwrap_or_default()
+                ),
+                success: Some(false),
+            },
+        };
+    }
+    if let SandboxErr::Crashed { code, cause, .. } = &error {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("command crashed: {cause} (exit code {code:#010X})"),
+                success: Some(false),
+            },
+        };
+    }
+    // Running it again without the sandbox would not help either.
+    if let SandboxErr::OutOfMemory { output } = &error {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!(
+                    "command was killed by the out-of-memory killer; it needs less memory \
+                     (e.g. fewer parallel jobs) to succeed\n{}",
+                    output.aggregated_output.text
+                ),
+                success: Some(false),
+            },
+        };
+    }
+
+    // The limit also applies without the sandbox, so a retry would not help.
+    if let SandboxErr::OpenFileLimit { output, .. } = &error {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: format!("{error}\n{}", output.aggregated_output.text),
+                success: Some(false),
+            },
+        };
+    }
+
+    // The policy cannot be honored on this host; no retry would change that.
+    if matches!(error, SandboxErr::RunAsUnavailable { .. }) {
+        return ResponseInputItem::FunctionCallOutput {
+            call_id,
+            output: FunctionCallOutputPayload {
+                content: error.to_string(),
+                success: Some(false),
+            },
+        };
+    }
 
     // Note that when `error` is `SandboxErr::Denied`, it could be a false
     // positive. That is, it may have exited with a non-zero exit code, not
@@ -2631,13 +2981,18 @@ async fn handle_sandbox_error(
     sess.notify_background_event(&sub_id, format!("Execution failed: {error}"))
         .await;
 
+    let reason = if matches!(error, SandboxErr::NetworkRequired) {
+        "command requires network access; run without sandbox?"
+    } else {
+        "command failed; retry without sandbox?"
+    };
     let rx_approve = sess
         .request_command_approval(
             sub_id.clone(),
             call_id.clone(),
             params.command.clone(),
             cwd.clone(),
-            Some("command failed; retry without sandbox?".to_string()),
+            Some(reason.to_string()),
         )
         .await;
 
@@ -2670,6 +3025,7 @@ async fn handle_sandbox_error(
                                 sub_id: sub_id.clone(),
                                 call_id: call_id.clone(),
                                 tx_event: sess.tx_event.clone(),
+                                max_bytes_per_sec: sess.output_stream_max_bytes_per_sec,
                             })
                         },
                     },
@@ -2678,10 +3034,12 @@ async fn handle_sandbox_error(
 
             match retry_output_result {
                 Ok(retry_output) => {
-                    let ExecToolCallOutput { exit_code, .. } = &retry_output;
-
-                    let is_success = *exit_code == 0;
-                    let content = format_exec_output(&retry_output);
+                    let is_success = retry_output.success;
+                    let content = format_exec_output(
+                        &retry_output,
+                        &params.command,
+                        &sess.output_summarizers,
+                    );
 
                     ResponseInputItem::FunctionCallOutput {
                         call_id: call_id.clone(),
@@ -2723,7 +3081,7 @@ fn format_exec_output_str(exec_output: &ExecToolCallOutput) -> String {
 
     let s = aggregated_output.text.as_str();
     let total_lines = s.lines().count();
-    if s.len() <= MODEL_FORMAT_MAX_BYTES && total_lines <= MODEL_FORMAT_MAX_LINES {
+    if fits_model_format(s) {
         return s.to_string();
     }
 
@@ -2775,6 +3133,10 @@ fn format_exec_output_str(exec_output: &ExecToolCallOutput) -> String {
     result
 }
 
+fn fits_model_format(s: &str) -> bool {
+    s.len() <= MODEL_FORMAT_MAX_BYTES && s.lines().count() <= MODEL_FORMAT_MAX_LINES
+}
+
 // Truncate a &str to a byte budget at a char boundary (prefix)
 #[inline]
 fn take_bytes_at_char_boundary(s: &str, maxb: usize) -> &str {
@@ -2814,11 +3176,19 @@ fn take_last_bytes_at_char_boundary(s: &str, maxb: usize) -> &str {
     &s[start..]
 }
 
-/// Exec output is a pre-serialized JSON payload
-fn format_exec_output(exec_output: &ExecToolCallOutput) -> String {
+/// Exec output is a pre-serialized JSON payload. When the output had to be
+/// truncated (when captured or for the model), a summary from `summarizers`
+/// is appended for the program `command` runs, if any. The summary covers the
+/// captured output, which lacks whatever was dropped when capturing it.
+fn format_exec_output(
+    exec_output: &ExecToolCallOutput,
+    command: &[String],
+    summarizers: &OutputSummarizers,
+) -> String {
     let ExecToolCallOutput {
         exit_code,
         duration,
+        aggregated_output,
         ..
     } = exec_output;
 
@@ -2837,7 +3207,23 @@ fn format_exec_output(exec_output: &ExecToolCallOutput) -> String {
     // round to 1 decimal place
     let duration_seconds = ((duration.as_secs_f32()) * 10.0).round() / 10.0;
 
-    let formatted_output = format_exec_output_str(exec_output);
+    // An empty string reads like missing data to the model, so say that the
+    // command succeeded without output. Clients still get the empty streams.
+    let formatted_output = if exec_output.success
+        && exec_output.outcome == ExecOutcome::Exited
+        && exec_output.is_empty_output()
+    {
+        NO_OUTPUT_MESSAGE.to_string()
+    } else {
+        let mut formatted_output = format_exec_output_str(exec_output);
+        let truncated = aggregated_output.truncated_after_lines.is_some()
+            || !fits_model_format(&aggregated_output.text);
+        if truncated && let Some(summary) = summarizers.summarize(command, &aggregated_output.text)
+        {
+            formatted_output.push_str(&format!("\n\n[summary of the captured output]\n{summary}"));
+        }
+        formatted_output
+    };
 
     let payload = ExecOutput {
         output: &formatted_output,
@@ -2876,8 +3262,13 @@ async fn drain_to_completed(
     turn_context: &TurnContext,
     sub_id: &str,
     prompt: &Prompt,
+    cancel: &CancellationToken,
 ) -> CodexResult<()> {
-    let mut stream = turn_context.client.clone().stream(prompt).await?;
+    let mut stream = turn_context
+        .client
+        .clone()
+        .stream_with_cancellation(prompt, cancel)
+        .await?;
     loop {
         let maybe_event = stream.next().await;
         let Some(event) = maybe_event else {
@@ -3054,11 +3445,9 @@ mod tests {
         let full = lines.join("\n");
 
         let exec = ExecToolCallOutput {
-            exit_code: 0,
-            stdout: StreamOutput::new(String::new()),
-            stderr: StreamOutput::new(String::new()),
             aggregated_output: StreamOutput::new(full.clone()),
             duration: StdDuration::from_secs(1),
+            ..Default::default()
         };
 
         let out = format_exec_output_str(&exec);
@@ -3096,11 +3485,9 @@ mod tests {
             .join("\n");
 
         let exec = ExecToolCallOutput {
-            exit_code: 0,
-            stdout: StreamOutput::new(String::new()),
-            stderr: StreamOutput::new(String::new()),
             aggregated_output: StreamOutput::new(full.clone()),
             duration: StdDuration::from_secs(1),
+            ..Default::default()
         };
 
         let out = format_exec_output_str(&exec);
@@ -3123,6 +3510,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn truncated_exec_output_gets_a_summary_of_known_commands() {
+        let mut full = String::from("error[E0308]: mismatched types\n");
+        full.push_str(&"   Compiling dep\n".repeat(1000));
+        full.push_str("test result: FAILED. 1 passed; 1 failed");
+
+        let exec = ExecToolCallOutput {
+            exit_code: 101,
+            aggregated_output: StreamOutput::new(full),
+            duration: StdDuration::from_secs(1),
+            success: false,
+            ..Default::default()
+        };
+        let summarizers = OutputSummarizers::default();
+        let command = vec!["cargo".to_string(), "test".to_string()];
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&format_exec_output(&exec, &command, &summarizers))
+                .expect("valid JSON");
+        let output = payload["output"].as_str().expect("output is a string");
+        assert!(output.starts_with(&format_exec_output_str(&exec)));
+        assert!(output.ends_with(
+            "[summary of the captured output]\n\
+             1 error(s):\n  error[E0308]: mismatched types\n\
+             test result: FAILED. 1 passed; 1 failed"
+        ));
+
+        // Output that fits is passed through as is.
+        let short = ExecToolCallOutput {
+            aggregated_output: StreamOutput::new("error: oops\n".to_string()),
+            ..exec
+        };
+        let payload: serde_json::Value =
+            serde_json::from_str(&format_exec_output(&short, &command, &summarizers))
+                .expect("valid JSON");
+        assert_eq!(payload["output"], "error: oops\n");
+    }
+
+    #[test]
+    fn successful_command_without_output_is_reported_as_such() {
+        let exec = ExecToolCallOutput {
+            duration: StdDuration::from_secs(1),
+            ..Default::default()
+        };
+        assert!(exec.is_empty_output());
+        let summarizers = OutputSummarizers::default();
+        let command = vec!["mkdir".to_string(), "out".to_string()];
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&format_exec_output(&exec, &command, &summarizers))
+                .expect("valid JSON");
+        assert_eq!(payload["output"], NO_OUTPUT_MESSAGE);
+        // The raw output stays empty for clients.
+        assert_eq!(format_exec_output_str(&exec), "");
+
+        // A failure without output is left empty; the exit code tells.
+        let failed = ExecToolCallOutput {
+            exit_code: 1,
+            success: false,
+            ..exec
+        };
+        let payload: serde_json::Value =
+            serde_json::from_str(&format_exec_output(&failed, &command, &summarizers))
+                .expect("valid JSON");
+        assert_eq!(payload["output"], "");
+    }
+
     #[test]
     fn falls_back_to_content_when_structured_is_null() {
         let ctr = CallToolResult {
@@ -1,20 +1,30 @@
 use crate::config_profile::ConfigProfile;
+use crate::config_types::GitIgnoredWrites;
 use crate::config_types::History;
 use crate::config_types::McpServerConfig;
 use crate::config_types::ReasoningSummaryFormat;
+use crate::config_types::RolloutSettings;
 use crate::config_types::SandboxWorkspaceWrite;
 use crate::config_types::ShellEnvironmentPolicy;
 use crate::config_types::ShellEnvironmentPolicyToml;
 use crate::config_types::Tui;
+use crate::config_types::UnsandboxedCommand;
 use crate::config_types::UriBasedFileOpener;
+use crate::exec::ArgvLimits;
+use crate::exec::DEFAULT_MAX_CONCURRENT_EXEC;
+use crate::exec::OutputEncoding;
 use crate::git_info::resolve_root_git_project_for_trust;
+use crate::model_capabilities::ModelCapabilities;
 use crate::model_family::ModelFamily;
 use crate::model_family::find_family_for_model;
+use crate::model_family::resolve_model_alias;
 use crate::model_provider_info::ModelProviderInfo;
 use crate::model_provider_info::built_in_model_providers;
-use crate::openai_model_info::get_model_info;
 use crate::protocol::AskForApproval;
 use crate::protocol::SandboxPolicy;
+use crate::sandbox_presets::SandboxPreset;
+use crate::sandbox_presets::find_sandbox_preset;
+use crate::seatbelt::load_seatbelt_base_policy;
 use codex_protocol::config_types::ReasoningEffort;
 use codex_protocol::config_types::ReasoningSummary;
 use codex_protocol::config_types::SandboxMode;
@@ -25,6 +35,7 @@ use codex_protocol::mcp_protocol::UserSavedConfig;
 use dirs::home_dir;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::num::NonZeroU64;
 use std::path::Path;
 use std::path::PathBuf;
 use tempfile::NamedTempFile;
@@ -56,6 +67,15 @@ pub struct Config {
     /// Maximum number of output tokens.
     pub model_max_output_tokens: Option<u64>,
 
+    /// When the context window of `model` is neither configured nor in
+    /// Codex's model table, look it up in the provider's `/models` endpoint
+    /// when a session starts. Off by default.
+    pub fetch_model_info: bool,
+
+    /// User-defined model aliases (alias -> canonical model id), consulted
+    /// before the built-in ones. `model` has already been resolved.
+    pub model_aliases: HashMap<String, String>,
+
     /// Key into the model_providers map that specifies which provider to use.
     pub model_provider_id: String,
 
@@ -69,6 +89,46 @@ pub struct Config {
 
     pub shell_environment_policy: ShellEnvironmentPolicy,
 
+    /// Whether patches touching git-ignored paths are allowed, warned about,
+    /// or blocked.
+    pub git_ignored_writes: GitIgnoredWrites,
+
+    /// Commands that run without the sandbox even when one is active. Empty
+    /// (off) by default.
+    pub unsandboxed_commands: Vec<UnsandboxedCommand>,
+
+    /// Programs commands may not run, in any sandbox mode and on top of
+    /// `sandbox_workspace_write.denied_programs`. Empty by default; see
+    /// [`crate::exec::check_denied_programs`].
+    pub denied_programs: Vec<String>,
+
+    /// Maximum number of commands that run at once across all sessions in
+    /// this process; further commands queue. Defaults to
+    /// [`DEFAULT_MAX_CONCURRENT_EXEC`].
+    pub max_concurrent_exec: usize,
+
+    /// Commands run before every shell command of the session, joined with
+    /// `&&`, when commands are wrapped in the user's shell.
+    pub exec_prelude: Vec<String>,
+
+    /// Log each stderr line of shell commands at debug level as it is read.
+    pub trace_exec_stderr: bool,
+
+    /// Limits on the size of a command's argv, checked before it is spawned.
+    pub argv_limits: ArgvLimits,
+
+    /// Encoding of shell command output; see [`OutputEncoding`].
+    pub output_encoding: OutputEncoding,
+
+    /// Rate at which live shell command output is streamed to the UI; see
+    /// [`StdoutStream::max_bytes_per_sec`](crate::exec::StdoutStream::max_bytes_per_sec).
+    /// Unlimited when `None`.
+    pub output_stream_max_bytes_per_sec: Option<NonZeroU64>,
+
+    /// Replacement for the embedded macOS Seatbelt base policy, loaded from
+    /// `seatbelt_base_policy_path`. `None` keeps the embedded policy.
+    pub seatbelt_base_policy: Option<String>,
+
     /// When `true`, `AgentReasoning` events emitted by the backend will be
     /// suppressed from the frontend output. This can reduce visual noise when
     /// users are only interested in the final agent responses.
@@ -127,6 +187,9 @@ pub struct Config {
     /// Settings that govern if and what will be written to `~/.codex/history.jsonl`.
     pub history: History,
 
+    /// Settings that govern how session rollouts are written to disk.
+    pub rollout: RolloutSettings,
+
     /// Optional URI-based file opener. If set, citations to files in the model
     /// output will be hyperlinked using the specified URI scheme.
     pub file_opener: UriBasedFileOpener,
@@ -400,6 +463,15 @@ pub struct ConfigToml {
     /// Maximum number of output tokens.
     pub model_max_output_tokens: Option<u64>,
 
+    /// Look up the context window of models Codex does not know in the
+    /// provider's `/models` endpoint.
+    pub fetch_model_info: Option<bool>,
+
+    /// Maps model names to the model id sent to the provider, e.g.
+    /// `fast = "o4-mini-2025-04-16"`. Overrides the built-in aliases.
+    #[serde(default)]
+    pub model_aliases: HashMap<String, String>,
+
     /// Default approval policy for executing commands.
     pub approval_policy: Option<AskForApproval>,
 
@@ -412,6 +484,45 @@ pub struct ConfigToml {
     /// Sandbox configuration to apply if `sandbox` is `WorkspaceWrite`.
     pub sandbox_workspace_write: Option<SandboxWorkspaceWrite>,
 
+    /// Opt-in check for patches that write to git-ignored paths.
+    pub git_ignored_writes: Option<GitIgnoredWrites>,
+
+    /// Trusted, read-only commands that may skip the sandbox.
+    pub unsandboxed_commands: Option<Vec<UnsandboxedCommand>>,
+
+    /// Programs commands may not run, in any sandbox mode.
+    pub denied_programs: Option<Vec<String>>,
+
+    /// Maximum number of commands running at once.
+    pub max_concurrent_exec: Option<usize>,
+
+    /// Commands to run before every shell command, e.g. to activate a venv.
+    pub exec_prelude: Option<Vec<String>>,
+
+    /// Log the stderr of shell commands line by line as it is read.
+    pub trace_exec_stderr: Option<bool>,
+
+    /// Largest total size of a command's arguments, in bytes.
+    pub max_command_bytes: Option<usize>,
+
+    /// Most arguments a command may have.
+    pub max_command_args: Option<usize>,
+
+    /// Encoding of shell command output, e.g. `cp1252` on Windows.
+    pub output_encoding: Option<OutputEncoding>,
+
+    /// Most bytes of live command output streamed to the UI per second.
+    pub output_stream_max_bytes_per_sec: Option<NonZeroU64>,
+
+    /// File whose contents replace the embedded macOS Seatbelt base policy.
+    /// Relative paths are resolved against `CODEX_HOME`.
+    pub seatbelt_base_policy_path: Option<PathBuf>,
+
+    /// Named sandbox preset (see [`crate::sandbox_presets`]). When set, it
+    /// replaces `sandbox_mode`/`sandbox_workspace_write`; an explicit
+    /// `--sandbox` override still wins.
+    pub sandbox_preset: Option<String>,
+
     /// Optional external command to spawn for end-user notifications.
     #[serde(default)]
     pub notify: Option<Vec<String>>,
@@ -441,6 +552,10 @@ pub struct ConfigToml {
     #[serde(default)]
     pub history: Option<History>,
 
+    /// Settings that govern how session rollouts are written to disk.
+    #[serde(default)]
+    pub rollout: Option<RolloutSettings>,
+
     /// Optional URI-based file opener. If set, citations to files in the model
     /// output will be hyperlinked using the specified URI scheme.
     pub file_opener: Option<UriBasedFileOpener>,
@@ -553,14 +668,30 @@ impl ConfigToml {
             SandboxMode::WorkspaceWrite => match self.sandbox_workspace_write.as_ref() {
                 Some(SandboxWorkspaceWrite {
                     writable_roots,
+                    writable_root_exclusions,
                     network_access,
                     exclude_tmpdir_env_var,
                     exclude_slash_tmp,
+                    denied_programs,
+                    resource_limits,
+                    disabled_sandbox_features,
+                    scratch_dir,
+                    run_as,
+                    network_loopback_only,
+                    audit_only,
                 }) => SandboxPolicy::WorkspaceWrite {
                     writable_roots: writable_roots.clone(),
+                    writable_root_exclusions: writable_root_exclusions.clone(),
                     network_access: *network_access,
                     exclude_tmpdir_env_var: *exclude_tmpdir_env_var,
                     exclude_slash_tmp: *exclude_slash_tmp,
+                    denied_programs: denied_programs.clone(),
+                    resource_limits: *resource_limits,
+                    disabled_sandbox_features: disabled_sandbox_features.clone(),
+                    scratch_dir: scratch_dir.clone(),
+                    run_as: *run_as,
+                    network_loopback_only: *network_loopback_only,
+                    audit_only: *audit_only,
                 },
                 None => SandboxPolicy::new_workspace_write_policy(),
             },
@@ -632,6 +763,7 @@ pub struct ConfigOverrides {
     pub include_view_image_tool: Option<bool>,
     pub show_raw_agent_reasoning: Option<bool>,
     pub tools_web_search_request: Option<bool>,
+    pub denied_programs: Option<Vec<String>>,
 }
 
 impl Config {
@@ -659,6 +791,7 @@ impl Config {
             include_view_image_tool,
             show_raw_agent_reasoning,
             tools_web_search_request: override_tools_web_search_request,
+            denied_programs,
         } = overrides;
 
         let config_profile = match config_profile_key.as_ref().or(cfg.profile.as_ref()) {
@@ -675,7 +808,20 @@ impl Config {
             None => ConfigProfile::default(),
         };
 
-        let sandbox_policy = cfg.derive_sandbox_policy(sandbox_mode);
+        let sandbox_policy = match (sandbox_mode, cfg.sandbox_preset.as_deref()) {
+            (None, Some(name)) => find_sandbox_preset(name)
+                .map(SandboxPreset::policy)
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("unknown sandbox preset `{name}`"),
+                    )
+                })?,
+            _ => cfg.derive_sandbox_policy(sandbox_mode),
+        };
+        if let Some(scratch_dir) = sandbox_policy.scratch_dir() {
+            validate_scratch_dir(scratch_dir)?;
+        }
 
         let mut model_providers = built_in_model_providers();
         // Merge user-defined providers into the built-in list.
@@ -720,6 +866,12 @@ impl Config {
 
         let history = cfg.history.unwrap_or_default();
 
+        let seatbelt_base_policy = cfg
+            .seatbelt_base_policy_path
+            .as_ref()
+            .map(|path| load_seatbelt_base_policy(&codex_home.join(path)))
+            .transpose()?;
+
         let tools_web_search_request = override_tools_web_search_request
             .or(cfg.tools.as_ref().and_then(|t| t.web_search))
             .unwrap_or(false);
@@ -732,6 +884,7 @@ impl Config {
             .or(config_profile.model)
             .or(cfg.model)
             .unwrap_or_else(default_model);
+        let model = resolve_model_alias(&model, &cfg.model_aliases).to_string();
 
         let mut model_family = find_family_for_model(&model).unwrap_or_else(|| ModelFamily {
             slug: model.clone(),
@@ -750,15 +903,15 @@ impl Config {
             model_family.reasoning_summary_format = model_reasoning_summary_format;
         }
 
-        let openai_model_info = get_model_info(&model_family);
+        let capabilities = ModelCapabilities::for_family(&model_family);
         let model_context_window = cfg
             .model_context_window
-            .or_else(|| openai_model_info.as_ref().map(|info| info.context_window));
-        let model_max_output_tokens = cfg.model_max_output_tokens.or_else(|| {
-            openai_model_info
-                .as_ref()
-                .map(|info| info.max_output_tokens)
-        });
+            .or_else(|| capabilities.known_context_window());
+        // Always provide an output budget, even for models we know nothing
+        // about, so requests are not sent uncapped.
+        let model_max_output_tokens = cfg
+            .model_max_output_tokens
+            .or(Some(capabilities.max_output_tokens));
 
         let experimental_resume = cfg.experimental_resume;
 
@@ -782,6 +935,8 @@ impl Config {
             model_family,
             model_context_window,
             model_max_output_tokens,
+            fetch_model_info: cfg.fetch_model_info.unwrap_or(false),
+            model_aliases: cfg.model_aliases,
             model_provider_id,
             model_provider,
             cwd: resolved_cwd,
@@ -791,6 +946,26 @@ impl Config {
                 .unwrap_or_else(AskForApproval::default),
             sandbox_policy,
             shell_environment_policy,
+            git_ignored_writes: cfg.git_ignored_writes.unwrap_or_default(),
+            unsandboxed_commands: cfg.unsandboxed_commands.unwrap_or_default(),
+            denied_programs: denied_programs
+                .or(config_profile.denied_programs)
+                .or(cfg.denied_programs)
+                .unwrap_or_default(),
+            max_concurrent_exec: cfg
+                .max_concurrent_exec
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_EXEC),
+            exec_prelude: cfg.exec_prelude.unwrap_or_default(),
+            trace_exec_stderr: cfg.trace_exec_stderr.unwrap_or(false),
+            argv_limits: ArgvLimits {
+                max_bytes: cfg
+                    .max_command_bytes
+                    .unwrap_or_else(|| ArgvLimits::default().max_bytes),
+                max_args: cfg.max_command_args,
+            },
+            output_encoding: cfg.output_encoding.unwrap_or_default(),
+            output_stream_max_bytes_per_sec: cfg.output_stream_max_bytes_per_sec,
+            seatbelt_base_policy,
             notify: cfg.notify,
             user_instructions,
             base_instructions,
@@ -799,6 +974,7 @@ impl Config {
             project_doc_max_bytes: cfg.project_doc_max_bytes.unwrap_or(PROJECT_DOC_MAX_BYTES),
             codex_home,
             history,
+            rollout: cfg.rollout.unwrap_or_default(),
             file_opener: cfg.file_opener.unwrap_or(UriBasedFileOpener::VsCode),
             tui: cfg.tui.unwrap_or_default(),
             codex_linux_sandbox_exe,
@@ -901,6 +1077,26 @@ fn default_model() -> String {
     OPENAI_DEFAULT_MODEL.to_string()
 }
 
+/// Fails unless `scratch_dir` is an existing, writable directory, so a bad
+/// `scratch_dir` is reported up front rather than by every command that
+/// needs a temporary file.
+fn validate_scratch_dir(scratch_dir: &Path) -> std::io::Result<()> {
+    let invalid = |reason: String| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("sandbox scratch_dir `{}` {reason}", scratch_dir.display()),
+        )
+    };
+    if !scratch_dir.is_absolute() {
+        return Err(invalid("must be an absolute path".to_string()));
+    }
+    if !scratch_dir.is_dir() {
+        return Err(invalid("is not an existing directory".to_string()));
+    }
+    tempfile::tempfile_in(scratch_dir).map_err(|e| invalid(format!("is not writable: {e}")))?;
+    Ok(())
+}
+
 /// Returns the path to the Codex configuration directory, which can be
 /// specified by the `CODEX_HOME` environment variable. If not set, defaults to
 /// `~/.codex`.
@@ -939,6 +1135,8 @@ pub fn log_dir(cfg: &Config) -> std::io::Result<PathBuf> {
 #[cfg(test)]
 mod tests {
     use crate::config_types::HistoryPersistence;
+    use crate::config_types::RolloutFlushPolicy;
+    use crate::protocol::RunAsUser;
 
     use super::*;
     use pretty_assertions::assert_eq;
@@ -974,6 +1172,27 @@ persistence = "none"
             }),
             history_no_persistence_cfg.history
         );
+
+        let rollout_interval = r#"
+[rollout]
+flush_policy = "interval"
+flush_interval_ms = 250
+"#;
+        let rollout_interval_cfg = toml::from_str::<ConfigToml>(rollout_interval)
+            .expect("TOML deserialization should succeed");
+        assert_eq!(
+            Some(RolloutSettings {
+                flush_policy: RolloutFlushPolicy::Interval,
+                flush_interval_ms: Some(250),
+            }),
+            rollout_interval_cfg.rollout
+        );
+
+        // Without a `[rollout]` table every item is synced as it is recorded.
+        assert_eq!(
+            RolloutFlushPolicy::EveryItem,
+            RolloutSettings::default().flush_policy
+        );
     }
 
     #[test]
@@ -1022,16 +1241,154 @@ exclude_slash_tmp = true
             .expect("TOML deserialization should succeed");
         let sandbox_mode_override = None;
         assert_eq!(
-            SandboxPolicy::WorkspaceWrite {
-                writable_roots: vec![PathBuf::from("/my/workspace")],
-                network_access: false,
-                exclude_tmpdir_env_var: true,
-                exclude_slash_tmp: true,
-            },
+            SandboxPolicy::new_workspace_write_policy_limited_to(vec![PathBuf::from(
+                "/my/workspace"
+            )]),
             sandbox_workspace_write_cfg.derive_sandbox_policy(sandbox_mode_override)
         );
     }
 
+    #[test]
+    fn test_sandbox_scratch_dir_must_be_a_writable_directory() -> std::io::Result<()> {
+        let codex_home = TempDir::new()?;
+        let scratch = TempDir::new()?;
+        let load = |scratch_dir: &Path| {
+            let cfg = toml::from_str::<ConfigToml>(&format!(
+                r#"
+sandbox_mode = "workspace-write"
+
+[sandbox_workspace_write]
+scratch_dir = {scratch_dir:?}
+"#
+            ))
+            .expect("TOML deserialization should succeed");
+            Config::load_from_base_config_with_overrides(
+                cfg,
+                ConfigOverrides::default(),
+                codex_home.path().to_path_buf(),
+            )
+        };
+
+        let config = load(scratch.path())?;
+        assert_eq!(Some(scratch.path()), config.sandbox_policy.scratch_dir());
+        let scratch_root = scratch.path().canonicalize()?;
+        assert!(
+            config
+                .sandbox_policy
+                .get_writable_roots_with_cwd(&config.cwd)
+                .iter()
+                .any(|writable_root| writable_root.root == scratch_root)
+        );
+
+        let err = load(&scratch.path().join("missing")).expect_err("missing scratch_dir");
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+        let err = load(Path::new("relative")).expect_err("relative scratch_dir");
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sandbox_run_as() {
+        let cfg = toml::from_str::<ConfigToml>(
+            r#"
+[sandbox_workspace_write]
+run_as = { uid = 1001, gid = 1002 }
+"#,
+        )
+        .expect("TOML deserialization should succeed");
+        let policy = cfg.derive_sandbox_policy(Some(SandboxMode::WorkspaceWrite));
+        assert_eq!(
+            Some(RunAsUser {
+                uid: 1001,
+                gid: 1002
+            }),
+            policy.run_as()
+        );
+    }
+
+    #[test]
+    fn test_denied_programs_config() -> std::io::Result<()> {
+        let codex_home = TempDir::new()?;
+        let cfg = toml::from_str::<ConfigToml>(
+            r#"
+denied_programs = ["curl"]
+
+[profiles.offline]
+denied_programs = ["curl", "ssh"]
+"#,
+        )
+        .expect("TOML deserialization should succeed");
+        let load = |overrides| {
+            Config::load_from_base_config_with_overrides(
+                cfg.clone(),
+                overrides,
+                codex_home.path().to_path_buf(),
+            )
+        };
+
+        let config = load(ConfigOverrides::default())?;
+        assert_eq!(config.denied_programs, vec!["curl".to_string()]);
+
+        let config = load(ConfigOverrides {
+            config_profile: Some("offline".to_string()),
+            ..Default::default()
+        })?;
+        assert_eq!(
+            config.denied_programs,
+            vec!["curl".to_string(), "ssh".to_string()]
+        );
+
+        let config = load(ConfigOverrides {
+            config_profile: Some("offline".to_string()),
+            denied_programs: Some(vec![]),
+            ..Default::default()
+        })?;
+        assert_eq!(config.denied_programs, Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sandbox_preset_config() -> std::io::Result<()> {
+        let codex_home = TempDir::new()?;
+        let cfg = toml::from_str::<ConfigToml>(
+            r#"
+sandbox_mode = "read-only"
+sandbox_preset = "trusted"
+"#,
+        )
+        .expect("TOML deserialization should succeed");
+
+        let config = Config::load_from_base_config_with_overrides(
+            cfg.clone(),
+            ConfigOverrides::default(),
+            codex_home.path().to_path_buf(),
+        )?;
+        assert!(config.sandbox_policy.has_full_network_access());
+
+        // An explicit `--sandbox` override still takes precedence.
+        let config = Config::load_from_base_config_with_overrides(
+            cfg,
+            ConfigOverrides {
+                sandbox_mode: Some(SandboxMode::ReadOnly),
+                ..Default::default()
+            },
+            codex_home.path().to_path_buf(),
+        )?;
+        assert_eq!(SandboxPolicy::ReadOnly, config.sandbox_policy);
+
+        let unknown = toml::from_str::<ConfigToml>(r#"sandbox_preset = "yolo""#)
+            .expect("TOML deserialization should succeed");
+        let err = Config::load_from_base_config_with_overrides(
+            unknown,
+            ConfigOverrides::default(),
+            codex_home.path().to_path_buf(),
+        )
+        .expect_err("unknown preset should be rejected");
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+
+        Ok(())
+    }
+
     struct PrecedenceTestFixture {
         cwd: TempDir,
         codex_home: TempDir,
@@ -1119,6 +1476,7 @@ model_verbosity = "high"
             stream_max_retries: Some(10),
             stream_idle_timeout_ms: Some(300_000),
             requires_openai_auth: false,
+            ..Default::default()
         };
         let model_provider_map = {
             let mut model_provider_map = built_in_model_providers();
@@ -1176,11 +1534,19 @@ model_verbosity = "high"
                 model_family: find_family_for_model("o3").expect("known model slug"),
                 model_context_window: Some(200_000),
                 model_max_output_tokens: Some(100_000),
+                fetch_model_info: false,
+                model_aliases: HashMap::new(),
                 model_provider_id: "openai".to_string(),
                 model_provider: fixture.openai_provider.clone(),
                 approval_policy: AskForApproval::Never,
                 sandbox_policy: SandboxPolicy::new_read_only_policy(),
                 shell_environment_policy: ShellEnvironmentPolicy::default(),
+                git_ignored_writes: GitIgnoredWrites::Off,
+                unsandboxed_commands: Vec::new(),
+                denied_programs: Vec::new(),
+                max_concurrent_exec: DEFAULT_MAX_CONCURRENT_EXEC,
+                output_encoding: OutputEncoding::Utf8,
+                output_stream_max_bytes_per_sec: None,
                 user_instructions: None,
                 notify: None,
                 cwd: fixture.cwd(),
@@ -1189,6 +1555,7 @@ model_verbosity = "high"
                 project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
                 codex_home: fixture.codex_home(),
                 history: History::default(),
+                rollout: RolloutSettings::default(),
                 file_opener: UriBasedFileOpener::VsCode,
                 tui: Tui::default(),
                 codex_linux_sandbox_exe: None,
@@ -1208,6 +1575,10 @@ model_verbosity = "high"
                 use_experimental_streamable_shell_tool: false,
                 include_view_image_tool: true,
                 disable_paste_burst: false,
+                seatbelt_base_policy: None,
+                argv_limits: ArgvLimits::default(),
+                exec_prelude: Vec::new(),
+                trace_exec_stderr: false,
             },
             o3_profile_config
         );
@@ -1233,11 +1604,19 @@ model_verbosity = "high"
             model_family: find_family_for_model("gpt-3.5-turbo").expect("known model slug"),
             model_context_window: Some(16_385),
             model_max_output_tokens: Some(4_096),
+            fetch_model_info: false,
+            model_aliases: HashMap::new(),
             model_provider_id: "openai-chat-completions".to_string(),
             model_provider: fixture.openai_chat_completions_provider.clone(),
             approval_policy: AskForApproval::UnlessTrusted,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            git_ignored_writes: GitIgnoredWrites::Off,
+            unsandboxed_commands: Vec::new(),
+            denied_programs: Vec::new(),
+            max_concurrent_exec: DEFAULT_MAX_CONCURRENT_EXEC,
+            output_encoding: OutputEncoding::Utf8,
+            output_stream_max_bytes_per_sec: None,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
@@ -1246,6 +1625,7 @@ model_verbosity = "high"
             project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
             codex_home: fixture.codex_home(),
             history: History::default(),
+            rollout: RolloutSettings::default(),
             file_opener: UriBasedFileOpener::VsCode,
             tui: Tui::default(),
             codex_linux_sandbox_exe: None,
@@ -1265,6 +1645,10 @@ model_verbosity = "high"
             use_experimental_streamable_shell_tool: false,
             include_view_image_tool: true,
             disable_paste_burst: false,
+            seatbelt_base_policy: None,
+            argv_limits: ArgvLimits::default(),
+            exec_prelude: Vec::new(),
+            trace_exec_stderr: false,
         };
 
         assert_eq!(expected_gpt3_profile_config, gpt3_profile_config);
@@ -1305,11 +1689,19 @@ model_verbosity = "high"
             model_family: find_family_for_model("o3").expect("known model slug"),
             model_context_window: Some(200_000),
             model_max_output_tokens: Some(100_000),
+            fetch_model_info: false,
+            model_aliases: HashMap::new(),
             model_provider_id: "openai".to_string(),
             model_provider: fixture.openai_provider.clone(),
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            git_ignored_writes: GitIgnoredWrites::Off,
+            unsandboxed_commands: Vec::new(),
+            denied_programs: Vec::new(),
+            max_concurrent_exec: DEFAULT_MAX_CONCURRENT_EXEC,
+            output_encoding: OutputEncoding::Utf8,
+            output_stream_max_bytes_per_sec: None,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
@@ -1318,6 +1710,7 @@ model_verbosity = "high"
             project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
             codex_home: fixture.codex_home(),
             history: History::default(),
+            rollout: RolloutSettings::default(),
             file_opener: UriBasedFileOpener::VsCode,
             tui: Tui::default(),
             codex_linux_sandbox_exe: None,
@@ -1337,6 +1730,10 @@ model_verbosity = "high"
             use_experimental_streamable_shell_tool: false,
             include_view_image_tool: true,
             disable_paste_burst: false,
+            seatbelt_base_policy: None,
+            argv_limits: ArgvLimits::default(),
+            exec_prelude: Vec::new(),
+            trace_exec_stderr: false,
         };
 
         assert_eq!(expected_zdr_profile_config, zdr_profile_config);
@@ -1363,11 +1760,19 @@ model_verbosity = "high"
             model_family: find_family_for_model("gpt-5").expect("known model slug"),
             model_context_window: Some(272_000),
             model_max_output_tokens: Some(128_000),
+            fetch_model_info: false,
+            model_aliases: HashMap::new(),
             model_provider_id: "openai".to_string(),
             model_provider: fixture.openai_provider.clone(),
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            git_ignored_writes: GitIgnoredWrites::Off,
+            unsandboxed_commands: Vec::new(),
+            denied_programs: Vec::new(),
+            max_concurrent_exec: DEFAULT_MAX_CONCURRENT_EXEC,
+            output_encoding: OutputEncoding::Utf8,
+            output_stream_max_bytes_per_sec: None,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
@@ -1376,6 +1781,7 @@ model_verbosity = "high"
             project_doc_max_bytes: PROJECT_DOC_MAX_BYTES,
             codex_home: fixture.codex_home(),
             history: History::default(),
+            rollout: RolloutSettings::default(),
             file_opener: UriBasedFileOpener::VsCode,
             tui: Tui::default(),
             codex_linux_sandbox_exe: None,
@@ -1395,6 +1801,10 @@ model_verbosity = "high"
             use_experimental_streamable_shell_tool: false,
             include_view_image_tool: true,
             disable_paste_burst: false,
+            seatbelt_base_policy: None,
+            argv_limits: ArgvLimits::default(),
+            exec_prelude: Vec::new(),
+            trace_exec_stderr: false,
         };
 
         assert_eq!(expected_gpt5_profile_config, gpt5_profile_config);
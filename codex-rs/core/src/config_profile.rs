@@ -20,6 +20,8 @@ pub struct ConfigProfile {
     pub model_verbosity: Option<Verbosity>,
     pub chatgpt_base_url: Option<String>,
     pub experimental_instructions_file: Option<PathBuf>,
+    /// Replaces the top-level `denied_programs` while the profile is active.
+    pub denied_programs: Option<Vec<String>>,
 }
 
 impl From<ConfigProfile> for codex_protocol::mcp_protocol::Profile {
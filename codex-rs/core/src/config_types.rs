@@ -5,10 +5,15 @@
 
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use wildmatch::WildMatchPattern;
 
 use serde::Deserialize;
 
+use crate::protocol::ResourceLimits;
+use crate::protocol::RunAsUser;
+use crate::protocol::SandboxFeature;
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct McpServerConfig {
     pub command: String,
@@ -76,6 +81,81 @@ pub enum HistoryPersistence {
     None,
 }
 
+/// Settings that govern how session rollouts (`~/.codex/sessions/**/*.jsonl`)
+/// are written.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct RolloutSettings {
+    /// When recorded items are synced to disk.
+    #[serde(default)]
+    pub flush_policy: RolloutFlushPolicy,
+
+    /// How often the `interval` flush policy syncs, in milliseconds. Defaults
+    /// to 1000.
+    pub flush_interval_ms: Option<u64>,
+}
+
+impl RolloutSettings {
+    const DEFAULT_FLUSH_INTERVAL_MS: u64 = 1_000;
+
+    pub fn flush_interval(&self) -> Duration {
+        // A zero period would make the sync timer spin.
+        let ms = self
+            .flush_interval_ms
+            .unwrap_or(Self::DEFAULT_FLUSH_INTERVAL_MS)
+            .max(1);
+        Duration::from_millis(ms)
+    }
+}
+
+/// When recorded rollout items are synced (`fsync`) to disk. Every item is
+/// written to the file as soon as the background writer gets to it, which is
+/// enough to survive Codex itself crashing once it has; syncing also
+/// protects against the machine going down.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RolloutFlushPolicy {
+    /// Recording an item waits until it has been written and synced, so a
+    /// crash loses at most the item being recorded. The slowest option, but
+    /// the default so that sessions can be resumed after a crash.
+    #[default]
+    EveryItem,
+    /// Sync whatever was written since the last sync every
+    /// `flush_interval_ms`, and when the session ends.
+    Interval,
+    /// Only sync when the session ends. Items still queued for the writer
+    /// when Codex crashes are lost.
+    OnClose,
+}
+
+/// How patches that write to git-ignored paths (per `.gitignore` and
+/// `.git/info/exclude`) are treated. Checking costs a `git` invocation per
+/// patch, so it is off by default.
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitIgnoredWrites {
+    /// Do not consult git.
+    #[default]
+    Off,
+    /// Apply the patch, but surface a warning and record it in the rollout.
+    Warn,
+    /// Reject the patch and record it in the rollout.
+    Block,
+}
+
+/// A command that may run outside the sandbox even when one is active (see
+/// `unsandboxed_commands`). Only intended for read-only commands.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UnsandboxedCommand {
+    /// Compared with `argv[0]` exactly as the model wrote it: `git` does not
+    /// match `/usr/bin/git` and vice versa.
+    pub program: String,
+    /// The exact arguments after `program`, in order. A command with other,
+    /// fewer, more, repeated or reordered arguments runs sandboxed as usual;
+    /// list one rule per argument list to allow several.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
 /// Collection of settings that are specific to the TUI.
 #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Tui {}
@@ -85,20 +165,47 @@ pub struct SandboxWorkspaceWrite {
     #[serde(default)]
     pub writable_roots: Vec<PathBuf>,
     #[serde(default)]
+    pub writable_root_exclusions: Vec<PathBuf>,
+    #[serde(default)]
     pub network_access: bool,
     #[serde(default)]
     pub exclude_tmpdir_env_var: bool,
     #[serde(default)]
     pub exclude_slash_tmp: bool,
+    #[serde(default)]
+    pub denied_programs: Vec<String>,
+    #[serde(flatten)]
+    pub resource_limits: ResourceLimits,
+    #[serde(default)]
+    pub disabled_sandbox_features: Vec<SandboxFeature>,
+    #[serde(default)]
+    pub scratch_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub run_as: Option<RunAsUser>,
+    #[serde(default)]
+    pub network_loopback_only: bool,
+    #[serde(default)]
+    pub audit_only: bool,
 }
 
 impl From<SandboxWorkspaceWrite> for codex_protocol::mcp_protocol::SandboxSettings {
     fn from(sandbox_workspace_write: SandboxWorkspaceWrite) -> Self {
         Self {
             writable_roots: sandbox_workspace_write.writable_roots,
+            writable_root_exclusions: sandbox_workspace_write.writable_root_exclusions,
             network_access: Some(sandbox_workspace_write.network_access),
             exclude_tmpdir_env_var: Some(sandbox_workspace_write.exclude_tmpdir_env_var),
             exclude_slash_tmp: Some(sandbox_workspace_write.exclude_slash_tmp),
+            denied_programs: sandbox_workspace_write.denied_programs,
+            max_processes: sandbox_workspace_write.resource_limits.max_processes,
+            max_file_size_bytes: sandbox_workspace_write.resource_limits.max_file_size_bytes,
+            cpu_time_limit_secs: sandbox_workspace_write.resource_limits.cpu_time_limit_secs,
+            max_open_files: sandbox_workspace_write.resource_limits.max_open_files,
+            disabled_sandbox_features: sandbox_workspace_write.disabled_sandbox_features,
+            scratch_dir: sandbox_workspace_write.scratch_dir,
+            run_as: sandbox_workspace_write.run_as,
+            network_loopback_only: Some(sandbox_workspace_write.network_loopback_only),
+            audit_only: Some(sandbox_workspace_write.audit_only),
         }
     }
 }
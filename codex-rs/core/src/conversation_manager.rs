@@ -7,9 +7,13 @@ use crate::codex_conversation::CodexConversation;
 use crate::config::Config;
 use crate::error::CodexErr;
 use crate::error::Result as CodexResult;
+use crate::exec::CommandRewriter;
+use crate::exec_backend::DefaultExecBackend;
+use crate::exec_backend::ExecBackend;
 use crate::protocol::Event;
 use crate::protocol::EventMsg;
 use crate::protocol::SessionConfiguredEvent;
+use crate::rollout::RolloutParent;
 use crate::rollout::RolloutRecorder;
 use codex_protocol::mcp_protocol::ConversationId;
 use codex_protocol::models::ResponseItem;
@@ -25,11 +29,19 @@ pub struct ResumedHistory {
     pub rollout_path: PathBuf,
 }
 
+/// History for a new conversation that branches off an existing rollout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchedHistory {
+    pub parent: RolloutParent,
+    pub history: Vec<ResponseItem>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum InitialHistory {
     New,
     Resumed(ResumedHistory),
     Forked(Vec<ResponseItem>),
+    Branched(BranchedHistory),
 }
 
 /// Represents a newly created Codex conversation, including the first event
@@ -45,6 +57,8 @@ pub struct NewConversation {
 pub struct ConversationManager {
     conversations: Arc<RwLock<HashMap<ConversationId, Arc<CodexConversation>>>>,
     auth_manager: Arc<AuthManager>,
+    exec_backend: Arc<dyn ExecBackend>,
+    command_rewriter: Option<CommandRewriter>,
 }
 
 impl ConversationManager {
@@ -52,9 +66,27 @@ impl ConversationManager {
         Self {
             conversations: Arc::new(RwLock::new(HashMap::new())),
             auth_manager,
+            exec_backend: Arc::new(DefaultExecBackend),
+            command_rewriter: None,
         }
     }
 
+    /// Run shell tool calls of conversations created from now on through
+    /// `exec_backend` instead of spawning real processes. Intended for tests,
+    /// e.g. with a [`FakeExecBackend`](crate::exec_backend::FakeExecBackend).
+    pub fn with_exec_backend(mut self, exec_backend: Arc<dyn ExecBackend>) -> Self {
+        self.exec_backend = exec_backend;
+        self
+    }
+
+    /// Rewrite every shell tool call of conversations created from now on
+    /// with `command_rewriter` before it runs. Both the original and the
+    /// rewritten command are recorded in the rollout.
+    pub fn with_command_rewriter(mut self, command_rewriter: CommandRewriter) -> Self {
+        self.command_rewriter = Some(command_rewriter);
+        self
+    }
+
     /// Construct with a dummy AuthManager containing the provided CodexAuth.
     /// Used for integration tests: should not be used by ordinary business logic.
     pub fn with_auth(auth: CodexAuth) -> Self {
@@ -77,13 +109,27 @@ impl ConversationManager {
             let CodexSpawnOk {
                 codex,
                 conversation_id,
-            } = Codex::spawn(config, auth_manager, initial_history).await?;
+            } = Codex::spawn(
+                config,
+                auth_manager,
+                initial_history,
+                self.exec_backend.clone(),
+                self.command_rewriter.clone(),
+            )
+            .await?;
             self.finalize_spawn(codex, conversation_id).await
         } else {
             let CodexSpawnOk {
                 codex,
                 conversation_id,
-            } = Codex::spawn(config, auth_manager, InitialHistory::New).await?;
+            } = Codex::spawn(
+                config,
+                auth_manager,
+                InitialHistory::New,
+                self.exec_backend.clone(),
+                self.command_rewriter.clone(),
+            )
+            .await?;
             self.finalize_spawn(codex, conversation_id).await
         }
     }
@@ -141,7 +187,41 @@ impl ConversationManager {
         let CodexSpawnOk {
             codex,
             conversation_id,
-        } = Codex::spawn(config, auth_manager, initial_history).await?;
+        } = Codex::spawn(
+            config,
+            auth_manager,
+            initial_history,
+            self.exec_backend.clone(),
+            self.command_rewriter.clone(),
+        )
+        .await?;
+        self.finalize_spawn(codex, conversation_id).await
+    }
+
+    /// Start a new conversation from the first `item_index` items of the
+    /// rollout at `rollout_path`. Unlike
+    /// [`ConversationManager::resume_conversation_from_rollout`], the new
+    /// conversation gets a fresh id and its own rollout file that references
+    /// the parent; the original rollout is left untouched.
+    pub async fn resume_from(
+        &self,
+        config: Config,
+        rollout_path: PathBuf,
+        item_index: usize,
+    ) -> CodexResult<NewConversation> {
+        let initial_history =
+            RolloutRecorder::get_rollout_branch(&rollout_path, item_index).await?;
+        let CodexSpawnOk {
+            codex,
+            conversation_id,
+        } = Codex::spawn(
+            config,
+            self.auth_manager.clone(),
+            initial_history,
+            self.exec_backend.clone(),
+            self.command_rewriter.clone(),
+        )
+        .await?;
         self.finalize_spawn(codex, conversation_id).await
     }
 
@@ -168,7 +248,14 @@ impl ConversationManager {
         let CodexSpawnOk {
             codex,
             conversation_id,
-        } = Codex::spawn(config, auth_manager, history).await?;
+        } = Codex::spawn(
+            config,
+            auth_manager,
+            history,
+            self.exec_backend.clone(),
+            self.command_rewriter.clone(),
+        )
+        .await?;
 
         self.finalize_spawn(codex, conversation_id).await
     }
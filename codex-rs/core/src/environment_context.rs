@@ -17,6 +17,8 @@ use std::path::PathBuf;
 #[strum(serialize_all = "kebab-case")]
 pub enum NetworkAccess {
     Restricted,
+    /// Only loopback addresses are reachable.
+    LoopbackOnly,
     Enabled,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -48,9 +50,15 @@ impl EnvironmentContext {
             network_access: match sandbox_policy {
                 Some(SandboxPolicy::DangerFullAccess) => Some(NetworkAccess::Enabled),
                 Some(SandboxPolicy::ReadOnly) => Some(NetworkAccess::Restricted),
-                Some(SandboxPolicy::WorkspaceWrite { network_access, .. }) => {
+                Some(SandboxPolicy::WorkspaceWrite {
+                    network_access,
+                    network_loopback_only,
+                    ..
+                }) => {
                     if network_access {
                         Some(NetworkAccess::Enabled)
+                    } else if network_loopback_only {
+                        Some(NetworkAccess::LoopbackOnly)
                     } else {
                         Some(NetworkAccess::Restricted)
                     }
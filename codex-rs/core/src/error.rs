@@ -1,7 +1,10 @@
+use crate::exec::ExecToolCallOutput;
+use crate::exec::SandboxType;
 use codex_protocol::mcp_protocol::ConversationId;
 use reqwest::StatusCode;
 use serde_json;
 use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 use thiserror::Error;
 use tokio::task::JoinError;
@@ -24,17 +27,68 @@ pub enum SandboxErr {
     #[error("seccomp backend error")]
     SeccompBackend(#[from] seccompiler::BackendError),
 
-    /// Command timed out
+    /// Command timed out; `output` is what it printed before it was killed
     #[error("command timed out")]
-    Timeout,
-
-    /// Command was killed by a signal
-    #[error("command was killed by a signal")]
-    Signal(i32),
+    Timeout { output: Box<ExecToolCallOutput> },
+
+    /// Command produced no output for longer than its idle timeout
+    #[error("command timed out waiting for output")]
+    IdleTimeout { output: Box<ExecToolCallOutput> },
+
+    /// Command was killed by a signal (including ours, when cancelled);
+    /// `output` is what it printed before that. `oom_possible` is set for a
+    /// `SIGKILL` we did not send when it is unknown whether the OOM killer
+    /// sent it (see [`crate::oom`]).
+    #[error("command was killed by a signal{}", oom_hint(.oom_possible))]
+    Signal {
+        signal: i32,
+        oom_possible: bool,
+        output: Box<ExecToolCallOutput>,
+    },
+
+    /// Command crashed with a well-known Windows NTSTATUS exit code, e.g. an
+    /// access violation; `cause` describes it and `code` is the raw exit
+    /// code. `output` is what it printed before that
+    #[error("command crashed: {cause} (exit code {code:#010X})")]
+    Crashed {
+        code: u32,
+        cause: &'static str,
+        output: Box<ExecToolCallOutput>,
+    },
+
+    /// Command was most likely killed by the Linux out-of-memory killer (a
+    /// `SIGKILL` we did not send while the OOM kill count of its cgroup went
+    /// up); `output` is what it printed before that
+    #[error("command was killed by the out-of-memory killer")]
+    OutOfMemory { output: Box<ExecToolCallOutput> },
+
+    /// Command failed after running out of file descriptors under the
+    /// policy's `max_open_files` (it printed "Too many open files"); `output`
+    /// is what it printed
+    #[error("command ran out of file descriptors (max_open_files = {limit})")]
+    OpenFileLimit {
+        limit: u64,
+        output: Box<ExecToolCallOutput>,
+    },
 
     /// Error from linux landlock
     #[error("Landlock was not able to fully enforce all sandbox rules")]
     LandlockRestrict,
+
+    /// The command was marked as needing network access, which the sandbox
+    /// policy does not allow.
+    #[error("command requires network access, but the sandbox policy does not allow it")]
+    NetworkRequired,
+
+    /// The sandbox policy asks to run commands as another user, which is not
+    /// possible here, e.g. because Codex is not running as root.
+    #[error("cannot run the command as uid {uid}, gid {gid}: {reason}")]
+    RunAsUnavailable { uid: u32, gid: u32, reason: String },
+
+    /// `ExecParams::output_file` is outside the places the sandbox policy
+    /// lets commands write to.
+    #[error("cannot save the output to {}: the sandbox policy does not allow writing there", path.display())]
+    OutputFileNotWritable { path: PathBuf },
 }
 
 #[derive(Error, Debug)]
@@ -68,6 +122,11 @@ pub enum CodexErr {
     #[error("interrupted (Ctrl-C)")]
     Interrupted,
 
+    /// The model provider did not start responding within the configured
+    /// `request_timeout_ms`.
+    #[error("model provider did not respond within {0:?}")]
+    RequestTimeout(Duration),
+
     /// Unexpected HTTP status code.
     #[error("unexpected status {0}: {1}")]
     UnexpectedStatus(StatusCode, String),
@@ -95,9 +154,54 @@ pub enum CodexErr {
     #[error("sandbox error: {0}")]
     Sandbox(#[from] SandboxErr),
 
+    /// The command failed validation before it was spawned (e.g. empty argv
+    /// or an argument containing a NUL byte).
+    #[error("invalid command: {0}")]
+    InvalidCommand(String),
+
+    /// The command's arguments exceed the configured or platform limit, so
+    /// spawning it would fail with `E2BIG`.
+    #[error(
+        "command is too long: {0}; write the arguments to a file or split the command into smaller ones"
+    )]
+    CommandTooLong(String),
+
+    /// The exec approval callback denied the command, so it was never run.
+    #[error("command rejected by user")]
+    Rejected,
+
+    /// No built-in sandbox preset has the requested name.
+    #[error("unknown sandbox preset `{0}`")]
+    UnknownSandboxPreset(String),
+
     #[error("codex-linux-sandbox was required but not provided")]
     LandlockSandboxExecutableNotProvided,
 
+    /// The requested sandbox does not exist on this platform, e.g.
+    /// [`SandboxType::MacosSeatbelt`] on Linux, or [`SandboxType::Auto`] where
+    /// there is no sandbox at all; see [`SandboxType::resolve`].
+    #[error("the {requested:?} sandbox is not supported on {platform}")]
+    SandboxNotSupportedOnPlatform {
+        requested: SandboxType,
+        platform: &'static str,
+    },
+
+    /// The `codex-linux-sandbox` helper is missing or cannot be run, so the
+    /// command was never started; see [`crate::landlock::check_linux_sandbox_exe`].
+    #[error(
+        "the sandbox helper {} cannot be run: {reason}; the command itself was not started",
+        path.display()
+    )]
+    LandlockSandboxExecutableUnusable { path: PathBuf, reason: String },
+
+    /// Waiting for a spawned command failed. `output` holds whatever the
+    /// command printed before that, to help debug the failure.
+    #[error("{source}")]
+    ExecIo {
+        source: io::Error,
+        output: Box<ExecToolCallOutput>,
+    },
+
     // -----------------------------------------------------------------
     // Automatic conversions for common external error types
     // -----------------------------------------------------------------
@@ -221,11 +325,81 @@ impl CodexErr {
     }
 }
 
+/// Whether a failed model request may be sent again.
+///
+/// Only failures that are transient *and* safe to repeat are retried:
+/// resending a request the provider rejected fails the same way, and
+/// replaying a response whose tool calls already ran would run them twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    Retry,
+    DoNotRetry,
+}
+
+impl RetryDecision {
+    /// Rate limits (429) and server errors (5xx) are transient; any other
+    /// status means the request itself was refused (bad request, auth, ...).
+    pub fn for_status(status: StatusCode) -> Self {
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            RetryDecision::Retry
+        } else {
+            RetryDecision::DoNotRetry
+        }
+    }
+
+    /// Connection and transport failures are transient; errors building the
+    /// request or decoding the response would happen again.
+    pub fn for_reqwest_error(err: &reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            Self::for_status(status)
+        } else if err.is_connect() || err.is_timeout() || err.is_request() || err.is_body() {
+            RetryDecision::Retry
+        } else {
+            RetryDecision::DoNotRetry
+        }
+    }
+}
+
+/// Decides whether the model request that failed with `err` may be retried.
+///
+/// `emitted_tool_call` is whether the response had already streamed a tool
+/// call before it failed. That call has been run, so the request is never
+/// retried silently, whatever the error.
+pub fn retry_decision(err: &CodexErr, emitted_tool_call: bool) -> RetryDecision {
+    if emitted_tool_call {
+        return RetryDecision::DoNotRetry;
+    }
+    match err {
+        CodexErr::Stream(..) | CodexErr::RequestTimeout(_) | CodexErr::InternalServerError => {
+            RetryDecision::Retry
+        }
+        CodexErr::UnexpectedStatus(status, _) | CodexErr::RetryLimit(status) => {
+            RetryDecision::for_status(*status)
+        }
+        CodexErr::Reqwest(err) => RetryDecision::for_reqwest_error(err),
+        _ => RetryDecision::DoNotRetry,
+    }
+}
+
+fn oom_hint(oom_possible: &bool) -> &'static str {
+    if *oom_possible {
+        " (possibly the out-of-memory killer)"
+    } else {
+        ""
+    }
+}
+
 pub fn get_error_message_ui(e: &CodexErr) -> String {
     match e {
         CodexErr::Sandbox(SandboxErr::Denied(_, _, stderr)) => stderr.to_string(),
         // Timeouts are not sandbox errors from a UX perspective; present them plainly
-        CodexErr::Sandbox(SandboxErr::Timeout) => "error: command timed out".to_string(),
+        CodexErr::Sandbox(SandboxErr::Timeout { .. }) => "error: command timed out".to_string(),
+        CodexErr::Sandbox(SandboxErr::IdleTimeout { .. }) => {
+            "error: command timed out waiting for output".to_string()
+        }
+        CodexErr::Sandbox(SandboxErr::NetworkRequired) => {
+            "error: command requires network access, which the sandbox does not allow".to_string()
+        }
         _ => e.to_string(),
     }
 }
@@ -233,6 +407,7 @@ pub fn get_error_message_ui(e: &CodexErr) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pretty_assertions::assert_eq;
 
     #[test]
     fn usage_limit_reached_error_formats_plus_plan() {
@@ -306,6 +481,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert_eq!(RetryDecision::for_status(status), RetryDecision::Retry);
+        }
+        for status in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::UNAUTHORIZED,
+            StatusCode::FORBIDDEN,
+            StatusCode::NOT_FOUND,
+        ] {
+            assert_eq!(RetryDecision::for_status(status), RetryDecision::DoNotRetry);
+        }
+    }
+
+    #[test]
+    fn retry_decision_classifies_errors() {
+        let stream = CodexErr::Stream("closed".to_string(), None);
+        assert_eq!(retry_decision(&stream, false), RetryDecision::Retry);
+        assert_eq!(
+            retry_decision(&CodexErr::RetryLimit(StatusCode::BAD_GATEWAY), false),
+            RetryDecision::Retry
+        );
+        assert_eq!(
+            retry_decision(
+                &CodexErr::UnexpectedStatus(StatusCode::BAD_REQUEST, String::new()),
+                false
+            ),
+            RetryDecision::DoNotRetry
+        );
+        assert_eq!(
+            retry_decision(&CodexErr::Interrupted, false),
+            RetryDecision::DoNotRetry
+        );
+        assert_eq!(
+            retry_decision(&CodexErr::UsageNotIncluded, false),
+            RetryDecision::DoNotRetry
+        );
+        // Even a transient failure is not retried once a tool call has run.
+        assert_eq!(retry_decision(&stream, true), RetryDecision::DoNotRetry);
+    }
+
     #[test]
     fn usage_limit_reached_less_than_minute() {
         let err = UsageLimitReachedError {
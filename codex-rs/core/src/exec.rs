@@ -3,63 +3,768 @@ use std::os::unix::process::ExitStatusExt;
 
 use std::collections::HashMap;
 use std::io;
+use std::num::NonZeroU64;
+use std::path::Path;
 use std::path::PathBuf;
 use std::process::ExitStatus;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use async_channel::Sender;
+use futures::future::BoxFuture;
+use futures::future::join_all;
+use regex_lite::Regex;
+use serde::Deserialize;
+use serde::Serialize;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
 use tokio::process::Child;
+use tokio::sync::Notify;
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+use uuid::Uuid;
 
+use crate::bash::all_commands_best_effort;
+use crate::bash::try_parse_bash;
+use crate::config_types::UnsandboxedCommand;
 use crate::error::CodexErr;
 use crate::error::Result;
 use crate::error::SandboxErr;
+use crate::exec_diagnostics::Diagnostics;
+use crate::exec_diagnostics::DiagnosticsHook;
+use crate::exec_env::EnvExpansion;
+use crate::landlock::check_linux_sandbox_exe;
+use crate::landlock::linux_sandbox_spawn_error;
 use crate::landlock::spawn_command_under_linux_sandbox;
+use crate::oom::OomKillWatch;
+use crate::oom::OomVerdict;
 use crate::protocol::Event;
 use crate::protocol::EventMsg;
 use crate::protocol::ExecCommandOutputDeltaEvent;
 use crate::protocol::ExecOutputStream;
+use crate::protocol::RunAsUser;
 use crate::protocol::SandboxPolicy;
+use crate::pty::PtyMaster;
+use crate::safety::get_platform_sandbox;
+use crate::sandbox_audit::AuditedSyscall;
+use crate::sandbox_audit::KernelLogCursor;
 use crate::seatbelt::spawn_command_under_seatbelt;
+use crate::spawn::InheritedFd;
+use crate::spawn::StdinSource;
 use crate::spawn::StdioPolicy;
 use crate::spawn::spawn_child_async;
 
 const DEFAULT_TIMEOUT_MS: u64 = 10_000;
 
-// Hardcode these since it does not seem worth including the libc crate just
-// for these.
+#[cfg(unix)]
+const SIGKILL_CODE: i32 = libc::SIGKILL;
+// Windows has no signals, but killed commands are reported the same way.
+#[cfg(not(unix))]
 const SIGKILL_CODE: i32 = 9;
+// Not signals: distinguish the timeouts from other kills.
 const TIMEOUT_CODE: i32 = 64;
+const IDLE_TIMEOUT_CODE: i32 = 65;
 const EXIT_CODE_SIGNAL_BASE: i32 = 128; // conventional shell: 128 + signal
 
 // I/O buffer sizing
 const READ_CHUNK_SIZE: usize = 8192; // bytes per read
 const AGGREGATE_BUFFER_INITIAL_CAPACITY: usize = 8 * 1024; // 8 KiB
 
+/// How long to wait for the readers to hand over partial output after the
+/// child was killed.
+const PARTIAL_OUTPUT_JOIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default for `max_concurrent_exec`.
+pub const DEFAULT_MAX_CONCURRENT_EXEC: usize = 8;
+
+/// Bounds how many commands [`process_exec_tool_call`] runs at once across
+/// the whole process, so an agent firing many parallel commands cannot
+/// exhaust file descriptors or pile up sandbox helpers.
+static EXEC_SLOTS: OnceLock<ExecSlots> = OnceLock::new();
+
+struct ExecSlots {
+    limit: usize,
+    semaphore: Semaphore,
+}
+
+impl ExecSlots {
+    fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            limit,
+            semaphore: Semaphore::new(limit),
+        }
+    }
+}
+
+/// Sets the process-wide limit on concurrently running commands and returns
+/// the limit in effect. Only the first call (or first command run) fixes the
+/// limit; later calls leave it unchanged, so callers should compare the
+/// result with what they asked for. A limit of 0 is treated as 1.
+pub fn init_max_concurrent_exec(max_concurrent_exec: usize) -> usize {
+    EXEC_SLOTS
+        .get_or_init(|| ExecSlots::new(max_concurrent_exec))
+        .limit
+}
+
+fn exec_slots() -> &'static Semaphore {
+    &EXEC_SLOTS
+        .get_or_init(|| ExecSlots::new(DEFAULT_MAX_CONCURRENT_EXEC))
+        .semaphore
+}
+
+/// `ARG_MAX` to assume where the platform does not report one.
+const FALLBACK_ARG_MAX: usize = 128 * 1024;
+
+/// Longest single argument Linux accepts (`MAX_ARG_STRLEN`), regardless of
+/// `ARG_MAX`.
+#[cfg(target_os = "linux")]
+const MAX_ARG_STRLEN: usize = 32 * 4096;
+
+/// Limits on the size of a command's argv, checked by
+/// [`process_exec_tool_call`] before spawning so an oversized command fails
+/// with [`CodexErr::CommandTooLong`] instead of an `E2BIG` from the OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgvLimits {
+    /// Largest total size of the arguments in bytes, counting each one's NUL
+    /// terminator and pointer the way the kernel does.
+    pub max_bytes: usize,
+    /// Most arguments a command may have; `None` for no limit beyond
+    /// `max_bytes`.
+    pub max_args: Option<usize>,
+}
+
+impl Default for ArgvLimits {
+    /// Half of the platform's `ARG_MAX`, leaving room for the environment
+    /// and the arguments a sandbox wrapper adds.
+    fn default() -> Self {
+        Self {
+            max_bytes: platform_arg_max().unwrap_or(FALLBACK_ARG_MAX) / 2,
+            max_args: None,
+        }
+    }
+}
+
 /// Limit the number of ExecCommandOutputDelta events emitted per exec call.
 /// Aggregation still collects full output; only the live event stream is capped.
 pub(crate) const MAX_EXEC_OUTPUT_DELTAS_PER_CALL: usize = 10_000;
 
-#[derive(Debug, Clone)]
+/// Most stderr lines of one command logged with [`ExecParams::trace_stderr`].
+pub const MAX_TRACED_STDERR_LINES: usize = 1_000;
+
+/// Tracing target of the stderr lines logged with
+/// [`ExecParams::trace_stderr`].
+const STDERR_TRACE_TARGET: &str = "codex_core::exec::stderr";
+
+/// What to run and how. The [`Default`] has every optional behavior turned
+/// off but an empty `command` and `cwd`, so literals name those two and then
+/// only the fields they change, followed by `..Default::default()`; see also
+/// [`ExecParams::builder`].
+#[derive(Debug, Clone, Default)]
 pub struct ExecParams {
     pub command: Vec<String>,
     pub cwd: PathBuf,
-    pub timeout_ms: Option<u64>,
+    pub timeout: Timeout,
+    /// When set, the command is killed if it writes nothing to stdout or
+    /// stderr for this many milliseconds, even though `timeout` has not
+    /// elapsed yet, e.g. because it waits for input it will never get.
+    /// Reported as [`SandboxErr::IdleTimeout`].
+    pub idle_timeout_ms: Option<u64>,
     pub env: HashMap<String, String>,
+    /// Whether `${VAR}` references in the `env` values are expanded before
+    /// the command is spawned, and against what. Off by default; see
+    /// [`EnvExpansion`].
+    pub env_expansion: EnvExpansion,
     pub with_escalated_permissions: Option<bool>,
     pub justification: Option<String>,
+    /// When `true`, bytes in the captured output that are not valid UTF-8 are
+    /// rendered as `\xNN` escapes instead of being replaced with U+FFFD, so
+    /// binary or legacy-encoded output stays meaningful to the model.
+    pub escape_invalid_utf8: bool,
+    /// When `true`, stdout/stderr are echoed to this process's stdout/stderr
+    /// as they are read, in addition to being captured for the model.
+    pub tee_to_terminal: bool,
+    /// When set, periodic [`ExecProgress`] reports are sent while the command
+    /// runs, even if it produces no output.
+    pub heartbeat: Option<ExecHeartbeat>,
+    /// Overrides `argv[0]` when the command is spawned without a sandbox,
+    /// e.g. `-bash` to start a login shell or an applet name for a busybox
+    /// multi-call binary. Unix only; ignored on Windows.
+    pub arg0: Option<String>,
+    /// How stdout and stderr are read while the command runs.
+    pub capture_strategy: CaptureStrategy,
+    /// When `true`, the command's stderr is redirected to its stdout pipe
+    /// (`2>&1`), so the kernel preserves the relative order of their writes.
+    /// All output is then reported as stdout and `stderr` is empty. Unix
+    /// only; ignored on Windows.
+    pub combine_stderr: bool,
+    /// Hint that the command cannot work offline (e.g. `npm install`). When
+    /// set and the command would run in a sandbox without network access, it
+    /// fails up front with [`SandboxErr::NetworkRequired`] instead of
+    /// failing later on a refused connection. It never grants network access.
+    pub network_required: bool,
+    /// When set, asked whether the command may run right before it is
+    /// spawned (after validation and sandbox checks).
+    pub approval: Option<ExecApproval>,
+    /// Commands trusted to run without the sandbox; see
+    /// [`is_unsandboxed_command`]. Empty unless configured.
+    pub unsandboxed_commands: Vec<UnsandboxedCommand>,
+    /// Programs the command may not run, checked together with the sandbox
+    /// policy's own list; see [`check_denied_programs`]. Empty unless
+    /// configured.
+    pub denied_programs: Vec<String>,
+    /// Limits on the size of `command`, checked before it is spawned so an
+    /// oversized one fails with [`CodexErr::CommandTooLong`].
+    pub argv_limits: ArgvLimits,
+    /// Replaces the embedded macOS Seatbelt base policy when the command
+    /// runs under Seatbelt; see [`crate::seatbelt::load_seatbelt_base_policy`].
+    pub seatbelt_base_policy: Option<String>,
+    /// Extra open file descriptors the command inherits, e.g. a pipe to
+    /// stream input through. Empty (only stdio) unless set; see
+    /// [`InheritedFd`] for how sandboxes treat them. Unix only.
+    pub inherited_fds: Vec<InheritedFd>,
+    /// When set, told the child's PID right after it is spawned.
+    pub on_spawn: Option<ExecSpawnHook>,
+    /// When set, told why the child is about to be killed, right before it
+    /// is (see [`KillReason`]).
+    pub on_kill: Option<ExecKillHook>,
+    /// When `true`, terminal escape sequences (colors, cursor movement,
+    /// window titles) are removed from the captured output with
+    /// [`strip_ansi`]. Off by default so output is returned as printed.
+    pub strip_ansi: bool,
+    /// When `true`, runs of identical consecutive lines in the captured
+    /// output are collapsed with [`collapse_repeated_lines`], so spinners and
+    /// progress bars do not use up the model's line budget. Off by default
+    /// since it changes the output.
+    pub collapse_repeated_lines: bool,
+    /// When `true`, `\r\n` and lone `\r` line endings in the captured output
+    /// are converted to `\n` with [`normalize_newlines`] before anything else
+    /// looks at its lines, so output from Windows tools counts lines the same
+    /// way. Off by default to keep the raw bytes.
+    pub normalize_newlines: bool,
+    /// When set, rewrites the captured output before it is returned; see
+    /// [`ExecToolCallOutput::filtered`].
+    pub output_filter: Option<ExecOutputFilter>,
+    /// When set, rewrites the command before anything else looks at it; see
+    /// [`CommandRewriter`].
+    pub command_rewriter: Option<CommandRewriter>,
+    /// When `true`, each line of stderr is logged at debug level to the
+    /// `codex_core::exec::stderr` tracing target as soon as it is read, up
+    /// to [`MAX_TRACED_STDERR_LINES`] lines, e.g. to watch a failing build.
+    /// Capture is unaffected.
+    pub trace_stderr: bool,
+    /// Encoding the command writes its output in. Only code pages need
+    /// decoding, which happens on Windows (see [`OutputEncoding`]).
+    pub output_encoding: OutputEncoding,
+    /// When `true`, the command runs with a pseudo-terminal as its stdin,
+    /// stdout and stderr, so tools that check for a terminal enable colors
+    /// and progress output (see `strip_ansi`). As with `combine_stderr`, all
+    /// output is reported as stdout. A command that reads its input waits
+    /// on the terminal until it times out. Unix only; elsewhere pipes are
+    /// used. `tee_to_terminal` does not apply.
+    pub pty: bool,
+    /// When `true`, the command is left running in the background: the call
+    /// returns as soon as it is spawned with [`ExecOutcome::Detached`], and
+    /// the process is neither killed when the call is dropped nor when Codex
+    /// exits. Its output is discarded and `timeout`, `idle_timeout_ms`,
+    /// `tee_to_terminal`, `combine_stderr` and `heartbeat` do not apply.
+    pub detach: bool,
+    /// Identifies this call in its [`ExecToolCallOutput`] and streamed
+    /// [`ExecCommandOutputDeltaEvent`]s, so they can be matched with e.g. the
+    /// rollout entry for the same tool call. Takes precedence over
+    /// [`StdoutStream::call_id`]; when neither is set, a UUID is generated.
+    pub call_id: Option<String>,
+    /// When set, the complete stdout is also written to this file (relative
+    /// paths are resolved against `cwd`), e.g. to keep a full test log while
+    /// the model only sees a truncated one. The sandbox policy must allow
+    /// writing it, or the call fails with [`SandboxErr::OutputFileNotWritable`]
+    /// before anything runs. The check is repeated when the file is written,
+    /// which fails if the command replaced it with a symlink; the output is
+    /// then only returned as usual. The file gets the output as printed,
+    /// before `strip_ansi`, `output_filter` and the like.
+    pub output_file: Option<PathBuf>,
+    /// When `true`, `output_file` also gets stderr, interleaved with stdout
+    /// as in the aggregated output.
+    pub output_file_include_stderr: bool,
+    /// When set, the command counts as having succeeded as soon as a line of
+    /// its stdout or stderr matches, e.g. a server's "listening on" message:
+    /// it is killed and the call returns the output so far with exit code
+    /// 0. Only complete lines are matched. A command that exits or times
+    /// out before printing a match is reported as usual.
+    pub success_when_output_matches: Option<Regex>,
+    /// When set, told what exactly was run if the command fails with a
+    /// [`SandboxErr`], e.g. to log it or attach it to a bug report; see
+    /// [`Diagnostics`].
+    pub collect_diagnostics: Option<DiagnosticsHook>,
+    /// When set, streamed to the command's stdin, which is closed when it
+    /// ends; see [`StdinSource`]. Otherwise stdin is `/dev/null`, so
+    /// commands that check for input do not wait for it.
+    pub stdin_source: Option<StdinSource>,
+    /// Exit codes that mean the command succeeded, e.g. `[0, 1]` for `grep`
+    /// (1: no match) or `diff` (1: differences found). `None` means `[0]`.
+    /// A code in the list is reported as success in
+    /// [`ExecToolCallOutput::success`] and never blamed on the sandbox.
+    pub success_exit_codes: Option<Vec<i32>>,
+}
+
+/// Answer from an [`ExecApproval`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    Allow,
+    /// Fail with [`CodexErr::Rejected`] without running the command.
+    Deny,
+    /// Allow this command and, for as long as this `ExecApproval` (or a clone
+    /// of it) is used, the identical argv without asking again.
+    AllowAndRememberForSession,
+}
+
+type ApprovalCallback = dyn Fn(&ExecParams) -> BoxFuture<'static, Approval> + Send + Sync;
+
+/// Hook that lets a UI gate commands interactively without the exec
+/// machinery knowing about approval policies. Clones share the set of
+/// commands remembered via [`Approval::AllowAndRememberForSession`].
+#[derive(Clone)]
+pub struct ExecApproval {
+    callback: Arc<ApprovalCallback>,
+    remembered: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl ExecApproval {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&ExecParams) -> BoxFuture<'static, Approval> + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+            remembered: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    async fn check(&self, params: &ExecParams) -> Result<()> {
+        let already_approved = self
+            .remembered
+            .lock()
+            .is_ok_and(|remembered| remembered.contains(&params.command));
+        if already_approved {
+            return Ok(());
+        }
+        match (self.callback)(params).await {
+            Approval::Allow => Ok(()),
+            Approval::Deny => Err(CodexErr::Rejected),
+            Approval::AllowAndRememberForSession => {
+                if let Ok(mut remembered) = self.remembered.lock() {
+                    remembered.push(params.command.clone());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for ExecApproval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecApproval").finish_non_exhaustive()
+    }
+}
+
+type SpawnCallback = dyn Fn(u32) + Send + Sync;
+
+/// Reports the PID of a spawned command, e.g. to attach `strace` or a
+/// profiler, or to show it in a UI. Under a sandbox this is the PID of the
+/// sandbox launcher, which on both macOS and Linux execs into the command
+/// and so keeps the same PID.
+#[derive(Clone)]
+pub struct ExecSpawnHook {
+    callback: Arc<SpawnCallback>,
+}
+
+impl ExecSpawnHook {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(u32) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    fn notify(&self, child: &Child) {
+        // `id()` is only `None` once the child has been reaped.
+        if let Some(pid) = child.id() {
+            (self.callback)(pid);
+        }
+    }
+}
+
+impl std::fmt::Debug for ExecSpawnHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecSpawnHook").finish_non_exhaustive()
+    }
+}
+
+/// Why a running command was killed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillReason {
+    /// It ran past its [`Timeout`].
+    Timeout,
+    /// It produced no output for longer than its `idle_timeout_ms`.
+    IdleTimeout,
+    /// The user pressed Ctrl-C.
+    Cancelled,
+    /// Its output matched [`ExecParams::success_when_output_matches`].
+    OutputMatched,
+}
+
+type KillCallback = dyn Fn(KillReason) + Send + Sync;
+
+/// Notified right before a command is force-killed, e.g. to tell the user
+/// why it stopped or to collect a stack dump first. The callback runs on the
+/// task waiting for the command, so it must return quickly; it is
+/// best-effort and not called when the command exits on its own.
+#[derive(Clone)]
+pub struct ExecKillHook {
+    callback: Arc<KillCallback>,
+}
+
+impl ExecKillHook {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(KillReason) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    fn notify(hook: Option<&ExecKillHook>, reason: KillReason) {
+        if let Some(hook) = hook {
+            (hook.callback)(reason);
+        }
+    }
+}
+
+impl std::fmt::Debug for ExecKillHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecKillHook").finish_non_exhaustive()
+    }
+}
+
+type RewriteCallback = dyn Fn(Vec<String>) -> Vec<String> + Send + Sync;
+
+/// Rewrites a command before it runs, e.g. to add `--no-color`, to run
+/// `python3` for `python` or to wrap it in `nice`. It gets and returns plain
+/// argv, unlike the shell wrapping done for `exec_prelude` or profiles, and
+/// the rewritten command is what gets validated, checked against the sandbox
+/// policy and approved. Not setting one leaves commands unchanged.
+#[derive(Clone)]
+pub struct CommandRewriter {
+    callback: Arc<RewriteCallback>,
+}
+
+impl CommandRewriter {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(Vec<String>) -> Vec<String> + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+impl std::fmt::Debug for CommandRewriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandRewriter").finish_non_exhaustive()
+    }
+}
+
+/// Applies and clears `params.command_rewriter`, returning the original
+/// command if the rewriter changed it. [`process_exec_tool_call`] does this
+/// itself; callers that need to know the command that will actually run
+/// (e.g. to record it) can do it first.
+pub fn rewrite_command(params: &mut ExecParams) -> Option<Vec<String>> {
+    let rewriter = params.command_rewriter.take()?;
+    let original = params.command.clone();
+    params.command = (rewriter.callback)(std::mem::take(&mut params.command));
+    (params.command != original).then_some(original)
+}
+
+type OutputFilterCallback = dyn Fn(&str) -> String + Send + Sync;
+
+/// Rewrites a command's captured `stdout`, `stderr` and aggregated output
+/// (after truncation) before they are returned, e.g. to redact secrets or
+/// collapse noisy progress output. Live output deltas are sent as the command
+/// runs and are not filtered.
+#[derive(Clone)]
+pub struct ExecOutputFilter {
+    callback: Arc<OutputFilterCallback>,
+}
+
+/// Replacement text used by [`ExecOutputFilter::redact`].
+pub const REDACTED: &str = "[REDACTED]";
+
+impl ExecOutputFilter {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Replaces every match of any of `patterns` with [`REDACTED`].
+    pub fn redact(patterns: Vec<regex_lite::Regex>) -> Self {
+        Self::new(move |text| {
+            patterns.iter().fold(text.to_string(), |text, pattern| {
+                pattern.replace_all(&text, REDACTED).into_owned()
+            })
+        })
+    }
+
+    /// Returns the filtered output and whether the filter changed it.
+    fn apply(&self, output: StreamOutput<String>) -> (StreamOutput<String>, bool) {
+        let text = (self.callback)(&output.text);
+        let changed = text != output.text;
+        (
+            StreamOutput {
+                text,
+                truncated_after_lines: output.truncated_after_lines,
+            },
+            changed,
+        )
+    }
+}
+
+impl std::fmt::Debug for ExecOutputFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecOutputFilter").finish_non_exhaustive()
+    }
+}
+
+/// How the output pipes of a command are drained.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaptureStrategy {
+    /// Read stdout and stderr from two independent tasks. The relative order
+    /// of stdout and stderr chunks in the aggregated output depends on task
+    /// scheduling.
+    #[default]
+    Parallel,
+    /// Read both streams from a single task, always preferring stdout when
+    /// both have data ready. Chunk order in the aggregated output, and which
+    /// stream uses up the shared live-delta budget first, is reproducible.
+    SingleTask,
+}
+
+/// Opt-in periodic progress reporting for a running command.
+#[derive(Debug, Clone)]
+pub struct ExecHeartbeat {
+    pub interval: Duration,
+    pub tx: Sender<ExecProgress>,
+}
+
+/// Snapshot sent on every heartbeat tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecProgress {
+    /// Time since the command's output started being consumed.
+    pub elapsed: Duration,
+    /// Total stdout + stderr bytes read so far.
+    pub bytes_so_far: usize,
+}
+
+/// Character encoding of a command's captured output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    /// A Windows code page such as 1252 (Western European) or 936
+    /// (Simplified Chinese). Only honored on Windows in builds with the
+    /// `output-encoding` feature, and only for code pages that have a WHATWG
+    /// encoding (not the DOS-era 437 or 850); otherwise the output is
+    /// decoded as UTF-8.
+    CodePage(u16),
+}
+
+impl std::str::FromStr for OutputEncoding {
+    type Err = String;
+
+    /// Accepts `utf-8` (or code page 65001) and code pages written as
+    /// `1252` or `cp1252`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if s == "utf-8" || s == "utf8" {
+            return Ok(OutputEncoding::Utf8);
+        }
+        match s.strip_prefix("cp").unwrap_or(&s).parse::<u16>() {
+            Ok(65001) => Ok(OutputEncoding::Utf8),
+            Ok(code_page) => Ok(OutputEncoding::CodePage(code_page)),
+            Err(_) => Err(format!(
+                "invalid output encoding `{s}`: expected `utf-8` or a code page such as `cp1252`"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for OutputEncoding {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// How long a command may run before it is killed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timeout {
+    /// `DEFAULT_TIMEOUT_MS` (10s).
+    #[default]
+    Default,
+    /// Never kill the command for taking too long.
+    None,
+    /// Kill the command once it has run this long. A zero duration times
+    /// out immediately.
+    Duration(Duration),
+}
+
+impl Timeout {
+    /// Maps the `timeout_ms` of tool calls and protocol requests, where an
+    /// absent value means the default.
+    pub fn from_ms(timeout_ms: Option<u64>) -> Self {
+        match timeout_ms {
+            Some(timeout_ms) => Timeout::Duration(Duration::from_millis(timeout_ms)),
+            None => Timeout::Default,
+        }
+    }
+
+    /// The time limit, or `None` when there is none.
+    pub fn duration(self) -> Option<Duration> {
+        match self {
+            Timeout::Default => Some(Duration::from_millis(DEFAULT_TIMEOUT_MS)),
+            Timeout::None => None,
+            Timeout::Duration(duration) => Some(duration),
+        }
+    }
 }
 
 impl ExecParams {
-    pub fn timeout_duration(&self) -> Duration {
-        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        self.timeout.duration()
+    }
+
+    pub fn idle_timeout_duration(&self) -> Option<Duration> {
+        self.idle_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Starts building params for `command`, run in the current directory
+    /// with an empty environment, the default [`Timeout`] and every optional
+    /// behavior turned off.
+    ///
+    /// ```
+    /// use codex_core::exec::ExecParams;
+    ///
+    /// let params = ExecParams::builder(["cargo", "test"])
+    ///     .cwd("/path/to/workspace")
+    ///     .timeout_ms(60_000)
+    ///     .env_var("RUST_BACKTRACE", "1")
+    ///     .build();
+    /// assert_eq!(params.command, ["cargo", "test"]);
+    /// assert_eq!(params.env["RUST_BACKTRACE"], "1");
+    /// ```
+    pub fn builder<I, S>(command: I) -> ExecParamsBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ExecParamsBuilder {
+            params: ExecParams {
+                command: command.into_iter().map(Into::into).collect(),
+                cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Builds [`ExecParams`]; see [`ExecParams::builder`]. Fields without a
+/// setter can be changed on the built params, which stay plain data.
+///
+/// ```
+/// use codex_core::exec::ExecParams;
+/// use codex_core::exec::Timeout;
+/// use std::collections::HashMap;
+///
+/// let env = HashMap::from([("PATH".to_string(), "/usr/bin".to_string())]);
+/// let mut params = ExecParams::builder(["ls"])
+///     .env(env)
+///     .env_var("LC_ALL", "C")
+///     .timeout(Timeout::None)
+///     .build();
+/// params.strip_ansi = true;
+/// assert_eq!(params.env.len(), 2);
+/// assert_eq!(params.timeout_duration(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExecParamsBuilder {
+    params: ExecParams,
+}
+
+impl ExecParamsBuilder {
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.params.cwd = cwd.into();
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Timeout) -> Self {
+        self.params.timeout = timeout;
+        self
+    }
+
+    /// Shorthand for `timeout(Timeout::Duration(..))`.
+    pub fn timeout_ms(self, timeout_ms: u64) -> Self {
+        self.timeout(Timeout::Duration(Duration::from_millis(timeout_ms)))
+    }
+
+    /// Replaces the whole environment.
+    pub fn env(mut self, env: HashMap<String, String>) -> Self {
+        self.params.env = env;
+        self
+    }
+
+    /// Sets one environment variable, keeping the others.
+    pub fn env_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Expands `${VAR}` references in the environment values; see
+    /// [`EnvExpansion`].
+    pub fn env_expansion(mut self, env_expansion: EnvExpansion) -> Self {
+        self.params.env_expansion = env_expansion;
+        self
+    }
+
+    pub fn call_id(mut self, call_id: impl Into<String>) -> Self {
+        self.params.call_id = Some(call_id.into());
+        self
+    }
+
+    pub fn build(self) -> ExecParams {
+        self.params
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum SandboxType {
     None,
 
@@ -68,6 +773,30 @@ pub enum SandboxType {
 
     /// Only available on Linux.
     LinuxSeccomp,
+
+    /// Whichever of the above this platform has; see
+    /// [`get_platform_sandbox`]. An error where there is none, rather than
+    /// running unsandboxed.
+    Auto,
+}
+
+impl SandboxType {
+    /// Resolves [`SandboxType::Auto`] and checks that the sandbox exists on
+    /// this platform, so asking for e.g. Seatbelt on Linux fails with
+    /// [`CodexErr::SandboxNotSupportedOnPlatform`] instead of on a missing
+    /// `sandbox-exec`.
+    pub fn resolve(self) -> Result<SandboxType> {
+        let unsupported = CodexErr::SandboxNotSupportedOnPlatform {
+            requested: self,
+            platform: std::env::consts::OS,
+        };
+        match (self, get_platform_sandbox()) {
+            (SandboxType::None, _) => Ok(SandboxType::None),
+            (SandboxType::Auto, Some(platform_sandbox)) => Ok(platform_sandbox),
+            (requested, Some(platform_sandbox)) if requested == platform_sandbox => Ok(requested),
+            _ => Err(unsupported),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -75,9 +804,74 @@ pub struct StdoutStream {
     pub sub_id: String,
     pub call_id: String,
     pub tx_event: Sender<Event>,
+    /// When set, output is forwarded as [`ExecCommandOutputDeltaEvent`]s at
+    /// no more than this many bytes per second (stdout and stderr together),
+    /// so a chatty command cannot flood the receiver. Output that arrives
+    /// faster is held back and sent as one larger delta later; of the output
+    /// held back, only the most recent second's worth is kept, and the delta
+    /// that follows a gap starts with a marker saying how many bytes were
+    /// skipped (see [`throttled_marker`]). Only the live deltas are affected,
+    /// never the captured output.
+    pub max_bytes_per_sec: Option<NonZeroU64>,
 }
 
 pub async fn process_exec_tool_call(
+    mut params: ExecParams,
+    sandbox_type: SandboxType,
+    sandbox_policy: &SandboxPolicy,
+    codex_linux_sandbox_exe: &Option<PathBuf>,
+    stdout_stream: Option<StdoutStream>,
+) -> Result<ExecToolCallOutput> {
+    if let Some(original) = rewrite_command(&mut params) {
+        tracing::debug!(?original, rewritten = ?params.command, "command rewritten");
+    }
+    std::mem::take(&mut params.env_expansion).apply(&mut params.env);
+
+    let Some(collect_diagnostics) = params.collect_diagnostics.clone() else {
+        return exec_tool_call(
+            params,
+            sandbox_type,
+            sandbox_policy,
+            codex_linux_sandbox_exe,
+            stdout_stream,
+        )
+        .await;
+    };
+    // Only what the diagnostics need is kept: holding on to a clone of the
+    // params would keep our copies of `inherited_fds` open.
+    let command = params.command.clone();
+    let cwd = params.cwd.clone();
+    let env = params.env.clone();
+    let seatbelt_base_policy = params.seatbelt_base_policy.clone();
+    let effective_type = sandbox_type
+        .resolve()
+        .map(|resolved| effective_sandbox_type(&params, resolved))
+        .unwrap_or(sandbox_type);
+    let result = exec_tool_call(
+        params,
+        sandbox_type,
+        sandbox_policy,
+        codex_linux_sandbox_exe,
+        stdout_stream,
+    )
+    .await;
+    if let Err(CodexErr::Sandbox(error)) = &result {
+        collect_diagnostics.notify(&Diagnostics::collect(
+            command,
+            cwd,
+            &env,
+            effective_type,
+            sandbox_policy,
+            seatbelt_base_policy.as_deref(),
+            codex_linux_sandbox_exe.as_deref(),
+            error,
+        ));
+    }
+    result
+}
+
+/// [`process_exec_tool_call`] once the command has been rewritten.
+async fn exec_tool_call(
     params: ExecParams,
     sandbox_type: SandboxType,
     sandbox_policy: &SandboxPolicy,
@@ -86,78 +880,371 @@ pub async fn process_exec_tool_call(
 ) -> Result<ExecToolCallOutput> {
     let start = Instant::now();
 
-    let raw_output_result: std::result::Result<RawExecToolCallOutput, CodexErr> = match sandbox_type
+    let call_id = params
+        .call_id
+        .clone()
+        .or_else(|| stdout_stream.as_ref().map(|stream| stream.call_id.clone()))
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let stdout_stream = stdout_stream.map(|stream| StdoutStream {
+        call_id: call_id.clone(),
+        ..stream
+    });
+    let sandbox_type = sandbox_type.resolve()?;
+
+    validate_command(&params.command)?;
+    check_argv_limits(&params.command, &params.env, params.argv_limits)?;
+    let resolved_program = resolve_program(
+        &params.command[0],
+        params.env.get("PATH").map(String::as_str),
+        &params.cwd,
+    );
+    let denied_programs: Vec<String> = params
+        .denied_programs
+        .iter()
+        .chain(sandbox_policy.denied_programs())
+        .cloned()
+        .collect();
+    check_denied_programs(
+        &params.command,
+        &denied_programs,
+        resolved_program.as_deref(),
+    )?;
+    let sandbox_type = effective_sandbox_type(&params, sandbox_type);
+    if params.network_required
+        && sandbox_type != SandboxType::None
+        && !sandbox_policy.has_full_network_access()
+    {
+        return Err(CodexErr::Sandbox(SandboxErr::NetworkRequired));
+    }
+    check_run_as(sandbox_policy.run_as(), sandbox_type)?;
+    let output_file = params
+        .output_file
+        .as_ref()
+        .map(|path| params.cwd.join(path));
+    if let Some(path) = &output_file
+        && !sandbox_policy.is_path_writable(path, &params.cwd)
     {
-        SandboxType::None => exec(params, sandbox_policy, stdout_stream.clone()).await,
+        return Err(CodexErr::Sandbox(SandboxErr::OutputFileNotWritable {
+            path: path.clone(),
+        }));
+    }
+    let output_file_include_stderr = params.output_file_include_stderr;
+    let output_file_cwd = params.cwd.clone();
+    if let Some(approval) = &params.approval {
+        approval.check(&params).await?;
+    }
+    let escape_invalid_utf8 = params.escape_invalid_utf8;
+    let output_encoding = params.output_encoding;
+    let strip_ansi_codes = params.strip_ansi;
+    let collapse_repeats = params.collapse_repeated_lines;
+    let normalize_line_endings = params.normalize_newlines;
+    let output_filter = params.output_filter.clone();
+    let heartbeat = params.heartbeat.clone();
+    let on_kill = params.on_kill.clone();
+    let idle_timeout = params.idle_timeout_duration();
+    let success_pattern = params.success_when_output_matches.clone();
+    let success_exit_codes = params.success_exit_codes.clone().unwrap_or_else(|| vec![0]);
+    let capture_strategy = params.capture_strategy;
+    let trace_stderr = params.trace_stderr;
+    let stdio_policy = match (params.tee_to_terminal, params.combine_stderr) {
+        _ if params.detach => StdioPolicy::Detached,
+        _ if params.pty && cfg!(unix) => StdioPolicy::Pty,
+        (false, false) => StdioPolicy::RedirectForShellTool,
+        (true, false) => StdioPolicy::TeeToTerminal,
+        (false, true) => StdioPolicy::CombinedForShellTool,
+        (true, true) => StdioPolicy::TeeCombinedToTerminal,
+    };
+
+    // Held until the child has been reaped (or killed on timeout), or only
+    // while spawning for detached commands; commands over the limit wait
+    // here rather than fail. Their timeout starts once they are spawned.
+    let _exec_slot = exec_slots()
+        .acquire()
+        .await
+        .map_err(|_| CodexErr::Interrupted)?;
+
+    #[cfg(unix)]
+    let (params, pty_master) = if stdio_policy == StdioPolicy::Pty {
+        let mut params = params;
+        let pty = crate::pty::open_pty()?;
+        params
+            .inherited_fds
+            .extend(InheritedFd::terminal(pty.slave));
+        (params, Some(PtyMaster::new(pty.master)))
+    } else {
+        (params, None)
+    };
+    #[cfg(not(unix))]
+    let pty_master = None;
+
+    let timeout = params.timeout_duration();
+    let detach = params.detach;
+    let ExecParams {
+        command,
+        cwd,
+        env,
+        arg0,
+        on_spawn,
+        inherited_fds,
+        stdin_source,
+        seatbelt_base_policy,
+        ..
+    } = params;
+    let mut env = env;
+    if let Some(scratch_dir) = sandbox_policy.scratch_dir() {
+        env.insert(
+            "TMPDIR".to_string(),
+            scratch_dir.to_string_lossy().into_owned(),
+        );
+    }
+    // With `audit_only` the Linux sandbox denies nothing, it only logs what
+    // it would have denied.
+    let audit_only = sandbox_type == SandboxType::LinuxSeccomp && sandbox_policy.is_audit_only();
+    let audit_log = if audit_only {
+        KernelLogCursor::open()
+    } else {
+        None
+    };
+    let started_at = SystemTime::now();
+    let child = match sandbox_type {
+        SandboxType::None => {
+            let (program, args) = command
+                .split_first()
+                .ok_or_else(|| CodexErr::InvalidCommand("command args are empty".to_string()))?;
+            spawn_child_async(
+                PathBuf::from(program),
+                args.into(),
+                arg0.as_deref(),
+                cwd,
+                sandbox_policy,
+                stdio_policy,
+                env,
+                &inherited_fds,
+                stdin_source,
+            )
+            .await?
+        }
         SandboxType::MacosSeatbelt => {
-            let timeout = params.timeout_duration();
-            let ExecParams {
-                command, cwd, env, ..
-            } = params;
-            let child = spawn_command_under_seatbelt(
+            spawn_command_under_seatbelt(
                 command,
                 sandbox_policy,
+                seatbelt_base_policy.as_deref(),
                 cwd,
-                StdioPolicy::RedirectForShellTool,
+                stdio_policy,
                 env,
+                &inherited_fds,
+                stdin_source,
             )
-            .await?;
-            consume_truncated_output(child, timeout, stdout_stream.clone()).await
+            .await?
         }
         SandboxType::LinuxSeccomp => {
-            let timeout = params.timeout_duration();
-            let ExecParams {
-                command, cwd, env, ..
-            } = params;
-
             let codex_linux_sandbox_exe = codex_linux_sandbox_exe
                 .as_ref()
                 .ok_or(CodexErr::LandlockSandboxExecutableNotProvided)?;
-            let child = spawn_command_under_linux_sandbox(
+            check_linux_sandbox_exe(codex_linux_sandbox_exe)?;
+            spawn_command_under_linux_sandbox(
                 codex_linux_sandbox_exe,
                 command,
                 sandbox_policy,
                 cwd,
-                StdioPolicy::RedirectForShellTool,
+                stdio_policy,
                 env,
+                &inherited_fds,
+                stdin_source,
             )
-            .await?;
-
-            consume_truncated_output(child, timeout, stdout_stream).await
+            .await
+            .map_err(|err| linux_sandbox_spawn_error(codex_linux_sandbox_exe, err))?
         }
+        SandboxType::Auto => unreachable!("resolved before spawning"),
     };
+    let spawned_at = Instant::now();
+    // Our copies of the descriptors are no longer needed; in particular, the
+    // PTY master only reports end-of-file once no process holds the slave.
+    drop(inherited_fds);
+    if let Some(on_spawn) = &on_spawn {
+        on_spawn.notify(&child);
+    }
+    let oom_kills = OomKillWatch::for_child(child.id());
+
+    if detach {
+        // Dropping the handle leaves the process running; tokio reaps it
+        // in the background once it exits.
+        let pid = child.id().unwrap_or_default();
+        return Ok(ExecToolCallOutput {
+            duration: start.elapsed(),
+            spawn_latency: spawned_at.saturating_duration_since(start),
+            resolved_program,
+            outcome: ExecOutcome::Detached { pid },
+            call_id,
+            started_at,
+            finished_at: SystemTime::now(),
+            ..Default::default()
+        });
+    }
+
+    let raw_output_result = consume_truncated_output(
+        child,
+        spawned_at,
+        timeout,
+        idle_timeout,
+        success_pattern,
+        stdio_policy,
+        pty_master,
+        capture_strategy,
+        trace_stderr,
+        heartbeat,
+        on_kill,
+        stdout_stream,
+    )
+    .await;
     let duration = start.elapsed();
     match raw_output_result {
         Ok(raw_output) => {
-            let stdout = raw_output.stdout.from_utf8_lossy();
-            let stderr = raw_output.stderr.from_utf8_lossy();
+            let output_file = match output_file {
+                Some(path) => {
+                    save_output_file(
+                        path,
+                        sandbox_policy,
+                        &output_file_cwd,
+                        &raw_output,
+                        output_file_include_stderr,
+                    )
+                    .await
+                }
+                None => None,
+            };
+            let to_text = |output: &StreamOutput<Vec<u8>>| {
+                let mut cleaned = None;
+                if normalize_line_endings {
+                    cleaned = Some(output.normalize_newlines());
+                }
+                if strip_ansi_codes {
+                    cleaned = Some(cleaned.as_ref().unwrap_or(output).strip_ansi());
+                }
+                // After stripping, so lines differing only in colors match.
+                if collapse_repeats {
+                    cleaned = Some(cleaned.as_ref().unwrap_or(output).collapse_repeated_lines());
+                }
+                let output = cleaned.as_ref().unwrap_or(output);
+                if output_encoding != OutputEncoding::Utf8 {
+                    output.decode(output_encoding)
+                } else if escape_invalid_utf8 {
+                    output.from_utf8_escaped()
+                } else {
+                    output.from_utf8_lossy()
+                }
+            };
+            // Applied before anything below can surface the output, so that
+            // e.g. redacted secrets do not leak through a sandbox error.
+            let mut filtered = false;
+            let mut filter = |output: StreamOutput<String>| match &output_filter {
+                Some(output_filter) => {
+                    let (output, changed) = output_filter.apply(output);
+                    filtered |= changed;
+                    output
+                }
+                None => output,
+            };
+            let stdout = filter(to_text(&raw_output.stdout));
+            let stderr = filter(to_text(&raw_output.stderr));
+            let aggregated_output = filter(to_text(&raw_output.aggregated_output));
+
+            let exit_code = raw_output.exit_status.code().unwrap_or(-1);
+            let output = ExecToolCallOutput {
+                exit_code,
+                stdout,
+                stderr,
+                aggregated_output,
+                duration,
+                spawn_latency: raw_output.spawned_at.saturating_duration_since(start),
+                run_duration: raw_output.run_duration,
+                resolved_program,
+                filtered,
+                call_id,
+                output_file,
+                stdout_was_valid_utf8: std::str::from_utf8(&raw_output.stdout.text).is_ok(),
+                stderr_was_valid_utf8: std::str::from_utf8(&raw_output.stderr.text).is_ok(),
+                success: success_exit_codes.contains(&exit_code),
+                started_at,
+                finished_at: raw_output.exited_at,
+                audited_syscalls: audit_log.map(KernelLogCursor::audited_syscalls),
+                ..Default::default()
+            };
+
+            if let Some(source) = raw_output.wait_error {
+                tracing::error!("exec error: {source}");
+                return Err(CodexErr::ExecIo {
+                    source,
+                    output: Box::new(output),
+                });
+            }
 
             #[cfg(target_family = "unix")]
             match raw_output.exit_status.signal() {
-                Some(TIMEOUT_CODE) => return Err(CodexErr::Sandbox(SandboxErr::Timeout)),
+                Some(TIMEOUT_CODE) => {
+                    return Err(CodexErr::Sandbox(SandboxErr::Timeout {
+                        output: Box::new(output),
+                    }));
+                }
+                Some(IDLE_TIMEOUT_CODE) => {
+                    return Err(CodexErr::Sandbox(SandboxErr::IdleTimeout {
+                        output: Box::new(output),
+                    }));
+                }
                 Some(signal) => {
-                    return Err(CodexErr::Sandbox(SandboxErr::Signal(signal)));
+                    // Only a SIGKILL we did not send can be the OOM killer's.
+                    let verdict = if signal == SIGKILL_CODE && raw_output.killed_by.is_none() {
+                        oom_kills.verdict()
+                    } else {
+                        OomVerdict::NotKilled
+                    };
+                    if verdict == OomVerdict::Killed {
+                        return Err(CodexErr::Sandbox(SandboxErr::OutOfMemory {
+                            output: Box::new(output),
+                        }));
+                    }
+                    return Err(CodexErr::Sandbox(SandboxErr::Signal {
+                        signal,
+                        oom_possible: verdict == OomVerdict::Unknown,
+                        output: Box::new(output),
+                    }));
                 }
                 None => {}
             }
 
-            let exit_code = raw_output.exit_status.code().unwrap_or(-1);
+            // Windows reports crashes as NTSTATUS exit codes rather than signals.
+            #[cfg(windows)]
+            if let Some(cause) = ntstatus_crash_cause(exit_code as u32) {
+                return Err(CodexErr::Sandbox(SandboxErr::Crashed {
+                    code: exit_code as u32,
+                    cause,
+                    output: Box::new(output),
+                }));
+            }
+
+            // Only blamed when Codex set the limit and the command failed:
+            // a command can mention EMFILE and still succeed, e.g. after a
+            // retry, or hit the system's own limit.
+            if !output.success
+                && exit_code != 0
+                && let Some(limit) = sandbox_policy.resource_limits().max_open_files
+                && hit_open_file_limit(&output)
+            {
+                return Err(CodexErr::Sandbox(SandboxErr::OpenFileLimit {
+                    limit,
+                    output: Box::new(output),
+                }));
+            }
 
-            if exit_code != 0 && is_likely_sandbox_denied(sandbox_type, exit_code) {
+            if !output.success && !audit_only && is_likely_sandbox_denied(sandbox_type, exit_code) {
                 return Err(CodexErr::Sandbox(SandboxErr::Denied(
                     exit_code,
-                    stdout.text,
-                    stderr.text,
+                    output.stdout.text,
+                    output.stderr.text,
                 )));
             }
 
-            Ok(ExecToolCallOutput {
-                exit_code,
-                stdout,
-                stderr,
-                aggregated_output: raw_output.aggregated_output.from_utf8_lossy(),
-                duration,
-            })
+            Ok(output)
         }
         Err(err) => {
             tracing::error!("exec error: {err}");
@@ -166,12 +1253,455 @@ pub async fn process_exec_tool_call(
     }
 }
 
-/// We don't have a fully deterministic way to tell if our command failed
-/// because of the sandbox - a command in the user's zshrc file might hit an
-/// error, but the command itself might fail or succeed for other reasons.
-/// For now, we conservatively check for 'command not found' (exit code 127),
-/// and can add additional cases as necessary.
-fn is_likely_sandbox_denied(sandbox_type: SandboxType, exit_code: i32) -> bool {
+/// Writes the complete output for [`ExecParams::output_file`] and returns
+/// where it went, or `None` (with a warning) if that failed.
+async fn save_output_file(
+    path: PathBuf,
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+    raw_output: &RawExecToolCallOutput,
+    include_stderr: bool,
+) -> Option<PathBuf> {
+    let contents = if include_stderr {
+        &raw_output.aggregated_output.text
+    } else {
+        &raw_output.stdout.text
+    };
+    match write_output_file(&path, sandbox_policy, cwd, contents.as_bytes()).await {
+        Ok(()) => Some(path),
+        Err(err) => {
+            tracing::warn!("failed to save the output to {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// Codex writes the output file itself, outside the sandbox, after the
+/// command had the chance to replace parts of `path` with symlinks. So the
+/// writability check is repeated on the resolved parent directory, and the
+/// file is opened without following a symlink in its place (`O_NOFOLLOW`)
+/// or writing through a hard link to a file elsewhere.
+async fn write_output_file(
+    path: &Path,
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+    contents: &[u8],
+) -> io::Result<()> {
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a path to a file",
+        ));
+    };
+    let target = tokio::fs::canonicalize(parent).await?.join(file_name);
+    if !sandbox_policy.is_path_writable(&target, cwd) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is outside the writable roots", target.display()),
+        ));
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create(true);
+    #[cfg(unix)]
+    options.custom_flags(libc::O_NOFOLLOW);
+    let mut file = options.open(&target).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        if file.metadata().await?.nlink() > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} has other hard links", target.display()),
+            ));
+        }
+    }
+    file.set_len(0).await?;
+    file.write_all(contents).await?;
+    file.flush().await
+}
+
+/// Runs independent commands with at most `concurrency` of them in flight at
+/// once. Each command keeps its own timeout and is spawned with the same
+/// sandbox treatment as [`process_exec_tool_call`]. Calling
+/// [`Notify::notify_waiters`] on `cancel` kills every running command and
+/// fails the ones that have not started yet with [`CodexErr::Interrupted`].
+/// Results are returned in the same order as `batch`.
+pub async fn process_exec_batch(
+    batch: Vec<ExecParams>,
+    concurrency: usize,
+    sandbox_type: SandboxType,
+    sandbox_policy: &SandboxPolicy,
+    codex_linux_sandbox_exe: &Option<PathBuf>,
+    cancel: Arc<Notify>,
+) -> Vec<Result<ExecToolCallOutput>> {
+    let semaphore = Semaphore::new(concurrency.max(1));
+    let runs = batch.into_iter().map(|params| {
+        // Created eagerly so a cancellation issued before this command gets a
+        // permit is still observed.
+        let cancelled = cancel.notified();
+        let semaphore = &semaphore;
+        async move {
+            let run = async {
+                let _permit = semaphore
+                    .acquire()
+                    .await
+                    .map_err(|_| CodexErr::Interrupted)?;
+                process_exec_tool_call(
+                    params,
+                    sandbox_type,
+                    sandbox_policy,
+                    codex_linux_sandbox_exe,
+                    None,
+                )
+                .await
+            };
+            // Dropping `run` drops the child, which is spawned with
+            // `kill_on_drop`.
+            tokio::select! {
+                result = run => result,
+                _ = cancelled => Err(CodexErr::Interrupted),
+            }
+        }
+    });
+    join_all(runs).await
+}
+
+/// Checks `command` before it is handed to any of the spawn paths so that
+/// malformed argv surfaces as an actionable [`CodexErr::InvalidCommand`]
+/// rather than an obscure spawn failure.
+pub fn validate_command(command: &[String]) -> Result<()> {
+    let Some(program) = command.first() else {
+        return Err(CodexErr::InvalidCommand(
+            "command args are empty".to_string(),
+        ));
+    };
+
+    if program.is_empty() {
+        return Err(CodexErr::InvalidCommand(
+            "program name is an empty string".to_string(),
+        ));
+    }
+
+    if let Some(index) = command.iter().position(|arg| arg.contains('\0')) {
+        return Err(CodexErr::InvalidCommand(format!(
+            "argument {index} contains a NUL byte"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Resolves `program` to the absolute path of the executable that would be
+/// spawned: names containing a path separator are taken relative to `cwd`,
+/// bare names are looked up in `path_env` (honoring `PATHEXT` on Windows).
+/// Returns `None` when nothing matches, e.g. for a typo'd program name.
+pub fn resolve_program(program: &str, path_env: Option<&str>, cwd: &Path) -> Option<PathBuf> {
+    which::which_in(program, path_env, cwd).ok()
+}
+
+/// Human-readable cause of a process that exited with one of the common
+/// Windows NTSTATUS crash codes, which are otherwise just large numbers.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn ntstatus_crash_cause(code: u32) -> Option<&'static str> {
+    let cause = match code {
+        0x8000_0003 => "breakpoint",
+        0xC000_0005 => "access violation",
+        0xC000_0006 => "in-page error (could not read mapped memory)",
+        0xC000_0017 => "out of memory",
+        0xC000_001D => "illegal instruction",
+        0xC000_0094 => "integer division by zero",
+        0xC000_0096 => "privileged instruction",
+        0xC000_00FD => "stack overflow",
+        0xC000_0135 => "a required DLL was not found",
+        0xC000_0139 => "an entry point was not found in a DLL",
+        0xC000_013A => "interrupted with Ctrl+C",
+        0xC000_0142 => "DLL initialization failed",
+        0xC000_0374 => "heap corruption",
+        0xC000_0409 => "stack buffer overrun (fail-fast exception)",
+        _ => return None,
+    };
+    Some(cause)
+}
+
+/// Rejects `command` with [`CodexErr::CommandTooLong`] when it exceeds
+/// `limits` or, together with `env`, the platform's `ARG_MAX`.
+fn check_argv_limits(
+    command: &[String],
+    env: &HashMap<String, String>,
+    limits: ArgvLimits,
+) -> Result<()> {
+    if let Some(max_args) = limits.max_args
+        && command.len() > max_args
+    {
+        return Err(CodexErr::CommandTooLong(format!(
+            "{} arguments, more than the limit of {max_args}",
+            command.len()
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some((index, arg)) = command
+        .iter()
+        .enumerate()
+        .find(|(_, arg)| arg.len() + 1 > MAX_ARG_STRLEN)
+    {
+        return Err(CodexErr::CommandTooLong(format!(
+            "argument {index} is {} bytes, more than the {MAX_ARG_STRLEN}-byte limit for a single argument",
+            arg.len()
+        )));
+    }
+
+    let argv_bytes: usize = command.iter().map(|arg| argv_entry_size(arg.len())).sum();
+    if argv_bytes > limits.max_bytes {
+        return Err(CodexErr::CommandTooLong(format!(
+            "arguments take {argv_bytes} bytes, more than the limit of {} bytes",
+            limits.max_bytes
+        )));
+    }
+
+    // The environment shares `ARG_MAX` with the arguments on Unix.
+    if let Some(arg_max) = platform_arg_max() {
+        let env_bytes: usize = if cfg!(unix) {
+            env.iter()
+                .map(|(key, value)| argv_entry_size(key.len() + 1 + value.len()))
+                .sum()
+        } else {
+            0
+        };
+        if argv_bytes + env_bytes > arg_max {
+            return Err(CodexErr::CommandTooLong(format!(
+                "arguments and environment take {} bytes, more than the system limit (ARG_MAX) of {arg_max} bytes",
+                argv_bytes + env_bytes
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Bytes an argument (or `KEY=value` pair) of `len` bytes takes up in the
+/// new process: the string, its NUL terminator and the pointer to it.
+fn argv_entry_size(len: usize) -> usize {
+    len + 1 + std::mem::size_of::<usize>()
+}
+
+/// The platform's limit on the combined size of argv and the environment,
+/// where it can be discovered.
+#[cfg(unix)]
+fn platform_arg_max() -> Option<usize> {
+    // SAFETY: `sysconf` has no preconditions.
+    let arg_max = unsafe { libc::sysconf(libc::_SC_ARG_MAX) };
+    usize::try_from(arg_max).ok().filter(|arg_max| *arg_max > 0)
+}
+
+/// `CreateProcess` limits the command line to 32,767 characters.
+#[cfg(windows)]
+fn platform_arg_max() -> Option<usize> {
+    Some(32_767)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_arg_max() -> Option<usize> {
+    None
+}
+
+/// Fails with [`SandboxErr::RunAsUnavailable`] when the command should run as
+/// another user (see [`SandboxPolicy::run_as`]) but cannot: only the Linux
+/// sandbox helper switches users, and only with root privileges.
+fn check_run_as(run_as: Option<RunAsUser>, sandbox_type: SandboxType) -> Result<()> {
+    let Some(RunAsUser { uid, gid }) = run_as else {
+        return Ok(());
+    };
+    let unavailable = |reason: &str| {
+        CodexErr::Sandbox(SandboxErr::RunAsUnavailable {
+            uid,
+            gid,
+            reason: reason.to_string(),
+        })
+    };
+    if sandbox_type != SandboxType::LinuxSeccomp {
+        return Err(unavailable("only the Linux sandbox can switch users"));
+    }
+    if !can_switch_to(uid, gid) {
+        return Err(unavailable("Codex must run as root to switch users"));
+    }
+    Ok(())
+}
+
+/// Whether this process may run a command as `uid`/`gid`: it is root, or
+/// already that user and group.
+#[cfg(unix)]
+fn can_switch_to(uid: u32, gid: u32) -> bool {
+    // SAFETY: these calls have no preconditions and cannot fail.
+    let (ruid, euid, rgid, egid) = unsafe {
+        (
+            libc::getuid(),
+            libc::geteuid(),
+            libc::getgid(),
+            libc::getegid(),
+        )
+    };
+    euid == 0 || (ruid == uid && euid == uid && rgid == gid && egid == gid)
+}
+
+#[cfg(not(unix))]
+fn can_switch_to(_uid: u32, _gid: u32) -> bool {
+    false
+}
+
+/// Rejects `command` with [`SandboxErr::Denied`] when its program appears in
+/// `denied_programs`. Matching is done on basenames, both for `command[0]`
+/// as written and for `resolved_program` (following symlinks), so `curl`,
+/// `/usr/bin/curl` and a symlink to `curl` are all treated the same. The
+/// programs a shell script passed with `-c` runs and those started through a
+/// wrapper such as `env` or `xargs` are checked too (see
+/// [`invoked_programs`]), but as that cannot be exhaustive this is a
+/// convenience, not a security boundary.
+pub fn check_denied_programs(
+    command: &[String],
+    denied_programs: &[String],
+    resolved_program: Option<&Path>,
+) -> Result<()> {
+    if denied_programs.is_empty() {
+        return Ok(());
+    }
+
+    let mut candidates = invoked_programs(command);
+    if let Some(resolved) = resolved_program {
+        let resolved = resolved
+            .canonicalize()
+            .unwrap_or_else(|_| resolved.to_path_buf());
+        if let Some(name) = resolved.file_name().and_then(|name| name.to_str()) {
+            candidates.push(name.to_string());
+        }
+    }
+
+    let denied = denied_programs.iter().find(|denied| {
+        candidates
+            .iter()
+            .any(|candidate| program_basename(denied) == candidate)
+    });
+    match denied {
+        Some(denied) => Err(CodexErr::Sandbox(SandboxErr::Denied(
+            -1,
+            String::new(),
+            format!(
+                "program `{}` is denied by the sandbox policy",
+                program_basename(denied)
+            ),
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether `command` matches one of the configured `unsandboxed_commands`:
+/// `command[0]` equals the rule's `program` and the remaining arguments are
+/// exactly its `args`, in order. Matching is deliberately literal (no globs,
+/// basenames, reordering or shell parsing), so `bash -lc "git status"` only
+/// matches a rule for `bash` with the args `-lc` and `git status`.
+pub fn is_unsandboxed_command(
+    command: &[String],
+    unsandboxed_commands: &[UnsandboxedCommand],
+) -> bool {
+    let Some((program, args)) = command.split_first() else {
+        return false;
+    };
+    unsandboxed_commands
+        .iter()
+        .any(|rule| rule.program == *program && rule.args == args)
+}
+
+/// The sandbox `params` will actually run under: [`SandboxType::None`] for
+/// commands allowed by `unsandboxed_commands`, otherwise `sandbox_type`.
+pub fn effective_sandbox_type(params: &ExecParams, sandbox_type: SandboxType) -> SandboxType {
+    if sandbox_type != SandboxType::None
+        && is_unsandboxed_command(&params.command, &params.unsandboxed_commands)
+    {
+        tracing::info!(
+            "running {:?} without the sandbox per unsandboxed_commands",
+            params.command
+        );
+        return SandboxType::None;
+    }
+    sandbox_type
+}
+
+/// Shells whose `-c` script [`invoked_programs`] looks into.
+const SCRIPT_SHELLS: &[&str] = &["bash", "sh", "zsh", "dash", "ksh"];
+
+/// Programs that run their remaining arguments as a command, after options,
+/// `NAME=value` assignments and numbers or durations (`nice -n 10`,
+/// `timeout 5s`).
+const WRAPPER_PROGRAMS: &[&str] = &[
+    "env", "xargs", "nice", "nohup", "timeout", "time", "command", "exec", "sudo", "doas",
+    "stdbuf", "setsid", "ionice", "taskset", "chrt",
+];
+
+/// Basenames of the programs `command` runs as far as can be told without
+/// running it: `command[0]`, the command behind a wrapper from
+/// [`WRAPPER_PROGRAMS`] and the commands of a `-c` script for one of
+/// [`SCRIPT_SHELLS`], recursively. Misses anything less direct, e.g. an
+/// option value taken for the wrapped program (`sudo -u root curl`),
+/// `python -c` or a script file.
+fn invoked_programs(command: &[String]) -> Vec<String> {
+    let Some((program, args)) = command.split_first() else {
+        return Vec::new();
+    };
+    let name = program_basename(program);
+    let mut programs = vec![name.to_string()];
+
+    if WRAPPER_PROGRAMS.contains(&name)
+        && let Some(start) = args.iter().position(|arg| !is_wrapper_operand(arg))
+    {
+        programs.extend(invoked_programs(&args[start..]));
+    }
+    if SCRIPT_SHELLS.contains(&name)
+        && let Some(flag) = args
+            .iter()
+            .position(|arg| arg.starts_with('-') && !arg.starts_with("--") && arg.contains('c'))
+        && let Some(script) = args.get(flag + 1)
+        && let Some(tree) = try_parse_bash(script)
+    {
+        for words in all_commands_best_effort(&tree, script) {
+            programs.extend(invoked_programs(&words));
+        }
+    }
+    programs
+}
+
+/// Whether a wrapper's argument is one of its own options or operands
+/// rather than the start of the wrapped command.
+fn is_wrapper_operand(arg: &str) -> bool {
+    arg.starts_with('-')
+        || arg.contains('=')
+        || arg
+            .trim_end_matches(['s', 'm', 'h', 'd'])
+            .parse::<f64>()
+            .is_ok()
+}
+
+fn program_basename(program: &str) -> &str {
+    Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program)
+}
+
+/// Whether the output shows an `EMFILE` error, which is how running out of
+/// file descriptors surfaces: the OS only reports it to the failing call.
+fn hit_open_file_limit(output: &ExecToolCallOutput) -> bool {
+    // strerror(EMFILE) on both Linux and macOS.
+    const EMFILE_MESSAGE: &str = "Too many open files";
+    output.stderr.text.contains(EMFILE_MESSAGE) || output.stdout.text.contains(EMFILE_MESSAGE)
+}
+
+/// We don't have a fully deterministic way to tell if our command failed
+/// because of the sandbox - a command in the user's zshrc file might hit an
+/// error, but the command itself might fail or succeed for other reasons.
+/// For now, we conservatively check for 'command not found' (exit code 127),
+/// and can add additional cases as necessary.
+fn is_likely_sandbox_denied(sandbox_type: SandboxType, exit_code: i32) -> bool {
     if sandbox_type == SandboxType::None {
         return false;
     }
@@ -186,9 +1716,11 @@ fn is_likely_sandbox_denied(sandbox_type: SandboxType, exit_code: i32) -> bool {
     true
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StreamOutput<T> {
     pub text: T,
+    /// Set when `text` was cut off, e.g. because the readers were abandoned
+    /// after a kill, to the number of lines it holds.
     pub truncated_after_lines: Option<u32>,
 }
 #[derive(Debug)]
@@ -197,6 +1729,18 @@ struct RawExecToolCallOutput {
     pub stdout: StreamOutput<Vec<u8>>,
     pub stderr: StreamOutput<Vec<u8>>,
     pub aggregated_output: StreamOutput<Vec<u8>>,
+    /// When the spawn call returned a running child.
+    pub spawned_at: Instant,
+    /// From `spawned_at` until the child exited (or was killed).
+    pub run_duration: Duration,
+    /// Wall-clock time the child exited (or was killed).
+    pub exited_at: SystemTime,
+    /// Set when we killed the child rather than it exiting (or being killed)
+    /// on its own.
+    pub killed_by: Option<KillReason>,
+    /// Set when waiting for the child failed; the output fields then hold
+    /// whatever was captured before that.
+    pub wait_error: Option<io::Error>,
 }
 
 impl StreamOutput<String> {
@@ -215,6 +1759,204 @@ impl StreamOutput<Vec<u8>> {
             truncated_after_lines: self.truncated_after_lines,
         }
     }
+
+    /// Like [`StreamOutput::from_utf8_lossy`], but keeps invalid bytes
+    /// visible using [`escape_invalid_utf8`].
+    pub fn from_utf8_escaped(&self) -> StreamOutput<String> {
+        StreamOutput {
+            text: escape_invalid_utf8(&self.text),
+            truncated_after_lines: self.truncated_after_lines,
+        }
+    }
+
+    /// Copy of this output with [`strip_ansi`] applied.
+    pub fn strip_ansi(&self) -> StreamOutput<Vec<u8>> {
+        StreamOutput {
+            text: strip_ansi(&self.text),
+            truncated_after_lines: self.truncated_after_lines,
+        }
+    }
+
+    /// Copy of this output with [`normalize_newlines`] applied.
+    pub fn normalize_newlines(&self) -> StreamOutput<Vec<u8>> {
+        StreamOutput {
+            text: normalize_newlines(&self.text),
+            truncated_after_lines: self.truncated_after_lines,
+        }
+    }
+
+    /// Copy of this output with [`collapse_repeated_lines`] applied.
+    pub fn collapse_repeated_lines(&self) -> StreamOutput<Vec<u8>> {
+        StreamOutput {
+            text: collapse_repeated_lines(&self.text),
+            truncated_after_lines: self.truncated_after_lines,
+        }
+    }
+
+    /// Decodes output written in `encoding`, falling back to
+    /// [`StreamOutput::from_utf8_lossy`] where the code page cannot be
+    /// decoded (see [`OutputEncoding::CodePage`]).
+    pub fn decode(&self, encoding: OutputEncoding) -> StreamOutput<String> {
+        match decode_code_page(&self.text, encoding) {
+            Some(text) => StreamOutput {
+                text,
+                truncated_after_lines: self.truncated_after_lines,
+            },
+            None => self.from_utf8_lossy(),
+        }
+    }
+}
+
+#[cfg(all(target_os = "windows", feature = "output-encoding"))]
+fn decode_code_page(bytes: &[u8], encoding: OutputEncoding) -> Option<String> {
+    let OutputEncoding::CodePage(code_page) = encoding else {
+        return None;
+    };
+    let Some(encoding) = codepage::to_encoding(code_page) else {
+        tracing::warn!("code page {code_page} is not supported; decoding output as UTF-8");
+        return None;
+    };
+    // Unmappable bytes become U+FFFD, as with `from_utf8_lossy`.
+    let (text, _had_errors) = encoding.decode_without_bom_handling(bytes);
+    Some(text.into_owned())
+}
+
+#[cfg(not(all(target_os = "windows", feature = "output-encoding")))]
+fn decode_code_page(_bytes: &[u8], _encoding: OutputEncoding) -> Option<String> {
+    None
+}
+
+/// Converts `bytes` to a `String`, copying valid UTF-8 sequences verbatim and
+/// rendering every byte that is not part of a valid sequence as `\xNN`.
+pub fn escape_invalid_utf8(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        out.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            out.push_str(&format!("\\x{byte:02x}"));
+        }
+    }
+    out
+}
+
+/// Removes ANSI escape sequences from `bytes`: CSI sequences such as SGR
+/// colors (`ESC [ 31 m`) and cursor movement, OSC sequences such as window
+/// titles and hyperlinks (terminated by BEL or `ESC \`), and the remaining
+/// two-byte and charset-selection escapes. A sequence cut off at the end of
+/// `bytes` is dropped.
+pub fn strip_ansi(bytes: &[u8]) -> Vec<u8> {
+    const ESC: u8 = 0x1b;
+    const BEL: u8 = 0x07;
+
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != ESC {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        match bytes.get(i) {
+            // CSI: parameter and intermediate bytes up to a final byte.
+            Some(b'[') => {
+                i += 1;
+                while i < bytes.len() && !(0x40..=0x7e).contains(&bytes[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            // OSC, DCS, SOS, PM and APC: a string up to BEL or ST (`ESC \`).
+            Some(b']' | b'P' | b'X' | b'^' | b'_') => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == BEL {
+                        i += 1;
+                        break;
+                    }
+                    if bytes[i] == ESC && bytes.get(i + 1) == Some(&b'\\') {
+                        i += 2;
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            // nF escapes such as `ESC ( B`: intermediate bytes, then a final
+            // byte.
+            Some(0x20..=0x2f) => {
+                while i < bytes.len() && (0x20..=0x2f).contains(&bytes[i]) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            // Any other two-byte escape, e.g. `ESC 7` or `ESC =`.
+            Some(_) => i += 1,
+            None => {}
+        }
+    }
+    out
+}
+
+/// Collapses each run of identical, non-empty consecutive lines in `bytes`
+/// into a single line with a ` (repeated N times)` suffix. A line that is
+/// rewritten in place with `\r`, as progress bars do, counts as the last
+/// text written to it, the way a terminal shows it, so
+/// `50%\r100%\n100%\n` becomes `100% (repeated 2 times)\n`. Line endings
+/// (`\n` or `\r\n`) are kept.
+pub fn collapse_repeated_lines(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut lines = bytes
+        .split_inclusive(|&byte| byte == b'\n')
+        .map(|raw| {
+            let (line, terminator): (&[u8], &[u8]) = if let Some(line) = raw.strip_suffix(b"\r\n") {
+                (line, b"\r\n")
+            } else if let Some(line) = raw.strip_suffix(b"\n") {
+                (line, b"\n")
+            } else {
+                (raw, b"")
+            };
+            let line = line
+                .rsplit(|&byte| byte == b'\r')
+                .find(|segment| !segment.is_empty())
+                .unwrap_or_default();
+            (line, terminator)
+        })
+        .peekable();
+
+    while let Some((line, mut terminator)) = lines.next() {
+        let mut count = 1;
+        while !line.is_empty()
+            && let Some(&(next, next_terminator)) = lines.peek()
+            && next == line
+        {
+            count += 1;
+            terminator = next_terminator;
+            lines.next();
+        }
+        out.extend_from_slice(line);
+        if count > 1 {
+            out.extend_from_slice(format!(" (repeated {count} times)").as_bytes());
+        }
+        out.extend_from_slice(terminator);
+    }
+    out
+}
+
+/// Converts `\r\n` and lone `\r` line endings in `bytes` to `\n`. A `\r`
+/// that rewrites a line in place (as progress bars do) therefore starts a
+/// new line.
+pub fn normalize_newlines(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut bytes = bytes.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if byte == b'\r' {
+            bytes.next_if_eq(&&b'\n');
+            out.push(b'\n');
+        } else {
+            out.push(byte);
+        }
+    }
+    out
 }
 
 #[inline]
@@ -222,113 +1964,292 @@ fn append_all(dst: &mut Vec<u8>, src: &[u8]) {
     dst.extend_from_slice(src);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ExecToolCallOutput {
     pub exit_code: i32,
     pub stdout: StreamOutput<String>,
     pub stderr: StreamOutput<String>,
     pub aggregated_output: StreamOutput<String>,
+    /// Total wall-clock time, from validating the command until its output
+    /// was fully collected. Always at least `spawn_latency + run_duration`.
     pub duration: Duration,
+    /// Time spent getting the child running, including sandbox setup.
+    pub spawn_latency: Duration,
+    /// Time from the child starting until it exited.
+    pub run_duration: Duration,
+    /// Absolute path of the executable that was run, resolved against the
+    /// command's `PATH` before spawning. `None` if it could not be resolved.
+    pub resolved_program: Option<PathBuf>,
+    /// Whether the call waited for the command to exit.
+    pub outcome: ExecOutcome,
+    /// Whether [`ExecParams::output_filter`] changed any of the output, so
+    /// it does not silently differ from what the command printed.
+    pub filtered: bool,
+    /// [`ExecParams::call_id`], or the ID generated for this call.
+    pub call_id: String,
+    /// Where the complete output was saved, when [`ExecParams::output_file`]
+    /// was set and writing the file succeeded.
+    pub output_file: Option<PathBuf>,
+    /// Whether the bytes the command wrote to stdout were valid UTF-8. When
+    /// they were not, `stdout` has the invalid bytes replaced with U+FFFD
+    /// (or escaped, with [`ExecParams::escape_invalid_utf8`]). Expected to be
+    /// `false` for output decoded from a non-UTF-8 [`OutputEncoding`].
+    pub stdout_was_valid_utf8: bool,
+    /// Like [`ExecToolCallOutput::stdout_was_valid_utf8`], for stderr.
+    pub stderr_was_valid_utf8: bool,
+    /// Whether `exit_code` is one of [`ExecParams::success_exit_codes`] (by
+    /// default only 0). Always `true` for a detached command.
+    pub success: bool,
+    /// Wall-clock time right before the command was spawned.
+    pub started_at: SystemTime,
+    /// Wall-clock time the command exited (or was killed). For a detached
+    /// command, when the call returned, right after spawning it.
+    pub finished_at: SystemTime,
+    /// For a command run under the Linux sandbox with `audit_only`, the
+    /// syscalls it would have denied, as far as the kernel log shows (see
+    /// [`crate::sandbox_audit`]). `None` when not auditing, for a detached
+    /// command, or when the kernel log could not be read.
+    pub audited_syscalls: Option<Vec<AuditedSyscall>>,
 }
 
-async fn exec(
-    params: ExecParams,
-    sandbox_policy: &SandboxPolicy,
-    stdout_stream: Option<StdoutStream>,
-) -> Result<RawExecToolCallOutput> {
-    let timeout = params.timeout_duration();
-    let ExecParams {
-        command, cwd, env, ..
-    } = params;
+impl Default for ExecToolCallOutput {
+    /// A run that exited with code 0 without printing anything, at the Unix
+    /// epoch, so literals such as test fakes only name the fields they set.
+    fn default() -> Self {
+        Self {
+            exit_code: 0,
+            stdout: StreamOutput::new(String::new()),
+            stderr: StreamOutput::new(String::new()),
+            aggregated_output: StreamOutput::new(String::new()),
+            duration: Duration::ZERO,
+            spawn_latency: Duration::ZERO,
+            run_duration: Duration::ZERO,
+            resolved_program: None,
+            outcome: ExecOutcome::Exited,
+            filtered: false,
+            call_id: String::new(),
+            output_file: None,
+            stdout_was_valid_utf8: true,
+            stderr_was_valid_utf8: true,
+            success: true,
+            started_at: SystemTime::UNIX_EPOCH,
+            finished_at: SystemTime::UNIX_EPOCH,
+            audited_syscalls: None,
+        }
+    }
+}
 
-    let (program, args) = command.split_first().ok_or_else(|| {
-        CodexErr::Io(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "command args are empty",
-        ))
-    })?;
-    let arg0 = None;
-    let child = spawn_child_async(
-        PathBuf::from(program),
-        args.into(),
-        arg0,
-        cwd,
-        sandbox_policy,
-        StdioPolicy::RedirectForShellTool,
-        env,
-    )
-    .await?;
-    consume_truncated_output(child, timeout, stdout_stream).await
+impl ExecToolCallOutput {
+    /// Whether the command wrote nothing at all to stdout or stderr, e.g.
+    /// `mkdir` or `touch`, as opposed to output that was filtered or
+    /// truncated away. The empty strings are still in `stdout` and `stderr`.
+    pub fn is_empty_output(&self) -> bool {
+        self.stdout.text.is_empty()
+            && self.stderr.text.is_empty()
+            && self.aggregated_output.text.is_empty()
+    }
+}
+
+/// How a call to [`process_exec_tool_call`] finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecOutcome {
+    /// The command ran to completion (or was killed); the output fields
+    /// describe that run.
+    Exited,
+    /// The command was started with [`ExecParams::detach`] and is still
+    /// running as `pid`. There is no exit code or output.
+    Detached { pid: u32 },
 }
 
 /// Consumes the output of a child process, truncating it so it is suitable for
-/// use as the output of a `shell` tool call. Also enforces specified timeout.
+/// use as the output of a `shell` tool call. Also enforces `timeout` and
+/// `idle_timeout`, if any, and kills the child as successful once a line of
+/// its output matches `success_pattern`.
+/// When `stdio_policy` tees to the terminal, everything read is also echoed to this
+/// process's stdout/stderr. `capture_strategy` picks how the pipes are read.
+/// When `heartbeat` is set, progress reports are sent until the child exits
+/// or is killed.
+///
+/// Runs in a `wait_child` debug span that records how the child ended in its
+/// `disposition` field: `exited`, `signaled`, `timed-out`, `idle-timed-out`,
+/// `output-matched`, `killed` (cancelled) or `wait-failed`.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    name = "wait_child",
+    level = "debug",
+    skip_all,
+    fields(
+        pid = child.id(),
+        timeout = ?timeout,
+        idle_timeout = ?idle_timeout,
+        disposition = tracing::field::Empty,
+        exit_code = tracing::field::Empty,
+    )
+)]
 async fn consume_truncated_output(
     mut child: Child,
-    timeout: Duration,
+    spawned_at: Instant,
+    timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    success_pattern: Option<Regex>,
+    stdio_policy: StdioPolicy,
+    pty_master: Option<PtyMaster>,
+    capture_strategy: CaptureStrategy,
+    trace_stderr: bool,
+    heartbeat: Option<ExecHeartbeat>,
+    on_kill: Option<ExecKillHook>,
     stdout_stream: Option<StdoutStream>,
 ) -> Result<RawExecToolCallOutput> {
     // Both stdout and stderr were configured with `Stdio::piped()`
     // above, therefore `take()` should normally return `Some`.  If it doesn't
     // we treat it as an exceptional I/O error
 
-    let stdout_reader = child.stdout.take().ok_or_else(|| {
-        CodexErr::Io(io::Error::other(
-            "stdout pipe was unexpectedly not available",
-        ))
-    })?;
-    let stderr_reader = child.stderr.take().ok_or_else(|| {
-        CodexErr::Io(io::Error::other(
-            "stderr pipe was unexpectedly not available",
-        ))
-    })?;
+    //
+    // With a PTY there are no pipes: everything arrives on the master.
+    let (stdout_reader, stderr_reader): (OutputReader, OutputReader) = match pty_master {
+        Some(pty_master) => (Box::new(pty_master), Box::new(tokio::io::empty())),
+        None => {
+            let stdout_reader = child.stdout.take().ok_or_else(|| {
+                CodexErr::Io(io::Error::other(
+                    "stdout pipe was unexpectedly not available",
+                ))
+            })?;
+            let stderr_reader = child.stderr.take().ok_or_else(|| {
+                CodexErr::Io(io::Error::other(
+                    "stderr pipe was unexpectedly not available",
+                ))
+            })?;
+            (Box::new(stdout_reader), Box::new(stderr_reader))
+        }
+    };
 
-    let (agg_tx, agg_rx) = async_channel::unbounded::<Vec<u8>>();
+    let tee_to_terminal = stdio_policy.tees_to_terminal();
+    let bytes_read = Arc::new(AtomicUsize::new(0));
+    let output_matched = Arc::new(Notify::new());
+    let matcher = success_pattern.map(|regex| OutputMatcher::new(regex, output_matched.clone()));
+    let watch_output = matcher.is_some();
+    let captured = SharedCapture::default();
 
-    let stdout_handle = tokio::spawn(read_capped(
-        BufReader::new(stdout_reader),
-        stdout_stream.clone(),
-        false,
-        Some(agg_tx.clone()),
-    ));
-    let stderr_handle = tokio::spawn(read_capped(
-        BufReader::new(stderr_reader),
-        stdout_stream.clone(),
-        true,
-        Some(agg_tx.clone()),
-    ));
+    // In the `wait_child` span, so traced stderr lines carry the pid.
+    let capture_handle = match capture_strategy {
+        CaptureStrategy::Parallel => tokio::spawn(
+            capture_parallel(
+                stdout_reader,
+                stderr_reader,
+                stdout_stream,
+                tee_to_terminal,
+                trace_stderr,
+                bytes_read.clone(),
+                matcher.clone(),
+                captured.clone(),
+            )
+            .in_current_span(),
+        ),
+        CaptureStrategy::SingleTask => tokio::spawn(
+            capture_single_task(
+                stdout_reader,
+                stderr_reader,
+                stdout_stream,
+                tee_to_terminal,
+                trace_stderr,
+                bytes_read.clone(),
+                matcher.clone(),
+                captured.clone(),
+            )
+            .in_current_span(),
+        ),
+    };
+    let heartbeat_handle =
+        heartbeat.map(|heartbeat| tokio::spawn(run_heartbeat(heartbeat, bytes_read.clone())));
 
-    let exit_status = tokio::select! {
-        result = tokio::time::timeout(timeout, child.wait()) => {
+    let mut wait_error = None;
+    // Inline so the borrow of `child` ends before the arms below run.
+    let (exit_status, killed_by) = tokio::select! {
+        result = async {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, child.wait()).await,
+                None => Ok(child.wait().await),
+            }
+        } => {
             match result {
-                Ok(Ok(exit_status)) => exit_status,
-                Ok(e) => e?,
+                Ok(Ok(exit_status)) => (exit_status, None),
+                Ok(Err(err)) => {
+                    // Kill the child so the readers reach EOF, and report
+                    // `err` together with whatever it printed so far.
+                    wait_error = Some(err);
+                    start_kill(&mut child, &mut wait_error);
+                    (
+                        synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + SIGKILL_CODE),
+                        None,
+                    )
+                }
                 Err(_) => {
-                    // timeout
-                    child.start_kill()?;
-                    // Debatable whether `child.wait().await` should be called here.
-                    synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + TIMEOUT_CODE)
+                    // The timer can fire just after the child exited but
+                    // before `wait()` observed it; report the real exit
+                    // status rather than a spurious timeout.
+                    match child.try_wait() {
+                        Ok(Some(exit_status)) => (exit_status, None),
+                        result => {
+                            wait_error = result.err();
+                            ExecKillHook::notify(on_kill.as_ref(), KillReason::Timeout);
+                            start_kill(&mut child, &mut wait_error);
+                            // Debatable whether `child.wait().await` should be called here.
+                            (
+                                synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + TIMEOUT_CODE),
+                                Some(KillReason::Timeout),
+                            )
+                        }
+                    }
                 }
             }
         }
+        _ = wait_for_idle(idle_timeout.unwrap_or_default(), bytes_read), if idle_timeout.is_some() => {
+            ExecKillHook::notify(on_kill.as_ref(), KillReason::IdleTimeout);
+            start_kill(&mut child, &mut wait_error);
+            (
+                synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + IDLE_TIMEOUT_CODE),
+                Some(KillReason::IdleTimeout),
+            )
+        }
+        _ = output_matched.notified(), if watch_output => {
+            ExecKillHook::notify(on_kill.as_ref(), KillReason::OutputMatched);
+            start_kill(&mut child, &mut wait_error);
+            (synthetic_exit_status(0), Some(KillReason::OutputMatched))
+        }
         _ = tokio::signal::ctrl_c() => {
-            child.start_kill()?;
-            synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + SIGKILL_CODE)
+            ExecKillHook::notify(on_kill.as_ref(), KillReason::Cancelled);
+            start_kill(&mut child, &mut wait_error);
+            (
+                synthetic_exit_status(EXIT_CODE_SIGNAL_BASE + SIGKILL_CODE),
+                Some(KillReason::Cancelled),
+            )
         }
     };
-
-    let stdout = stdout_handle.await??;
-    let stderr = stderr_handle.await??;
-
-    drop(agg_tx);
-
-    let mut combined_buf = Vec::with_capacity(AGGREGATE_BUFFER_INITIAL_CAPACITY);
-    while let Ok(chunk) = agg_rx.recv().await {
-        append_all(&mut combined_buf, &chunk);
+    let run_duration = spawned_at.elapsed();
+    let exited_at = SystemTime::now();
+    let disposition = disposition(&exit_status, killed_by, wait_error.as_ref());
+    let span = tracing::Span::current();
+    span.record("disposition", disposition);
+    if let Some(code) = exit_status.code() {
+        span.record("exit_code", code);
     }
-    let aggregated_output = StreamOutput {
-        text: combined_buf,
-        truncated_after_lines: None,
+    tracing::debug!(?run_duration, "child {disposition}");
+    if let Some(heartbeat_handle) = heartbeat_handle {
+        heartbeat_handle.abort();
+    }
+
+    // A child that exited on its own closed its pipes, so wait for the
+    // readers to drain them. After a kill (or a failed wait) a grandchild may
+    // still hold them open; only wait briefly for what made it out.
+    let CapturedOutput {
+        stdout,
+        stderr,
+        aggregated_output,
+    } = if killed_by.is_none() && wait_error.is_none() {
+        capture_handle.await??;
+        CapturedOutput::take(&captured, false)
+    } else {
+        join_partial_output(capture_handle, &captured).await
     };
 
     Ok(RawExecToolCallOutput {
@@ -336,72 +2257,2340 @@ async fn consume_truncated_output(
         stdout,
         stderr,
         aggregated_output,
+        spawned_at,
+        run_duration,
+        exited_at,
+        killed_by,
+        wait_error,
     })
 }
 
-async fn read_capped<R: AsyncRead + Unpin + Send + 'static>(
-    mut reader: R,
-    stream: Option<StdoutStream>,
-    is_stderr: bool,
-    aggregate_tx: Option<Sender<Vec<u8>>>,
-) -> io::Result<StreamOutput<Vec<u8>>> {
-    let mut buf = Vec::with_capacity(AGGREGATE_BUFFER_INITIAL_CAPACITY);
-    let mut tmp = [0u8; READ_CHUNK_SIZE];
-    let mut emitted_deltas: usize = 0;
+/// Starts killing `child`. A failure is kept in `wait_error` (unless it
+/// already holds one), so the call reports it with the output read so far
+/// instead of losing that output to a bare I/O error.
+fn start_kill(child: &mut Child, wait_error: &mut Option<io::Error>) {
+    #[cfg(test)]
+    let result = if tests::FAIL_START_KILL.with(std::cell::Cell::get) {
+        Err(io::Error::other("injected start_kill failure"))
+    } else {
+        child.start_kill()
+    };
+    #[cfg(not(test))]
+    let result = child.start_kill();
+    if let Err(err) = result {
+        wait_error.get_or_insert(err);
+    }
+}
 
-    // No caps: append all bytes
+/// How a child ended, as recorded on the `wait_child` span.
+fn disposition(
+    exit_status: &ExitStatus,
+    killed_by: Option<KillReason>,
+    wait_error: Option<&io::Error>,
+) -> &'static str {
+    match (killed_by, wait_error) {
+        (Some(KillReason::Timeout), _) => "timed-out",
+        (Some(KillReason::IdleTimeout), _) => "idle-timed-out",
+        (Some(KillReason::Cancelled), _) => "killed",
+        (Some(KillReason::OutputMatched), _) => "output-matched",
+        (None, Some(_)) => "wait-failed",
+        (None, None) if exit_status.code().is_some() => "exited",
+        (None, None) => "signaled",
+    }
+}
 
-    loop {
-        let n = reader.read(&mut tmp).await?;
-        if n == 0 {
-            break;
+/// Best-effort join of the reader task after the child was killed. Gives up
+/// after [`PARTIAL_OUTPUT_JOIN_TIMEOUT`] (or on a read error) rather than
+/// failing the call, and then returns what was read until then, marked as
+/// truncated.
+async fn join_partial_output(
+    capture_handle: tokio::task::JoinHandle<io::Result<()>>,
+    captured: &SharedCapture,
+) -> CapturedOutput {
+    let abort_handle = capture_handle.abort_handle();
+    let complete = match tokio::time::timeout(PARTIAL_OUTPUT_JOIN_TIMEOUT, capture_handle).await {
+        Ok(Ok(Ok(()))) => true,
+        Ok(Ok(Err(err))) => {
+            tracing::warn!("failed to read partial output: {err}");
+            false
         }
-
-        if let Some(stream) = &stream
-            && emitted_deltas < MAX_EXEC_OUTPUT_DELTAS_PER_CALL
-        {
-            let chunk = tmp[..n].to_vec();
-            let msg = EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent {
-                call_id: stream.call_id.clone(),
-                stream: if is_stderr {
-                    ExecOutputStream::Stderr
-                } else {
-                    ExecOutputStream::Stdout
-                },
-                chunk,
-            });
-            let event = Event {
-                id: stream.sub_id.clone(),
-                msg,
-            };
-            #[allow(clippy::let_unit_value)]
-            let _ = stream.tx_event.send(event).await;
-            emitted_deltas += 1;
+        Ok(Err(err)) => {
+            tracing::warn!("failed to join output reader: {err}");
+            false
         }
-
-        if let Some(tx) = &aggregate_tx {
-            let _ = tx.send(tmp[..n].to_vec()).await;
+        Err(_) => {
+            abort_handle.abort();
+            false
         }
+    };
+    CapturedOutput::take(captured, !complete)
+}
 
-        append_all(&mut buf, &tmp[..n]);
-        // Continue reading to EOF to avoid back-pressure
-    }
+type OutputReader = Box<dyn AsyncRead + Unpin + Send>;
 
-    Ok(StreamOutput {
-        text: buf,
-        truncated_after_lines: None,
-    })
+/// The bytes read so far, appended to by the reader tasks as they go so the
+/// output is not lost with a task that never finishes.
+type SharedCapture = Arc<Mutex<CapturedBytes>>;
+
+struct CapturedBytes {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    aggregated_output: Vec<u8>,
 }
 
-#[cfg(unix)]
-fn synthetic_exit_status(code: i32) -> ExitStatus {
-    use std::os::unix::process::ExitStatusExt;
-    std::process::ExitStatus::from_raw(code)
+impl Default for CapturedBytes {
+    fn default() -> Self {
+        Self {
+            stdout: Vec::with_capacity(AGGREGATE_BUFFER_INITIAL_CAPACITY),
+            stderr: Vec::with_capacity(AGGREGATE_BUFFER_INITIAL_CAPACITY),
+            aggregated_output: Vec::with_capacity(AGGREGATE_BUFFER_INITIAL_CAPACITY),
+        }
+    }
 }
 
-#[cfg(windows)]
+impl CapturedBytes {
+    /// Appends a chunk of one stream, and to the aggregate.
+    fn append(&mut self, is_stderr: bool, chunk: &[u8]) {
+        let buf = if is_stderr {
+            &mut self.stderr
+        } else {
+            &mut self.stdout
+        };
+        append_all(buf, chunk);
+        append_all(&mut self.aggregated_output, chunk);
+    }
+}
+
+fn lock_capture(captured: &Mutex<CapturedBytes>) -> std::sync::MutexGuard<'_, CapturedBytes> {
+    // Appends leave the buffers consistent even if a holder panicked.
+    captured
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+struct CapturedOutput {
+    stdout: StreamOutput<Vec<u8>>,
+    stderr: StreamOutput<Vec<u8>>,
+    aggregated_output: StreamOutput<Vec<u8>>,
+}
+
+impl CapturedOutput {
+    /// Takes the captured bytes out of `captured`. With `truncated`, each
+    /// stream is marked as cut off after the lines read so far.
+    fn take(captured: &SharedCapture, truncated: bool) -> Self {
+        let captured = std::mem::take(&mut *lock_capture(captured));
+        let stream = |text: Vec<u8>| StreamOutput {
+            truncated_after_lines: truncated.then(|| count_lines(&text)),
+            text,
+        };
+        Self {
+            stdout: stream(captured.stdout),
+            stderr: stream(captured.stderr),
+            aggregated_output: stream(captured.aggregated_output),
+        }
+    }
+}
+
+/// Number of lines in `text`, counting an unterminated last line.
+fn count_lines(text: &[u8]) -> u32 {
+    let newlines = text.iter().filter(|&&byte| byte == b'\n').count();
+    let unterminated = usize::from(text.last().is_some_and(|&byte| byte != b'\n'));
+    u32::try_from(newlines + unterminated).unwrap_or(u32::MAX)
+}
+
+/// [`CaptureStrategy::Parallel`]: one reader task per stream, merged into the
+/// aggregate in whatever order the scheduler delivers chunks.
+#[allow(clippy::too_many_arguments)]
+async fn capture_parallel(
+    stdout_reader: OutputReader,
+    stderr_reader: OutputReader,
+    stdout_stream: Option<StdoutStream>,
+    tee_to_terminal: bool,
+    trace_stderr: bool,
+    bytes_read: Arc<AtomicUsize>,
+    matcher: Option<OutputMatcher>,
+    captured: SharedCapture,
+) -> io::Result<()> {
+    let throttle = DeltaThrottle::for_stream(stdout_stream.as_ref());
+
+    let stdout_handle = tokio::spawn(read_capped(
+        BufReader::new(stdout_reader),
+        stdout_stream.clone(),
+        false,
+        captured.clone(),
+        tee_to_terminal,
+        bytes_read.clone(),
+        throttle.clone(),
+        None,
+        matcher.clone(),
+    ));
+    let stderr_handle = tokio::spawn(
+        read_capped(
+            BufReader::new(stderr_reader),
+            stdout_stream,
+            true,
+            captured,
+            tee_to_terminal,
+            bytes_read,
+            throttle,
+            StderrTracer::new(trace_stderr),
+            matcher,
+        )
+        .in_current_span(),
+    );
+
+    stdout_handle.await??;
+    stderr_handle.await??;
+    Ok(())
+}
+
+/// [`CaptureStrategy::SingleTask`]: both streams are read from this task with
+/// a `biased` select (stdout first), so the aggregate reflects the order in
+/// which chunks were read and the live-delta cap is shared by both streams.
+#[allow(clippy::too_many_arguments)]
+async fn capture_single_task(
+    stdout_reader: OutputReader,
+    stderr_reader: OutputReader,
+    stdout_stream: Option<StdoutStream>,
+    tee_to_terminal: bool,
+    trace_stderr: bool,
+    bytes_read: Arc<AtomicUsize>,
+    matcher: Option<OutputMatcher>,
+    captured: SharedCapture,
+) -> io::Result<()> {
+    let mut stdout_reader = BufReader::new(stdout_reader);
+    let mut stderr_reader = BufReader::new(stderr_reader);
+    let mut stdout_tmp = [0u8; READ_CHUNK_SIZE];
+    let mut stderr_tmp = [0u8; READ_CHUNK_SIZE];
+    let mut stdout_open = true;
+    let mut stderr_open = true;
+    let mut emitted_deltas: usize = 0;
+    let throttle = DeltaThrottle::for_stream(stdout_stream.as_ref());
+    let mut stderr_tracer = StderrTracer::new(trace_stderr);
+    let mut stdout_matcher = matcher.clone();
+    let mut stderr_matcher = matcher;
+
+    while stdout_open || stderr_open {
+        // `read` is cancel safe, so the losing branch does not drop data.
+        let (is_stderr, n) = tokio::select! {
+            biased;
+            n = stdout_reader.read(&mut stdout_tmp), if stdout_open => (false, n?),
+            n = stderr_reader.read(&mut stderr_tmp), if stderr_open => (true, n?),
+        };
+        if n == 0 {
+            if is_stderr {
+                stderr_open = false;
+                if let Some(tracer) = stderr_tracer.take() {
+                    tracer.finish();
+                }
+            } else {
+                stdout_open = false;
+            }
+            flush_throttled(
+                stdout_stream.as_ref(),
+                is_stderr,
+                &mut emitted_deltas,
+                throttle.as_deref(),
+            )
+            .await;
+            continue;
+        }
+        bytes_read.fetch_add(n, Ordering::Relaxed);
+
+        let (chunk, matcher) = if is_stderr {
+            if let Some(tracer) = &mut stderr_tracer {
+                tracer.push(&stderr_tmp[..n]);
+            }
+            (&stderr_tmp[..n], &mut stderr_matcher)
+        } else {
+            (&stdout_tmp[..n], &mut stdout_matcher)
+        };
+        if let Some(matcher) = matcher {
+            matcher.push(chunk);
+        }
+        forward_chunk(
+            stdout_stream.as_ref(),
+            is_stderr,
+            chunk,
+            &mut emitted_deltas,
+            tee_to_terminal,
+            throttle.as_deref(),
+        )
+        .await;
+        lock_capture(&captured).append(is_stderr, chunk);
+    }
+
+    Ok(())
+}
+
+async fn read_capped<R: AsyncRead + Unpin + Send + 'static>(
+    mut reader: R,
+    stream: Option<StdoutStream>,
+    is_stderr: bool,
+    captured: SharedCapture,
+    tee_to_terminal: bool,
+    bytes_read: Arc<AtomicUsize>,
+    throttle: Option<Arc<Mutex<DeltaThrottle>>>,
+    mut tracer: Option<StderrTracer>,
+    mut matcher: Option<OutputMatcher>,
+) -> io::Result<()> {
+    let mut tmp = [0u8; READ_CHUNK_SIZE];
+    let mut emitted_deltas: usize = 0;
+
+    // No caps: append all bytes
+
+    loop {
+        let n = reader.read(&mut tmp).await?;
+        if n == 0 {
+            break;
+        }
+        bytes_read.fetch_add(n, Ordering::Relaxed);
+        if let Some(tracer) = &mut tracer {
+            tracer.push(&tmp[..n]);
+        }
+        if let Some(matcher) = &mut matcher {
+            matcher.push(&tmp[..n]);
+        }
+
+        forward_chunk(
+            stream.as_ref(),
+            is_stderr,
+            &tmp[..n],
+            &mut emitted_deltas,
+            tee_to_terminal,
+            throttle.as_deref(),
+        )
+        .await;
+
+        lock_capture(&captured).append(is_stderr, &tmp[..n]);
+        // Continue reading to EOF to avoid back-pressure
+    }
+    if let Some(tracer) = tracer {
+        tracer.finish();
+    }
+    flush_throttled(
+        stream.as_ref(),
+        is_stderr,
+        &mut emitted_deltas,
+        throttle.as_deref(),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Splits output that arrives in arbitrary byte chunks (e.g. the bytes of
+/// `ExecCommandOutputDelta` events) into complete lines as soon as they are
+/// available, buffering a partial trailing line until the next chunk or
+/// [`LineChunker::finish`].
+///
+/// `\n` and `\r\n` end a line, even when the `\r` and `\n` arrive in
+/// different chunks. A lone `\r` returns to the start of the line, as
+/// progress bars use it, so only the text after it is kept: `10%\r100%\n`
+/// yields the single line `100%`. Line terminators are not included in the
+/// returned lines and invalid UTF-8 is replaced with U+FFFD.
+#[derive(Debug, Default, Clone)]
+pub struct LineChunker {
+    partial: Vec<u8>,
+    /// A `\r` was the last byte seen; whether it ends the line or overwrites
+    /// it depends on the next byte.
+    pending_cr: bool,
+}
+
+impl LineChunker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the next chunk and returns the lines it completed, in order.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in chunk {
+            if self.pending_cr {
+                self.pending_cr = false;
+                if byte == b'\n' {
+                    lines.push(self.take_line());
+                    continue;
+                }
+                self.partial.clear();
+            }
+            match byte {
+                b'\n' => lines.push(self.take_line()),
+                b'\r' => self.pending_cr = true,
+                _ => self.partial.push(byte),
+            }
+        }
+        lines
+    }
+
+    /// Returns the unterminated last line, if any, once the output has ended.
+    pub fn finish(mut self) -> Option<String> {
+        if self.partial.is_empty() {
+            None
+        } else {
+            Some(self.take_line())
+        }
+    }
+
+    fn take_line(&mut self) -> String {
+        let line = String::from_utf8_lossy(&self.partial).into_owned();
+        self.partial.clear();
+        line
+    }
+}
+
+/// Logs stderr line by line as it is read, for [`ExecParams::trace_stderr`].
+struct StderrTracer {
+    lines: LineChunker,
+    logged: usize,
+}
+
+impl StderrTracer {
+    fn new(enabled: bool) -> Option<Self> {
+        enabled.then(|| Self {
+            lines: LineChunker::new(),
+            logged: 0,
+        })
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        for line in self.lines.push(chunk) {
+            self.log(&line);
+        }
+    }
+
+    /// Logs the unterminated last line, if any, once stderr has ended.
+    fn finish(mut self) {
+        let last = std::mem::take(&mut self.lines).finish();
+        if let Some(line) = last {
+            self.log(&line);
+        }
+    }
+
+    fn log(&mut self, line: &str) {
+        match self.logged.cmp(&MAX_TRACED_STDERR_LINES) {
+            std::cmp::Ordering::Less => {
+                tracing::debug!(target: STDERR_TRACE_TARGET, "{line}");
+            }
+            std::cmp::Ordering::Equal => {
+                tracing::debug!(
+                    target: STDERR_TRACE_TARGET,
+                    "not logging more than {MAX_TRACED_STDERR_LINES} stderr lines"
+                );
+            }
+            std::cmp::Ordering::Greater => return,
+        }
+        self.logged += 1;
+    }
+}
+
+/// Watches one stream line by line for
+/// [`ExecParams::success_when_output_matches`]; each stream gets its own
+/// clone so their partial lines are kept apart. Notifies `matched` on the
+/// first matching line.
+#[derive(Clone)]
+struct OutputMatcher {
+    regex: Regex,
+    lines: LineChunker,
+    matched: Arc<Notify>,
+}
+
+impl OutputMatcher {
+    fn new(regex: Regex, matched: Arc<Notify>) -> Self {
+        Self {
+            regex,
+            lines: LineChunker::new(),
+            matched,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        let lines = self.lines.push(chunk);
+        if lines.iter().any(|line| self.regex.is_match(line)) {
+            // Stores a permit if the select loop is not waiting yet.
+            self.matched.notify_one();
+        }
+    }
+}
+
+/// Live side effects of reading `chunk`: an `ExecCommandOutputDelta` event
+/// (at most [`MAX_EXEC_OUTPUT_DELTAS_PER_CALL`] per `emitted_deltas` counter,
+/// and as fast as `throttle` allows) and, if requested, an echo to this
+/// process's terminal.
+async fn forward_chunk(
+    stream: Option<&StdoutStream>,
+    is_stderr: bool,
+    chunk: &[u8],
+    emitted_deltas: &mut usize,
+    tee_to_terminal: bool,
+    throttle: Option<&Mutex<DeltaThrottle>>,
+) {
+    if let Some(stream) = stream {
+        let delta = match throttle {
+            Some(throttle) => lock_throttle(throttle).admit(is_stderr, chunk, Instant::now()),
+            None => Some(chunk.to_vec()),
+        };
+        if let Some(delta) = delta {
+            send_delta(stream, is_stderr, delta, emitted_deltas).await;
+        }
+    }
+
+    if tee_to_terminal {
+        // Echoing is best-effort: a closed terminal must not abort capture.
+        let _ = if is_stderr {
+            write_to_terminal(tokio::io::stderr(), chunk).await
+        } else {
+            write_to_terminal(tokio::io::stdout(), chunk).await
+        };
+    }
+}
+
+/// Sends whatever `throttle` still holds back for one stream once it has
+/// ended, regardless of the rate.
+async fn flush_throttled(
+    stream: Option<&StdoutStream>,
+    is_stderr: bool,
+    emitted_deltas: &mut usize,
+    throttle: Option<&Mutex<DeltaThrottle>>,
+) {
+    if let (Some(stream), Some(throttle)) = (stream, throttle) {
+        let pending = lock_throttle(throttle).take_pending(is_stderr);
+        if !pending.is_empty() {
+            send_delta(stream, is_stderr, pending, emitted_deltas).await;
+        }
+    }
+}
+
+async fn send_delta(
+    stream: &StdoutStream,
+    is_stderr: bool,
+    chunk: Vec<u8>,
+    emitted_deltas: &mut usize,
+) {
+    if *emitted_deltas >= MAX_EXEC_OUTPUT_DELTAS_PER_CALL {
+        return;
+    }
+    let msg = EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent {
+        call_id: stream.call_id.clone(),
+        stream: if is_stderr {
+            ExecOutputStream::Stderr
+        } else {
+            ExecOutputStream::Stdout
+        },
+        chunk,
+    });
+    let event = Event {
+        id: stream.sub_id.clone(),
+        msg,
+    };
+    #[allow(clippy::let_unit_value)]
+    let _ = stream.tx_event.send(event).await;
+    *emitted_deltas += 1;
+}
+
+/// Token bucket behind [`StdoutStream::max_bytes_per_sec`], shared by the
+/// readers of both streams.
+#[derive(Debug)]
+struct DeltaThrottle {
+    max_bytes_per_sec: u64,
+    /// Bytes that may be sent now; refills continuously, up to one second's
+    /// worth.
+    budget: f64,
+    refilled_at: Instant,
+    /// Output held back, indexed by `is_stderr`.
+    pending: [Vec<u8>; 2],
+    /// Bytes dropped from `pending` since its last release, indexed by
+    /// `is_stderr`.
+    dropped: [usize; 2],
+}
+
+impl DeltaThrottle {
+    fn for_stream(stream: Option<&StdoutStream>) -> Option<Arc<Mutex<Self>>> {
+        let max_bytes_per_sec = stream?.max_bytes_per_sec?;
+        Some(Arc::new(Mutex::new(Self::new(
+            max_bytes_per_sec,
+            Instant::now(),
+        ))))
+    }
+
+    fn new(max_bytes_per_sec: NonZeroU64, now: Instant) -> Self {
+        Self {
+            max_bytes_per_sec: max_bytes_per_sec.get(),
+            budget: max_bytes_per_sec.get() as f64,
+            refilled_at: now,
+            pending: [Vec::new(), Vec::new()],
+            dropped: [0, 0],
+        }
+    }
+
+    /// Adds `chunk` to the output held back for its stream and returns all
+    /// of it if the budget covers it, after the marker for any bytes dropped
+    /// meanwhile.
+    fn admit(&mut self, is_stderr: bool, chunk: &[u8], now: Instant) -> Option<Vec<u8>> {
+        let rate = self.max_bytes_per_sec as f64;
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.budget = (self.budget + elapsed.as_secs_f64() * rate).min(rate);
+        self.refilled_at = now;
+
+        let index = usize::from(is_stderr);
+        let pending = &mut self.pending[index];
+        pending.extend_from_slice(chunk);
+        let keep = usize::try_from(self.max_bytes_per_sec).unwrap_or(usize::MAX);
+        if pending.len() > keep {
+            let excess = pending.len() - keep;
+            pending.drain(..excess);
+            self.dropped[index] += excess;
+        }
+        if (pending.len() as f64) > self.budget {
+            return None;
+        }
+        self.budget -= pending.len() as f64;
+        Some(self.take_pending(is_stderr))
+    }
+
+    /// Everything still held back for one stream, after the marker for any
+    /// bytes dropped meanwhile.
+    fn take_pending(&mut self, is_stderr: bool) -> Vec<u8> {
+        let index = usize::from(is_stderr);
+        let pending = std::mem::take(&mut self.pending[index]);
+        match std::mem::take(&mut self.dropped[index]) {
+            0 => pending,
+            dropped => {
+                let mut released = throttled_marker(dropped).into_bytes();
+                released.extend_from_slice(&pending);
+                released
+            }
+        }
+    }
+}
+
+/// Stands in for the `dropped` bytes [`DeltaThrottle`] skipped on a stream.
+/// They are still in the captured output.
+fn throttled_marker(dropped: usize) -> String {
+    format!("[{dropped} bytes of output throttled]\n")
+}
+
+fn lock_throttle(throttle: &Mutex<DeltaThrottle>) -> std::sync::MutexGuard<'_, DeltaThrottle> {
+    // The state stays consistent even if a holder panicked.
+    throttle
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Sends an [`ExecProgress`] every `heartbeat.interval` until aborted or the
+/// receiver goes away.
+async fn run_heartbeat(heartbeat: ExecHeartbeat, bytes_read: Arc<AtomicUsize>) {
+    let start = Instant::now();
+    let mut ticker = tokio::time::interval_at(start + heartbeat.interval, heartbeat.interval);
+    loop {
+        ticker.tick().await;
+        let progress = ExecProgress {
+            elapsed: start.elapsed(),
+            bytes_so_far: bytes_read.load(Ordering::Relaxed),
+        };
+        if heartbeat.tx.send(progress).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Resolves once `bytes_read` (bumped by the reader tasks for every chunk of
+/// output) has not changed for `idle_timeout`. Checked a few times per
+/// `idle_timeout`, so it may fire up to a quarter of it late.
+async fn wait_for_idle(idle_timeout: Duration, bytes_read: Arc<AtomicUsize>) {
+    let check_interval = (idle_timeout / 4).max(Duration::from_millis(10));
+    let mut ticker = tokio::time::interval(check_interval);
+    let mut last_seen = bytes_read.load(Ordering::Relaxed);
+    let mut last_activity = Instant::now();
+    loop {
+        ticker.tick().await;
+        let seen = bytes_read.load(Ordering::Relaxed);
+        if seen != last_seen {
+            last_seen = seen;
+            last_activity = Instant::now();
+        } else if last_activity.elapsed() >= idle_timeout {
+            return;
+        }
+    }
+}
+
+async fn write_to_terminal<W: AsyncWrite + Unpin>(mut writer: W, chunk: &[u8]) -> io::Result<()> {
+    writer.write_all(chunk).await?;
+    writer.flush().await
+}
+
+#[cfg(unix)]
+fn synthetic_exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    std::process::ExitStatus::from_raw(code)
+}
+
+#[cfg(windows)]
 fn synthetic_exit_status(code: i32) -> ExitStatus {
     use std::os::windows::process::ExitStatusExt;
     #[expect(clippy::unwrap_used)]
     std::process::ExitStatus::from_raw(code.try_into().unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::get_error_message_ui;
+    use crate::protocol::ResourceLimits;
+    use pretty_assertions::assert_eq;
+
+    thread_local! {
+        /// Makes [`super::start_kill`] fail on this thread.
+        pub(super) static FAIL_START_KILL: std::cell::Cell<bool> =
+            const { std::cell::Cell::new(false) };
+    }
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn ntstatus_crash_codes_have_causes() {
+        assert_eq!(ntstatus_crash_cause(0xC000_0005), Some("access violation"));
+        assert_eq!(ntstatus_crash_cause(0xC000_00FD), Some("stack overflow"));
+        assert_eq!(ntstatus_crash_cause(1), None);
+    }
+
+    #[test]
+    fn argv_limits_reject_oversized_commands() {
+        let env = HashMap::new();
+        let limits = ArgvLimits {
+            max_bytes: 1024,
+            max_args: Some(3),
+        };
+        check_argv_limits(&argv(&["echo", "hi"]), &env, limits).expect("small command");
+
+        let err = check_argv_limits(&argv(&["echo", "a", "b", "c"]), &env, limits)
+            .expect_err("too many arguments");
+        assert_eq!(
+            err.to_string(),
+            "command is too long: 4 arguments, more than the limit of 3; write the arguments to a file or split the command into smaller ones"
+        );
+
+        let long = "x".repeat(1024);
+        let err =
+            check_argv_limits(&argv(&["echo", &long]), &env, limits).expect_err("too many bytes");
+        assert!(matches!(err, CodexErr::CommandTooLong(_)), "{err:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn argv_limits_count_the_environment_against_arg_max() {
+        let arg_max = platform_arg_max().expect("ARG_MAX on unix");
+        let env = HashMap::from([("BIG".to_string(), "x".repeat(arg_max))]);
+        let limits = ArgvLimits {
+            max_bytes: usize::MAX,
+            max_args: None,
+        };
+        let err = check_argv_limits(&argv(&["true"]), &env, limits).expect_err("over ARG_MAX");
+        assert!(err.to_string().contains("ARG_MAX"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disposition_names_how_the_child_ended() {
+        let exited = ExitStatus::from_raw(1 << 8);
+        let signaled = ExitStatus::from_raw(libc::SIGTERM);
+        let wait_error = io::Error::other("wait failed");
+        assert_eq!(disposition(&exited, None, None), "exited");
+        assert_eq!(disposition(&signaled, None, None), "signaled");
+        assert_eq!(
+            disposition(&signaled, Some(KillReason::Timeout), None),
+            "timed-out"
+        );
+        assert_eq!(
+            disposition(&signaled, Some(KillReason::IdleTimeout), None),
+            "idle-timed-out"
+        );
+        assert_eq!(
+            disposition(&signaled, Some(KillReason::Cancelled), None),
+            "killed"
+        );
+        assert_eq!(
+            disposition(&signaled, Some(KillReason::OutputMatched), None),
+            "output-matched"
+        );
+        assert_eq!(
+            disposition(&signaled, None, Some(&wait_error)),
+            "wait-failed"
+        );
+    }
+
+    #[test]
+    fn strip_ansi_removes_escape_sequences() {
+        assert_eq!(b"plain text\n".to_vec(), strip_ansi(b"plain text\n"));
+        assert_eq!(
+            b"error: failed".to_vec(),
+            strip_ansi(b"\x1b[1;31merror\x1b[0m: failed")
+        );
+        assert_eq!(
+            b"50%100%".to_vec(),
+            strip_ansi(b"50%\x1b[2K\x1b[1G100%\x1b[?25h")
+        );
+        assert_eq!(
+            b"link".to_vec(),
+            strip_ansi(b"\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\")
+        );
+        assert_eq!(b"ok".to_vec(), strip_ansi(b"\x1b]0;title\x07ok"));
+        assert_eq!(b"ab".to_vec(), strip_ansi(b"a\x1b(Bb\x1b7"));
+        assert_eq!(b"cut ".to_vec(), strip_ansi(b"cut \x1b[38;5"));
+        assert_eq!(
+            "héllo".as_bytes().to_vec(),
+            strip_ansi("h\x1b[4méllo".as_bytes())
+        );
+    }
+
+    #[test]
+    fn collapse_repeated_lines_merges_runs_of_identical_lines() {
+        assert_eq!(
+            b"a\nb (repeated 3 times)\nc\nb\n".to_vec(),
+            collapse_repeated_lines(b"a\nb\nb\nb\nc\nb\n")
+        );
+        assert_eq!(
+            b"Downloading 100% (repeated 2 times)\r\ndone".to_vec(),
+            collapse_repeated_lines(
+                b"Downloading 10%\rDownloading 100%\r\nDownloading 100%\r\ndone"
+            )
+        );
+        assert_eq!(
+            b"x (repeated 2 times)".to_vec(),
+            collapse_repeated_lines(b"x\nx")
+        );
+        assert_eq!(b"\n\n\n".to_vec(), collapse_repeated_lines(b"\n\n\n"));
+        assert_eq!(Vec::<u8>::new(), collapse_repeated_lines(b""));
+    }
+
+    #[test]
+    fn normalize_newlines_converts_crlf_and_lone_cr() {
+        assert_eq!(b"a\nb\nc\n".to_vec(), normalize_newlines(b"a\r\nb\rc\n"));
+        assert_eq!(b"\n\n".to_vec(), normalize_newlines(b"\r\r\n"));
+        assert_eq!(b"end\n".to_vec(), normalize_newlines(b"end\r"));
+        assert_eq!(Vec::<u8>::new(), normalize_newlines(b""));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn normalize_newlines_applies_to_captured_output() {
+        let params = ExecParams {
+            command: argv(&["printf", "one\\r\\ntwo\\rthree\\n"]),
+            network_required: false,
+            normalize_newlines: true,
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("printf runs");
+        assert_eq!(output.stdout.text, "one\ntwo\nthree\n");
+        assert_eq!(output.aggregated_output.text, "one\ntwo\nthree\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn env_expansion_resolves_references_before_spawning() {
+        let base = HashMap::from([("WHO".to_string(), "world".to_string())]);
+        let params = ExecParams::builder(["sh", "-c", "printf %s \"$GREETING\""])
+            .env_var("GREETING", "hello ${WHO}")
+            .env_expansion(EnvExpansion::Base(base))
+            .build();
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("sh runs");
+        assert_eq!(output.stdout.text, "hello world");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn output_file_keeps_the_complete_output() {
+        let cwd = tempfile::tempdir().expect("tempdir");
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        let linux_sandbox_exe = None;
+        let run = |output_file: &str, include_stderr: bool| {
+            let mut params = ExecParams::builder(["sh", "-c", "echo out; echo err >&2"])
+                .cwd(cwd.path())
+                .build();
+            params.output_file = Some(PathBuf::from(output_file));
+            params.output_file_include_stderr = include_stderr;
+            process_exec_tool_call(params, SandboxType::None, &policy, &linux_sandbox_exe, None)
+        };
+
+        let output = run("stdout.log", false).await.expect("sh runs");
+        let path = cwd.path().join("stdout.log");
+        assert_eq!(output.output_file.as_deref(), Some(path.as_path()));
+        assert_eq!(std::fs::read_to_string(&path).expect("log"), "out\n");
+        // The model still gets the captured output as usual.
+        assert_eq!(output.stdout.text, "out\n");
+
+        let output = run("all.log", true).await.expect("sh runs");
+        let log = std::fs::read_to_string(output.output_file.expect("saved")).expect("log");
+        assert!(log.contains("out\n") && log.contains("err\n"), "{log:?}");
+
+        let outside = cwd.path().parent().expect("parent").join("escaped.log");
+        let err = run(outside.to_str().expect("utf-8"), false)
+            .await
+            .expect_err("outside the writable roots");
+        assert!(matches!(
+            err,
+            CodexErr::Sandbox(SandboxErr::OutputFileNotWritable { path }) if path == outside
+        ));
+        assert!(!outside.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn output_file_is_not_written_through_a_planted_symlink() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let cwd = tmp.path().join("workspace");
+        std::fs::create_dir(&cwd).expect("workspace");
+        let outside = tmp.path().join("escaped.log");
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+
+        // The path is writable when checked before the run, but the command
+        // replaces it with a symlink out of the workspace.
+        let script = format!("ln -s '{}' out.log; echo out", outside.display());
+        let mut params = ExecParams::builder(["sh", "-c", script.as_str()])
+            .cwd(&cwd)
+            .build();
+        params.output_file = Some(PathBuf::from("out.log"));
+        let output = process_exec_tool_call(params, SandboxType::None, &policy, &None, None)
+            .await
+            .expect("sh runs");
+
+        assert_eq!(output.output_file, None);
+        assert_eq!(output.stdout.text, "out\n");
+        assert!(!outside.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn collapse_repeated_lines_applies_to_captured_output() {
+        let params = ExecParams {
+            command: argv(&[
+                "sh",
+                "-c",
+                "for i in 1 2 3 4; do echo waiting; done; echo ready",
+            ]),
+            network_required: false,
+            collapse_repeated_lines: true,
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("sh runs");
+        assert_eq!(output.stdout.text, "waiting (repeated 4 times)\nready\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn strip_ansi_applies_to_captured_output() {
+        let params = ExecParams {
+            command: argv(&["printf", "\\033[32mgreen\\033[0m\\n"]),
+            network_required: false,
+            strip_ansi: true,
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("printf runs");
+        assert_eq!(output.stdout.text, "green\n");
+        assert_eq!(output.aggregated_output.text, "green\n");
+    }
+
+    #[test]
+    fn escape_invalid_utf8_keeps_valid_text_and_escapes_the_rest() {
+        assert_eq!("héllo", escape_invalid_utf8("héllo".as_bytes()));
+        assert_eq!(
+            "caf\\xe9 \\xff\\xfe ok",
+            escape_invalid_utf8(b"caf\xe9 \xff\xfe ok")
+        );
+    }
+
+    #[test]
+    fn validate_command_rejects_empty_argv() {
+        let err = validate_command(&[]).expect_err("empty argv should be rejected");
+        assert_eq!(err.to_string(), "invalid command: command args are empty");
+    }
+
+    #[test]
+    fn validate_command_rejects_empty_program() {
+        let err = validate_command(&argv(&["", "foo"])).expect_err("empty program");
+        assert_eq!(
+            err.to_string(),
+            "invalid command: program name is an empty string"
+        );
+    }
+
+    #[test]
+    fn validate_command_rejects_nul_bytes() {
+        let err = validate_command(&argv(&["echo", "a\0b"])).expect_err("NUL byte");
+        assert_eq!(
+            err.to_string(),
+            "invalid command: argument 1 contains a NUL byte"
+        );
+    }
+
+    #[test]
+    fn validate_command_accepts_regular_argv() {
+        assert!(validate_command(&argv(&["echo", "hello"])).is_ok());
+    }
+
+    #[test]
+    fn check_denied_programs_matches_basename() {
+        let denied = argv(&["sudo"]);
+        let err = check_denied_programs(&argv(&["/usr/bin/sudo", "ls"]), &denied, None)
+            .expect_err("sudo should be denied");
+        assert_eq!(
+            get_error_message_ui(&err),
+            "program `sudo` is denied by the sandbox policy"
+        );
+        assert!(check_denied_programs(&argv(&["ls"]), &denied, None).is_ok());
+    }
+
+    #[test]
+    fn check_denied_programs_looks_through_shells_and_wrappers() {
+        let denied = argv(&["curl"]);
+        for command in [
+            argv(&["bash", "-lc", "curl https://example.com"]),
+            argv(&["sh", "-c", "echo hi && FOO=1 /usr/bin/curl -s x > out"]),
+            argv(&["bash", "-lc", "cat $(curl -s x)"]),
+            argv(&["env", "FOO=1", "curl", "x"]),
+            argv(&["xargs", "-0", "curl"]),
+            argv(&["timeout", "5s", "nice", "-n", "10", "curl"]),
+            argv(&["env", "bash", "-c", "xargs curl"]),
+        ] {
+            assert!(
+                check_denied_programs(&command, &denied, None).is_err(),
+                "{command:?} should be denied"
+            );
+        }
+        for command in [
+            argv(&["echo", "curl"]),
+            argv(&["env", "echo", "curl"]),
+            argv(&["bash", "-lc", "echo curl"]),
+        ] {
+            assert!(
+                check_denied_programs(&command, &denied, None).is_ok(),
+                "{command:?} should be allowed"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn check_denied_programs_follows_symlinks() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let target = dir.path().join("curl");
+        std::fs::write(&target, "#!/bin/sh\n").expect("write target");
+        std::fs::set_permissions(&target, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+            .expect("chmod");
+        std::os::unix::fs::symlink(&target, dir.path().join("fetch")).expect("symlink");
+
+        let path_env = dir.path().to_string_lossy().to_string();
+        let resolved = resolve_program("fetch", Some(&path_env), dir.path());
+        assert_eq!(Some(dir.path().join("fetch")), resolved);
+        let err = check_denied_programs(
+            &argv(&["fetch", "https://example.com"]),
+            &argv(&["curl"]),
+            resolved.as_deref(),
+        )
+        .expect_err("symlink to curl should be denied");
+        assert!(matches!(err, CodexErr::Sandbox(SandboxErr::Denied(..))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_as_needs_the_linux_sandbox_and_privileges() {
+        // SAFETY: these calls have no preconditions and cannot fail.
+        let (uid, gid, euid) = unsafe { (libc::getuid(), libc::getgid(), libc::geteuid()) };
+        let current = RunAsUser { uid, gid };
+
+        assert!(check_run_as(None, SandboxType::None).is_ok());
+        assert!(check_run_as(Some(current), SandboxType::LinuxSeccomp).is_ok());
+        assert!(matches!(
+            check_run_as(Some(current), SandboxType::MacosSeatbelt),
+            Err(CodexErr::Sandbox(SandboxErr::RunAsUnavailable { .. }))
+        ));
+
+        let other = RunAsUser {
+            uid: uid.wrapping_add(1),
+            gid,
+        };
+        let result = check_run_as(Some(other), SandboxType::LinuxSeccomp);
+        if euid == 0 {
+            assert!(result.is_ok());
+        } else {
+            assert!(matches!(
+                result,
+                Err(CodexErr::Sandbox(SandboxErr::RunAsUnavailable { .. }))
+            ));
+        }
+    }
+
+    fn network_params() -> ExecParams {
+        ExecParams {
+            command: argv(&["npm", "install"]),
+            cwd: std::env::temp_dir(),
+            network_required: true,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    #[tokio::test]
+    async fn network_required_fails_fast_without_network() {
+        let policy = SandboxPolicy::new_read_only_policy();
+        let err = process_exec_tool_call(network_params(), SandboxType::Auto, &policy, &None, None)
+            .await
+            .expect_err("no network in a read-only sandbox");
+        assert!(matches!(
+            err,
+            CodexErr::Sandbox(SandboxErr::NetworkRequired)
+        ));
+    }
+
+    #[tokio::test]
+    async fn network_required_is_only_a_hint_when_network_is_available() {
+        let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        if let SandboxPolicy::WorkspaceWrite { network_access, .. } = &mut policy {
+            *network_access = true;
+        }
+        // Spawning may still fail (no such sandbox or program here); what
+        // matters is that the network check lets the command through.
+        let result = process_exec_tool_call(
+            network_params(),
+            SandboxType::LinuxSeccomp,
+            &policy,
+            &None,
+            None,
+        )
+        .await;
+        assert!(!matches!(
+            result,
+            Err(CodexErr::Sandbox(SandboxErr::NetworkRequired))
+        ));
+    }
+
+    #[test]
+    fn sandbox_type_resolves_to_what_this_platform_has() {
+        let platform_sandbox = get_platform_sandbox();
+        assert_eq!(SandboxType::None.resolve().ok(), Some(SandboxType::None));
+        assert_eq!(SandboxType::Auto.resolve().ok(), platform_sandbox);
+        for requested in [SandboxType::MacosSeatbelt, SandboxType::LinuxSeccomp] {
+            match requested.resolve() {
+                Ok(resolved) => {
+                    assert_eq!(Some(resolved), platform_sandbox);
+                    assert_eq!(resolved, requested);
+                }
+                Err(CodexErr::SandboxNotSupportedOnPlatform {
+                    requested: reported,
+                    platform,
+                }) => {
+                    assert_ne!(Some(requested), platform_sandbox);
+                    assert_eq!(reported, requested);
+                    assert_eq!(platform, std::env::consts::OS);
+                }
+                Err(other) => panic!("unexpected error: {other:?}"),
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn seatbelt_on_linux_fails_before_spawning() {
+        let err = process_exec_tool_call(
+            ExecParams::builder(["true"]).build(),
+            SandboxType::MacosSeatbelt,
+            &SandboxPolicy::new_read_only_policy(),
+            &None,
+            None,
+        )
+        .await
+        .expect_err("there is no seatbelt on linux");
+        assert_eq!(
+            err.to_string(),
+            "the MacosSeatbelt sandbox is not supported on linux"
+        );
+    }
+
+    fn approval_params(command: &[&str], approval: ExecApproval) -> ExecParams {
+        ExecParams {
+            command: argv(command),
+            approval: Some(approval),
+            network_required: false,
+            ..network_params()
+        }
+    }
+
+    #[tokio::test]
+    async fn denied_commands_are_never_spawned() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let marker = tmp.path().join("marker");
+        let approval = ExecApproval::new(|_| Box::pin(async { Approval::Deny }));
+        let params = approval_params(&["touch", &marker.to_string_lossy()], approval);
+
+        let err = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect_err("denied command");
+        assert!(matches!(err, CodexErr::Rejected));
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn remembered_approvals_are_not_asked_again() {
+        let asked = Arc::new(AtomicUsize::new(0));
+        let approval = ExecApproval::new({
+            let asked = asked.clone();
+            move |_| {
+                asked.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Approval::AllowAndRememberForSession })
+            }
+        });
+
+        for _ in 0..2 {
+            let output = process_exec_tool_call(
+                approval_params(&["true"], approval.clone()),
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                &None,
+                None,
+            )
+            .await
+            .expect("approved command");
+            assert_eq!(output.exit_code, 0);
+        }
+        assert_eq!(asked.load(Ordering::SeqCst), 1);
+    }
+
+    fn rule(program: &str, args: &[&str]) -> UnsandboxedCommand {
+        UnsandboxedCommand {
+            program: program.to_string(),
+            args: argv(args),
+        }
+    }
+
+    #[test]
+    fn unsandboxed_commands_match_exact_program_and_args() {
+        let rules = vec![
+            rule("git", &["status"]),
+            rule("git", &["status", "--short"]),
+            rule("ls", &[]),
+        ];
+
+        assert!(is_unsandboxed_command(&argv(&["git", "status"]), &rules));
+        assert!(is_unsandboxed_command(
+            &argv(&["git", "status", "--short"]),
+            &rules
+        ));
+        assert!(is_unsandboxed_command(&argv(&["ls"]), &rules));
+
+        assert!(!is_unsandboxed_command(&argv(&["git"]), &rules));
+        assert!(!is_unsandboxed_command(&argv(&["git", "push"]), &rules));
+        assert!(!is_unsandboxed_command(
+            &argv(&["git", "status", "-c"]),
+            &rules
+        ));
+        assert!(!is_unsandboxed_command(
+            &argv(&["/usr/bin/git", "status"]),
+            &rules
+        ));
+        assert!(!is_unsandboxed_command(&argv(&["ls", "-la"]), &rules));
+        assert!(!is_unsandboxed_command(&argv(&["git", "status"]), &[]));
+        assert!(!is_unsandboxed_command(&[], &rules));
+    }
+
+    #[test]
+    fn unsandboxed_commands_do_not_match_reordered_or_repeated_args() {
+        let rules = vec![rule("git", &["status", "--short"])];
+
+        assert!(!is_unsandboxed_command(
+            &argv(&["git", "--short", "status"]),
+            &rules
+        ));
+        assert!(!is_unsandboxed_command(
+            &argv(&["git", "--short", "status", "--short"]),
+            &rules
+        ));
+        assert!(!is_unsandboxed_command(
+            &argv(&["git", "status", "status"]),
+            &rules
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn missing_sandbox_helper_is_blamed_instead_of_the_command() {
+        let helper = std::env::temp_dir().join("no-such-dir/codex-linux-sandbox");
+        let err = process_exec_tool_call(
+            ExecParams::builder(["true"]).build(),
+            SandboxType::LinuxSeccomp,
+            &SandboxPolicy::new_read_only_policy(),
+            &Some(helper.clone()),
+            None,
+        )
+        .await
+        .expect_err("the helper does not exist");
+        match err {
+            CodexErr::LandlockSandboxExecutableUnusable { path, reason } => {
+                assert_eq!(path, helper);
+                assert_eq!(reason, "it does not exist");
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn unsandboxed_commands_skip_the_sandbox() {
+        let policy = SandboxPolicy::new_read_only_policy();
+        let params = ExecParams {
+            command: argv(&["true"]),
+            network_required: false,
+            ..network_params()
+        };
+
+        // Without a rule, the Linux sandbox is required (and missing here).
+        let err = process_exec_tool_call(
+            params.clone(),
+            SandboxType::LinuxSeccomp,
+            &policy,
+            &None,
+            None,
+        )
+        .await
+        .expect_err("sandbox executable is missing");
+        assert!(matches!(
+            err,
+            CodexErr::LandlockSandboxExecutableNotProvided
+        ));
+
+        let params = ExecParams {
+            unsandboxed_commands: vec![rule("true", &[])],
+            ..params
+        };
+        let output =
+            process_exec_tool_call(params, SandboxType::LinuxSeccomp, &policy, &None, None)
+                .await
+                .expect("allowlisted command runs unsandboxed");
+        assert_eq!(output.exit_code, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn denied_programs_apply_without_a_sandbox() {
+        let params = ExecParams {
+            command: argv(&["true"]),
+            network_required: false,
+            denied_programs: argv(&["true"]),
+            ..network_params()
+        };
+        let err = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect_err("true is denied");
+        assert!(matches!(err, CodexErr::Sandbox(SandboxErr::Denied(..))));
+    }
+
+    #[tokio::test]
+    async fn argv_limits_come_from_the_params() {
+        let params = ExecParams {
+            command: argv(&["echo", "a", "b"]),
+            network_required: false,
+            argv_limits: ArgvLimits {
+                max_args: Some(2),
+                ..ArgvLimits::default()
+            },
+            ..network_params()
+        };
+        let err = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect_err("three arguments are over the limit");
+        assert!(matches!(err, CodexErr::CommandTooLong(..)));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn on_kill_is_told_about_a_timeout() {
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let on_kill = ExecKillHook::new({
+            let reasons = reasons.clone();
+            move |reason| {
+                if let Ok(mut reasons) = reasons.lock() {
+                    reasons.push(reason);
+                }
+            }
+        });
+        let run = |command: &[&str], timeout_ms| ExecParams {
+            command: argv(command),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_millis(timeout_ms)),
+            on_kill: Some(on_kill.clone()),
+            ..network_params()
+        };
+
+        let result = process_exec_tool_call(
+            run(&["sleep", "5"], 100),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(CodexErr::Sandbox(SandboxErr::Timeout { .. }))
+        ));
+        assert_eq!(*reasons.lock().unwrap(), vec![KillReason::Timeout]);
+
+        process_exec_tool_call(
+            run(&["true"], 5_000),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("true exits on its own");
+        assert_eq!(*reasons.lock().unwrap(), vec![KillReason::Timeout]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn timeout_keeps_output_when_a_grandchild_holds_the_pipes() {
+        // Only `sh` is killed; the backgrounded `sleep` keeps the pipes open
+        // past the partial-output join timeout.
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "echo before; sleep 5 & wait"]),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_millis(200)),
+            ..network_params()
+        };
+
+        let result = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        let Err(CodexErr::Sandbox(SandboxErr::Timeout { output })) = result else {
+            panic!("expected a timeout, got {result:?}");
+        };
+        assert_eq!(output.stdout.text, "before\n");
+        assert_eq!(output.stdout.truncated_after_lines, Some(1));
+        assert_eq!(output.aggregated_output.text, "before\n");
+        assert_eq!(output.aggregated_output.truncated_after_lines, Some(1));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failed_kill_keeps_the_output_read_so_far() {
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "echo before; sleep 3"]),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_millis(200)),
+            ..network_params()
+        };
+
+        // The test runtime is single-threaded, so the kill happens here.
+        FAIL_START_KILL.with(|fail| fail.set(true));
+        let result = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        FAIL_START_KILL.with(|fail| fail.set(false));
+        let Err(CodexErr::ExecIo { source, output }) = result else {
+            panic!("expected an I/O error with output, got {result:?}");
+        };
+        assert_eq!(source.to_string(), "injected start_kill failure");
+        assert_eq!(output.stdout.text, "before\n");
+        assert_eq!(output.stdout.truncated_after_lines, Some(1));
+    }
+
+    #[test]
+    fn count_lines_counts_an_unterminated_last_line() {
+        assert_eq!(count_lines(b""), 0);
+        assert_eq!(count_lines(b"a\n"), 1);
+        assert_eq!(count_lines(b"a\nb"), 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn success_exit_codes_decide_whether_the_command_succeeded() {
+        let run = |success_exit_codes| ExecParams {
+            command: argv(&["sh", "-c", "exit 1"]),
+            network_required: false,
+            success_exit_codes,
+            ..network_params()
+        };
+
+        for (success_exit_codes, success) in [(None, false), (Some(vec![0, 1]), true)] {
+            let output = process_exec_tool_call(
+                run(success_exit_codes),
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                &None,
+                None,
+            )
+            .await
+            .expect("the exit code is reported, not an error");
+            assert_eq!(output.exit_code, 1);
+            assert_eq!(output.success, success);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn started_at_and_finished_at_bracket_the_command() {
+        let before = SystemTime::now();
+        let output = process_exec_tool_call(
+            ExecParams {
+                command: argv(&["sleep", "0.2"]),
+                network_required: false,
+                ..network_params()
+            },
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("sleep exits on its own");
+        let after = SystemTime::now();
+
+        assert!(before <= output.started_at);
+        assert!(output.finished_at <= after);
+        let elapsed = output
+            .finished_at
+            .duration_since(output.started_at)
+            .expect("finished after it started");
+        assert!(elapsed >= Duration::from_millis(200), "{elapsed:?}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn stdin_source_is_streamed_to_the_command_and_then_closed() {
+        let run = |command: &[&str], stdin_source| ExecParams {
+            command: argv(command),
+            network_required: false,
+            stdin_source: Some(stdin_source),
+            ..network_params()
+        };
+
+        let output = process_exec_tool_call(
+            run(
+                &["tr", "a-z", "A-Z"],
+                StdinSource::new(&b"hello\nworld\n"[..]),
+            ),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("tr reads until stdin is closed");
+        assert_eq!(output.stdout.text, "HELLO\nWORLD\n");
+
+        // More than a pipe holds in both directions: only works if stdin is
+        // fed while the output is read.
+        let input = vec![b'x'; 1024 * 1024];
+        let output = process_exec_tool_call(
+            run(
+                &["sh", "-c", "cat >&2; echo done"],
+                StdinSource::new(std::io::Cursor::new(input)),
+            ),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("cat copies all of stdin");
+        assert_eq!(output.stdout.text, "done\n");
+
+        // A source that was already used up leaves stdin empty.
+        let source = StdinSource::new(&b"once\n"[..]);
+        for expected in ["once\n", ""] {
+            let output = process_exec_tool_call(
+                run(&["cat"], source.clone()),
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                &None,
+                None,
+            )
+            .await
+            .expect("cat succeeds");
+            assert_eq!(output.stdout.text, expected);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn collect_diagnostics_reports_a_sandbox_error_with_secrets_redacted() {
+        let collected = Arc::new(Mutex::new(Vec::new()));
+        let collect_diagnostics = DiagnosticsHook::new({
+            let collected = collected.clone();
+            move |diagnostics: &Diagnostics| {
+                if let Ok(mut collected) = collected.lock() {
+                    collected.push(diagnostics.clone());
+                }
+            }
+        });
+        let run = |command: &[&str]| ExecParams {
+            command: argv(command),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_millis(100)),
+            env: HashMap::from([
+                ("API_KEY".to_string(), "hunter2".to_string()),
+                ("LANG".to_string(), "C".to_string()),
+            ]),
+            collect_diagnostics: Some(collect_diagnostics.clone()),
+            ..network_params()
+        };
+
+        process_exec_tool_call(
+            run(&["true"]),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("true succeeds");
+        assert_eq!(collected.lock().unwrap().len(), 0);
+
+        let result = process_exec_tool_call(
+            run(&["sleep", "5"]),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(CodexErr::Sandbox(SandboxErr::Timeout { .. }))
+        ));
+        let collected = collected.lock().unwrap();
+        let [diagnostics] = collected.as_slice() else {
+            panic!("expected one report, got {collected:?}");
+        };
+        assert_eq!(diagnostics.command, ["sleep", "5"]);
+        assert_eq!(diagnostics.error, "command timed out");
+        assert_eq!(diagnostics.sandbox_type, SandboxType::None);
+        assert_eq!(diagnostics.sandbox_argv, Vec::<String>::new());
+        assert_eq!(diagnostics.env["API_KEY"], REDACTED);
+        assert_eq!(diagnostics.env["LANG"], "C");
+
+        // The launcher invocation carries the redacted environment too.
+        let linux = Diagnostics::collect(
+            argv(&["ls"]),
+            PathBuf::from("/work"),
+            &HashMap::from([("GITHUB_TOKEN".to_string(), "hunter2".to_string())]),
+            SandboxType::LinuxSeccomp,
+            &SandboxPolicy::new_read_only_policy(),
+            None,
+            Some(Path::new("/opt/codex-linux-sandbox")),
+            &SandboxErr::Denied(1, String::new(), String::new()),
+        );
+        assert_eq!(linux.sandbox_argv[0], "/opt/codex-linux-sandbox");
+        assert_eq!(linux.sandbox_argv[1], "/work");
+        assert!(!linux.sandbox_argv.concat().contains("hunter2"));
+        assert_eq!(linux.exit_code, Some(1));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn idle_timeout_kills_a_silent_command_but_not_a_chatty_one() {
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let on_kill = ExecKillHook::new({
+            let reasons = reasons.clone();
+            move |reason| {
+                if let Ok(mut reasons) = reasons.lock() {
+                    reasons.push(reason);
+                }
+            }
+        });
+        let run = |script: &str| ExecParams {
+            command: argv(&["bash", "-c", script]),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_secs(5)),
+            idle_timeout_ms: Some(300),
+            on_kill: Some(on_kill.clone()),
+            ..network_params()
+        };
+
+        let start = Instant::now();
+        let result = process_exec_tool_call(
+            run("echo started; sleep 5"),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        let Err(CodexErr::Sandbox(SandboxErr::IdleTimeout { output })) = result else {
+            panic!("expected an idle timeout, got {result:?}");
+        };
+        assert_eq!(output.stdout.text, "started\n");
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert_eq!(*reasons.lock().unwrap(), vec![KillReason::IdleTimeout]);
+
+        // Runs for well over the idle timeout, but never goes quiet for long.
+        let output = process_exec_tool_call(
+            run("for i in 1 2 3 4 5 6; do echo $i; sleep 0.1; done"),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("chatty command is not idle");
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(*reasons.lock().unwrap(), vec![KillReason::IdleTimeout]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn success_when_output_matches_stops_a_command_that_is_ready() {
+        let reasons = Arc::new(Mutex::new(Vec::new()));
+        let on_kill = ExecKillHook::new({
+            let reasons = reasons.clone();
+            move |reason| {
+                if let Ok(mut reasons) = reasons.lock() {
+                    reasons.push(reason);
+                }
+            }
+        });
+        let run = |script: &str| ExecParams {
+            command: argv(&["bash", "-c", script]),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_secs(5)),
+            on_kill: Some(on_kill.clone()),
+            success_when_output_matches: Some(Regex::new("listening on [0-9]+").expect("regex")),
+            ..network_params()
+        };
+
+        let start = Instant::now();
+        let output = process_exec_tool_call(
+            run("echo booting; echo 'listening on 8080' >&2; sleep 5"),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("a matching server counts as a success");
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.stdout.text, "booting\n");
+        assert_eq!(output.stderr.text, "listening on 8080\n");
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert_eq!(*reasons.lock().unwrap(), vec![KillReason::OutputMatched]);
+
+        // Without a match, the exit code is reported as usual.
+        let output = process_exec_tool_call(
+            run("echo 'listening on port'; exit 3"),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("bash runs");
+        assert_eq!(output.exit_code, 3);
+        assert_eq!(*reasons.lock().unwrap(), vec![KillReason::OutputMatched]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn max_open_files_limits_the_child_and_is_blamed_for_emfile() {
+        let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        if let SandboxPolicy::WorkspaceWrite {
+            resource_limits, ..
+        } = &mut policy
+        {
+            *resource_limits = ResourceLimits {
+                max_open_files: Some(64),
+                ..Default::default()
+            };
+        }
+        let run = |script: &str| {
+            process_exec_tool_call(
+                ExecParams::builder(["sh", "-c", script]).build(),
+                SandboxType::None,
+                &policy,
+                &None,
+                None,
+            )
+        };
+
+        let output = run("ulimit -n").await.expect("sh runs");
+        assert_eq!(output.stdout.text, "64\n");
+
+        let result = run("echo 'open: Too many open files' >&2; exit 1").await;
+        let Err(CodexErr::Sandbox(SandboxErr::OpenFileLimit { limit, output })) = result else {
+            panic!("expected an open file limit error, got {result:?}");
+        };
+        assert_eq!(limit, 64);
+        assert_eq!(output.stderr.text, "open: Too many open files\n");
+
+        // Other failures are reported as usual.
+        let output = run("exit 1").await.expect("sh runs");
+        assert_eq!(output.exit_code, 1);
+
+        // So is a command that mentions EMFILE but succeeds.
+        let output = run("echo 'open: Too many open files' >&2")
+            .await
+            .expect("sh runs");
+        assert_eq!(output.exit_code, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn emfile_is_not_blamed_on_an_unset_open_file_limit() {
+        let result = process_exec_tool_call(
+            ExecParams::builder(["sh", "-c", "echo 'open: Too many open files' >&2; exit 1"])
+                .build(),
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        let Ok(output) = result else {
+            panic!("expected the failure to be reported as usual, got {result:?}");
+        };
+        assert_eq!(output.exit_code, 1);
+        assert_eq!(output.stderr.text, "open: Too many open files\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sigkill_without_an_oom_kill_is_a_plain_signal() {
+        let params = ExecParams {
+            command: argv(&["bash", "-c", "kill -KILL $$"]),
+            network_required: false,
+            ..network_params()
+        };
+        let result = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(CodexErr::Sandbox(SandboxErr::Signal {
+                signal: SIGKILL_CODE,
+                ..
+            }))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn on_spawn_reports_the_child_pid() {
+        let reported = Arc::new(Mutex::new(None));
+        let on_spawn = ExecSpawnHook::new({
+            let reported = reported.clone();
+            move |pid| {
+                if let Ok(mut reported) = reported.lock() {
+                    *reported = Some(pid);
+                }
+            }
+        });
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "echo $$"]),
+            network_required: false,
+            on_spawn: Some(on_spawn),
+            ..network_params()
+        };
+
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("sh runs");
+        let pid = *reported.lock().unwrap();
+        assert_eq!(
+            pid.map(|pid| pid.to_string()),
+            Some(output.stdout.text.trim().to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn detached_commands_keep_running_after_the_call_returns() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let marker = tmp.path().join("marker");
+        let params = ExecParams {
+            command: argv(&[
+                "sh",
+                "-c",
+                &format!("sleep 0.5; touch '{}'", marker.display()),
+            ]),
+            network_required: false,
+            detach: true,
+            ..network_params()
+        };
+
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("detached spawn");
+        assert!(matches!(output.outcome, ExecOutcome::Detached { pid } if pid > 0));
+        assert!(output.run_duration.is_zero());
+        assert!(!marker.exists(), "call should not wait for the command");
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !marker.exists() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert!(marker.exists(), "detached command should outlive the call");
+    }
+
+    #[test]
+    fn timeout_distinguishes_default_from_unbounded() {
+        assert_eq!(Timeout::from_ms(None), Timeout::Default);
+        assert_eq!(Timeout::from_ms(Some(0)).duration(), Some(Duration::ZERO));
+        assert_eq!(
+            Timeout::Default.duration(),
+            Some(Duration::from_millis(DEFAULT_TIMEOUT_MS))
+        );
+        assert_eq!(Timeout::None.duration(), None);
+        assert_eq!(
+            Timeout::Duration(Duration::from_secs(3 * 60 * 60)).duration(),
+            Some(Duration::from_secs(3 * 60 * 60))
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn inherited_fd_is_readable_by_the_command() {
+        let mut input = tempfile::tempfile().expect("create temp file");
+        std::io::Write::write_all(&mut input, b"streamed input\n").expect("write input");
+        std::io::Seek::rewind(&mut input).expect("rewind input");
+
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "cat <&3"]),
+            network_required: false,
+            inherited_fds: vec![InheritedFd::new(input, 3).expect("fd 3 is not stdio")],
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("cat runs");
+        assert_eq!(output.stdout.text, "streamed input\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn pty_gives_the_command_a_terminal() {
+        async fn run(pty: bool) -> Result<ExecToolCallOutput> {
+            let script =
+                "if test -t 0 && test -t 1; then echo tty; else echo pipe; fi; echo err >&2";
+            let params = ExecParams {
+                command: argv(&["sh", "-c", script]),
+                network_required: false,
+                pty,
+                ..network_params()
+            };
+            process_exec_tool_call(
+                params,
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                &None,
+                None,
+            )
+            .await
+        }
+
+        let output = run(true).await.expect("sh runs in a pty");
+        assert_eq!(output.stdout.text, "tty\nerr\n");
+        assert_eq!(output.stderr.text, "");
+        assert_eq!(output.aggregated_output.text, "tty\nerr\n");
+
+        let output = run(false).await.expect("sh runs with pipes");
+        assert_eq!(output.stdout.text, "pipe\n");
+        assert_eq!(output.stderr.text, "err\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inherited_fd_cannot_replace_stdio() {
+        let file = tempfile::tempfile().expect("create temp file");
+        let err = InheritedFd::new(file, 1).expect_err("stdout is reserved");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unbounded_timeout_waits_for_the_command() {
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "sleep 0.2; echo done"]),
+            network_required: false,
+            timeout: Timeout::None,
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("command without a timeout");
+        assert_eq!(output.stdout.text, "done\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_exiting_just_before_the_timeout_reports_its_exit_status() {
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "sleep 0.3; exit 3"]),
+            network_required: false,
+            timeout: Timeout::Duration(Duration::from_millis(400)),
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("command exits before the timeout");
+        assert_eq!(output.exit_code, 3);
+    }
+
+    #[test]
+    fn output_encoding_parses_utf8_and_code_pages() {
+        assert_eq!("UTF-8".parse(), Ok(OutputEncoding::Utf8));
+        assert_eq!("65001".parse(), Ok(OutputEncoding::Utf8));
+        assert_eq!("cp1252".parse(), Ok(OutputEncoding::CodePage(1252)));
+        assert_eq!("936".parse(), Ok(OutputEncoding::CodePage(936)));
+        assert!("latin-1".parse::<OutputEncoding>().is_err());
+    }
+
+    #[cfg(all(target_os = "windows", feature = "output-encoding"))]
+    #[test]
+    fn code_page_output_is_decoded() {
+        let output = StreamOutput {
+            text: b"caf\xe9".to_vec(),
+            truncated_after_lines: None,
+        };
+        assert_eq!(output.decode(OutputEncoding::CodePage(1252)).text, "café");
+    }
+
+    #[cfg(not(all(target_os = "windows", feature = "output-encoding")))]
+    #[test]
+    fn code_page_output_falls_back_to_utf8() {
+        let output = StreamOutput {
+            text: b"caf\xe9".to_vec(),
+            truncated_after_lines: None,
+        };
+        assert_eq!(
+            output.decode(OutputEncoding::CodePage(1252)).text,
+            "caf\u{fffd}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn output_filter_redacts_captured_output() {
+        let params = ExecParams {
+            command: argv(&["sh", "-c", "echo token=hunter2; echo token=hunter2 >&2"]),
+            network_required: false,
+            output_filter: Some(ExecOutputFilter::redact(vec![
+                regex_lite::Regex::new("hunter[0-9]").expect("valid regex"),
+            ])),
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("sh runs");
+        assert_eq!(output.stdout.text, "token=[REDACTED]\n");
+        assert_eq!(output.stderr.text, "token=[REDACTED]\n");
+        assert!(!output.aggregated_output.text.contains("hunter2"));
+        assert!(output.filtered);
+
+        let params = ExecParams {
+            command: argv(&["echo", "nothing to hide"]),
+            network_required: false,
+            output_filter: Some(ExecOutputFilter::redact(vec![
+                regex_lite::Regex::new("hunter[0-9]").expect("valid regex"),
+            ])),
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("echo runs");
+        assert_eq!(output.stdout.text, "nothing to hide\n");
+        assert!(!output.filtered);
+    }
+
+    #[test]
+    fn rewrite_command_reports_the_original_only_when_changed() {
+        let mut params = ExecParams {
+            command: argv(&["python", "x.py"]),
+            command_rewriter: Some(CommandRewriter::new(|mut command| {
+                if command[0] == "python" {
+                    command[0] = "python3".to_string();
+                }
+                command
+            })),
+            ..network_params()
+        };
+        let rewriter = params.command_rewriter.clone();
+        assert_eq!(
+            rewrite_command(&mut params),
+            Some(argv(&["python", "x.py"]))
+        );
+        assert_eq!(params.command, argv(&["python3", "x.py"]));
+        // Applied once: `process_exec_tool_call` does not rewrite it again.
+        assert!(params.command_rewriter.is_none());
+
+        params.command_rewriter = rewriter;
+        assert_eq!(rewrite_command(&mut params), None);
+        assert_eq!(params.command, argv(&["python3", "x.py"]));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_rewriter_runs_the_rewritten_command() {
+        let params = ExecParams {
+            command: argv(&["echo", "hello"]),
+            network_required: false,
+            command_rewriter: Some(CommandRewriter::new(|command| {
+                [argv(&["echo", "rewritten:"]), command[1..].to_vec()].concat()
+            })),
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            None,
+        )
+        .await
+        .expect("echo runs");
+        assert_eq!(output.stdout.text, "rewritten: hello\n");
+    }
+
+    #[test]
+    fn delta_throttle_holds_back_output_over_the_rate() {
+        let start = Instant::now();
+        let mut throttle = DeltaThrottle::new(NonZeroU64::new(10).expect("non-zero"), start);
+        assert_eq!(
+            throttle.admit(false, b"0123456789", start),
+            Some(b"0123456789".to_vec())
+        );
+        // The budget is used up, so these wait...
+        assert_eq!(throttle.admit(false, b"abc", start), None);
+        assert_eq!(throttle.admit(true, b"err", start), None);
+        // ...until enough of it has refilled, and then go out together.
+        let later = start + Duration::from_millis(500);
+        assert_eq!(throttle.admit(false, b"de", later), Some(b"abcde".to_vec()));
+        // Only the most recent second's worth is held back; what is dropped
+        // is accounted for once, ahead of the rest.
+        assert_eq!(throttle.admit(true, b"0123456789XY", later), None);
+        assert_eq!(throttle.admit(true, b"Z", later), None);
+        assert_eq!(
+            throttle.take_pending(true),
+            b"[6 bytes of output throttled]\n3456789XYZ".to_vec()
+        );
+        assert_eq!(throttle.take_pending(true), Vec::<u8>::new());
+        assert_eq!(throttle.take_pending(false), Vec::<u8>::new());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn throttled_stream_keeps_the_captured_output_intact() {
+        let (tx_event, rx_event) = async_channel::unbounded();
+        let stdout_stream = StdoutStream {
+            sub_id: "sub".to_string(),
+            call_id: "call".to_string(),
+            tx_event,
+            max_bytes_per_sec: NonZeroU64::new(10),
+        };
+        let params = ExecParams {
+            command: argv(&["printf", "%s%s", &"x".repeat(90), "0123456789"]),
+            network_required: false,
+            ..network_params()
+        };
+        let output = process_exec_tool_call(
+            params,
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            &None,
+            Some(stdout_stream),
+        )
+        .await
+        .expect("printf runs");
+        assert_eq!(output.stdout.text, format!("{}0123456789", "x".repeat(90)));
+
+        let mut streamed = Vec::new();
+        while let Ok(event) = rx_event.try_recv() {
+            if let EventMsg::ExecCommandOutputDelta(delta) = event.msg {
+                streamed.extend(delta.chunk);
+            }
+        }
+        // Far more than 10 bytes arrived at once: the tail is streamed at
+        // the end instead, and the marker accounts for the rest.
+        let streamed = String::from_utf8(streamed).expect("ascii output");
+        assert!(streamed.ends_with("0123456789"), "{streamed:?}");
+        let marker = Regex::new(r"\[(\d+) bytes of output throttled\]\n").expect("valid regex");
+        let dropped: usize = marker
+            .captures_iter(&streamed)
+            .map(|captures| captures[1].parse::<usize>().expect("byte count"))
+            .sum();
+        assert!(dropped > 0, "{streamed:?}");
+        let sent = marker.replace_all(&streamed, "").len();
+        assert_eq!(sent + dropped, 100, "{streamed:?}");
+    }
+
+    #[test]
+    fn stderr_tracer_stops_after_the_line_limit() {
+        assert!(StderrTracer::new(false).is_none());
+
+        let mut tracer = StderrTracer::new(true).expect("enabled");
+        tracer.push(b"error: first\nerror: sec");
+        assert_eq!(tracer.logged, 1);
+        tracer.push(b"ond\n");
+        assert_eq!(tracer.logged, 2);
+
+        let lines = "line\n".repeat(MAX_TRACED_STDERR_LINES + 10);
+        tracer.push(lines.as_bytes());
+        // Every line up to the limit, plus the note that the rest is skipped.
+        assert_eq!(tracer.logged, MAX_TRACED_STDERR_LINES + 1);
+    }
+
+    #[test]
+    fn max_concurrent_exec_is_fixed_by_the_first_caller() {
+        // Whichever of this call or an earlier command run came first has
+        // fixed the limit; either way it cannot be changed afterwards.
+        let limit = init_max_concurrent_exec(DEFAULT_MAX_CONCURRENT_EXEC);
+        assert_eq!(init_max_concurrent_exec(1), limit);
+        assert_eq!(init_max_concurrent_exec(limit + 1), limit);
+    }
+
+    fn push_all(chunker: &mut LineChunker, chunks: &[&str]) -> Vec<String> {
+        chunks
+            .iter()
+            .flat_map(|chunk| chunker.push(chunk.as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn line_chunker_buffers_partial_lines_across_chunks() {
+        let mut chunker = LineChunker::new();
+        assert_eq!(chunker.push(b"hel"), Vec::<String>::new());
+        assert_eq!(
+            push_all(&mut chunker, &["lo\nwor", "ld\n\nlast"]),
+            vec!["hello".to_string(), "world".to_string(), String::new()]
+        );
+        assert_eq!(chunker.finish(), Some("last".to_string()));
+    }
+
+    #[test]
+    fn line_chunker_handles_crlf_split_across_chunks() {
+        let mut chunker = LineChunker::new();
+        assert_eq!(
+            push_all(&mut chunker, &["one\r", "\ntwo\r\n"]),
+            vec!["one".to_string(), "two".to_string()]
+        );
+        assert_eq!(chunker.finish(), None);
+    }
+
+    #[test]
+    fn line_chunker_keeps_final_state_of_progress_bars() {
+        let mut chunker = LineChunker::new();
+        assert_eq!(
+            push_all(&mut chunker, &["10%\r", "50%\r100%\ndone\r"]),
+            vec!["100%".to_string()]
+        );
+        assert_eq!(chunker.finish(), Some("done".to_string()));
+    }
+}
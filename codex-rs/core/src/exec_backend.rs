@@ -0,0 +1,190 @@
+//! Pluggable execution of shell tool calls.
+//!
+//! Sessions run commands through an [`ExecBackend`]. Production code uses
+//! [`DefaultExecBackend`], which spawns real (sandboxed) processes via
+//! [`process_exec_tool_call`]. Tests that only care about agent behavior can
+//! install a [`FakeExecBackend`] on the
+//! [`ConversationManager`](crate::ConversationManager) to answer commands with
+//! canned output instead, much like `CODEX_RS_SSE_FIXTURE` fakes the model.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+
+use crate::error::CodexErr;
+use crate::error::Result;
+use crate::exec::ExecParams;
+use crate::exec::ExecToolCallOutput;
+use crate::exec::SandboxType;
+use crate::exec::StdoutStream;
+use crate::exec::StreamOutput;
+use crate::exec::process_exec_tool_call;
+use crate::protocol::SandboxPolicy;
+
+/// Runs the command described by an [`ExecParams`]. Takes the same arguments
+/// as [`process_exec_tool_call`].
+pub trait ExecBackend: Send + Sync {
+    fn exec<'a>(
+        &'a self,
+        params: ExecParams,
+        sandbox_type: SandboxType,
+        sandbox_policy: &'a SandboxPolicy,
+        codex_linux_sandbox_exe: &'a Option<PathBuf>,
+        stdout_stream: Option<StdoutStream>,
+    ) -> BoxFuture<'a, Result<ExecToolCallOutput>>;
+}
+
+/// Spawns real processes with [`process_exec_tool_call`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultExecBackend;
+
+impl ExecBackend for DefaultExecBackend {
+    fn exec<'a>(
+        &'a self,
+        params: ExecParams,
+        sandbox_type: SandboxType,
+        sandbox_policy: &'a SandboxPolicy,
+        codex_linux_sandbox_exe: &'a Option<PathBuf>,
+        stdout_stream: Option<StdoutStream>,
+    ) -> BoxFuture<'a, Result<ExecToolCallOutput>> {
+        Box::pin(process_exec_tool_call(
+            params,
+            sandbox_type,
+            sandbox_policy,
+            codex_linux_sandbox_exe,
+            stdout_stream,
+        ))
+    }
+}
+
+/// Test double that never spawns anything: each command (matched on its
+/// exact argv) is answered with the output registered via
+/// [`FakeExecBackend::with_output`]. Commands without a registered output
+/// fail with [`CodexErr::InvalidCommand`]. Every command received is kept so
+/// tests can assert on what the agent asked to run.
+#[derive(Debug, Default)]
+pub struct FakeExecBackend {
+    outputs: HashMap<Vec<String>, ExecToolCallOutput>,
+    calls: Mutex<Vec<Vec<String>>>,
+}
+
+impl FakeExecBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `command` with `output`.
+    pub fn with_output(mut self, command: Vec<String>, output: ExecToolCallOutput) -> Self {
+        self.outputs.insert(command, output);
+        self
+    }
+
+    /// Shorthand for [`FakeExecBackend::with_output`] with a command that
+    /// printed `stdout` and exited with `exit_code`.
+    pub fn with_stdout(self, command: Vec<String>, exit_code: i32, stdout: &str) -> Self {
+        let output = ExecToolCallOutput {
+            exit_code,
+            stdout: StreamOutput::new(stdout.to_string()),
+            aggregated_output: StreamOutput::new(stdout.to_string()),
+            success: exit_code == 0,
+            ..Default::default()
+        };
+        self.with_output(command, output)
+    }
+
+    /// The commands received so far, in order.
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls
+            .lock()
+            .map(|calls| calls.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ExecBackend for FakeExecBackend {
+    fn exec<'a>(
+        &'a self,
+        params: ExecParams,
+        _sandbox_type: SandboxType,
+        _sandbox_policy: &'a SandboxPolicy,
+        _codex_linux_sandbox_exe: &'a Option<PathBuf>,
+        _stdout_stream: Option<StdoutStream>,
+    ) -> BoxFuture<'a, Result<ExecToolCallOutput>> {
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(params.command.clone());
+        }
+        let result = match self.outputs.get(&params.command) {
+            Some(output) => Ok(ExecToolCallOutput {
+                call_id: params.call_id.clone().unwrap_or_default(),
+                ..output.clone()
+            }),
+            None => Err(CodexErr::InvalidCommand(format!(
+                "no fake output registered for {:?}",
+                params.command
+            ))),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn params(command: &[&str]) -> ExecParams {
+        ExecParams {
+            command: command.iter().map(|s| s.to_string()).collect(),
+            cwd: PathBuf::from("/"),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn fake_backend_answers_registered_commands() {
+        let backend = FakeExecBackend::new().with_stdout(
+            vec!["cargo".to_string(), "test".to_string()],
+            101,
+            "1 test failed",
+        );
+        let policy = SandboxPolicy::new_read_only_policy();
+
+        let output = backend
+            .exec(
+                params(&["cargo", "test"]),
+                SandboxType::None,
+                &policy,
+                &None,
+                None,
+            )
+            .await
+            .expect("registered command");
+        assert_eq!(output.exit_code, 101);
+        assert_eq!(output.stdout.text, "1 test failed");
+
+        let err = backend
+            .exec(
+                params(&["rm", "-rf", "/"]),
+                SandboxType::None,
+                &policy,
+                &None,
+                None,
+            )
+            .await
+            .expect_err("unregistered command");
+        assert_eq!(
+            err.to_string(),
+            r#"invalid command: no fake output registered for ["rm", "-rf", "/"]"#
+        );
+
+        assert_eq!(
+            backend.calls(),
+            vec![
+                vec!["cargo".to_string(), "test".to_string()],
+                vec!["rm".to_string(), "-rf".to_string(), "/".to_string()],
+            ]
+        );
+    }
+}
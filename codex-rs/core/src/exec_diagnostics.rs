@@ -0,0 +1,188 @@
+//! Context for reproducing a failed command.
+//!
+//! When [`ExecParams::collect_diagnostics`] is set and a command fails with a
+//! [`SandboxErr`], [`process_exec_tool_call`] hands the hook a
+//! [`Diagnostics`] describing exactly what was run: the command, its working
+//! directory and environment, the resolved sandbox policy, the invocation of
+//! the sandbox launcher and the platform. That is everything needed to rerun
+//! the command by hand, so it can be logged or attached to a bug report.
+//!
+//! [`ExecParams::collect_diagnostics`]: crate::exec::ExecParams::collect_diagnostics
+//! [`process_exec_tool_call`]: crate::exec::process_exec_tool_call
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::config_types::EnvironmentVariablePattern;
+use crate::error::SandboxErr;
+use crate::exec::REDACTED;
+use crate::exec::SandboxType;
+use crate::landlock::create_linux_sandbox_command_args;
+use crate::protocol::SandboxPolicy;
+use crate::seatbelt::MACOS_PATH_TO_SEATBELT_EXECUTABLE;
+use crate::seatbelt::create_seatbelt_command_args;
+
+/// Environment variables whose values are replaced with [`REDACTED`]: the
+/// names the default `shell_environment_policy` keeps from commands, and
+/// passwords.
+const SECRET_ENV_PATTERNS: &[&str] = &["*KEY*", "*SECRET*", "*TOKEN*", "*PASSWORD*"];
+
+/// Everything needed to reproduce a command that failed with a
+/// [`SandboxErr`]; see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostics {
+    /// The command as run, after any rewriting.
+    pub command: Vec<String>,
+    pub cwd: PathBuf,
+    /// The command's environment, with the values of variables that look
+    /// like secrets (`*KEY*`, `*SECRET*`, `*TOKEN*`, `*PASSWORD*`) replaced
+    /// with [`REDACTED`].
+    pub env: BTreeMap<String, String>,
+    /// The sandbox the command ran under, after resolving
+    /// [`SandboxType::Auto`] and any per-command exemptions.
+    pub sandbox_type: SandboxType,
+    pub sandbox_policy: SandboxPolicy,
+    /// The sandbox launcher followed by its arguments, e.g.
+    /// `/usr/bin/sandbox-exec -p <policy> -- <command>`. Empty without a
+    /// sandbox.
+    pub sandbox_argv: Vec<String>,
+    /// `std::env::consts::OS` and `ARCH`, e.g. `linux-x86_64`.
+    pub platform: String,
+    /// The error the command failed with.
+    pub error: String,
+    /// The exit code, when the command got far enough to have one.
+    pub exit_code: Option<i32>,
+    /// The signal that killed the command, if any.
+    pub signal: Option<i32>,
+}
+
+impl Diagnostics {
+    pub(crate) fn collect(
+        command: Vec<String>,
+        cwd: PathBuf,
+        env: &HashMap<String, String>,
+        sandbox_type: SandboxType,
+        sandbox_policy: &SandboxPolicy,
+        seatbelt_base_policy: Option<&str>,
+        codex_linux_sandbox_exe: Option<&Path>,
+        error: &SandboxErr,
+    ) -> Self {
+        let mut env = redact_env(env);
+        if let Some(scratch_dir) = sandbox_policy.scratch_dir() {
+            env.insert(
+                "TMPDIR".to_string(),
+                scratch_dir.to_string_lossy().into_owned(),
+            );
+        }
+        let sandbox_argv = match sandbox_type {
+            SandboxType::MacosSeatbelt => {
+                let mut argv = vec![MACOS_PATH_TO_SEATBELT_EXECUTABLE.to_string()];
+                argv.extend(
+                    create_seatbelt_command_args(
+                        command.clone(),
+                        sandbox_policy,
+                        seatbelt_base_policy,
+                        &cwd,
+                    )
+                    .into_args(),
+                );
+                argv
+            }
+            SandboxType::LinuxSeccomp => {
+                let mut argv = vec![
+                    codex_linux_sandbox_exe
+                        .map(|exe| exe.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "codex-linux-sandbox".to_string()),
+                ];
+                argv.extend(create_linux_sandbox_command_args(
+                    command.clone(),
+                    sandbox_policy,
+                    &cwd,
+                ));
+                argv
+            }
+            SandboxType::None | SandboxType::Auto => Vec::new(),
+        };
+        let (exit_code, signal) = exit_status(error);
+        Self {
+            command,
+            cwd,
+            env,
+            sandbox_type,
+            sandbox_policy: sandbox_policy.clone(),
+            sandbox_argv,
+            platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            error: error.to_string(),
+            exit_code,
+            signal,
+        }
+    }
+}
+
+fn redact_env(env: &HashMap<String, String>) -> BTreeMap<String, String> {
+    let secrets: Vec<_> = SECRET_ENV_PATTERNS
+        .iter()
+        .map(|pattern| EnvironmentVariablePattern::new_case_insensitive(pattern))
+        .collect();
+    env.iter()
+        .map(|(key, value)| {
+            let value = if secrets.iter().any(|pattern| pattern.matches(key)) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// The exit code and signal recorded in `error`, where it has them.
+fn exit_status(error: &SandboxErr) -> (Option<i32>, Option<i32>) {
+    match error {
+        SandboxErr::Denied(exit_code, _, _) => (Some(*exit_code), None),
+        SandboxErr::Signal { signal, output, .. } => (Some(output.exit_code), Some(*signal)),
+        SandboxErr::Crashed { code, .. } => (Some(*code as i32), None),
+        SandboxErr::Timeout { output }
+        | SandboxErr::IdleTimeout { output }
+        | SandboxErr::OutOfMemory { output }
+        | SandboxErr::OpenFileLimit { output, .. } => (Some(output.exit_code), None),
+        _ => (None, None),
+    }
+}
+
+type DiagnosticsCallback = dyn Fn(&Diagnostics) + Send + Sync;
+
+/// Opts a command into collecting [`Diagnostics`] when it fails with a
+/// [`SandboxErr`]. The callback runs once, right before the error is
+/// returned; it is not called when the command succeeds or fails for other
+/// reasons, e.g. because it could not be found.
+#[derive(Clone)]
+pub struct DiagnosticsHook {
+    callback: Arc<DiagnosticsCallback>,
+}
+
+impl DiagnosticsHook {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&Diagnostics) + Send + Sync + 'static,
+    {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub(crate) fn notify(&self, diagnostics: &Diagnostics) {
+        (self.callback)(diagnostics);
+    }
+}
+
+impl std::fmt::Debug for DiagnosticsHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiagnosticsHook").finish_non_exhaustive()
+    }
+}
@@ -68,6 +68,79 @@ where
     env_map
 }
 
+/// Where `${VAR}` references in the values of `ExecParams::env` are looked
+/// up before the command is spawned; see [`expand_env_references`].
+///
+/// Expansion is opt-in: commands are spawned with a cleared environment, so
+/// no shell ever expands these values, and a value that happens to contain
+/// `${` is passed through literally unless expansion was asked for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum EnvExpansion {
+    /// Values are used as written.
+    #[default]
+    Disabled,
+    /// References are resolved against the environment of this process, not
+    /// against the other values being set.
+    ParentEnv,
+    /// References are resolved against the given variables.
+    Base(HashMap<String, String>),
+}
+
+impl EnvExpansion {
+    /// Expands every value of `env` in place.
+    pub fn apply(&self, env: &mut HashMap<String, String>) {
+        if *self == EnvExpansion::Disabled {
+            return;
+        }
+        let lookup = |name: &str| match self {
+            EnvExpansion::Disabled => None,
+            EnvExpansion::ParentEnv => std::env::var(name).ok(),
+            EnvExpansion::Base(base) => base.get(name).cloned(),
+        };
+        for value in env.values_mut() {
+            *value = expand_env_references(value, lookup);
+        }
+    }
+}
+
+/// Replaces each `${NAME}` in `value` with `lookup(NAME)`, or with nothing if
+/// the variable is not set, so `PATH=/opt/tool/bin:${PATH}` prepends to the
+/// base `PATH`. `NAME` consists of ASCII letters, digits and `_`.
+///
+/// `$$` stands for a literal `$`. Every other `$`, including a bare `$NAME`
+/// or an unterminated `${`, is kept as written.
+pub fn expand_env_references(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(dollar) = rest.find('$') {
+        expanded.push_str(&rest[..dollar]);
+        rest = &rest[dollar..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let reference = rest
+            .strip_prefix("${")
+            .and_then(|inner| inner.split_once('}'))
+            .filter(|(name, _)| {
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        match reference {
+            Some((name, after)) => {
+                expanded.push_str(&lookup(name).unwrap_or_default());
+                rest = after;
+            }
+            None => {
+                expanded.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -191,4 +264,34 @@ mod tests {
         };
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn expands_braced_references_against_the_base() {
+        let base = EnvExpansion::Base(hashmap! {
+            "PATH".to_string() => "/usr/bin".to_string(),
+            "HOME".to_string() => "/home/me".to_string(),
+        });
+        let mut env = hashmap! {
+            "PATH".to_string() => "/opt/tool/bin:${PATH}".to_string(),
+            "CACHE".to_string() => "${HOME}/.cache/${MISSING}x".to_string(),
+            "LITERAL".to_string() => "$HOME costs $$5, ${not closed, ${}".to_string(),
+        };
+        base.apply(&mut env);
+        let expected: HashMap<String, String> = hashmap! {
+            "PATH".to_string() => "/opt/tool/bin:/usr/bin".to_string(),
+            "CACHE".to_string() => "/home/me/.cache/x".to_string(),
+            "LITERAL".to_string() => "$HOME costs $5, ${not closed, ${}".to_string(),
+        };
+        assert_eq!(env, expected);
+    }
+
+    #[test]
+    fn disabled_expansion_keeps_values_literal() {
+        let mut env = hashmap! {
+            "PATH".to_string() => "/opt/tool/bin:${PATH}".to_string(),
+        };
+        let before = env.clone();
+        EnvExpansion::Disabled.apply(&mut env);
+        assert_eq!(env, before);
+    }
 }
@@ -0,0 +1,331 @@
+//! Compact summaries of long command output for the model.
+//!
+//! When a command's output is too long to be sent to the model in full, only
+//! its head and tail survive, which can cut out exactly the lines that
+//! matter: the compiler errors in the middle of a build log or the list of
+//! failed tests. A summarizer for the command's family (cargo, npm, pytest)
+//! extracts those lines from the captured output (everything kept when the
+//! command ran, before it is shortened for the model) so they can be
+//! appended to the truncated text. The raw output is never replaced, only
+//! augmented.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Maximum number of individual errors or failures listed in a summary.
+pub const MAX_SUMMARY_ITEMS: usize = 5;
+
+/// Longest line (in bytes) copied into a summary; longer lines are cut.
+const MAX_SUMMARY_LINE_BYTES: usize = 200;
+
+/// Extracts the salient lines from the captured output of a command, or returns
+/// `None` if it finds nothing worth reporting.
+pub type OutputSummarizer = fn(&str) -> Option<String>;
+
+/// Summarizers keyed by program name (e.g. `cargo`).
+#[derive(Clone)]
+pub struct OutputSummarizers {
+    by_program: HashMap<String, OutputSummarizer>,
+}
+
+impl Default for OutputSummarizers {
+    /// The built-in summarizers for `cargo`, `npm` and `pytest`.
+    fn default() -> Self {
+        let mut summarizers = Self::empty();
+        summarizers.register("cargo", summarize_cargo);
+        summarizers.register("npm", summarize_npm);
+        summarizers.register("pytest", summarize_pytest);
+        summarizers
+    }
+}
+
+impl std::fmt::Debug for OutputSummarizers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut programs: Vec<&String> = self.by_program.keys().collect();
+        programs.sort();
+        f.debug_struct("OutputSummarizers")
+            .field("programs", &programs)
+            .finish()
+    }
+}
+
+impl OutputSummarizers {
+    pub fn empty() -> Self {
+        Self {
+            by_program: HashMap::new(),
+        }
+    }
+
+    /// Uses `summarizer` for commands that run `program`, replacing any
+    /// summarizer previously registered for it.
+    pub fn register(&mut self, program: impl Into<String>, summarizer: OutputSummarizer) {
+        self.by_program.insert(program.into(), summarizer);
+    }
+
+    /// Summarizes `output` with the summarizer registered for the program
+    /// `command` runs; see [`program_name`].
+    pub fn summarize(&self, command: &[String], output: &str) -> Option<String> {
+        let program = program_name(command)?;
+        let summarizer = self.by_program.get(&program)?;
+        summarizer(output)
+    }
+}
+
+/// Name of the program `command` runs, looking through a `bash -lc <script>`
+/// wrapper (first word of the script) and `python -m <module>`.
+pub fn program_name(command: &[String]) -> Option<String> {
+    let words: Vec<&str> = match command {
+        [shell, flag, script] if is_shell(shell) && matches!(flag.as_str(), "-c" | "-lc") => {
+            script.split_whitespace().collect()
+        }
+        _ => command.iter().map(String::as_str).collect(),
+    };
+    let program = basename(words.first()?);
+    match words.as_slice() {
+        [_, "-m", module, ..] if program.starts_with("python") => Some((*module).to_string()),
+        _ => Some(program),
+    }
+}
+
+fn is_shell(program: &str) -> bool {
+    matches!(basename(program).as_str(), "bash" | "sh" | "zsh")
+}
+
+fn basename(program: &str) -> String {
+    Path::new(program)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| program.to_string())
+}
+
+/// Compiler errors and `test result:` lines from cargo output.
+fn summarize_cargo(output: &str) -> Option<String> {
+    let errors: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("error:") || line.starts_with("error["))
+        // Closing lines that only repeat the count, e.g. "error: could not
+        // compile `foo`".
+        .filter(|line| {
+            !line.starts_with("error: could not compile") && !line.starts_with("error: aborting")
+        })
+        .collect();
+    let failed_tests: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.strip_prefix("---- "))
+        .filter_map(|line| line.strip_suffix(" stdout ----"))
+        .collect();
+    let results: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("test result:"))
+        .collect();
+
+    let mut summary = Summary::default();
+    summary.items(&format!("{} error(s)", errors.len()), &errors);
+    summary.items(
+        &format!("{} failed test(s)", failed_tests.len()),
+        &failed_tests,
+    );
+    summary.last_line(&results);
+    summary.finish()
+}
+
+/// `npm ERR!` lines and the final Jest-style `Tests:` line from npm output.
+fn summarize_npm(output: &str) -> Option<String> {
+    let errors: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("npm ERR!") || line.starts_with("npm error"))
+        .collect();
+    let results: Vec<&str> = output
+        .lines()
+        .filter(|line| line.trim_start().starts_with("Tests:"))
+        .collect();
+
+    let mut summary = Summary::default();
+    summary.items(&format!("{} npm error line(s)", errors.len()), &errors);
+    summary.last_line(&results);
+    summary.finish()
+}
+
+/// `FAILED`/`ERROR` lines and the final `=== ... ===` line from pytest output.
+fn summarize_pytest(output: &str) -> Option<String> {
+    let failures: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("FAILED ") || line.starts_with("ERROR "))
+        .collect();
+    let results: Vec<&str> = output
+        .lines()
+        .filter(|line| {
+            line.starts_with('=')
+                && line.ends_with('=')
+                && [" passed", " failed", " error"]
+                    .iter()
+                    .any(|word| line.contains(word))
+        })
+        .collect();
+
+    let mut summary = Summary::default();
+    summary.items(
+        &format!("{} failure(s)/error(s)", failures.len()),
+        &failures,
+    );
+    summary.last_line(&results);
+    summary.finish()
+}
+
+#[derive(Default)]
+struct Summary {
+    lines: Vec<String>,
+}
+
+impl Summary {
+    /// Adds `heading` and the first [`MAX_SUMMARY_ITEMS`] of `items`, if any.
+    fn items(&mut self, heading: &str, items: &[&str]) {
+        if items.is_empty() {
+            return;
+        }
+        self.lines.push(format!("{heading}:"));
+        self.lines.extend(
+            items
+                .iter()
+                .take(MAX_SUMMARY_ITEMS)
+                .map(|item| format!("  {}", clip(item))),
+        );
+        if items.len() > MAX_SUMMARY_ITEMS {
+            self.lines.push(format!(
+                "  ... and {} more",
+                items.len() - MAX_SUMMARY_ITEMS
+            ));
+        }
+    }
+
+    /// Adds the last of `lines`, which for test runners is the overall
+    /// result.
+    fn last_line(&mut self, lines: &[&str]) {
+        if let Some(line) = lines.last() {
+            self.lines.push(clip(line.trim()).to_string());
+        }
+    }
+
+    fn finish(self) -> Option<String> {
+        (!self.lines.is_empty()).then(|| self.lines.join("\n"))
+    }
+}
+
+fn clip(line: &str) -> &str {
+    if line.len() <= MAX_SUMMARY_LINE_BYTES {
+        return line;
+    }
+    let mut end = MAX_SUMMARY_LINE_BYTES;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn program_name_looks_through_shell_and_python_wrappers() {
+        assert_eq!(
+            program_name(&argv(&["/usr/bin/cargo", "test"])),
+            Some("cargo".to_string())
+        );
+        assert_eq!(
+            program_name(&argv(&["bash", "-lc", "npm test -- --ci"])),
+            Some("npm".to_string())
+        );
+        assert_eq!(
+            program_name(&argv(&["python3", "-m", "pytest", "-x"])),
+            Some("pytest".to_string())
+        );
+        assert_eq!(program_name(&[]), None);
+    }
+
+    #[test]
+    fn cargo_summary_lists_errors_failed_tests_and_result() {
+        let output = "\
+   Compiling foo v0.1.0
+error[E0425]: cannot find value `x` in this scope
+warning: unused import
+error: aborting due to 1 previous error
+error: could not compile `foo`
+---- tests::adds stdout ----
+thread 'tests::adds' panicked
+test result: FAILED. 3 passed; 1 failed; 0 ignored
+";
+        let summary = OutputSummarizers::default().summarize(&argv(&["cargo", "test"]), output);
+        assert_eq!(
+            summary.as_deref(),
+            Some(
+                "\
+1 error(s):
+  error[E0425]: cannot find value `x` in this scope
+1 failed test(s):
+  tests::adds
+test result: FAILED. 3 passed; 1 failed; 0 ignored"
+            )
+        );
+    }
+
+    #[test]
+    fn pytest_summary_caps_the_number_of_failures() {
+        let mut output = String::from("collected 7 items\n");
+        for i in 0..7 {
+            output.push_str(&format!("FAILED test_mod.py::test_{i} - assert 0\n"));
+        }
+        output.push_str("========= 7 failed in 0.12s =========\n");
+        let summary = OutputSummarizers::default()
+            .summarize(&argv(&["pytest"]), &output)
+            .expect("pytest summary");
+        assert_eq!(
+            summary.lines().collect::<Vec<_>>(),
+            vec![
+                "7 failure(s)/error(s):",
+                "  FAILED test_mod.py::test_0 - assert 0",
+                "  FAILED test_mod.py::test_1 - assert 0",
+                "  FAILED test_mod.py::test_2 - assert 0",
+                "  FAILED test_mod.py::test_3 - assert 0",
+                "  FAILED test_mod.py::test_4 - assert 0",
+                "  ... and 2 more",
+                "========= 7 failed in 0.12s =========",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_programs_and_clean_output_have_no_summary() {
+        let summarizers = OutputSummarizers::default();
+        assert_eq!(
+            summarizers.summarize(&argv(&["make"]), "error: boom\n"),
+            None
+        );
+        assert_eq!(
+            summarizers.summarize(&argv(&["npm", "install"]), "added 12 packages\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn registered_summarizer_is_used_for_its_program() {
+        let mut summarizers = OutputSummarizers::empty();
+        summarizers.register("make", |output| {
+            output
+                .lines()
+                .find(|line| line.starts_with("make:"))
+                .map(str::to_string)
+        });
+        assert_eq!(
+            summarizers
+                .summarize(&argv(&["make"]), "cc foo.c\nmake: *** [all] Error 1\n")
+                .as_deref(),
+            Some("make: *** [all] Error 1")
+        );
+    }
+}
@@ -11,6 +11,15 @@ env_flags! {
         value.parse().map(Duration::from_millis)
     };
 
-    /// Fixture path for offline tests (see client.rs).
+    /// Fixture path for offline tests (see sse_fixture.rs).
     pub CODEX_RS_SSE_FIXTURE: Option<&str> = None;
+
+    /// Directory to record response streams to as timed fixtures.
+    pub CODEX_RS_SSE_RECORD: Option<&str> = None;
+
+    /// Set to `1` to keep Linux children from receiving SIGTERM when Codex
+    /// dies (see spawn.rs), e.g. while a debugger has Codex stopped.
+    pub CODEX_RS_NO_PDEATHSIG: bool = false, |value| {
+        Ok::<_, std::convert::Infallible>(matches!(value, "1" | "true"))
+    };
 }
@@ -40,6 +40,63 @@ pub fn get_git_repo_root(base_dir: &Path) -> Option<PathBuf> {
     None
 }
 
+/// Resolves `relative` (e.g. `.` or `crates/foo`) against the root of the
+/// git repository containing `base_dir`, for use as a command's working
+/// directory. The result is canonical, so symlinks and `..` segments are
+/// resolved before checking that it stays inside the repository.
+///
+/// Fails with `NotFound` when `base_dir` is not in a repository or the
+/// directory does not exist, and with `InvalidInput` when `relative` is
+/// absolute, escapes the repository, or is not a directory.
+pub fn resolve_repo_relative_cwd(base_dir: &Path, relative: &Path) -> std::io::Result<PathBuf> {
+    use std::io::Error;
+    use std::io::ErrorKind;
+
+    if relative.is_absolute() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{} is not relative to the repository root",
+                relative.display()
+            ),
+        ));
+    }
+    let repo_root = get_git_repo_root(base_dir).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("{} is not inside a git repository", base_dir.display()),
+        )
+    })?;
+    let repo_root = repo_root.canonicalize()?;
+    let cwd = repo_root.join(relative).canonicalize().map_err(|err| {
+        Error::new(
+            err.kind(),
+            format!(
+                "{} does not exist in {}: {err}",
+                relative.display(),
+                repo_root.display()
+            ),
+        )
+    })?;
+    if !cwd.starts_with(&repo_root) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{} escapes the repository root {}",
+                relative.display(),
+                repo_root.display()
+            ),
+        ));
+    }
+    if !cwd.is_dir() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is not a directory", relative.display()),
+        ));
+    }
+    Ok(cwd)
+}
+
 /// Timeout for git commands to prevent freezing on large repositories
 const GIT_COMMAND_TIMEOUT: TokioDuration = TokioDuration::from_secs(5);
 
@@ -135,6 +192,36 @@ pub async fn git_diff_to_remote(cwd: &Path) -> Option<GitDiffToRemote> {
     })
 }
 
+/// Returns the subset of `paths` that git would ignore (via `.gitignore`,
+/// `.git/info/exclude` or the global excludes file) in the repository that
+/// contains `cwd`. Paths outside that repository, or any git failure, yield
+/// no matches.
+pub async fn git_ignored_paths(cwd: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let Some(repo_root) = get_git_repo_root(cwd) else {
+        return Vec::new();
+    };
+    let candidates: Vec<String> = paths
+        .iter()
+        .filter(|path| path.starts_with(&repo_root))
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+
+    let mut args = vec!["check-ignore", "--"];
+    args.extend(candidates.iter().map(String::as_str));
+    // `git check-ignore` exits with 1 when nothing is ignored, so only the
+    // printed paths matter here.
+    let Some(output) = run_git_command_with_timeout(&args, cwd).await else {
+        return Vec::new();
+    };
+    let Ok(stdout) = String::from_utf8(output.stdout) else {
+        return Vec::new();
+    };
+    stdout.lines().map(PathBuf::from).collect()
+}
+
 /// Run a git command with a timeout to prevent blocking on large repositories
 async fn run_git_command_with_timeout(args: &[&str], cwd: &Path) -> Option<std::process::Output> {
     let result = timeout(
@@ -605,6 +692,40 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[tokio::test]
+    async fn test_git_ignored_paths() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = create_test_git_repo(&temp_dir).await;
+        fs::write(repo_path.join(".gitignore"), "target/\n").expect("write .gitignore");
+        fs::write(repo_path.join(".git/info/exclude"), "*.local\n").expect("write exclude");
+
+        let ignored = git_ignored_paths(
+            &repo_path,
+            &[
+                repo_path.join("target/debug/out.o"),
+                repo_path.join("settings.local"),
+                repo_path.join("src/main.rs"),
+                temp_dir.path().join("outside.txt"),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            ignored,
+            vec![
+                repo_path.join("target/debug/out.o"),
+                repo_path.join("settings.local"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_git_ignored_paths_non_git_directory() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let ignored = git_ignored_paths(temp_dir.path(), &[temp_dir.path().join("a")]).await;
+        assert!(ignored.is_empty());
+    }
+
     #[tokio::test]
     async fn test_collect_git_info_git_repository() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -810,6 +931,66 @@ mod tests {
         assert!(resolve_root_git_project_for_trust(tmp.path()).is_none());
     }
 
+    #[tokio::test]
+    async fn resolve_repo_relative_cwd_resolves_against_repo_root() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = create_test_git_repo(&temp_dir).await;
+        let root = repo_path.canonicalize().unwrap();
+        let nested = repo_path.join("crates/foo");
+        fs::create_dir_all(&nested).unwrap();
+
+        // Resolution starts at the repo root, wherever `base_dir` is.
+        assert_eq!(
+            resolve_repo_relative_cwd(&nested, Path::new(".")).unwrap(),
+            root
+        );
+        assert_eq!(
+            resolve_repo_relative_cwd(&repo_path, Path::new("crates/foo")).unwrap(),
+            root.join("crates/foo")
+        );
+        assert_eq!(
+            resolve_repo_relative_cwd(&nested, Path::new("crates/foo/../foo")).unwrap(),
+            root.join("crates/foo")
+        );
+    }
+
+    #[tokio::test]
+    async fn resolve_repo_relative_cwd_rejects_bad_paths() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = create_test_git_repo(&temp_dir).await;
+        fs::write(repo_path.join("file.txt"), "x").unwrap();
+
+        let kind = |relative: &str| {
+            resolve_repo_relative_cwd(&repo_path, Path::new(relative))
+                .unwrap_err()
+                .kind()
+        };
+        assert_eq!(kind(".."), std::io::ErrorKind::InvalidInput);
+        assert_eq!(kind("../repo/.."), std::io::ErrorKind::InvalidInput);
+        assert_eq!(kind("/tmp"), std::io::ErrorKind::InvalidInput);
+        assert_eq!(kind("file.txt"), std::io::ErrorKind::InvalidInput);
+        assert_eq!(kind("missing"), std::io::ErrorKind::NotFound);
+
+        let outside = TempDir::new().expect("Failed to create temp dir");
+        assert_eq!(
+            resolve_repo_relative_cwd(outside.path(), Path::new("."))
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resolve_repo_relative_cwd_rejects_symlinks_out_of_the_repo() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let repo_path = create_test_git_repo(&temp_dir).await;
+        std::os::unix::fs::symlink(temp_dir.path(), repo_path.join("escape")).unwrap();
+
+        let err = resolve_repo_relative_cwd(&repo_path, Path::new("escape")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
     #[tokio::test]
     async fn resolve_root_git_project_for_trust_regular_repo_returns_repo_root() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -1,11 +1,28 @@
+use crate::error::CodexErr;
+use crate::error::Result;
 use crate::protocol::SandboxPolicy;
+use crate::spawn::InheritedFd;
+use crate::spawn::StdinSource;
 use crate::spawn::StdioPolicy;
 use crate::spawn::spawn_child_async;
+use serde::Deserialize;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 use tokio::process::Child;
 
+/// The sandbox policy and environment for `codex-linux-sandbox`, as JSON.
+///
+/// Passed through a pipe (`--policy-fd`) rather than as arguments, since
+/// arguments show up in the process table, where other users on a shared
+/// host could read the writable roots and environment.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinuxSandboxPayload {
+    pub sandbox_policy: SandboxPolicy,
+    pub sandbox_env: HashMap<String, String>,
+}
+
 /// Spawn a shell tool command under the Linux Landlock+seccomp sandbox helper
 /// (codex-linux-sandbox).
 ///
@@ -13,6 +30,11 @@ use tokio::process::Child;
 /// helper accepts a list of `--sandbox-permission`/`-s` flags mirroring the
 /// public CLI. We convert the internal [`SandboxPolicy`] representation into
 /// the equivalent CLI options.
+///
+/// The policy and environment are written to a pipe the helper inherits. If
+/// the pipe cannot be set up, the policy is passed as an argument instead
+/// (see [`helper_invocation`]).
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_command_under_linux_sandbox<P>(
     codex_linux_sandbox_exe: P,
     command: Vec<String>,
@@ -20,13 +42,29 @@ pub async fn spawn_command_under_linux_sandbox<P>(
     cwd: PathBuf,
     stdio_policy: StdioPolicy,
     env: HashMap<String, String>,
+    inherited_fds: &[InheritedFd],
+    stdin_source: Option<StdinSource>,
 ) -> std::io::Result<Child>
 where
     P: AsRef<Path>,
 {
-    let args = create_linux_sandbox_command_args(command, sandbox_policy, &cwd);
+    let (args, piped) = helper_invocation(
+        command,
+        sandbox_policy,
+        &cwd,
+        pipe_payload(sandbox_policy, &env, inherited_fds),
+    );
+    let mut all_fds = Vec::with_capacity(inherited_fds.len() + 1);
+    // First, so its target cannot clobber another entry's source before that
+    // one is installed.
+    let payload = piped.map(|(policy_fd, payload)| {
+        all_fds.push(policy_fd);
+        payload
+    });
+    all_fds.extend(inherited_fds.iter().cloned());
+
     let arg0 = Some("codex-linux-sandbox");
-    spawn_child_async(
+    let child = spawn_child_async(
         codex_linux_sandbox_exe.as_ref().to_path_buf(),
         args,
         arg0,
@@ -34,12 +72,185 @@ where
         sandbox_policy,
         stdio_policy,
         env,
+        &all_fds,
+        stdin_source,
     )
-    .await
+    .await?;
+    // The child holds the read end now; close ours.
+    drop(all_fds);
+    if let Some(payload) = payload {
+        payload.write();
+    }
+    Ok(child)
+}
+
+/// The warning shown when `sandbox_policy` lists
+/// `writable_root_exclusions`, which `codex-linux-sandbox` cannot enforce for
+/// commands (see [`SandboxPolicy::WorkspaceWrite`]). `None` when there are
+/// none.
+pub(crate) fn unenforced_exclusions_warning(sandbox_policy: &SandboxPolicy) -> Option<String> {
+    let exclusions = sandbox_policy.writable_root_exclusions();
+    if exclusions.is_empty() {
+        return None;
+    }
+    let listed = exclusions
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!(
+        "writable_root_exclusions ({listed}) only keep Codex's own edits out; the Linux sandbox cannot stop commands from writing there"
+    ))
+}
+
+/// Checks that `codex_linux_sandbox_exe` is a file this process can run, so
+/// a broken install is reported as such rather than as a failure of the
+/// command that was to run under it.
+pub fn check_linux_sandbox_exe(codex_linux_sandbox_exe: &Path) -> Result<()> {
+    let metadata = std::fs::metadata(codex_linux_sandbox_exe).map_err(|err| {
+        let reason = match err.kind() {
+            std::io::ErrorKind::NotFound => "it does not exist".to_string(),
+            _ => err.to_string(),
+        };
+        unusable(codex_linux_sandbox_exe, reason)
+    })?;
+    if !metadata.is_file() {
+        return Err(unusable(codex_linux_sandbox_exe, "it is not a file"));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(unusable(codex_linux_sandbox_exe, "it is not executable"));
+        }
+    }
+    Ok(())
+}
+
+/// Attributes a failure to spawn `codex-linux-sandbox` to the helper where
+/// it can only be the helper's fault, e.g. a binary for another
+/// architecture. The user's command is only started by the helper itself.
+pub(crate) fn linux_sandbox_spawn_error(
+    codex_linux_sandbox_exe: &Path,
+    err: std::io::Error,
+) -> CodexErr {
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(libc::ENOEXEC) {
+        return unusable(
+            codex_linux_sandbox_exe,
+            "it is not a program this system can run",
+        );
+    }
+    match err.kind() {
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied => {
+            unusable(codex_linux_sandbox_exe, err.to_string())
+        }
+        _ => CodexErr::Io(err),
+    }
+}
+
+fn unusable(codex_linux_sandbox_exe: &Path, reason: impl Into<String>) -> CodexErr {
+    CodexErr::LandlockSandboxExecutableUnusable {
+        path: codex_linux_sandbox_exe.to_path_buf(),
+        reason: reason.into(),
+    }
+}
+
+/// The write end of the policy pipe, with the JSON still to be written.
+#[cfg_attr(not(unix), allow(dead_code))]
+struct PipedPayload {
+    writer: std::io::PipeWriter,
+    json: Vec<u8>,
+}
+
+impl PipedPayload {
+    /// Writes the payload and closes the pipe, on a blocking thread since it
+    /// may not fit into the pipe buffer until the helper starts reading.
+    fn write(self) {
+        tokio::task::spawn_blocking(move || {
+            use std::io::Write;
+
+            let PipedPayload { mut writer, json } = self;
+            if let Err(err) = writer.write_all(&json) {
+                tracing::warn!("failed to write the sandbox policy to the pipe: {err}");
+            }
+        });
+    }
+}
+
+/// Serializes the policy and `env` and creates the pipe to pass them
+/// through, as the helper's descriptor numbered above every descriptor in
+/// `inherited_fds`.
+#[cfg(unix)]
+fn pipe_payload(
+    sandbox_policy: &SandboxPolicy,
+    env: &HashMap<String, String>,
+    inherited_fds: &[InheritedFd],
+) -> std::io::Result<(InheritedFd, PipedPayload)> {
+    let json = serde_json::to_vec(&LinuxSandboxPayload {
+        sandbox_policy: sandbox_policy.clone(),
+        sandbox_env: env.clone(),
+    })?;
+    let (reader, writer) = std::io::pipe()?;
+    let target = inherited_fds
+        .iter()
+        .flat_map(|fd| [fd.target(), fd.source()])
+        .fold(libc::STDERR_FILENO, i32::max)
+        + 1;
+    Ok((
+        InheritedFd::new(reader, target)?,
+        PipedPayload { writer, json },
+    ))
+}
+
+#[cfg(not(unix))]
+fn pipe_payload(
+    _sandbox_policy: &SandboxPolicy,
+    _env: &HashMap<String, String>,
+    _inherited_fds: &[InheritedFd],
+) -> std::io::Result<(InheritedFd, PipedPayload)> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "descriptors can only be inherited on Unix",
+    ))
+}
+
+/// The helper's arguments and, if there is one, the policy pipe to hand it,
+/// given how setting up that pipe (`piped`) went. Without a pipe the policy
+/// is passed as the positional `SANDBOX_POLICY` argument, which the helper
+/// still accepts for compatibility; the environment stays off the command
+/// line either way.
+fn helper_invocation(
+    command: Vec<String>,
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+    piped: std::io::Result<(InheritedFd, PipedPayload)>,
+) -> (Vec<String>, Option<(InheritedFd, PipedPayload)>) {
+    match piped {
+        Ok((policy_fd, payload)) => (
+            create_linux_sandbox_command_args_with_policy_fd(command, policy_fd.target(), cwd),
+            Some((policy_fd, payload)),
+        ),
+        Err(err) => {
+            tracing::warn!(
+                "cannot pass the sandbox policy through a pipe, passing it as an argument: {err}"
+            );
+            (
+                create_linux_sandbox_command_args(command, sandbox_policy, cwd),
+                None,
+            )
+        }
+    }
 }
 
 /// Converts the sandbox policy into the CLI invocation for `codex-linux-sandbox`.
-fn create_linux_sandbox_command_args(
+///
+/// The environment is deliberately not part of it: arguments are visible to
+/// every user through `ps` and `/proc/<pid>/cmdline`, and the environment
+/// may hold tokens. Invoked like this, the command gets the helper's own
+/// environment, which is the one the helper is spawned with.
+pub(crate) fn create_linux_sandbox_command_args(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
     cwd: &Path,
@@ -64,3 +275,156 @@ fn create_linux_sandbox_command_args(
 
     linux_cmd
 }
+
+/// Like [`create_linux_sandbox_command_args`], but the helper reads the
+/// policy and environment from descriptor `policy_fd` instead.
+fn create_linux_sandbox_command_args_with_policy_fd(
+    command: Vec<String>,
+    policy_fd: i32,
+    cwd: &Path,
+) -> Vec<String> {
+    #[expect(clippy::expect_used)]
+    let sandbox_policy_cwd = cwd.to_str().expect("cwd must be valid UTF-8").to_string();
+
+    let mut linux_cmd: Vec<String> = vec![
+        "--policy-fd".to_string(),
+        policy_fd.to_string(),
+        sandbox_policy_cwd,
+        "--".to_string(),
+    ];
+    linux_cmd.extend(command);
+    linux_cmd
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Read;
+
+    #[test]
+    fn policy_fd_args_keep_the_policy_out_of_argv() {
+        let args = create_linux_sandbox_command_args_with_policy_fd(
+            vec!["ls".to_string(), "-l".to_string()],
+            3,
+            Path::new("/work"),
+        );
+        assert_eq!(args, ["--policy-fd", "3", "/work", "--", "ls", "-l"]);
+    }
+
+    #[test]
+    fn policy_goes_into_argv_when_the_pipe_cannot_be_set_up() {
+        let (args, piped) = helper_invocation(
+            vec!["ls".to_string()],
+            &SandboxPolicy::ReadOnly,
+            Path::new("/work"),
+            Err(std::io::Error::other("no pipes here")),
+        );
+        assert_eq!(args, ["/work", r#"{"mode":"read-only"}"#, "--", "ls"]);
+        assert!(piped.is_none());
+
+        let piped = pipe_payload(&SandboxPolicy::ReadOnly, &HashMap::new(), &[]);
+        let (args, piped) = helper_invocation(
+            vec!["ls".to_string()],
+            &SandboxPolicy::ReadOnly,
+            Path::new("/work"),
+            piped,
+        );
+        let (policy_fd, _payload) = piped.expect("pipe is used");
+        assert_eq!(
+            args,
+            [
+                "--policy-fd".to_string(),
+                policy_fd.target().to_string(),
+                "/work".to_string(),
+                "--".to_string(),
+                "ls".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn argv_fallback_leaves_out_the_environment() {
+        let args = create_linux_sandbox_command_args(
+            vec!["ls".to_string()],
+            &SandboxPolicy::ReadOnly,
+            Path::new("/work"),
+        );
+        assert_eq!(args, ["/work", r#"{"mode":"read-only"}"#, "--", "ls"]);
+    }
+
+    #[test]
+    fn only_explicit_exclusions_are_warned_about() {
+        let mut policy = SandboxPolicy::new_workspace_write_policy();
+        assert_eq!(unenforced_exclusions_warning(&policy), None);
+
+        if let SandboxPolicy::WorkspaceWrite {
+            writable_root_exclusions,
+            ..
+        } = &mut policy
+        {
+            *writable_root_exclusions = vec![PathBuf::from("node_modules")];
+        }
+        let warning = unenforced_exclusions_warning(&policy).expect("warning");
+        assert!(warning.contains("(node_modules)"), "{warning}");
+    }
+
+    #[test]
+    fn policy_fd_is_above_every_inherited_descriptor() {
+        let (reader, _writer) = std::io::pipe().expect("pipe");
+        let inherited = InheritedFd::new(reader, 7).expect("inherited fd");
+        let highest = inherited.source().max(7);
+
+        let env = HashMap::from([("SECRET_PATH".to_string(), "/home/me".to_string())]);
+        let (policy_fd, payload) =
+            pipe_payload(&SandboxPolicy::ReadOnly, &env, &[inherited]).expect("pipe payload");
+        assert_eq!(policy_fd.target(), highest + 1);
+
+        let PipedPayload { mut writer, json } = payload;
+        std::io::Write::write_all(&mut writer, &json).expect("write payload");
+        drop(writer);
+        // Read the payload back through the same pipe.
+        let reader = std::fs::File::from(policy_fd_into_owned(policy_fd));
+        let mut text = String::new();
+        (&reader).read_to_string(&mut text).expect("read payload");
+        let payload: LinuxSandboxPayload = serde_json::from_str(&text).expect("payload json");
+        assert_eq!(payload.sandbox_policy, SandboxPolicy::ReadOnly);
+        assert_eq!(payload.sandbox_env, env);
+    }
+
+    #[test]
+    fn broken_sandbox_helpers_are_reported_as_such() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let reason = |path: &Path| match check_linux_sandbox_exe(path) {
+            Err(CodexErr::LandlockSandboxExecutableUnusable { reason, .. }) => reason,
+            other => panic!("unexpected result for {}: {other:?}", path.display()),
+        };
+
+        assert_eq!(reason(&dir.path().join("missing")), "it does not exist");
+        assert_eq!(reason(dir.path()), "it is not a file");
+
+        let helper = dir.path().join("codex-linux-sandbox");
+        std::fs::write(&helper, "#!/bin/sh\n").expect("write helper");
+        std::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o644)).expect("chmod");
+        assert_eq!(reason(&helper), "it is not executable");
+
+        std::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+        check_linux_sandbox_exe(&helper).expect("executable helper");
+
+        let err =
+            linux_sandbox_spawn_error(&helper, std::io::Error::from_raw_os_error(libc::ENOEXEC));
+        assert!(matches!(
+            err,
+            CodexErr::LandlockSandboxExecutableUnusable { .. }
+        ));
+    }
+
+    fn policy_fd_into_owned(policy_fd: InheritedFd) -> std::os::fd::OwnedFd {
+        use std::os::fd::FromRawFd;
+
+        // SAFETY: the descriptor is duplicated, so the copy is owned here.
+        unsafe { std::os::fd::OwnedFd::from_raw_fd(libc::dup(policy_fd.source())) }
+    }
+}
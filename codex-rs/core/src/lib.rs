@@ -23,8 +23,11 @@ pub mod custom_prompts;
 mod environment_context;
 pub mod error;
 pub mod exec;
+pub mod exec_backend;
 mod exec_command;
+pub mod exec_diagnostics;
 pub mod exec_env;
+pub mod exec_summary;
 mod flags;
 pub mod git_info;
 mod is_safe_command;
@@ -33,6 +36,8 @@ mod mcp_connection_manager;
 mod mcp_tool_call;
 mod message_history;
 mod model_provider_info;
+mod model_registry;
+mod oom;
 pub mod parse_command;
 mod user_instructions;
 pub use model_provider_info::BUILT_IN_OSS_MODEL_PROVIDER_ID;
@@ -48,19 +53,27 @@ pub use conversation_manager::NewConversation;
 pub use auth::AuthManager;
 pub use auth::CodexAuth;
 pub mod default_client;
+pub mod model_capabilities;
 pub mod model_family;
 mod openai_model_info;
 mod openai_tools;
 pub mod plan_tool;
 pub mod project_doc;
+mod pty;
 mod rollout;
 pub(crate) mod safety;
+pub mod sandbox_audit;
+pub mod sandbox_presets;
+pub mod sandbox_self_test;
 pub mod seatbelt;
 pub mod shell;
 pub mod spawn;
+mod sse_fixture;
 pub mod terminal;
+pub mod token_estimate;
 mod tool_apply_patch;
 pub mod turn_diff_tracker;
+pub use rollout::RolloutParent;
 pub use rollout::RolloutRecorder;
 pub use rollout::SessionMeta;
 pub use rollout::list::ConversationItem;
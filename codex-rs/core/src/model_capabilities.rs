@@ -0,0 +1,187 @@
+//! What a model can do, in one place.
+//!
+//! [`ModelCapabilities`] combines the per-model table in
+//! [`openai_model_info`](crate::openai_model_info) with the [`ModelFamily`]
+//! heuristics, so features that depend on the model (output budgets,
+//! request parameters, tools) consult the same answer.
+
+use serde::Serialize;
+
+use crate::config_types::ReasoningSummaryFormat;
+use crate::model_family::ModelFamily;
+use crate::model_family::find_family_for_model;
+use crate::openai_model_info::ModelInfoMatch;
+use crate::openai_model_info::default_model_info;
+use crate::openai_model_info::get_model_info;
+
+/// Slug prefixes of models that accept image inputs. Checked in order and
+/// the first match wins, so exceptions come before their family.
+const VISION_PREFIXES: &[(&str, bool)] = &[
+    ("o3-mini", false),
+    ("o3", true),
+    ("o4", true),
+    ("gpt-4o", true),
+    ("gpt-4.1", true),
+    ("gpt-5", true),
+    ("codex-", true),
+];
+
+/// Capabilities of a model, as far as Codex knows them.
+///
+/// For a model Codex knows nothing about (`known` is `false`) every field
+/// errs on the safe side: small default budgets and no optional features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ModelCapabilities {
+    /// Whether the model has an entry of its own in Codex's model table; see
+    /// the struct docs. A model only matched by its family (e.g. a new
+    /// snapshot) gets that family's conservative values and is not known.
+    pub known: bool,
+    /// Size of the context window in tokens.
+    pub context_window: u64,
+    /// Most output tokens the model can generate for one request.
+    pub max_output_tokens: u64,
+    /// Whether the model can call tools (function calling).
+    pub supports_tools: bool,
+    /// Whether the model accepts image inputs.
+    pub supports_vision: bool,
+    /// Whether the model accepts a reasoning effort.
+    pub supports_reasoning: bool,
+    /// Whether the model can return summaries of its reasoning.
+    pub supports_reasoning_summaries: bool,
+    /// Whether the model accepts `temperature` and `top_p`.
+    pub supports_temperature: bool,
+    /// Whether responses can be streamed. Codex only talks to models over
+    /// streaming APIs, so this is always `true`.
+    pub supports_streaming: bool,
+}
+
+/// Capabilities of the model named `name`, e.g. `"gpt-4.1"` or a snapshot
+/// such as `"o3-2025-04-16"`.
+pub fn model_capabilities(name: &str) -> ModelCapabilities {
+    let family = find_family_for_model(name).unwrap_or_else(|| ModelFamily {
+        slug: name.to_string(),
+        family: name.to_string(),
+        needs_special_apply_patch_instructions: false,
+        supports_reasoning_summaries: false,
+        reasoning_summary_format: ReasoningSummaryFormat::None,
+        uses_local_shell_tool: false,
+        apply_patch_tool_type: None,
+    });
+    ModelCapabilities::for_family(&family)
+}
+
+impl ModelCapabilities {
+    /// Capabilities of `model_family`, including any overrides from
+    /// config.toml already applied to it.
+    pub(crate) fn for_family(model_family: &ModelFamily) -> Self {
+        let found = get_model_info(model_family);
+        let known = found.is_some_and(ModelInfoMatch::is_exact);
+        let has_info = found.is_some();
+        let info = found.map_or_else(default_model_info, ModelInfoMatch::info);
+        Self {
+            known,
+            context_window: info.context_window,
+            max_output_tokens: info.max_output_tokens,
+            supports_tools: has_info,
+            supports_vision: has_info && supports_vision(&model_family.slug),
+            supports_reasoning: info.supports_reasoning,
+            supports_reasoning_summaries: model_family.supports_reasoning_summaries,
+            supports_temperature: info.supports_temperature,
+            supports_streaming: true,
+        }
+    }
+
+    /// The context window, or `None` for a model Codex does not know, whose
+    /// window could be larger than the conservative default.
+    pub(crate) fn known_context_window(&self) -> Option<u64> {
+        self.known.then_some(self.context_window)
+    }
+}
+
+fn supports_vision(slug: &str) -> bool {
+    VISION_PREFIXES
+        .iter()
+        .find(|(prefix, _)| slug.starts_with(prefix))
+        .is_some_and(|(_, vision)| *vision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn known_models_report_their_table_entry() {
+        assert_eq!(
+            ModelCapabilities {
+                known: true,
+                context_window: 200_000,
+                max_output_tokens: 100_000,
+                supports_tools: true,
+                supports_vision: true,
+                supports_reasoning: true,
+                supports_reasoning_summaries: true,
+                supports_temperature: false,
+                supports_streaming: true,
+            },
+            model_capabilities("o3")
+        );
+        assert_eq!(
+            ModelCapabilities {
+                known: true,
+                context_window: 16_385,
+                max_output_tokens: 4_096,
+                supports_tools: true,
+                supports_vision: false,
+                supports_reasoning: false,
+                supports_reasoning_summaries: false,
+                supports_temperature: true,
+                supports_streaming: true,
+            },
+            model_capabilities("gpt-3.5-turbo")
+        );
+        assert!(!model_capabilities("o3-mini").supports_vision);
+        assert!(!model_capabilities("gpt-oss-20b").supports_vision);
+    }
+
+    #[test]
+    fn only_reasoning_models_support_reasoning() {
+        for slug in ["o3", "o4-mini", "gpt-5", "codex-mini-latest", "gpt-oss-20b"] {
+            assert!(model_capabilities(slug).supports_reasoning, "{slug}");
+        }
+        for slug in ["gpt-4o", "gpt-4.1", "gpt-3.5-turbo", "llama3"] {
+            assert!(!model_capabilities(slug).supports_reasoning, "{slug}");
+        }
+    }
+
+    #[test]
+    fn unknown_models_get_conservative_defaults() {
+        let info = default_model_info();
+        let capabilities = model_capabilities("llama3");
+        assert_eq!(
+            ModelCapabilities {
+                known: false,
+                context_window: info.context_window,
+                max_output_tokens: info.max_output_tokens,
+                supports_tools: false,
+                supports_vision: false,
+                supports_reasoning: false,
+                supports_reasoning_summaries: false,
+                supports_temperature: false,
+                supports_streaming: true,
+            },
+            capabilities
+        );
+        assert_eq!(None, capabilities.known_context_window());
+    }
+
+    #[test]
+    fn family_defaults_are_not_known() {
+        let capabilities = model_capabilities("gpt-4o-2099-01-01");
+        assert!(!capabilities.known);
+        assert_eq!(128_000, capabilities.context_window);
+        assert_eq!(None, capabilities.known_context_window());
+        assert!(capabilities.supports_tools);
+        assert!(model_capabilities("gpt-4o-2024-08-06").known);
+    }
+}
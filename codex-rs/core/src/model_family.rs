@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::config_types::ReasoningSummaryFormat;
 use crate::tool_apply_patch::ApplyPatchToolType;
 
@@ -73,6 +75,34 @@ macro_rules! simple_model_family {
     }};
 }
 
+/// Model names that point at whatever snapshot OpenAI currently serves for
+/// them, mapped to that snapshot (as of Jun 25, 2025). Both the model info
+/// table and the request builder use the resolved id, so they cannot drift
+/// apart when the default snapshot changes.
+const BUILT_IN_MODEL_ALIASES: &[(&str, &str)] = &[
+    ("gpt-4.1", "gpt-4.1-2025-04-14"),
+    ("gpt-4o", "gpt-4o-2024-08-06"),
+    ("o4-mini", "o4-mini-2025-04-16"),
+];
+
+/// Resolves `name` to a canonical model id. `overrides` (the `model_aliases`
+/// table from config.toml) take precedence over the built-in aliases; names
+/// that are not aliases are returned unchanged.
+pub fn resolve_model_alias<'a>(name: &'a str, overrides: &'a HashMap<String, String>) -> &'a str {
+    match overrides.get(name) {
+        Some(target) => target,
+        None => resolve_built_in_model_alias(name),
+    }
+}
+
+/// Like [`resolve_model_alias`], but only consults the built-in aliases.
+pub(crate) fn resolve_built_in_model_alias(name: &str) -> &str {
+    BUILT_IN_MODEL_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical)
+}
+
 /// Returns a `ModelFamily` for the given model slug, or `None` if the slug
 /// does not match any known model family.
 pub fn find_family_for_model(slug: &str) -> Option<ModelFamily> {
@@ -118,3 +148,50 @@ pub fn find_family_for_model(slug: &str) -> Option<ModelFamily> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn aliases_resolve_to_canonical_ids() {
+        let no_overrides = HashMap::new();
+        assert_eq!(
+            "gpt-4o-2024-08-06",
+            resolve_model_alias("gpt-4o", &no_overrides)
+        );
+        assert_eq!(
+            "o4-mini-2025-04-16",
+            resolve_model_alias("o4-mini", &no_overrides)
+        );
+        assert_eq!(
+            "gpt-4o-2024-05-13",
+            resolve_model_alias("gpt-4o-2024-05-13", &no_overrides)
+        );
+        assert_eq!(
+            "my-fine-tune",
+            resolve_model_alias("my-fine-tune", &no_overrides)
+        );
+    }
+
+    #[test]
+    fn config_overrides_take_precedence() {
+        let overrides = HashMap::from([
+            ("gpt-4o".to_string(), "gpt-4o-2024-11-20".to_string()),
+            ("fast".to_string(), "o4-mini-2025-04-16".to_string()),
+        ]);
+        assert_eq!(
+            "gpt-4o-2024-11-20",
+            resolve_model_alias("gpt-4o", &overrides)
+        );
+        assert_eq!(
+            "o4-mini-2025-04-16",
+            resolve_model_alias("fast", &overrides)
+        );
+        assert_eq!(
+            "gpt-4.1-2025-04-14",
+            resolve_model_alias("gpt-4.1", &overrides)
+        );
+    }
+}
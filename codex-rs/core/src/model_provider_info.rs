@@ -6,6 +6,7 @@
 //!      key. These override or extend the defaults at runtime.
 
 use crate::CodexAuth;
+use codex_protocol::config_types::ReasoningEffort;
 use codex_protocol::mcp_protocol::AuthMode;
 use serde::Deserialize;
 use serde::Serialize;
@@ -14,6 +15,9 @@ use std::env::VarError;
 use std::time::Duration;
 
 use crate::error::EnvVarError;
+use crate::util::DEFAULT_RETRY_INITIAL_DELAY_MS;
+use crate::util::DEFAULT_RETRY_MAX_DELAY_MS;
+use crate::util::RetryBackoff;
 const DEFAULT_STREAM_IDLE_TIMEOUT_MS: u64 = 300_000;
 const DEFAULT_STREAM_MAX_RETRIES: u64 = 5;
 const DEFAULT_REQUEST_MAX_RETRIES: u64 = 4;
@@ -40,7 +44,7 @@ pub enum WireApi {
 }
 
 /// Serializable representation of a provider definition.
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 pub struct ModelProviderInfo {
     /// Friendly display name.
     pub name: String,
@@ -80,6 +84,37 @@ pub struct ModelProviderInfo {
     /// the connection as lost.
     pub stream_idle_timeout_ms: Option<u64>,
 
+    /// Request-level timeout (in milliseconds). Bounds both the time until the
+    /// provider starts responding and the gap between consecutive SSE events.
+    /// Unlike `ExecParams.timeout`, this only applies to model requests.
+    pub request_timeout_ms: Option<u64>,
+
+    /// Delay (in milliseconds) before the first retry of a failed request or
+    /// dropped stream; later retries back off exponentially from it.
+    pub retry_initial_delay_ms: Option<u64>,
+
+    /// Upper bound (in milliseconds) for a single backoff delay.
+    pub retry_max_delay_ms: Option<u64>,
+
+    /// Give up retrying once this much time (in milliseconds) has passed
+    /// since the first failure, even if retries remain.
+    pub retry_max_elapsed_ms: Option<u64>,
+
+    /// Reasoning effort to request from this provider, overriding
+    /// `model_reasoning_effort`. Only sent for models known to support
+    /// reasoning; silently dropped for all others.
+    pub reasoning_effort: Option<ReasoningEffort>,
+
+    /// Model ids to send to this provider in place of the configured model,
+    /// e.g. a gateway's deployment name for `gpt-4o`. The configured id is
+    /// still used to look up what the model can do.
+    pub model_map: Option<HashMap<String, String>>,
+
+    /// Whether to ask a Chat Completions provider for token usage
+    /// (`stream_options.include_usage`). Not every provider accepts it.
+    #[serde(default)]
+    pub stream_include_usage: bool,
+
     /// Whether this provider requires some form of standard authentication (API key, ChatGPT token).
     #[serde(default)]
     pub requires_openai_auth: bool,
@@ -99,22 +134,47 @@ impl ModelProviderInfo {
         client: &'a reqwest::Client,
         auth: &Option<CodexAuth>,
     ) -> crate::error::Result<reqwest::RequestBuilder> {
-        let effective_auth = match self.api_key() {
-            Ok(Some(key)) => Some(CodexAuth::from_api_key(&key)),
-            Ok(None) => auth.clone(),
+        let effective_auth = self.effective_auth(auth)?;
+        let url = self.get_full_url(&effective_auth);
+        self.authorize(client.post(url), &effective_auth).await
+    }
+
+    /// Like [`ModelProviderInfo::create_request_builder`], but for a `GET` of
+    /// the provider's `/models` endpoint, which lists the models it serves.
+    pub(crate) async fn create_models_request_builder(
+        &self,
+        client: &reqwest::Client,
+        auth: &Option<CodexAuth>,
+    ) -> crate::error::Result<reqwest::RequestBuilder> {
+        let effective_auth = self.effective_auth(auth)?;
+        let url = format!(
+            "{}/models{}",
+            self.get_base_url(&effective_auth),
+            self.get_query_string()
+        );
+        self.authorize(client.get(url), &effective_auth).await
+    }
+
+    /// The provider's API key if it has one, `auth` otherwise.
+    fn effective_auth(&self, auth: &Option<CodexAuth>) -> crate::error::Result<Option<CodexAuth>> {
+        match self.api_key() {
+            Ok(Some(key)) => Ok(Some(CodexAuth::from_api_key(&key))),
+            Ok(None) => Ok(auth.clone()),
             Err(err) => {
                 if auth.is_some() {
-                    auth.clone()
+                    Ok(auth.clone())
                 } else {
-                    return Err(err);
+                    Err(err)
                 }
             }
-        };
-
-        let url = self.get_full_url(&effective_auth);
-
-        let mut builder = client.post(url);
+        }
+    }
 
+    async fn authorize(
+        &self,
+        mut builder: reqwest::RequestBuilder,
+        effective_auth: &Option<CodexAuth>,
+    ) -> crate::error::Result<reqwest::RequestBuilder> {
         if let Some(auth) = effective_auth.as_ref() {
             builder = builder.bearer_auth(auth.get_token().await?);
         }
@@ -136,6 +196,16 @@ impl ModelProviderInfo {
     }
 
     pub(crate) fn get_full_url(&self, auth: &Option<CodexAuth>) -> String {
+        let query_string = self.get_query_string();
+        let base_url = self.get_base_url(auth);
+
+        match self.wire_api {
+            WireApi::Responses => format!("{base_url}/responses{query_string}"),
+            WireApi::Chat => format!("{base_url}/chat/completions{query_string}"),
+        }
+    }
+
+    fn get_base_url(&self, auth: &Option<CodexAuth>) -> String {
         let default_base_url = if matches!(
             auth,
             Some(CodexAuth {
@@ -147,16 +217,9 @@ impl ModelProviderInfo {
         } else {
             "https://api.openai.com/v1"
         };
-        let query_string = self.get_query_string();
-        let base_url = self
-            .base_url
+        self.base_url
             .clone()
-            .unwrap_or(default_base_url.to_string());
-
-        match self.wire_api {
-            WireApi::Responses => format!("{base_url}/responses{query_string}"),
-            WireApi::Chat => format!("{base_url}/chat/completions{query_string}"),
-        }
+            .unwrap_or(default_base_url.to_string())
     }
 
     /// Apply provider-specific HTTP headers (both static and environment-based)
@@ -221,11 +284,45 @@ impl ModelProviderInfo {
             .min(MAX_STREAM_MAX_RETRIES)
     }
 
-    /// Effective idle timeout for streaming responses.
+    /// Effective idle timeout for streaming responses. When a
+    /// `request_timeout_ms` is configured it also caps the idle timeout.
     pub fn stream_idle_timeout(&self) -> Duration {
-        self.stream_idle_timeout_ms
+        let idle = self
+            .stream_idle_timeout_ms
             .map(Duration::from_millis)
-            .unwrap_or(Duration::from_millis(DEFAULT_STREAM_IDLE_TIMEOUT_MS))
+            .unwrap_or(Duration::from_millis(DEFAULT_STREAM_IDLE_TIMEOUT_MS));
+        match self.request_timeout() {
+            Some(request_timeout) => idle.min(request_timeout),
+            None => idle,
+        }
+    }
+
+    /// Maximum time to wait for the provider to start responding, if configured.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// The id to send to this provider for `model`, after `model_map`.
+    pub fn wire_model<'a>(&'a self, model: &'a str) -> &'a str {
+        self.model_map
+            .as_ref()
+            .and_then(|map| map.get(model))
+            .map_or(model, String::as_str)
+    }
+
+    /// Fresh backoff state for one sequence of retries against this provider.
+    pub(crate) fn retry_backoff(&self) -> RetryBackoff {
+        RetryBackoff::new(
+            Duration::from_millis(
+                self.retry_initial_delay_ms
+                    .unwrap_or(DEFAULT_RETRY_INITIAL_DELAY_MS),
+            ),
+            Duration::from_millis(
+                self.retry_max_delay_ms
+                    .unwrap_or(DEFAULT_RETRY_MAX_DELAY_MS),
+            ),
+            self.retry_max_elapsed_ms.map(Duration::from_millis),
+        )
     }
 }
 
@@ -278,7 +375,13 @@ pub fn built_in_model_providers() -> HashMap<String, ModelProviderInfo> {
                 request_max_retries: None,
                 stream_max_retries: None,
                 stream_idle_timeout_ms: None,
+                request_timeout_ms: None,
+                retry_initial_delay_ms: None,
+                retry_max_delay_ms: None,
+                retry_max_elapsed_ms: None,
                 requires_openai_auth: true,
+                model_map: None,
+                stream_include_usage: false,
             },
         ),
         (BUILT_IN_OSS_MODEL_PROVIDER_ID, create_oss_provider()),
@@ -322,7 +425,14 @@ pub fn create_oss_provider_with_base_url(base_url: &str) -> ModelProviderInfo {
         request_max_retries: None,
         stream_max_retries: None,
         stream_idle_timeout_ms: None,
+        request_timeout_ms: None,
+        retry_initial_delay_ms: None,
+        retry_max_delay_ms: None,
+        retry_max_elapsed_ms: None,
         requires_openai_auth: false,
+        reasoning_effort: None,
+        model_map: None,
+        stream_include_usage: false,
     }
 }
 
@@ -350,6 +460,7 @@ base_url = "http://localhost:11434/v1"
             stream_max_retries: None,
             stream_idle_timeout_ms: None,
             requires_openai_auth: false,
+            ..Default::default()
         };
 
         let provider: ModelProviderInfo = toml::from_str(azure_provider_toml).unwrap();
@@ -379,6 +490,7 @@ query_params = { api-version = "2025-04-01-preview" }
             stream_max_retries: None,
             stream_idle_timeout_ms: None,
             requires_openai_auth: false,
+            ..Default::default()
         };
 
         let provider: ModelProviderInfo = toml::from_str(azure_provider_toml).unwrap();
@@ -411,9 +523,48 @@ env_http_headers = { "X-Example-Env-Header" = "EXAMPLE_ENV_VAR" }
             stream_max_retries: None,
             stream_idle_timeout_ms: None,
             requires_openai_auth: false,
+            ..Default::default()
         };
 
         let provider: ModelProviderInfo = toml::from_str(azure_provider_toml).unwrap();
         assert_eq!(expected_provider, provider);
     }
+
+    #[test]
+    fn test_request_timeout_caps_stream_idle_timeout() {
+        let provider_toml = r#"
+name = "Slow"
+base_url = "https://example.com"
+stream_idle_timeout_ms = 300000
+request_timeout_ms = 1500
+        "#;
+        let provider: ModelProviderInfo = toml::from_str(provider_toml).unwrap();
+        assert_eq!(
+            Some(Duration::from_millis(1500)),
+            provider.request_timeout()
+        );
+        assert_eq!(Duration::from_millis(1500), provider.stream_idle_timeout());
+
+        let provider = ModelProviderInfo {
+            request_timeout_ms: None,
+            ..provider
+        };
+        assert_eq!(None, provider.request_timeout());
+        assert_eq!(
+            Duration::from_millis(300_000),
+            provider.stream_idle_timeout()
+        );
+    }
+
+    #[test]
+    fn test_model_map_renames_only_mapped_models() {
+        let provider_toml = r#"
+name = "Gateway"
+base_url = "https://gateway.example.com/v1"
+model_map = { "gpt-4o" = "prod-gpt4o-deployment" }
+        "#;
+        let provider: ModelProviderInfo = toml::from_str(provider_toml).unwrap();
+        assert_eq!("prod-gpt4o-deployment", provider.wire_model("gpt-4o"));
+        assert_eq!("o3", provider.wire_model("o3"));
+    }
 }
@@ -0,0 +1,264 @@
+//! Context windows of models Codex does not know, from the provider.
+//!
+//! Codex's model table (see [`crate::openai_model_info`]) only covers the
+//! models it shipped with. With `fetch_model_info` set, the context window of
+//! any other model is looked up in the provider's `/models` endpoint, which
+//! many providers (OpenRouter, vLLM, LM Studio, ...) annotate with it, and
+//! cached in `$CODEX_HOME/model_info_cache.json` so it is fetched only once.
+//! `model_context_window` in config.toml and the model table still take
+//! precedence.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use tempfile::NamedTempFile;
+use tracing::debug;
+use tracing::warn;
+
+use crate::auth::CodexAuth;
+use crate::config::Config;
+use crate::default_client::create_client;
+use crate::error::CodexErr;
+use crate::error::Result;
+use crate::model_provider_info::ModelProviderInfo;
+use crate::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+
+/// Name of the cache file under `CODEX_HOME`.
+const MODEL_INFO_CACHE_FILE: &str = "model_info_cache.json";
+
+/// How long to wait for the `/models` endpoint before starting without it.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Fields providers report a model's context window in, tried in order.
+const CONTEXT_WINDOW_FIELDS: &[&str] = &[
+    "context_window",
+    "context_length",
+    "max_context_length",
+    "max_model_len",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ModelInfoCache {
+    /// Keyed by `<provider id>:<model>`.
+    #[serde(default)]
+    models: BTreeMap<String, CachedModelInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedModelInfo {
+    context_window: u64,
+}
+
+/// Looks up the context window of `config.model` in the cache, or else in
+/// the provider's `/models` endpoint (unless the sandbox disabled network
+/// access), and caches what it finds. Returns `None`, after logging why,
+/// when neither has it.
+pub(crate) async fn lookup_context_window(
+    config: &Config,
+    auth: &Option<CodexAuth>,
+) -> Option<u64> {
+    let model = config.model_provider.wire_model(&config.model);
+    let key = format!("{}:{model}", config.model_provider_id);
+    let cache_path = config.codex_home.join(MODEL_INFO_CACHE_FILE);
+    let mut cache = read_cache(&cache_path).await;
+    if let Some(cached) = cache.models.get(&key) {
+        return Some(cached.context_window);
+    }
+
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        debug!("network access is disabled; not looking up model info for {model}");
+        return None;
+    }
+    let context_window = match fetch_context_window(
+        &config.model_provider,
+        auth,
+        model,
+        &config.responses_originator_header,
+    )
+    .await
+    {
+        Ok(Some(context_window)) => context_window,
+        Ok(None) => {
+            debug!("the provider does not report a context window for {model}");
+            return None;
+        }
+        Err(err) => {
+            warn!("failed to look up model info for {model}: {err}");
+            return None;
+        }
+    };
+
+    cache.models.insert(key, CachedModelInfo { context_window });
+    if let Err(err) = write_cache(&cache_path, &cache).await {
+        warn!(
+            "failed to cache model info in {}: {err}",
+            cache_path.display()
+        );
+    }
+    Some(context_window)
+}
+
+async fn fetch_context_window(
+    provider: &ModelProviderInfo,
+    auth: &Option<CodexAuth>,
+    model: &str,
+    originator: &str,
+) -> Result<Option<u64>> {
+    let client = create_client(originator);
+    let response = provider
+        .create_models_request_builder(&client, auth)
+        .await?
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(CodexErr::UnexpectedStatus(status, body));
+    }
+    let models: Value = response.json().await?;
+    Ok(context_window_from_models(&models, model))
+}
+
+/// Finds `model` in a `/models` response, either OpenAI's `{"data": [...]}`
+/// or a bare list, and returns the first of [`CONTEXT_WINDOW_FIELDS`] it has.
+fn context_window_from_models(models: &Value, model: &str) -> Option<u64> {
+    let entry = models
+        .get("data")
+        .unwrap_or(models)
+        .as_array()?
+        .iter()
+        .find(|entry| entry.get("id").and_then(Value::as_str) == Some(model))?;
+    CONTEXT_WINDOW_FIELDS
+        .iter()
+        .find_map(|field| entry.get(field).and_then(Value::as_u64))
+}
+
+/// A missing or unreadable cache is treated as empty; it is rewritten on the
+/// next successful lookup.
+async fn read_cache(path: &Path) -> ModelInfoCache {
+    match tokio::fs::read_to_string(path).await {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+            warn!(
+                "ignoring unreadable model info cache {}: {err}",
+                path.display()
+            );
+            ModelInfoCache::default()
+        }),
+        Err(_) => ModelInfoCache::default(),
+    }
+}
+
+/// Writes the cache to a temporary file next to `path` and renames it into
+/// place, so concurrent sessions never read a partially written cache.
+async fn write_cache(path: &Path, cache: &ModelInfoCache) -> Result<()> {
+    let text = serde_json::to_string_pretty(cache)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let tmp_file = NamedTempFile::new_in(dir)?;
+    tokio::fs::write(tmp_file.path(), text).await?;
+    tmp_file.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::config::ConfigOverrides;
+    use crate::config::ConfigToml;
+    use crate::model_provider_info::create_oss_provider_with_base_url;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use tempfile::TempDir;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+
+    #[test]
+    fn context_window_is_read_from_the_models_list() {
+        let openai_style = json!({
+            "object": "list",
+            "data": [
+                {"id": "other", "context_length": 8192},
+                {"id": "my-model", "context_length": 32768},
+            ],
+        });
+        assert_eq!(
+            Some(32_768),
+            context_window_from_models(&openai_style, "my-model")
+        );
+
+        let bare_list = json!([{"id": "my-model", "max_model_len": 4096}]);
+        assert_eq!(
+            Some(4_096),
+            context_window_from_models(&bare_list, "my-model")
+        );
+
+        let no_window = json!({"data": [{"id": "my-model", "owned_by": "me"}]});
+        assert_eq!(None, context_window_from_models(&no_window, "my-model"));
+        assert_eq!(None, context_window_from_models(&openai_style, "missing"));
+    }
+
+    #[tokio::test]
+    async fn lookup_fetches_once_and_then_uses_the_cache() {
+        if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+            println!("Skipping test because network access is disabled in this sandbox.");
+            return;
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{"id": "my-model", "context_length": 65536}],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let codex_home = TempDir::new().unwrap();
+        let mut config = Config::load_from_base_config_with_overrides(
+            ConfigToml::default(),
+            ConfigOverrides::default(),
+            codex_home.path().to_path_buf(),
+        )
+        .unwrap();
+        config.model = "my-model".to_string();
+        config.model_provider = create_oss_provider_with_base_url(&format!("{}/v1", server.uri()));
+
+        assert_eq!(Some(65_536), lookup_context_window(&config, &None).await);
+        assert_eq!(Some(65_536), lookup_context_window(&config, &None).await);
+        assert!(codex_home.path().join(MODEL_INFO_CACHE_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn write_cache_replaces_the_file_without_leaving_temp_files() {
+        let codex_home = TempDir::new().unwrap();
+        let cache_path = codex_home.path().join(MODEL_INFO_CACHE_FILE);
+        std::fs::write(&cache_path, "not json").unwrap();
+
+        let mut cache = ModelInfoCache::default();
+        cache.models.insert(
+            "key".to_string(),
+            CachedModelInfo {
+                context_window: 4_096,
+            },
+        );
+        write_cache(&cache_path, &cache).await.unwrap();
+
+        let read_back = read_cache(&cache_path).await;
+        assert_eq!(
+            Some(4_096),
+            read_back.models.get("key").map(|info| info.context_window)
+        );
+        let entries = std::fs::read_dir(codex_home.path()).unwrap().count();
+        assert_eq!(1, entries);
+    }
+}
@@ -0,0 +1,138 @@
+//! Best-effort detection of commands killed by the Linux out-of-memory
+//! killer, which otherwise look like any other `SIGKILL`.
+//!
+//! The kernel counts OOM kills in the `memory.events` file of every cgroup v2
+//! (including those of descendant cgroups). Right after a command is spawned
+//! its cgroup is looked up through `/proc/<pid>/cgroup` and the counter read;
+//! if the counter went up by the time the command died of a `SIGKILL` Codex
+//! did not send, that points at the OOM killer. Only processes sharing the
+//! command's cgroup can cause a false positive, never the rest of the host.
+//! Where the counter cannot be read (no cgroup v2, outside Linux) the kill
+//! is reported as a plain signal that may have been an OOM kill.
+
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+
+/// The OOM kill counter of a command's cgroup, as read right after it was
+/// spawned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OomKillWatch {
+    #[cfg(target_os = "linux")]
+    counter: Option<(PathBuf, u64)>,
+}
+
+/// What an [`OomKillWatch`] can tell about a `SIGKILL` Codex did not send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OomVerdict {
+    /// The command's cgroup counted an OOM kill while it ran.
+    Killed,
+    /// The counter is known and did not change.
+    NotKilled,
+    /// The counter could not be read, so an OOM kill is possible.
+    Unknown,
+}
+
+impl OomKillWatch {
+    /// Starts watching the cgroup of the process `pid` (the spawned command),
+    /// if it is known.
+    pub(crate) fn for_child(pid: Option<u32>) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            Self {
+                counter: pid.and_then(|pid| {
+                    let cgroup = std::fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+                    let events = PathBuf::from(format!(
+                        "/sys/fs/cgroup{}/memory.events",
+                        cgroup_v2_path(&cgroup)?
+                    ));
+                    let count = read_oom_kills(&events)?;
+                    Some((events, count))
+                }),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = pid;
+            Self {}
+        }
+    }
+
+    /// Whether the OOM killer struck in the watched cgroup since
+    /// [`OomKillWatch::for_child`].
+    pub(crate) fn verdict(&self) -> OomVerdict {
+        #[cfg(target_os = "linux")]
+        if let Some((events, before)) = &self.counter
+            && let Some(now) = read_oom_kills(events)
+        {
+            return compare(*before, now);
+        }
+        OomVerdict::Unknown
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_oom_kills(events: &std::path::Path) -> Option<u64> {
+    let events = std::fs::read_to_string(events).ok()?;
+    parse_counter(&events, "oom_kill")
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn compare(before: u64, now: u64) -> OomVerdict {
+    if now > before {
+        OomVerdict::Killed
+    } else {
+        OomVerdict::NotKilled
+    }
+}
+
+/// The cgroup v2 entry of `/proc/<pid>/cgroup` looks like `0::/user.slice/...`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn cgroup_v2_path(proc_pid_cgroup: &str) -> Option<&str> {
+    proc_pid_cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .filter(|path| path.starts_with('/'))
+}
+
+/// Value of a `<key> <value>` line, the format of `memory.events`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_counter(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (name, value) = line.split_once(' ')?;
+        if name == key {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_oom_kill_counter() {
+        let events = "low 0\nhigh 0\nmax 3\noom 2\noom_kill 1\noom_group_kill 0\n";
+        assert_eq!(parse_counter(events, "oom_kill"), Some(1));
+        assert_eq!(parse_counter(events, "missing"), None);
+    }
+
+    #[test]
+    fn finds_the_cgroup_v2_path() {
+        assert_eq!(
+            cgroup_v2_path("0::/user.slice/user-1000.slice/session-2.scope\n"),
+            Some("/user.slice/user-1000.slice/session-2.scope")
+        );
+        // cgroup v1 only.
+        assert_eq!(cgroup_v2_path("4:memory:/user.slice\n"), None);
+    }
+
+    #[test]
+    fn only_a_known_increase_counts() {
+        assert_eq!(compare(1, 2), OomVerdict::Killed);
+        assert_eq!(compare(1, 1), OomVerdict::NotKilled);
+        assert_eq!(OomKillWatch::for_child(None).verdict(), OomVerdict::Unknown);
+    }
+}
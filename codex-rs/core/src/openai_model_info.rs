@@ -1,21 +1,198 @@
 use crate::model_family::ModelFamily;
+use crate::model_family::resolve_built_in_model_alias;
+use crate::token_estimate::Tokenizer;
 
 /// Metadata about a model, particularly OpenAI models.
 /// We may want to consider including details like the pricing for
 /// input tokens, output tokens, etc., though users will need to be able to
 /// override this in config.toml, as this information can get out of date.
 /// Though this would help present more accurate pricing information in the UI.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) struct ModelInfo {
     /// Size of the context window in tokens.
     pub(crate) context_window: u64,
 
     /// Maximum number of output tokens that can be generated for the model.
     pub(crate) max_output_tokens: u64,
+
+    /// Whether the model accepts a reasoning effort (`reasoning.effort` on
+    /// the Responses API, `reasoning_effort` on Chat Completions).
+    pub(crate) supports_reasoning: bool,
+
+    /// Whether the model accepts `temperature` and `top_p`. Reasoning models
+    /// reject them, so they are never sent to those.
+    pub(crate) supports_temperature: bool,
+
+    /// `temperature` to send when `supports_temperature` is set; `None`
+    /// leaves it to the provider.
+    pub(crate) default_temperature: Option<f32>,
+
+    /// `top_p` to send when `supports_temperature` is set; `None` leaves it
+    /// to the provider.
+    pub(crate) default_top_p: Option<f32>,
+
+    /// BPE vocabulary the model's inputs are tokenized with.
+    pub(crate) tokenizer: Tokenizer,
+}
+
+/// What [`get_model_info`] found for a model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ModelInfoMatch {
+    /// The model has an entry of its own.
+    Exact(ModelInfo),
+    /// Only a prefix in [`MODEL_FAMILY_DEFAULTS`] matched, e.g. for a new
+    /// snapshot, so the values are the family's conservative defaults.
+    FamilyDefault(ModelInfo),
+}
+
+impl ModelInfoMatch {
+    pub(crate) fn info(self) -> ModelInfo {
+        match self {
+            Self::Exact(info) | Self::FamilyDefault(info) => info,
+        }
+    }
+
+    pub(crate) fn is_exact(self) -> bool {
+        matches!(self, Self::Exact(_))
+    }
+}
+
+/// Conservative defaults for models that match neither an explicit entry in
+/// [`get_model_info`] nor any prefix in [`MODEL_FAMILY_DEFAULTS`].
+const DEFAULT_MODEL_INFO: ModelInfo = ModelInfo {
+    context_window: 16_385,
+    max_output_tokens: 4_096,
+    supports_reasoning: false,
+    supports_temperature: false,
+    default_temperature: None,
+    default_top_p: None,
+    tokenizer: Tokenizer::Unknown,
+};
+
+/// Prefix-based defaults for models that are not listed explicitly in
+/// [`get_model_info`], such as brand-new snapshots or fine-tunes of a known
+/// model. Entries are checked in order and the first matching prefix wins, so
+/// more specific prefixes must come before more general ones. Values err on
+/// the low side of what the family supports.
+const MODEL_FAMILY_DEFAULTS: &[(&str, ModelInfo)] = &[
+    (
+        "codex-",
+        ModelInfo {
+            context_window: 272_000,
+            max_output_tokens: 128_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
+        },
+    ),
+    (
+        "gpt-4.1",
+        ModelInfo {
+            context_window: 1_047_576,
+            max_output_tokens: 32_768,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
+        },
+    ),
+    (
+        "gpt-4o",
+        ModelInfo {
+            context_window: 128_000,
+            max_output_tokens: 4_096,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
+        },
+    ),
+    (
+        "gpt-4",
+        ModelInfo {
+            context_window: 8_192,
+            max_output_tokens: 4_096,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::Cl100kBase,
+        },
+    ),
+    (
+        "o1",
+        ModelInfo {
+            context_window: 128_000,
+            max_output_tokens: 32_768,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
+        },
+    ),
+    (
+        "o3",
+        ModelInfo {
+            context_window: 200_000,
+            max_output_tokens: 100_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
+        },
+    ),
+    (
+        "o4",
+        ModelInfo {
+            context_window: 200_000,
+            max_output_tokens: 100_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
+        },
+    ),
+];
+
+/// Returns the fallback [`ModelInfo`] to use when nothing is known about a
+/// model.
+pub(crate) fn default_model_info() -> ModelInfo {
+    DEFAULT_MODEL_INFO
 }
 
-pub(crate) fn get_model_info(model_family: &ModelFamily) -> Option<ModelInfo> {
-    let slug = model_family.slug.as_str();
+fn model_family_default_info(slug: &str) -> Option<ModelInfo> {
+    MODEL_FAMILY_DEFAULTS
+        .iter()
+        .find(|(prefix, _)| slug.starts_with(prefix))
+        .map(|(_, info)| *info)
+}
+
+/// `temperature` and `top_p` to send for `model_family`. Both are omitted for
+/// models that reject them and for models we know nothing about.
+pub(crate) fn sampling_params(model_family: &ModelFamily) -> (Option<f32>, Option<f32>) {
+    match get_model_info(model_family).map(ModelInfoMatch::info) {
+        Some(info) if info.supports_temperature => (info.default_temperature, info.default_top_p),
+        _ => (None, None),
+    }
+}
+
+/// Looks up `model_family` in the model table, falling back to
+/// [`MODEL_FAMILY_DEFAULTS`]; the result says which of the two matched.
+pub(crate) fn get_model_info(model_family: &ModelFamily) -> Option<ModelInfoMatch> {
+    let slug = resolve_built_in_model_alias(model_family.slug.as_str());
+    exact_model_info(slug)
+        .map(ModelInfoMatch::Exact)
+        .or_else(|| model_family_default_info(slug).map(ModelInfoMatch::FamilyDefault))
+}
+
+fn exact_model_info(slug: &str) -> Option<ModelInfo> {
     match slug {
         // OSS models have a 128k shared token pool.
         // Arbitrarily splitting it: 3/4 input context, 1/4 output.
@@ -23,71 +200,210 @@ pub(crate) fn get_model_info(model_family: &ModelFamily) -> Option<ModelInfo> {
         "gpt-oss-20b" => Some(ModelInfo {
             context_window: 96_000,
             max_output_tokens: 32_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
         "gpt-oss-120b" => Some(ModelInfo {
             context_window: 96_000,
             max_output_tokens: 32_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
         // https://platform.openai.com/docs/models/o3
         "o3" => Some(ModelInfo {
             context_window: 200_000,
             max_output_tokens: 100_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
         // https://platform.openai.com/docs/models/o4-mini
-        "o4-mini" => Some(ModelInfo {
+        "o4-mini-2025-04-16" => Some(ModelInfo {
             context_window: 200_000,
             max_output_tokens: 100_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
         // https://platform.openai.com/docs/models/codex-mini-latest
         "codex-mini-latest" => Some(ModelInfo {
             context_window: 200_000,
             max_output_tokens: 100_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
-        // As of Jun 25, 2025, gpt-4.1 defaults to gpt-4.1-2025-04-14.
         // https://platform.openai.com/docs/models/gpt-4.1
-        "gpt-4.1" | "gpt-4.1-2025-04-14" => Some(ModelInfo {
+        "gpt-4.1-2025-04-14" => Some(ModelInfo {
             context_window: 1_047_576,
             max_output_tokens: 32_768,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
-        // As of Jun 25, 2025, gpt-4o defaults to gpt-4o-2024-08-06.
         // https://platform.openai.com/docs/models/gpt-4o
-        "gpt-4o" | "gpt-4o-2024-08-06" => Some(ModelInfo {
+        "gpt-4o-2024-08-06" => Some(ModelInfo {
             context_window: 128_000,
             max_output_tokens: 16_384,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
         // https://platform.openai.com/docs/models/gpt-4o?snapshot=gpt-4o-2024-05-13
         "gpt-4o-2024-05-13" => Some(ModelInfo {
             context_window: 128_000,
             max_output_tokens: 4_096,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
         // https://platform.openai.com/docs/models/gpt-4o?snapshot=gpt-4o-2024-11-20
         "gpt-4o-2024-11-20" => Some(ModelInfo {
             context_window: 128_000,
             max_output_tokens: 16_384,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
         // https://platform.openai.com/docs/models/gpt-3.5-turbo
         "gpt-3.5-turbo" => Some(ModelInfo {
             context_window: 16_385,
             max_output_tokens: 4_096,
+            supports_reasoning: false,
+            supports_temperature: true,
+            default_temperature: Some(1.0),
+            default_top_p: None,
+            tokenizer: Tokenizer::Cl100kBase,
         }),
 
         "gpt-5" => Some(ModelInfo {
             context_window: 272_000,
             max_output_tokens: 128_000,
-        }),
-
-        _ if slug.starts_with("codex-") => Some(ModelInfo {
-            context_window: 272_000,
-            max_output_tokens: 128_000,
+            supports_reasoning: true,
+            supports_temperature: false,
+            default_temperature: None,
+            default_top_p: None,
+            tokenizer: Tokenizer::O200kBase,
         }),
 
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_family::find_family_for_model;
+    use pretty_assertions::assert_eq;
+
+    fn info_for(slug: &str) -> Option<ModelInfoMatch> {
+        let family = find_family_for_model(slug).unwrap_or_else(|| ModelFamily {
+            slug: slug.to_string(),
+            family: slug.to_string(),
+            needs_special_apply_patch_instructions: false,
+            supports_reasoning_summaries: false,
+            reasoning_summary_format: crate::config_types::ReasoningSummaryFormat::None,
+            uses_local_shell_tool: false,
+            apply_patch_tool_type: None,
+        });
+        get_model_info(&family)
+    }
+
+    #[test]
+    fn explicit_entries_take_precedence_over_family_defaults() {
+        assert_eq!(
+            Some(ModelInfoMatch::Exact(ModelInfo {
+                context_window: 128_000,
+                max_output_tokens: 16_384,
+                supports_reasoning: false,
+                supports_temperature: true,
+                default_temperature: Some(1.0),
+                default_top_p: None,
+                tokenizer: Tokenizer::O200kBase,
+            })),
+            info_for("gpt-4o")
+        );
+    }
+
+    #[test]
+    fn unknown_snapshots_fall_back_to_family_defaults() {
+        assert_eq!(
+            Some(ModelInfoMatch::FamilyDefault(ModelInfo {
+                context_window: 128_000,
+                max_output_tokens: 4_096,
+                supports_reasoning: false,
+                supports_temperature: true,
+                default_temperature: Some(1.0),
+                default_top_p: None,
+                tokenizer: Tokenizer::O200kBase,
+            })),
+            info_for("gpt-4o-2099-01-01")
+        );
+        assert_eq!(
+            Some(ModelInfoMatch::FamilyDefault(ModelInfo {
+                context_window: 200_000,
+                max_output_tokens: 100_000,
+                supports_reasoning: true,
+                supports_temperature: false,
+                default_temperature: None,
+                default_top_p: None,
+                tokenizer: Tokenizer::O200kBase,
+            })),
+            info_for("o3-mini")
+        );
+        assert_eq!(
+            Some(ModelInfoMatch::FamilyDefault(ModelInfo {
+                context_window: 8_192,
+                max_output_tokens: 4_096,
+                supports_reasoning: false,
+                supports_temperature: true,
+                default_temperature: Some(1.0),
+                default_top_p: None,
+                tokenizer: Tokenizer::Cl100kBase,
+            })),
+            info_for("gpt-4-0613")
+        );
+    }
+
+    #[test]
+    fn sampling_params_are_only_sent_to_models_that_accept_them() {
+        let family = |slug: &str| find_family_for_model(slug).expect("known model");
+        assert_eq!((None, None), sampling_params(&family("o3")));
+        assert_eq!((None, None), sampling_params(&family("gpt-5")));
+        assert_eq!((Some(1.0), None), sampling_params(&family("gpt-4o")));
+        assert_eq!((Some(1.0), None), sampling_params(&family("gpt-4.1")));
+    }
+
+    #[test]
+    fn unrelated_models_have_no_info() {
+        assert_eq!(None, info_for("llama3"));
+        assert_eq!(DEFAULT_MODEL_INFO, default_model_info());
+    }
+}
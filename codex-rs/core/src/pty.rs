@@ -0,0 +1,124 @@
+//! Pseudo-terminals for commands run with [`StdioPolicy::Pty`], so that tools
+//! which check `isatty` (colors, progress bars, pagers) behave as they would
+//! in a terminal. Unix only.
+//!
+//! [`StdioPolicy::Pty`]: crate::spawn::StdioPolicy::Pty
+
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use tokio::io::AsyncRead;
+use tokio::io::ReadBuf;
+
+/// Terminal size reported to the command.
+#[cfg(unix)]
+const PTY_COLS: u16 = 120;
+#[cfg(unix)]
+const PTY_ROWS: u16 = 40;
+
+/// A newly allocated pseudo-terminal. The child gets `slave` as its stdin,
+/// stdout and stderr; everything it writes is read back from `master`.
+#[cfg(unix)]
+pub(crate) struct Pty {
+    pub(crate) master: std::os::fd::OwnedFd,
+    pub(crate) slave: std::os::fd::OwnedFd,
+}
+
+/// Opens a pseudo-terminal with output post-processing disabled, so lines
+/// end in `\n` as they would in a pipe rather than `\r\n`.
+#[cfg(unix)]
+pub(crate) fn open_pty() -> io::Result<Pty> {
+    use std::os::fd::AsRawFd;
+    use std::os::fd::FromRawFd;
+    use std::os::fd::OwnedFd;
+
+    let mut master = -1;
+    let mut slave = -1;
+    let mut winsize = libc::winsize {
+        ws_row: PTY_ROWS,
+        ws_col: PTY_COLS,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut winsize,
+        )
+    };
+    if rc == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `openpty` succeeded, so both are open descriptors we now own.
+    let pty = unsafe {
+        Pty {
+            master: OwnedFd::from_raw_fd(master),
+            slave: OwnedFd::from_raw_fd(slave),
+        }
+    };
+
+    for fd in [&pty.master, &pty.slave] {
+        // Neither may leak into the child (or any other process spawned
+        // meanwhile); the child gets the slave as stdio via `dup2`.
+        set_cloexec(fd.as_raw_fd())?;
+    }
+
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(pty.slave.as_raw_fd(), &mut termios) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        termios.c_oflag &= !libc::ONLCR;
+        if libc::tcsetattr(pty.slave.as_raw_fd(), libc::TCSANOW, &termios) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(pty)
+}
+
+#[cfg(unix)]
+fn set_cloexec(fd: std::os::fd::RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags == -1 || libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Reads what the child writes to its terminal.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) struct PtyMaster {
+    file: tokio::fs::File,
+}
+
+impl PtyMaster {
+    #[cfg(unix)]
+    pub(crate) fn new(master: std::os::fd::OwnedFd) -> Self {
+        Self {
+            file: tokio::fs::File::from_std(std::fs::File::from(master)),
+        }
+    }
+}
+
+impl AsyncRead for PtyMaster {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match Pin::new(&mut self.file).poll_read(cx, buf) {
+            // Once every copy of the slave is closed, Linux fails reads with
+            // EIO rather than returning end-of-file.
+            #[cfg(unix)]
+            Poll::Ready(Err(err)) if err.raw_os_error() == Some(libc::EIO) => Poll::Ready(Ok(())),
+            poll => poll,
+        }
+    }
+}
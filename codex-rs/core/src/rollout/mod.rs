@@ -6,8 +6,15 @@ pub mod list;
 pub(crate) mod policy;
 pub mod recorder;
 
+pub use recorder::CommandRewrite;
+pub use recorder::ExecSandboxRecord;
+pub use recorder::ExecTimingRecord;
+pub use recorder::IgnoredWriteRecord;
+pub use recorder::RolloutItem;
+pub use recorder::RolloutParent;
 pub use recorder::RolloutRecorder;
 pub use recorder::RolloutRecorderParams;
+pub use recorder::SandboxMeta;
 pub use recorder::SessionMeta;
 pub use recorder::SessionStateSnapshot;
 
@@ -3,8 +3,13 @@
 use std::fs::File;
 use std::fs::{self};
 use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use codex_protocol::mcp_protocol::ConversationId;
 use serde::Deserialize;
@@ -12,11 +17,12 @@ use serde::Serialize;
 use serde_json::Value;
 use time::OffsetDateTime;
 use time::format_description::FormatItem;
+use time::format_description::well_known::Rfc3339;
 use time::macros::format_description;
-use tokio::io::AsyncWriteExt;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::mpsc::{self};
 use tokio::sync::oneshot;
+use tokio::time::MissedTickBehavior;
 use tracing::info;
 use tracing::warn;
 
@@ -26,30 +32,188 @@ use super::list::Cursor;
 use super::list::get_conversations;
 use super::policy::is_persisted_response_item;
 use crate::config::Config;
+use crate::config_types::RolloutFlushPolicy;
+use crate::config_types::RolloutSettings;
+use crate::conversation_manager::BranchedHistory;
 use crate::conversation_manager::InitialHistory;
 use crate::conversation_manager::ResumedHistory;
+use crate::exec::SandboxType;
 use crate::git_info::GitInfo;
 use crate::git_info::collect_git_info;
+use crate::protocol::SandboxFeature;
+use crate::protocol::SandboxPolicy;
+use crate::safety::get_platform_sandbox;
 use codex_protocol::models::ResponseItem;
 
-#[derive(Serialize, Deserialize, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SessionMeta {
     pub id: ConversationId,
     pub timestamp: String,
     pub instructions: Option<String>,
+    /// Set when this rollout was branched from an earlier point of another
+    /// rollout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<RolloutParent>,
+    /// The security context the session started with. Individual commands
+    /// may deviate from it; see [`ExecSandboxRecord`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxMeta>,
 }
 
-#[derive(Serialize)]
-struct SessionMetaWithGit {
+/// The sandbox a command ran under, together with the parts of the effective
+/// [`SandboxPolicy`] needed to audit what it could touch.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SandboxMeta {
+    pub sandbox_type: SandboxType,
+    /// The policy's mode, e.g. `workspace-write`.
+    pub policy: String,
+    /// Resolved against the cwd. Empty unless the policy is `workspace-write`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writable_roots: Vec<PathBuf>,
+    pub network_access: bool,
+    /// The parts of the sandbox that were enforced; empty when there was no
+    /// sandbox.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<SandboxFeature>,
+    /// Set when the Linux sandbox only logged what it would have denied
+    /// (`audit_only`); `features` is then empty.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub audit_only: bool,
+}
+
+impl SandboxMeta {
+    pub fn new(sandbox_type: SandboxType, sandbox_policy: &SandboxPolicy, cwd: &Path) -> Self {
+        let sandbox_type = sandbox_type.resolve().unwrap_or(sandbox_type);
+        Self {
+            sandbox_type,
+            policy: sandbox_policy.to_string(),
+            writable_roots: sandbox_policy
+                .get_writable_roots_with_cwd(cwd)
+                .into_iter()
+                .map(|writable_root| writable_root.root)
+                .collect(),
+            network_access: sandbox_policy.has_full_network_access(),
+            features: active_sandbox_features(sandbox_type, sandbox_policy),
+            audit_only: sandbox_type == SandboxType::LinuxSeccomp && sandbox_policy.is_audit_only(),
+        }
+    }
+
+    /// What commands run under by default: the platform sandbox, unless the
+    /// policy disables sandboxing altogether.
+    fn for_session(sandbox_policy: &SandboxPolicy, cwd: &Path) -> Self {
+        let sandbox_type = match sandbox_policy {
+            SandboxPolicy::DangerFullAccess => SandboxType::None,
+            _ => get_platform_sandbox().unwrap_or(SandboxType::None),
+        };
+        Self::new(sandbox_type, sandbox_policy, cwd)
+    }
+}
+
+fn active_sandbox_features(
+    sandbox_type: SandboxType,
+    sandbox_policy: &SandboxPolicy,
+) -> Vec<SandboxFeature> {
+    [SandboxFeature::Filesystem, SandboxFeature::Network]
+        .into_iter()
+        .filter(|&feature| match sandbox_type {
+            SandboxType::None => false,
+            // Seatbelt ignores `disabled_sandbox_features`.
+            SandboxType::MacosSeatbelt => match feature {
+                SandboxFeature::Filesystem => !sandbox_policy.has_full_disk_write_access(),
+                SandboxFeature::Network => !sandbox_policy.has_full_network_access(),
+            },
+            SandboxType::LinuxSeccomp => {
+                !sandbox_policy.is_audit_only() && sandbox_policy.enforces_sandbox_feature(feature)
+            }
+            // Only left unresolved where there is no sandbox to enforce anything.
+            SandboxType::Auto => false,
+        })
+        .collect()
+}
+
+/// Rollout line recording the sandbox one shell command actually ran under,
+/// which can differ from the session's (e.g. approved unsandboxed retries or
+/// per-turn policy overrides).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExecSandboxRecord {
+    pub call_id: String,
     #[serde(flatten)]
-    meta: SessionMeta,
+    pub sandbox: SandboxMeta,
+    /// Set when the command skipped the session's sandbox because it matched
+    /// `unsandboxed_commands`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unsandboxed_command: bool,
+    /// Set when the session's `exec_prelude` was prepended to the command.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exec_prelude: bool,
+    /// Set when the session's command rewriter changed the command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_rewrite: Option<CommandRewrite>,
+}
+
+/// Rollout line recording when one shell command started and finished, as
+/// RFC 3339 timestamps. Written once the command is done, so a command that
+/// is still running has only its [`ExecSandboxRecord`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExecTimingRecord {
+    pub call_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+impl ExecTimingRecord {
+    pub fn new(call_id: String, started_at: SystemTime, finished_at: SystemTime) -> Self {
+        Self {
+            call_id,
+            started_at: format_rfc3339(started_at),
+            finished_at: format_rfc3339(finished_at),
+        }
+    }
+}
+
+fn format_rfc3339(time: SystemTime) -> String {
+    // Only fails for years RFC 3339 cannot represent.
+    OffsetDateTime::from(time)
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
+
+/// A command as the model issued it and as it ran after rewriting.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommandRewrite {
+    pub original: Vec<String>,
+    pub rewritten: Vec<String>,
+}
+
+/// The rollout (and position within it) that a branched session started from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RolloutParent {
+    pub conversation_id: ConversationId,
+    pub path: PathBuf,
+    /// Number of items carried over from the parent rollout.
+    pub item_index: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SessionMetaWithGit {
+    #[serde(flatten)]
+    pub meta: SessionMeta,
     #[serde(skip_serializing_if = "Option::is_none")]
-    git: Option<GitInfo>,
+    pub git: Option<GitInfo>,
 }
 
-#[derive(Serialize, Deserialize, Default, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct SessionStateSnapshot {}
 
+/// Rollout line noting that a patch targeted git-ignored paths.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IgnoredWriteRecord {
+    pub call_id: String,
+    pub paths: Vec<PathBuf>,
+    /// Whether the write was blocked rather than just warned about.
+    pub blocked: bool,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct SavedSession {
     pub session: SessionMeta,
@@ -60,8 +224,9 @@ pub struct SavedSession {
     pub session_id: ConversationId,
 }
 
-/// Records all [`ResponseItem`]s for a session and flushes them to disk after
-/// every update.
+/// Records all [`ResponseItem`]s for a session and writes them to disk after
+/// every update, syncing them as `[rollout] flush_policy` says (see
+/// [`RolloutFlushPolicy`]).
 ///
 /// Rollouts are recorded as JSONL and can be inspected with tools such as:
 ///
@@ -73,6 +238,7 @@ pub struct SavedSession {
 pub struct RolloutRecorder {
     tx: Sender<RolloutCmd>,
     pub(crate) rollout_path: PathBuf,
+    flush_policy: RolloutFlushPolicy,
 }
 
 #[derive(Clone)]
@@ -80,6 +246,7 @@ pub enum RolloutRecorderParams {
     Create {
         conversation_id: ConversationId,
         instructions: Option<String>,
+        parent: Option<RolloutParent>,
     },
     Resume {
         path: PathBuf,
@@ -89,7 +256,16 @@ pub enum RolloutRecorderParams {
 enum RolloutCmd {
     AddItems(Vec<ResponseItem>),
     UpdateState(SessionStateSnapshot),
-    Shutdown { ack: oneshot::Sender<()> },
+    AddIgnoredWrite(IgnoredWriteRecord),
+    AddExecSandbox(ExecSandboxRecord),
+    AddExecTiming(ExecTimingRecord),
+    /// Acknowledged once everything queued before it is written and synced.
+    Flush {
+        ack: oneshot::Sender<()>,
+    },
+    Shutdown {
+        ack: oneshot::Sender<()>,
+    },
 }
 
 impl RolloutRecorderParams {
@@ -97,6 +273,21 @@ impl RolloutRecorderParams {
         Self::Create {
             conversation_id,
             instructions,
+            parent: None,
+        }
+    }
+
+    /// Like [`RolloutRecorderParams::new`], but records `parent` in the
+    /// session meta so the branch can be traced back to where it started.
+    pub fn branch(
+        conversation_id: ConversationId,
+        instructions: Option<String>,
+        parent: RolloutParent,
+    ) -> Self {
+        Self::Create {
+            conversation_id,
+            instructions,
+            parent: Some(parent),
         }
     }
 
@@ -124,6 +315,7 @@ impl RolloutRecorder {
             RolloutRecorderParams::Create {
                 conversation_id,
                 instructions,
+                parent,
             } => {
                 let LogFileInfo {
                     file,
@@ -141,39 +333,77 @@ impl RolloutRecorder {
                     .map_err(|e| IoError::other(format!("failed to format timestamp: {e}")))?;
 
                 (
-                    tokio::fs::File::from_std(file),
+                    file,
                     path,
                     Some(SessionMeta {
                         timestamp,
                         id: session_id,
                         instructions,
+                        parent,
+                        sandbox: Some(SandboxMeta::for_session(
+                            &config.sandbox_policy,
+                            &config.cwd,
+                        )),
                     }),
                 )
             }
             RolloutRecorderParams::Resume { path } => (
-                tokio::fs::OpenOptions::new()
-                    .append(true)
-                    .open(&path)
-                    .await?,
+                std::fs::OpenOptions::new().append(true).open(&path)?,
                 path,
                 None,
             ),
         };
 
-        // Clone the cwd for the spawned task to collect git info asynchronously
-        let cwd = config.cwd.clone();
+        // The cwd is passed along to collect git info asynchronously.
+        Ok(Self::spawn(
+            JsonlWriter {
+                file: Arc::new(file),
+            },
+            meta,
+            Some(config.cwd.clone()),
+            rollout_path,
+            &config.rollout,
+        ))
+    }
+
+    /// Records into `rollout` instead of a file, so tests can inspect the
+    /// recorded items without touching disk. `meta` is recorded first, without
+    /// git info; pass `None` to append to an existing in-memory rollout, like
+    /// [`RolloutRecorderParams::Resume`] does for files. The returned
+    /// recorder's `rollout_path` is empty.
+    #[cfg(test)]
+    pub(crate) fn in_memory(rollout: &InMemoryRollout, meta: Option<SessionMeta>) -> Self {
+        Self::spawn(
+            rollout.clone(),
+            meta,
+            None,
+            PathBuf::new(),
+            &RolloutSettings::default(),
+        )
+    }
 
+    fn spawn(
+        sink: impl RolloutSink,
+        meta: Option<SessionMeta>,
+        git_cwd: Option<PathBuf>,
+        rollout_path: PathBuf,
+        settings: &RolloutSettings,
+    ) -> Self {
         // A reasonably-sized bounded channel. If the buffer fills up the send
         // future will yield, which is fine – we only need to ensure we do not
         // perform *blocking* I/O on the caller's thread.
         let (tx, rx) = mpsc::channel::<RolloutCmd>(256);
 
-        // Spawn a Tokio task that owns the file handle and performs async
-        // writes. Using `tokio::fs::File` keeps everything on the async I/O
-        // driver instead of blocking the runtime.
-        tokio::task::spawn(rollout_writer(file, rx, meta, cwd));
+        // Spawn a Tokio task that owns the sink and performs async writes.
+        let sync_interval = (settings.flush_policy == RolloutFlushPolicy::Interval)
+            .then(|| settings.flush_interval());
+        tokio::task::spawn(rollout_writer(sink, rx, meta, git_cwd, sync_interval));
 
-        Ok(Self { tx, rollout_path })
+        Self {
+            tx,
+            rollout_path,
+            flush_policy: settings.flush_policy,
+        }
     }
 
     pub(crate) async fn record_items(&self, items: &[ResponseItem]) -> std::io::Result<()> {
@@ -192,74 +422,72 @@ impl RolloutRecorder {
         self.tx
             .send(RolloutCmd::AddItems(filtered))
             .await
-            .map_err(|e| IoError::other(format!("failed to queue rollout items: {e}")))
+            .map_err(|e| IoError::other(format!("failed to queue rollout items: {e}")))?;
+        self.flush_if_every_item().await
     }
 
     pub(crate) async fn record_state(&self, state: SessionStateSnapshot) -> std::io::Result<()> {
         self.tx
             .send(RolloutCmd::UpdateState(state))
             .await
-            .map_err(|e| IoError::other(format!("failed to queue rollout state: {e}")))
+            .map_err(|e| IoError::other(format!("failed to queue rollout state: {e}")))?;
+        self.flush_if_every_item().await
     }
 
-    pub async fn get_rollout_history(path: &Path) -> std::io::Result<InitialHistory> {
-        info!("Resuming rollout from {path:?}");
-        tracing::error!("Resuming rollout from {path:?}");
-        let text = tokio::fs::read_to_string(path).await?;
-        let mut lines = text.lines();
-        let first_line = lines
-            .next()
-            .ok_or_else(|| IoError::other("empty session file"))?;
-        let conversation_id = match serde_json::from_str::<SessionMeta>(first_line) {
-            Ok(rollout_session_meta) => {
-                tracing::error!(
-                    "Parsed conversation ID from rollout file: {:?}",
-                    rollout_session_meta.id
-                );
-                Some(rollout_session_meta.id)
-            }
-            Err(e) => {
-                return Err(IoError::other(format!(
-                    "failed to parse first line of rollout file as SessionMeta: {e}"
-                )));
-            }
-        };
+    pub(crate) async fn record_ignored_write(
+        &self,
+        record: IgnoredWriteRecord,
+    ) -> std::io::Result<()> {
+        self.tx
+            .send(RolloutCmd::AddIgnoredWrite(record))
+            .await
+            .map_err(|e| IoError::other(format!("failed to queue rollout record: {e}")))?;
+        self.flush_if_every_item().await
+    }
 
-        let mut items = Vec::new();
-        for line in lines {
-            if line.trim().is_empty() {
-                continue;
-            }
-            let v: Value = match serde_json::from_str(line) {
-                Ok(v) => v,
-                Err(_) => continue,
-            };
-            if v.get("record_type")
-                .and_then(|rt| rt.as_str())
-                .map(|s| s == "state")
-                .unwrap_or(false)
-            {
-                continue;
-            }
-            match serde_json::from_value::<ResponseItem>(v.clone()) {
-                Ok(item) => {
-                    if is_persisted_response_item(&item) {
-                        items.push(item);
-                    }
-                }
-                Err(e) => {
-                    warn!("failed to parse item: {v:?}, error: {e}");
-                }
-            }
+    pub(crate) async fn record_exec_sandbox(
+        &self,
+        record: ExecSandboxRecord,
+    ) -> std::io::Result<()> {
+        self.tx
+            .send(RolloutCmd::AddExecSandbox(record))
+            .await
+            .map_err(|e| IoError::other(format!("failed to queue rollout record: {e}")))?;
+        self.flush_if_every_item().await
+    }
+
+    pub(crate) async fn record_exec_timing(&self, record: ExecTimingRecord) -> std::io::Result<()> {
+        self.tx
+            .send(RolloutCmd::AddExecTiming(record))
+            .await
+            .map_err(|e| IoError::other(format!("failed to queue rollout record: {e}")))?;
+        self.flush_if_every_item().await
+    }
+
+    /// Waits until everything recorded so far has been written and synced to
+    /// disk.
+    pub async fn flush(&self) -> std::io::Result<()> {
+        let (tx_done, rx_done) = oneshot::channel();
+        self.tx
+            .send(RolloutCmd::Flush { ack: tx_done })
+            .await
+            .map_err(|e| IoError::other(format!("failed to send rollout flush command: {e}")))?;
+        rx_done
+            .await
+            .map_err(|e| IoError::other(format!("failed waiting for rollout flush: {e}")))
+    }
+
+    async fn flush_if_every_item(&self) -> std::io::Result<()> {
+        match self.flush_policy {
+            RolloutFlushPolicy::EveryItem => self.flush().await,
+            RolloutFlushPolicy::Interval | RolloutFlushPolicy::OnClose => Ok(()),
         }
+    }
 
-        tracing::error!(
-            "Resumed rollout with {} items, conversation ID: {:?}",
-            items.len(),
-            conversation_id
-        );
-        let conversation_id = conversation_id
-            .ok_or_else(|| IoError::other("failed to parse conversation ID from rollout file"))?;
+    pub async fn get_rollout_history(path: &Path) -> std::io::Result<InitialHistory> {
+        info!("Resuming rollout from {path:?}");
+        tracing::error!("Resuming rollout from {path:?}");
+        let (conversation_id, items) = read_rollout_items(path).await?;
 
         if items.is_empty() {
             return Ok(InitialHistory::New);
@@ -273,6 +501,36 @@ impl RolloutRecorder {
         }))
     }
 
+    /// Load the first `item_index` items of the rollout at `path` as the
+    /// starting point for a new branch. The branch is recorded to a new
+    /// rollout file; the file at `path` is never modified.
+    pub async fn get_rollout_branch(
+        path: &Path,
+        item_index: usize,
+    ) -> std::io::Result<InitialHistory> {
+        info!("Branching rollout from {path:?} at item {item_index}");
+        let (conversation_id, mut items) = read_rollout_items(path).await?;
+        if item_index > items.len() {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "item index {item_index} is past the end of the rollout ({} items)",
+                    items.len()
+                ),
+            ));
+        }
+        items.truncate(item_index);
+
+        Ok(InitialHistory::Branched(BranchedHistory {
+            parent: RolloutParent {
+                conversation_id,
+                path: path.to_path_buf(),
+                item_index,
+            },
+            history: items,
+        }))
+    }
+
     pub async fn shutdown(&self) -> std::io::Result<()> {
         let (tx_done, rx_done) = oneshot::channel();
         match self.tx.send(RolloutCmd::Shutdown { ack: tx_done }).await {
@@ -289,6 +547,68 @@ impl RolloutRecorder {
     }
 }
 
+/// Read the conversation id and the persisted response items of a rollout.
+async fn read_rollout_items(path: &Path) -> std::io::Result<(ConversationId, Vec<ResponseItem>)> {
+    let text = tokio::fs::read_to_string(path).await?;
+    // The session may still be writing; an unterminated last line is not
+    // complete yet.
+    let complete = text.rfind('\n').map_or("", |end| &text[..=end]);
+    let mut lines = complete.lines();
+    let first_line = lines
+        .next()
+        .ok_or_else(|| IoError::other("empty session file"))?;
+    let conversation_id = match serde_json::from_str::<SessionMeta>(first_line) {
+        Ok(rollout_session_meta) => {
+            tracing::error!(
+                "Parsed conversation ID from rollout file: {:?}",
+                rollout_session_meta.id
+            );
+            Some(rollout_session_meta.id)
+        }
+        Err(e) => {
+            return Err(IoError::other(format!(
+                "failed to parse first line of rollout file as SessionMeta: {e}"
+            )));
+        }
+    };
+
+    let mut items = Vec::new();
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let v: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        // `state`, `ignored_write`, `exec_sandbox` and `exec_timing` lines are
+        // bookkeeping, not conversation items.
+        if v.get("record_type").is_some() {
+            continue;
+        }
+        match serde_json::from_value::<ResponseItem>(v.clone()) {
+            Ok(item) => {
+                if is_persisted_response_item(&item) {
+                    items.push(item);
+                }
+            }
+            Err(e) => {
+                warn!("failed to parse item: {v:?}, error: {e}");
+            }
+        }
+    }
+
+    tracing::error!(
+        "Resumed rollout with {} items, conversation ID: {:?}",
+        items.len(),
+        conversation_id
+    );
+    let conversation_id = conversation_id
+        .ok_or_else(|| IoError::other("failed to parse conversation ID from rollout file"))?;
+
+    Ok((conversation_id, items))
+}
+
 struct LogFileInfo {
     /// Opened file handle to the rollout file.
     file: File,
@@ -341,51 +661,106 @@ fn create_log_file(
     })
 }
 
+/// One line of a rollout, as handed to a [`RolloutSink`].
+#[derive(Debug, Clone)]
+pub enum RolloutItem {
+    /// Always the first line of a new rollout.
+    SessionMeta(SessionMetaWithGit),
+    ResponseItem(ResponseItem),
+    State(SessionStateSnapshot),
+    IgnoredWrite(IgnoredWriteRecord),
+    ExecSandbox(ExecSandboxRecord),
+    ExecTiming(ExecTimingRecord),
+}
+
+/// Where the rollout writer task puts recorded items, in order.
+pub(crate) trait RolloutSink: Send + 'static {
+    fn write(&mut self, item: RolloutItem) -> impl Future<Output = std::io::Result<()>> + Send;
+
+    /// Makes what was written so far durable.
+    fn sync(&mut self) -> impl Future<Output = std::io::Result<()>> + Send;
+}
+
+/// Writes queued items to `sink`, syncing every `sync_interval` (if set)
+/// when something was written since the last sync, and on flush and
+/// shutdown.
 async fn rollout_writer(
-    file: tokio::fs::File,
+    mut sink: impl RolloutSink,
     mut rx: mpsc::Receiver<RolloutCmd>,
     mut meta: Option<SessionMeta>,
-    cwd: std::path::PathBuf,
+    git_cwd: Option<PathBuf>,
+    sync_interval: Option<Duration>,
 ) -> std::io::Result<()> {
-    let mut writer = JsonlWriter { file };
+    let mut unsynced = false;
 
     // If we have a meta, collect git info asynchronously and write meta first
     if let Some(session_meta) = meta.take() {
-        let git_info = collect_git_info(&cwd).await;
-        let session_meta_with_git = SessionMetaWithGit {
+        let git_info = match &git_cwd {
+            Some(cwd) => collect_git_info(cwd).await,
+            None => None,
+        };
+        sink.write(RolloutItem::SessionMeta(SessionMetaWithGit {
             meta: session_meta,
             git: git_info,
-        };
-
-        // Write the SessionMeta as the first item in the file
-        writer.write_line(&session_meta_with_git).await?;
+        }))
+        .await?;
+        unsynced = true;
     }
 
+    let mut sync_timer = sync_interval.map(|period| {
+        let mut timer = tokio::time::interval(period);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        timer
+    });
+
     // Process rollout commands
-    while let Some(cmd) = rx.recv().await {
+    loop {
+        let cmd = match &mut sync_timer {
+            Some(timer) => tokio::select! {
+                cmd = rx.recv() => cmd,
+                _ = timer.tick() => {
+                    if unsynced {
+                        sink.sync().await?;
+                        unsynced = false;
+                    }
+                    continue;
+                }
+            },
+            None => rx.recv().await,
+        };
+        let Some(cmd) = cmd else {
+            break;
+        };
         match cmd {
             RolloutCmd::AddItems(items) => {
                 for item in items {
                     if is_persisted_response_item(&item) {
-                        writer.write_line(&item).await?;
+                        sink.write(RolloutItem::ResponseItem(item)).await?;
+                        unsynced = true;
                     }
                 }
             }
             RolloutCmd::UpdateState(state) => {
-                #[derive(Serialize)]
-                struct StateLine<'a> {
-                    record_type: &'static str,
-                    #[serde(flatten)]
-                    state: &'a SessionStateSnapshot,
-                }
-                writer
-                    .write_line(&StateLine {
-                        record_type: "state",
-                        state: &state,
-                    })
-                    .await?;
+                sink.write(RolloutItem::State(state)).await?;
+                unsynced = true;
+            }
+            RolloutCmd::AddIgnoredWrite(record) => {
+                sink.write(RolloutItem::IgnoredWrite(record)).await?;
+                unsynced = true;
             }
-            RolloutCmd::Shutdown { ack } => {
+            RolloutCmd::AddExecSandbox(record) => {
+                sink.write(RolloutItem::ExecSandbox(record)).await?;
+                unsynced = true;
+            }
+            RolloutCmd::AddExecTiming(record) => {
+                sink.write(RolloutItem::ExecTiming(record)).await?;
+                unsynced = true;
+            }
+            RolloutCmd::Flush { ack } | RolloutCmd::Shutdown { ack } => {
+                if unsynced {
+                    sink.sync().await?;
+                    unsynced = false;
+                }
                 let _ = ack.send(());
             }
         }
@@ -394,16 +769,111 @@ async fn rollout_writer(
     Ok(())
 }
 
+/// Writes each item as one JSON line.
+///
+/// Other tools tail rollouts while a session is running, so every line goes
+/// out as a single `write` on the unbuffered, `O_APPEND` file: a line is never
+/// interleaved with another one, and readers only have to skip an unterminated
+/// last line. `tokio::fs::File` would split lines larger than its buffer into
+/// several writes, so the write runs on the blocking pool instead.
 struct JsonlWriter {
-    file: tokio::fs::File,
+    file: Arc<File>,
 }
 
 impl JsonlWriter {
     async fn write_line(&mut self, item: &impl serde::Serialize) -> std::io::Result<()> {
         let mut json = serde_json::to_string(item)?;
         json.push('\n');
-        self.file.write_all(json.as_bytes()).await?;
-        self.file.flush().await?;
+        let file = Arc::clone(&self.file);
+        tokio::task::spawn_blocking(move || (&*file).write_all(json.as_bytes()))
+            .await
+            .map_err(|e| IoError::other(format!("rollout write task failed: {e}")))?
+    }
+}
+
+impl RolloutSink for JsonlWriter {
+    async fn write(&mut self, item: RolloutItem) -> std::io::Result<()> {
+        /// Bookkeeping lines are tagged with a `record_type` so they are not
+        /// mistaken for conversation items when the rollout is read back.
+        #[derive(Serialize)]
+        struct RecordLine<'a, T> {
+            record_type: &'static str,
+            #[serde(flatten)]
+            record: &'a T,
+        }
+
+        match &item {
+            RolloutItem::SessionMeta(meta) => self.write_line(meta).await,
+            RolloutItem::ResponseItem(item) => self.write_line(item).await,
+            RolloutItem::State(state) => {
+                self.write_line(&RecordLine {
+                    record_type: "state",
+                    record: state,
+                })
+                .await
+            }
+            RolloutItem::IgnoredWrite(record) => {
+                self.write_line(&RecordLine {
+                    record_type: "ignored_write",
+                    record,
+                })
+                .await
+            }
+            RolloutItem::ExecSandbox(record) => {
+                self.write_line(&RecordLine {
+                    record_type: "exec_sandbox",
+                    record,
+                })
+                .await
+            }
+            RolloutItem::ExecTiming(record) => {
+                self.write_line(&RecordLine {
+                    record_type: "exec_timing",
+                    record,
+                })
+                .await
+            }
+        }
+    }
+
+    async fn sync(&mut self) -> std::io::Result<()> {
+        let file = Arc::clone(&self.file);
+        tokio::task::spawn_blocking(move || file.sync_data())
+            .await
+            .map_err(|e| IoError::other(format!("rollout sync task failed: {e}")))?
+    }
+}
+
+/// Keeps rollout items in memory instead of writing them to disk. Clones
+/// share the same items, so several recorders can append to one rollout.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct InMemoryRollout {
+    items: std::sync::Arc<std::sync::Mutex<Vec<RolloutItem>>>,
+}
+
+#[cfg(test)]
+impl InMemoryRollout {
+    /// The items recorded so far. Call [`RolloutRecorder::shutdown`] first to
+    /// make sure everything queued has been recorded.
+    pub(crate) fn items(&self) -> Vec<RolloutItem> {
+        #[expect(clippy::unwrap_used)]
+        let items = self.items.lock().unwrap();
+        items.clone()
+    }
+}
+
+#[cfg(test)]
+impl RolloutSink for InMemoryRollout {
+    async fn write(&mut self, item: RolloutItem) -> std::io::Result<()> {
+        self.items
+            .lock()
+            .map_err(|e| IoError::other(format!("in-memory rollout poisoned: {e}")))?
+            .push(item);
+        Ok(())
+    }
+
+    async fn sync(&mut self) -> std::io::Result<()> {
         Ok(())
     }
 }
@@ -4,6 +4,9 @@ use std::fs::File;
 use std::fs::{self};
 use std::io::Write;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 
 use tempfile::TempDir;
 use time::OffsetDateTime;
@@ -12,11 +15,34 @@ use time::format_description::FormatItem;
 use time::macros::format_description;
 use uuid::Uuid;
 
+use codex_protocol::mcp_protocol::ConversationId;
+use codex_protocol::models::ContentItem;
+use codex_protocol::models::ResponseItem;
+
+use crate::config::Config;
+use crate::config::ConfigOverrides;
+use crate::config::ConfigToml;
+use crate::config_types::RolloutFlushPolicy;
+use crate::conversation_manager::BranchedHistory;
+use crate::conversation_manager::InitialHistory;
+use crate::exec::SandboxType;
+use crate::protocol::SandboxFeature;
+use crate::protocol::SandboxPolicy;
+use crate::rollout::CommandRewrite;
+use crate::rollout::ExecSandboxRecord;
+use crate::rollout::ExecTimingRecord;
+use crate::rollout::RolloutItem;
+use crate::rollout::RolloutParent;
+use crate::rollout::RolloutRecorder;
+use crate::rollout::RolloutRecorderParams;
+use crate::rollout::SandboxMeta;
+use crate::rollout::SessionMeta;
 use crate::rollout::list::ConversationItem;
 use crate::rollout::list::ConversationsPage;
 use crate::rollout::list::Cursor;
 use crate::rollout::list::get_conversation;
 use crate::rollout::list::get_conversations;
+use crate::rollout::recorder::InMemoryRollout;
 
 fn write_session_file(
     root: &Path,
@@ -382,3 +408,375 @@ async fn test_stable_ordering_same_second_pagination() {
     };
     assert_eq!(page2, expected_page2);
 }
+
+#[tokio::test]
+async fn test_get_rollout_branch_truncates_history() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("rollout.jsonl");
+    let uuid = Uuid::new_v4();
+    let mut file = File::create(&path).unwrap();
+    writeln!(
+        file,
+        "{}",
+        serde_json::json!({"timestamp": "2025-01-01T00-00-00", "id": uuid.to_string()})
+    )
+    .unwrap();
+    let items: Vec<ResponseItem> = ["one", "two", "three"]
+        .into_iter()
+        .map(|text| ResponseItem::Message {
+            id: None,
+            role: "user".to_string(),
+            content: vec![ContentItem::InputText {
+                text: text.to_string(),
+            }],
+        })
+        .collect();
+    for item in &items {
+        writeln!(file, "{}", serde_json::to_string(item).unwrap()).unwrap();
+    }
+    drop(file);
+    let original = fs::read_to_string(&path).unwrap();
+
+    let history = RolloutRecorder::get_rollout_branch(&path, 2).await.unwrap();
+    let expected = InitialHistory::Branched(BranchedHistory {
+        parent: RolloutParent {
+            conversation_id: ConversationId(uuid),
+            path: path.clone(),
+            item_index: 2,
+        },
+        history: items[..2].to_vec(),
+    });
+    assert_eq!(history, expected);
+    assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+    let err = RolloutRecorder::get_rollout_branch(&path, 4)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_exec_sandbox_record_serializes_effective_policy() {
+    let temp = TempDir::new().unwrap();
+    let cwd = temp.path().canonicalize().unwrap();
+    let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+    let record = ExecSandboxRecord {
+        call_id: "call-1".to_string(),
+        sandbox: SandboxMeta::new(SandboxType::LinuxSeccomp, &policy, &cwd),
+        unsandboxed_command: false,
+        exec_prelude: false,
+        command_rewrite: None,
+    };
+
+    let value = serde_json::to_value(&record).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "call_id": "call-1",
+            "sandbox_type": "linux-seccomp",
+            "policy": "workspace-write",
+            "writable_roots": [cwd],
+            "network_access": false,
+            "features": ["filesystem", "network"],
+        })
+    );
+    let parsed: ExecSandboxRecord = serde_json::from_value(value).unwrap();
+    assert_eq!(parsed, record);
+
+    let SandboxPolicy::WorkspaceWrite {
+        disabled_sandbox_features,
+        ..
+    } = &mut policy
+    else {
+        unreachable!();
+    };
+    disabled_sandbox_features.push(SandboxFeature::Filesystem);
+    assert_eq!(
+        SandboxMeta::new(SandboxType::LinuxSeccomp, &policy, &cwd).features,
+        vec![SandboxFeature::Network]
+    );
+    assert_eq!(
+        SandboxMeta::new(SandboxType::MacosSeatbelt, &policy, &cwd).features,
+        vec![SandboxFeature::Filesystem, SandboxFeature::Network]
+    );
+
+    let allowlisted = ExecSandboxRecord {
+        call_id: "call-2".to_string(),
+        sandbox: SandboxMeta::new(SandboxType::None, &policy, &cwd),
+        unsandboxed_command: true,
+        exec_prelude: true,
+        command_rewrite: Some(CommandRewrite {
+            original: vec!["python".to_string(), "x.py".to_string()],
+            rewritten: vec!["python3".to_string(), "x.py".to_string()],
+        }),
+    };
+    assert_eq!(
+        serde_json::to_value(&allowlisted).unwrap(),
+        serde_json::json!({
+            "call_id": "call-2",
+            "sandbox_type": "none",
+            "policy": "workspace-write",
+            "writable_roots": [cwd],
+            "network_access": false,
+            "unsandboxed_command": true,
+            "exec_prelude": true,
+            "command_rewrite": {
+                "original": ["python", "x.py"],
+                "rewritten": ["python3", "x.py"],
+            },
+        })
+    );
+
+    let unsandboxed = SandboxMeta::new(SandboxType::None, &SandboxPolicy::DangerFullAccess, &cwd);
+    assert_eq!(
+        serde_json::to_value(&unsandboxed).unwrap(),
+        serde_json::json!({
+            "sandbox_type": "none",
+            "policy": "danger-full-access",
+            "network_access": true,
+        })
+    );
+}
+
+#[test]
+fn test_exec_timing_record_uses_rfc3339_timestamps() {
+    let started_at = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_700_000_000_250);
+    let finished_at = started_at + std::time::Duration::from_secs(3);
+    let record = ExecTimingRecord::new("call-1".to_string(), started_at, finished_at);
+    assert_eq!(
+        serde_json::to_value(&record).unwrap(),
+        serde_json::json!({
+            "call_id": "call-1",
+            "started_at": "2023-11-14T22:13:20.25Z",
+            "finished_at": "2023-11-14T22:13:23.25Z",
+        })
+    );
+}
+
+fn user_message(text: &str) -> ResponseItem {
+    ResponseItem::Message {
+        id: None,
+        role: "user".to_string(),
+        content: vec![ContentItem::InputText {
+            text: text.to_string(),
+        }],
+    }
+}
+
+fn recorded_messages(rollout: &InMemoryRollout) -> Vec<ResponseItem> {
+    rollout
+        .items()
+        .into_iter()
+        .filter_map(|item| match item {
+            RolloutItem::ResponseItem(item) => Some(item),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_in_memory_rollout_records_meta_then_persisted_items() {
+    let rollout = InMemoryRollout::default();
+    let conversation_id = ConversationId(Uuid::new_v4());
+    let recorder = RolloutRecorder::in_memory(
+        &rollout,
+        Some(SessionMeta {
+            id: conversation_id,
+            ..Default::default()
+        }),
+    );
+
+    recorder
+        .record_items(&[user_message("one"), ResponseItem::Other])
+        .await
+        .unwrap();
+    recorder
+        .record_exec_sandbox(ExecSandboxRecord {
+            call_id: "call-1".to_string(),
+            sandbox: SandboxMeta::new(
+                SandboxType::None,
+                &SandboxPolicy::DangerFullAccess,
+                Path::new("/"),
+            ),
+            unsandboxed_command: false,
+            exec_prelude: false,
+            command_rewrite: None,
+        })
+        .await
+        .unwrap();
+    recorder.shutdown().await.unwrap();
+
+    let items = rollout.items();
+    assert_eq!(items.len(), 3);
+    match &items[0] {
+        RolloutItem::SessionMeta(meta) => {
+            assert_eq!(meta.meta.id, conversation_id);
+            assert!(meta.git.is_none());
+        }
+        other => panic!("expected session meta first, got {other:?}"),
+    }
+    assert!(matches!(&items[2], RolloutItem::ExecSandbox(record) if record.call_id == "call-1"));
+    assert_eq!(recorded_messages(&rollout), vec![user_message("one")]);
+}
+
+#[tokio::test]
+async fn test_in_memory_rollout_resume_appends_without_meta() {
+    let rollout = InMemoryRollout::default();
+    let first = RolloutRecorder::in_memory(&rollout, Some(SessionMeta::default()));
+    first.record_items(&[user_message("one")]).await.unwrap();
+    first.shutdown().await.unwrap();
+
+    let resumed = RolloutRecorder::in_memory(&rollout, None);
+    resumed.record_items(&[user_message("two")]).await.unwrap();
+    resumed.shutdown().await.unwrap();
+
+    let items = rollout.items();
+    assert_eq!(
+        items
+            .iter()
+            .filter(|item| matches!(item, RolloutItem::SessionMeta(_)))
+            .count(),
+        1
+    );
+    assert_eq!(
+        recorded_messages(&rollout),
+        vec![user_message("one"), user_message("two")]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_concurrent_reader_only_sees_whole_lines() {
+    let codex_home = TempDir::new().unwrap();
+    let config = Config::load_from_base_config_with_overrides(
+        ConfigToml::default(),
+        ConfigOverrides::default(),
+        codex_home.path().to_path_buf(),
+    )
+    .unwrap();
+    let recorder = RolloutRecorder::new(
+        &config,
+        RolloutRecorderParams::new(ConversationId(Uuid::new_v4()), None),
+    )
+    .await
+    .unwrap();
+    let path = recorder.rollout_path.clone();
+
+    // Tail the rollout while it is being written, the way external tools do.
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = std::thread::spawn({
+        let done = Arc::clone(&done);
+        let path = path.clone();
+        move || {
+            while !done.load(Ordering::Acquire) {
+                let bytes = fs::read(&path).unwrap();
+                for line in bytes
+                    .split_inclusive(|b| *b == b'\n')
+                    .filter(|line| line.ends_with(b"\n"))
+                {
+                    serde_json::from_slice::<serde_json::Value>(line)
+                        .expect("every complete line is valid JSON");
+                }
+            }
+        }
+    });
+
+    // A few lines larger than a pipe buffer or page exercise the
+    // single-write path without writing much to disk.
+    let big = "x".repeat(128 * 1024);
+    for i in 0..5 {
+        let text = format!("{i}:{big}");
+        recorder.record_items(&[user_message(&text)]).await.unwrap();
+    }
+    recorder.shutdown().await.unwrap();
+    done.store(true, Ordering::Release);
+    reader.join().unwrap();
+
+    let InitialHistory::Resumed(resumed) =
+        RolloutRecorder::get_rollout_history(&path).await.unwrap()
+    else {
+        panic!("expected resumed history");
+    };
+    assert_eq!(resumed.history.len(), 5);
+}
+
+#[tokio::test]
+async fn test_read_rollout_ignores_unterminated_last_line() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("rollout.jsonl");
+    let meta = serde_json::json!({
+        "timestamp": "2025-01-01T00:00:00.000Z",
+        "id": Uuid::new_v4().to_string(),
+    });
+    let complete = serde_json::to_string(&user_message("one")).unwrap();
+    let partial = serde_json::to_string(&user_message("two")).unwrap();
+    fs::write(
+        &path,
+        format!("{meta}\n{complete}\n{}", &partial[..partial.len() / 2]),
+    )
+    .unwrap();
+
+    let InitialHistory::Resumed(resumed) =
+        RolloutRecorder::get_rollout_history(&path).await.unwrap()
+    else {
+        panic!("expected resumed history");
+    };
+    assert_eq!(resumed.history, vec![user_message("one")]);
+}
+
+/// Set to the Codex home of the child process that
+/// [`test_every_item_flush_policy_survives_a_crash`] starts.
+const CRASH_TEST_CODEX_HOME_ENV: &str = "CODEX_ROLLOUT_CRASH_TEST_HOME";
+
+#[tokio::test]
+async fn test_every_item_flush_policy_survives_a_crash() {
+    if let Some(codex_home) = std::env::var_os(CRASH_TEST_CODEX_HOME_ENV) {
+        // In the child: record part of a turn, then die without shutting the
+        // recorder down.
+        let mut config = Config::load_from_base_config_with_overrides(
+            ConfigToml::default(),
+            ConfigOverrides::default(),
+            codex_home.into(),
+        )
+        .unwrap();
+        config.rollout.flush_policy = RolloutFlushPolicy::EveryItem;
+        let recorder = RolloutRecorder::new(
+            &config,
+            RolloutRecorderParams::new(ConversationId(Uuid::new_v4()), None),
+        )
+        .await
+        .unwrap();
+        for i in 0..20 {
+            recorder
+                .record_items(&[user_message(&format!("item {i}"))])
+                .await
+                .unwrap();
+        }
+        std::process::abort();
+    }
+
+    let codex_home = TempDir::new().unwrap();
+    let status = std::process::Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "rollout::tests::test_every_item_flush_policy_survives_a_crash",
+        ])
+        .env(CRASH_TEST_CODEX_HOME_ENV, codex_home.path())
+        .status()
+        .unwrap();
+    assert!(!status.success(), "the child should have crashed");
+
+    let page = get_conversations(codex_home.path(), 1, None).await.unwrap();
+    let [conversation] = page.items.as_slice() else {
+        panic!("expected one rollout, got {:?}", page.items);
+    };
+    let InitialHistory::Resumed(resumed) = RolloutRecorder::get_rollout_history(&conversation.path)
+        .await
+        .unwrap()
+    else {
+        panic!("expected resumed history");
+    };
+    let expected: Vec<_> = (0..20)
+        .map(|i| user_message(&format!("item {i}")))
+        .collect();
+    assert_eq!(resumed.history, expected);
+}
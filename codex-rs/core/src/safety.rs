@@ -1,5 +1,4 @@
 use std::collections::HashSet;
-use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -181,51 +180,7 @@ fn is_write_patch_constrained_to_writable_paths(
     sandbox_policy: &SandboxPolicy,
     cwd: &Path,
 ) -> bool {
-    // Early‑exit if there are no declared writable roots.
-    let writable_roots = match sandbox_policy {
-        SandboxPolicy::ReadOnly => {
-            return false;
-        }
-        SandboxPolicy::DangerFullAccess => {
-            return true;
-        }
-        SandboxPolicy::WorkspaceWrite { .. } => sandbox_policy.get_writable_roots_with_cwd(cwd),
-    };
-
-    // Normalize a path by removing `.` and resolving `..` without touching the
-    // filesystem (works even if the file does not exist).
-    fn normalize(path: &Path) -> Option<PathBuf> {
-        let mut out = PathBuf::new();
-        for comp in path.components() {
-            match comp {
-                Component::ParentDir => {
-                    out.pop();
-                }
-                Component::CurDir => { /* skip */ }
-                other => out.push(other.as_os_str()),
-            }
-        }
-        Some(out)
-    }
-
-    // Determine whether `path` is inside **any** writable root. Both `path`
-    // and roots are converted to absolute, normalized forms before the
-    // prefix check.
-    let is_path_writable = |p: &PathBuf| {
-        let abs = if p.is_absolute() {
-            p.clone()
-        } else {
-            cwd.join(p)
-        };
-        let abs = match normalize(&abs) {
-            Some(v) => v,
-            None => return false,
-        };
-
-        writable_roots
-            .iter()
-            .any(|writable_root| writable_root.is_path_writable(&abs))
-    };
+    let is_path_writable = |path: &PathBuf| sandbox_policy.is_path_writable(path, cwd);
 
     for (path, change) in action.changes() {
         match change {
@@ -271,12 +226,7 @@ mod tests {
 
         // Policy limited to the workspace only; exclude system temp roots so
         // only `cwd` is writable by default.
-        let policy_workspace_only = SandboxPolicy::WorkspaceWrite {
-            writable_roots: vec![],
-            network_access: false,
-            exclude_tmpdir_env_var: true,
-            exclude_slash_tmp: true,
-        };
+        let policy_workspace_only = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
 
         assert!(is_write_patch_constrained_to_writable_paths(
             &add_inside,
@@ -292,12 +242,8 @@ mod tests {
 
         // With the parent dir explicitly added as a writable root, the
         // outside write should be permitted.
-        let policy_with_parent = SandboxPolicy::WorkspaceWrite {
-            writable_roots: vec![parent.clone()],
-            network_access: false,
-            exclude_tmpdir_env_var: true,
-            exclude_slash_tmp: true,
-        };
+        let policy_with_parent =
+            SandboxPolicy::new_workspace_write_policy_limited_to(vec![parent.clone()]);
         assert!(is_write_patch_constrained_to_writable_paths(
             &add_outside,
             &policy_with_parent,
@@ -305,6 +251,26 @@ mod tests {
         ));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_writable_roots_constraint_through_symlinked_cwd() {
+        let tmp = TempDir::new().unwrap();
+        let real = tmp.path().join("real");
+        std::fs::create_dir_all(&real).unwrap();
+        let cwd = tmp.path().join("link");
+        std::os::unix::fs::symlink(&real, &cwd).unwrap();
+
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        let add_inside =
+            ApplyPatchAction::new_add_for_test(&cwd.join("new/inner.txt"), "".to_string());
+
+        assert!(is_write_patch_constrained_to_writable_paths(
+            &add_inside,
+            &policy,
+            &cwd,
+        ));
+    }
+
     #[test]
     fn test_request_escalated_privileges() {
         // Should not be a trusted command
@@ -0,0 +1,204 @@
+//! Best-effort report of what the Linux sandbox would have denied in
+//! `audit_only` mode.
+//!
+//! With `audit_only`, the sandbox's seccomp filter answers the network
+//! syscalls it would otherwise reject with `SECCOMP_RET_LOG`: they go through,
+//! and the kernel writes an audit record (`type=1326`) for each. Unless
+//! `auditd` collects those records itself, they end up in the kernel log,
+//! from which [`KernelLogCursor`] reads the ones logged while a command ran.
+//! Nothing can be reported when `/dev/kmsg` is not readable (e.g. with
+//! `kernel.dmesg_restrict`), and records of other processes audited the same
+//! way at the same time cannot be told apart from the command's. Landlock
+//! has no such mode, so file-system writes are not reported at all.
+
+use serde::Serialize;
+
+/// `SECCOMP_RET_LOG`, as the kernel prints it in the `code` field.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const SECCOMP_RET_LOG_CODE: &str = "0x7ffc0000";
+
+/// A syscall the sandbox let through that it would have denied if enforced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditedSyscall {
+    pub pid: u32,
+    /// The process name (`comm`), e.g. `curl`.
+    pub comm: String,
+    /// The syscall's name, e.g. `connect`, or its number for syscalls the
+    /// sandbox does not filter.
+    pub syscall: String,
+}
+
+impl std::fmt::Display for AuditedSyscall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}, pid {})", self.syscall, self.comm, self.pid)
+    }
+}
+
+/// The end of the kernel log when a command started.
+#[derive(Debug)]
+pub(crate) struct KernelLogCursor {
+    #[cfg(target_os = "linux")]
+    kmsg: std::fs::File,
+}
+
+impl KernelLogCursor {
+    /// `None` where the kernel log cannot be read.
+    pub(crate) fn open() -> Option<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::io::Seek;
+            use std::io::SeekFrom;
+            use std::os::unix::fs::OpenOptionsExt;
+
+            let mut kmsg = std::fs::OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open("/dev/kmsg")
+                .ok()?;
+            kmsg.seek(SeekFrom::End(0)).ok()?;
+            Some(Self { kmsg })
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+
+    /// The syscalls logged by an auditing seccomp filter since [`Self::open`].
+    pub(crate) fn audited_syscalls(self) -> Vec<AuditedSyscall> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::io::Read;
+
+            let mut kmsg = self.kmsg;
+            let mut audited = Vec::new();
+            // Each read returns exactly one record, which is at most 8 KiB.
+            let mut record = vec![0u8; 8192];
+            loop {
+                match kmsg.read(&mut record) {
+                    Ok(0) => break,
+                    Ok(len) => {
+                        if let Some(syscall) =
+                            parse_audit_record(&String::from_utf8_lossy(&record[..len]))
+                        {
+                            audited.push(syscall);
+                        }
+                    }
+                    // Records were overwritten before we got to them; the
+                    // read position moved on to the oldest remaining one.
+                    Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+                    // `WouldBlock` once we have caught up.
+                    Err(_) => break,
+                }
+            }
+            audited
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Vec::new()
+        }
+    }
+}
+
+/// Parses a `/dev/kmsg` record like `5,1234,5678,-;audit: type=1326
+/// audit(...): ... pid=42 comm="curl" ... syscall=42 ... code=0x7ffc0000`,
+/// keeping only seccomp records of syscalls that were logged and allowed.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_audit_record(record: &str) -> Option<AuditedSyscall> {
+    let (_, message) = record.split_once(';')?;
+    let field = |name: &str| {
+        message
+            .split_whitespace()
+            .find_map(|token| token.strip_prefix(name)?.strip_prefix('='))
+    };
+    if !message.starts_with("audit: type=1326 ") || field("code")? != SECCOMP_RET_LOG_CODE {
+        return None;
+    }
+    let syscall: SyscallNumber = field("syscall")?.parse().ok()?;
+    Some(AuditedSyscall {
+        pid: field("pid")?.parse().ok()?,
+        comm: field("comm")?.trim_matches('"').to_string(),
+        syscall: syscall_name(syscall)
+            .map(str::to_string)
+            .unwrap_or_else(|| syscall.to_string()),
+    })
+}
+
+#[cfg(target_os = "linux")]
+type SyscallNumber = libc::c_long;
+#[cfg(not(target_os = "linux"))]
+type SyscallNumber = i64;
+
+/// Names of the syscalls the sandbox's network filter covers.
+#[cfg(target_os = "linux")]
+fn syscall_name(nr: SyscallNumber) -> Option<&'static str> {
+    Some(match nr {
+        libc::SYS_connect => "connect",
+        libc::SYS_accept => "accept",
+        libc::SYS_accept4 => "accept4",
+        libc::SYS_bind => "bind",
+        libc::SYS_listen => "listen",
+        libc::SYS_getpeername => "getpeername",
+        libc::SYS_getsockname => "getsockname",
+        libc::SYS_shutdown => "shutdown",
+        libc::SYS_sendto => "sendto",
+        libc::SYS_sendmsg => "sendmsg",
+        libc::SYS_sendmmsg => "sendmmsg",
+        libc::SYS_recvmsg => "recvmsg",
+        libc::SYS_recvmmsg => "recvmmsg",
+        libc::SYS_getsockopt => "getsockopt",
+        libc::SYS_setsockopt => "setsockopt",
+        libc::SYS_ptrace => "ptrace",
+        libc::SYS_socket => "socket",
+        libc::SYS_socketpair => "socketpair",
+        _ => return None,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn syscall_name(_nr: SyscallNumber) -> Option<&'static str> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_logged_seccomp_records_only() {
+        let logged = "5,2048,123456789,-;audit: type=1326 audit(1700000000.123:45): \
+                      auid=1000 uid=1000 gid=1000 ses=2 pid=4242 comm=\"curl\" \
+                      exe=\"/usr/bin/curl\" sig=0 arch=c000003e syscall=99999 compat=0 \
+                      ip=0x7f0000000000 code=0x7ffc0000\n";
+        assert_eq!(
+            parse_audit_record(logged),
+            Some(AuditedSyscall {
+                pid: 4242,
+                comm: "curl".to_string(),
+                syscall: "99999".to_string(),
+            })
+        );
+
+        // Denied rather than logged, e.g. by another program's filter.
+        let denied = logged.replace("code=0x7ffc0000", "code=0x50001");
+        assert_eq!(parse_audit_record(&denied), None);
+        assert_eq!(
+            parse_audit_record("6,2049,123456790,-;eth0: link up\n"),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn names_the_filtered_syscalls() {
+        let record = format!(
+            "5,1,1,-;audit: type=1326 audit(1.1:1): pid=1 comm=\"nc\" syscall={} code=0x7ffc0000",
+            libc::SYS_connect
+        );
+        assert_eq!(
+            parse_audit_record(&record).map(|audited| audited.to_string()),
+            Some("connect (nc, pid 1)".to_string())
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Named [`SandboxPolicy`] presets.
+//!
+//! Assembling a policy by hand (writable roots, network, temp dirs) is easy to
+//! get wrong, so users can instead pick one of a small set of named presets,
+//! either in `config.toml` (`sandbox_preset = "workspace-write"`) or when
+//! driving the exec layer directly.
+
+use std::path::Path;
+
+use crate::error::CodexErr;
+use crate::error::Result;
+use crate::protocol::SandboxPolicy;
+
+/// A named, documented way of constructing a [`SandboxPolicy`].
+#[derive(Debug, Clone, Copy)]
+pub struct SandboxPreset {
+    pub name: &'static str,
+    pub description: &'static str,
+    build: fn() -> SandboxPolicy,
+}
+
+impl SandboxPreset {
+    /// The policy this preset expands to, independent of any cwd.
+    pub fn policy(&self) -> SandboxPolicy {
+        (self.build)()
+    }
+
+    /// Expands the preset against `cwd`: the writable roots that would be
+    /// derived at exec time (cwd, `/tmp`, `$TMPDIR`, ...) are spelled out
+    /// explicitly so the resulting policy no longer depends on the cwd it is
+    /// later evaluated with.
+    pub fn materialize(&self, cwd: &Path) -> SandboxPolicy {
+        let policy = self.policy();
+        match &policy {
+            SandboxPolicy::WorkspaceWrite {
+                writable_root_exclusions,
+                network_access,
+                denied_programs,
+                resource_limits,
+                disabled_sandbox_features,
+                scratch_dir,
+                run_as,
+                network_loopback_only,
+                audit_only,
+                ..
+            } => SandboxPolicy::WorkspaceWrite {
+                writable_roots: policy
+                    .get_writable_roots_with_cwd(cwd)
+                    .into_iter()
+                    .map(|writable_root| writable_root.root)
+                    .collect(),
+                writable_root_exclusions: writable_root_exclusions.clone(),
+                network_access: *network_access,
+                exclude_tmpdir_env_var: true,
+                exclude_slash_tmp: true,
+                denied_programs: denied_programs.clone(),
+                resource_limits: *resource_limits,
+                disabled_sandbox_features: disabled_sandbox_features.clone(),
+                scratch_dir: scratch_dir.clone(),
+                run_as: *run_as,
+                network_loopback_only: *network_loopback_only,
+                audit_only: *audit_only,
+            },
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => policy,
+        }
+    }
+}
+
+/// All built-in presets, in the order they should be presented to users.
+pub const SANDBOX_PRESETS: &[SandboxPreset] = &[
+    SandboxPreset {
+        name: "read-only",
+        description: "Read the whole disk; no writes, no network.",
+        build: SandboxPolicy::new_read_only_policy,
+    },
+    SandboxPreset {
+        name: "workspace-write",
+        description: "Read the whole disk; write to the workspace and temp dirs; no network.",
+        build: SandboxPolicy::new_workspace_write_policy,
+    },
+    SandboxPreset {
+        name: "trusted",
+        description: "Like workspace-write, but with outbound network access.",
+        build: trusted_policy,
+    },
+];
+
+fn trusted_policy() -> SandboxPolicy {
+    let mut policy = SandboxPolicy::new_workspace_write_policy();
+    if let SandboxPolicy::WorkspaceWrite { network_access, .. } = &mut policy {
+        *network_access = true;
+    }
+    policy
+}
+
+/// Looks up a preset by name.
+pub fn find_sandbox_preset(name: &str) -> Option<&'static SandboxPreset> {
+    SANDBOX_PRESETS.iter().find(|preset| preset.name == name)
+}
+
+/// Resolves `name` to a preset and materializes it against `cwd`.
+pub fn resolve_sandbox_preset(name: &str, cwd: &Path) -> Result<SandboxPolicy> {
+    find_sandbox_preset(name)
+        .map(|preset| preset.materialize(cwd))
+        .ok_or_else(|| CodexErr::UnknownSandboxPreset(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn presets_resolve_by_name() {
+        assert_eq!(
+            Some(SandboxPolicy::ReadOnly),
+            find_sandbox_preset("read-only").map(SandboxPreset::policy)
+        );
+        assert_eq!(
+            Some(SandboxPolicy::new_workspace_write_policy()),
+            find_sandbox_preset("workspace-write").map(SandboxPreset::policy)
+        );
+        assert!(
+            find_sandbox_preset("trusted")
+                .expect("trusted preset")
+                .policy()
+                .has_full_network_access()
+        );
+        assert!(find_sandbox_preset("yolo").is_none());
+    }
+
+    #[test]
+    fn materialize_spells_out_cwd() {
+        let cwd = tempfile::tempdir().expect("tempdir");
+        let policy = resolve_sandbox_preset("workspace-write", cwd.path()).expect("preset");
+        let SandboxPolicy::WorkspaceWrite {
+            writable_roots,
+            exclude_tmpdir_env_var,
+            exclude_slash_tmp,
+            ..
+        } = policy
+        else {
+            panic!("expected workspace-write policy");
+        };
+        assert!(writable_roots.contains(&cwd.path().to_path_buf()));
+        assert!(exclude_tmpdir_env_var);
+        assert!(exclude_slash_tmp);
+    }
+
+    #[test]
+    fn unknown_preset_is_an_error() {
+        let err = resolve_sandbox_preset("yolo", Path::new("/")).expect_err("unknown preset");
+        assert_eq!(err.to_string(), "unknown sandbox preset `yolo`");
+    }
+}
@@ -0,0 +1,321 @@
+//! Checks that the platform sandbox actually enforces a [`SandboxPolicy`] on
+//! this machine.
+//!
+//! Each probe runs a command the policy forbids through
+//! [`process_exec_tool_call`], exactly like a model-issued command would be
+//! run, and looks at whether it got through. A probe that has nothing to
+//! check under the policy (e.g. the network probe when network access is
+//! allowed) is skipped rather than reported as passing.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+use crate::error::CodexErr;
+use crate::error::SandboxErr;
+use crate::exec::ExecParams;
+use crate::exec::SandboxType;
+use crate::exec::process_exec_tool_call;
+use crate::protocol::SandboxPolicy;
+use crate::safety::get_platform_sandbox;
+
+/// How long a single probe may run.
+const PROBE_TIMEOUT_MS: u64 = 10_000;
+
+/// Address the network probe tries to reach (Cloudflare DNS over TCP).
+const NETWORK_PROBE_ADDR: &str = "1.1.1.1/53";
+
+/// What happened to one probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeOutcome {
+    /// The forbidden operation failed, as it should.
+    Blocked,
+    /// The forbidden operation succeeded: the sandbox is not enforcing the
+    /// policy.
+    Escaped,
+    /// The probe did not run or its result says nothing about the sandbox.
+    Skipped { reason: String },
+}
+
+impl fmt::Display for ProbeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeOutcome::Blocked => f.write_str("blocked"),
+            ProbeOutcome::Escaped => f.write_str("NOT BLOCKED"),
+            ProbeOutcome::Skipped { reason } => write!(f, "skipped ({reason})"),
+        }
+    }
+}
+
+/// The result of one probe of [`run_sandbox_self_test`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeReport {
+    pub name: &'static str,
+    /// What the probe attempted, for display.
+    pub description: String,
+    pub outcome: ProbeOutcome,
+}
+
+/// Runs every probe against `sandbox_policy` under the platform sandbox,
+/// with `cwd` as the workspace and `env` as the commands' environment.
+pub async fn run_sandbox_self_test(
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+    env: HashMap<String, String>,
+    codex_linux_sandbox_exe: &Option<PathBuf>,
+) -> Vec<ProbeReport> {
+    let Some(sandbox_type) = get_platform_sandbox() else {
+        return probe_names()
+            .map(|name| ProbeReport {
+                name,
+                description: String::new(),
+                outcome: skipped("no sandbox is available on this platform"),
+            })
+            .collect();
+    };
+    run_probes(
+        sandbox_type,
+        sandbox_policy,
+        cwd,
+        env,
+        codex_linux_sandbox_exe,
+    )
+    .await
+}
+
+fn probe_names() -> impl Iterator<Item = &'static str> {
+    [
+        "write-outside-writable-roots",
+        "write-protected-subtree",
+        "network",
+    ]
+    .into_iter()
+}
+
+async fn run_probes(
+    sandbox_type: SandboxType,
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+    env: HashMap<String, String>,
+    codex_linux_sandbox_exe: &Option<PathBuf>,
+) -> Vec<ProbeReport> {
+    let runner = ProbeRunner {
+        sandbox_type,
+        sandbox_policy,
+        cwd,
+        env,
+        codex_linux_sandbox_exe,
+    };
+
+    // Outside the workspace, but somewhere the user can normally write, so a
+    // failure is down to the sandbox rather than file permissions.
+    let outside_dir = cwd
+        .parent()
+        .map(Path::to_path_buf)
+        .filter(|dir| !sandbox_policy.is_path_writable(dir, cwd))
+        .or_else(dirs::home_dir);
+    let outside = runner
+        .write_probe("write-outside-writable-roots", outside_dir)
+        .await;
+
+    // The top-level `.git` of the workspace stays read-only even when the
+    // workspace itself is writable.
+    let git_dir = Some(cwd.join(".git")).filter(|dir| dir.is_dir());
+    let protected = runner.write_probe("write-protected-subtree", git_dir).await;
+
+    let network = runner.network_probe("network").await;
+
+    vec![outside, protected, network]
+}
+
+struct ProbeRunner<'a> {
+    sandbox_type: SandboxType,
+    sandbox_policy: &'a SandboxPolicy,
+    cwd: &'a Path,
+    env: HashMap<String, String>,
+    codex_linux_sandbox_exe: &'a Option<PathBuf>,
+}
+
+impl ProbeRunner<'_> {
+    /// Tries to create a file in `dir`, which the policy must not allow.
+    async fn write_probe(&self, name: &'static str, dir: Option<PathBuf>) -> ProbeReport {
+        let Some(dir) = dir else {
+            return ProbeReport {
+                name,
+                description: "write a file".to_string(),
+                outcome: skipped("no suitable directory to write to"),
+            };
+        };
+        let target = dir.join(format!(".codex-sandbox-probe-{}", Uuid::new_v4()));
+        let description = format!("write {}", target.display());
+        if self.sandbox_policy.is_path_writable(&target, self.cwd) {
+            return ProbeReport {
+                name,
+                description,
+                outcome: skipped("the policy allows this write"),
+            };
+        }
+
+        let script = "echo probe > \"$1\"";
+        let result = self
+            .run(
+                self.sandbox_type,
+                self.sandbox_policy,
+                ["/bin/sh", "-c", script, "sh", &target.to_string_lossy()],
+            )
+            .await;
+        // Whatever the command reported, the file is the ground truth.
+        let outcome = if target.exists() {
+            let _ = std::fs::remove_file(&target);
+            ProbeOutcome::Escaped
+        } else {
+            match result {
+                Ok(_) => ProbeOutcome::Blocked,
+                Err(reason) => skipped(&reason),
+            }
+        };
+        ProbeReport {
+            name,
+            description,
+            outcome,
+        }
+    }
+
+    /// Tries to open a TCP connection, which the policy must not allow.
+    async fn network_probe(&self, name: &'static str) -> ProbeReport {
+        let description = format!("connect to {NETWORK_PROBE_ADDR}");
+        if self.sandbox_policy.has_full_network_access() {
+            return ProbeReport {
+                name,
+                description,
+                outcome: skipped("the policy allows network access"),
+            };
+        }
+
+        let script = format!("exec 3<>/dev/tcp/{NETWORK_PROBE_ADDR}");
+        let command = ["bash", "-c", script.as_str()];
+        // Only a connection that works outside the sandbox says anything
+        // about the sandbox when it fails inside it.
+        let outcome = match self
+            .run(SandboxType::None, &SandboxPolicy::DangerFullAccess, command)
+            .await
+        {
+            Ok(true) => match self
+                .run(self.sandbox_type, self.sandbox_policy, command)
+                .await
+            {
+                Ok(true) => ProbeOutcome::Escaped,
+                Ok(false) => ProbeOutcome::Blocked,
+                Err(reason) => skipped(&reason),
+            },
+            Ok(false) => skipped("no network access outside the sandbox either"),
+            Err(reason) => skipped(&reason),
+        };
+        ProbeReport {
+            name,
+            description,
+            outcome,
+        }
+    }
+
+    /// Runs `command` and returns whether it succeeded, or why it could not
+    /// tell.
+    async fn run<'c>(
+        &self,
+        sandbox_type: SandboxType,
+        sandbox_policy: &SandboxPolicy,
+        command: impl IntoIterator<Item = &'c str>,
+    ) -> Result<bool, String> {
+        let params = ExecParams::builder(command)
+            .cwd(self.cwd)
+            .env(self.env.clone())
+            .timeout_ms(PROBE_TIMEOUT_MS)
+            .build();
+        match process_exec_tool_call(
+            params,
+            sandbox_type,
+            sandbox_policy,
+            self.codex_linux_sandbox_exe,
+            None,
+        )
+        .await
+        {
+            Ok(output) => Ok(output.exit_code == 0),
+            Err(CodexErr::Sandbox(SandboxErr::Denied(..))) => Ok(false),
+            Err(err) => Err(format!("probe failed to run: {err}")),
+        }
+    }
+}
+
+fn skipped(reason: &str) -> ProbeOutcome {
+    ProbeOutcome::Skipped {
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn test_env() -> HashMap<String, String> {
+        HashMap::from([(
+            "PATH".to_string(),
+            std::env::var("PATH").unwrap_or_default(),
+        )])
+    }
+
+    #[tokio::test]
+    async fn full_access_skips_every_probe() {
+        let cwd = tempfile::tempdir().expect("tempdir");
+        let reports = run_probes(
+            SandboxType::None,
+            &SandboxPolicy::DangerFullAccess,
+            cwd.path(),
+            test_env(),
+            &None,
+        )
+        .await;
+        assert_eq!(reports.len(), 3);
+        for report in reports {
+            assert!(
+                matches!(report.outcome, ProbeOutcome::Skipped { .. }),
+                "{report:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_an_unenforced_write_and_cleans_up() {
+        let root = tempfile::tempdir().expect("tempdir");
+        let cwd = root.path().join("workspace");
+        std::fs::create_dir_all(cwd.join(".git")).expect("create workspace");
+
+        // Only the workspace is writable, so the probe writes next to it.
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+
+        // Without a sandbox nothing stops the writes the policy forbids.
+        let reports = run_probes(SandboxType::None, &policy, &cwd, test_env(), &None).await;
+        assert_eq!(reports[0].name, "write-outside-writable-roots");
+        assert_eq!(reports[0].outcome, ProbeOutcome::Escaped);
+        assert_eq!(reports[1].name, "write-protected-subtree");
+        assert_eq!(reports[1].outcome, ProbeOutcome::Escaped);
+
+        let leftovers = std::fs::read_dir(root.path())
+            .expect("read root")
+            .chain(std::fs::read_dir(cwd.join(".git")).expect("read .git"))
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .expect("dir entry")
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(".codex-sandbox-probe-")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
@@ -1,10 +1,13 @@
 use std::collections::HashMap;
+use std::io;
 use std::path::Path;
 use std::path::PathBuf;
 use tokio::process::Child;
 
 use crate::protocol::SandboxPolicy;
 use crate::spawn::CODEX_SANDBOX_ENV_VAR;
+use crate::spawn::InheritedFd;
+use crate::spawn::StdinSource;
 use crate::spawn::StdioPolicy;
 use crate::spawn::spawn_child_async;
 
@@ -14,16 +17,21 @@ const MACOS_SEATBELT_BASE_POLICY: &str = include_str!("seatbelt_base_policy.sbpl
 /// to defend against an attacker trying to inject a malicious version on the
 /// PATH. If /usr/bin/sandbox-exec has been tampered with, then the attacker
 /// already has root access.
-const MACOS_PATH_TO_SEATBELT_EXECUTABLE: &str = "/usr/bin/sandbox-exec";
+pub(crate) const MACOS_PATH_TO_SEATBELT_EXECUTABLE: &str = "/usr/bin/sandbox-exec";
 
+/// Runs `command` under `sandbox-exec`. `base_policy` replaces the embedded
+/// base policy when set (see [`load_seatbelt_base_policy`]).
 pub async fn spawn_command_under_seatbelt(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
+    base_policy: Option<&str>,
     cwd: PathBuf,
     stdio_policy: StdioPolicy,
     mut env: HashMap<String, String>,
+    inherited_fds: &[InheritedFd],
+    stdin_source: Option<StdinSource>,
 ) -> std::io::Result<Child> {
-    let args = create_seatbelt_command_args(command, sandbox_policy, &cwd);
+    let args = create_seatbelt_command_args(command, sandbox_policy, base_policy, &cwd).into_args();
     let arg0 = None;
     env.insert(CODEX_SANDBOX_ENV_VAR.to_string(), "seatbelt".to_string());
     spawn_child_async(
@@ -34,36 +42,147 @@ pub async fn spawn_command_under_seatbelt(
         sandbox_policy,
         stdio_policy,
         env,
+        inherited_fds,
+        stdin_source,
     )
     .await
 }
 
-fn create_seatbelt_command_args(
+/// Reads a replacement base policy from `path`. On macOS, it is also checked
+/// by running `/usr/bin/true` under the read-only policy built on top of it,
+/// so a policy `sandbox-exec` rejects fails here rather than on every
+/// command.
+pub fn load_seatbelt_base_policy(path: &Path) -> io::Result<String> {
+    let base_policy = std::fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "failed to read seatbelt base policy {}: {e}",
+                path.display()
+            ),
+        )
+    })?;
+    if base_policy.trim().is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("seatbelt base policy is empty: {}", path.display()),
+        ));
+    }
+
+    if cfg!(target_os = "macos") {
+        let (policy, params) =
+            build_seatbelt_policy(&base_policy, &SandboxPolicy::ReadOnly, Path::new("/"));
+        debug_assert!(params.is_empty());
+        let output = std::process::Command::new(MACOS_PATH_TO_SEATBELT_EXECUTABLE)
+            .args(["-p", &policy, "--", "/usr/bin/true"])
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "sandbox-exec rejected the seatbelt base policy {}: {}",
+                    path.display(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+    }
+    Ok(base_policy)
+}
+
+/// A `sandbox-exec` invocation, before it is flattened into arguments by
+/// [`SeatbeltCommand::into_args`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatbeltCommand {
+    /// The full policy text, passed with `-p`.
+    pub policy: String,
+    /// The `(param "...")` bindings the policy references, passed as
+    /// `-D<name>=<path>` in this order.
+    pub params: Vec<(String, PathBuf)>,
+    /// The command to run under the policy.
+    pub command: Vec<String>,
+}
+
+impl SeatbeltCommand {
+    /// The arguments for `sandbox-exec`.
+    pub fn into_args(self) -> Vec<String> {
+        let mut seatbelt_args: Vec<String> = vec!["-p".to_string(), self.policy];
+        seatbelt_args.extend(
+            self.params
+                .iter()
+                .map(|(key, value)| format!("-D{key}={}", value.to_string_lossy())),
+        );
+        seatbelt_args.push("--".to_string());
+        seatbelt_args.extend(self.command);
+        seatbelt_args
+    }
+}
+
+/// Builds the `sandbox-exec` invocation for `command`, on top of
+/// `base_policy` or the embedded base policy. Kept free of any macOS-only
+/// calls so the generated policy can be asserted on every platform.
+pub fn create_seatbelt_command_args(
     command: Vec<String>,
     sandbox_policy: &SandboxPolicy,
+    base_policy: Option<&str>,
     cwd: &Path,
-) -> Vec<String> {
-    let (file_write_policy, extra_cli_args) = {
+) -> SeatbeltCommand {
+    let base_policy = base_policy.unwrap_or(MACOS_SEATBELT_BASE_POLICY);
+    let (policy, params) = build_seatbelt_policy(base_policy, sandbox_policy, cwd);
+    SeatbeltCommand {
+        policy,
+        params,
+        command,
+    }
+}
+
+/// Returns the full Seatbelt policy that would be passed to `sandbox-exec`
+/// for `sandbox_policy`, with every `(param "WRITABLE_ROOT_n")` reference
+/// replaced by the path it is bound to. The result is meant for logging and
+/// diffing when debugging policy issues; it is not what we hand to
+/// `sandbox-exec`, which receives the parameters separately via `-D`.
+pub fn resolve_seatbelt_policy(
+    sandbox_policy: &SandboxPolicy,
+    base_policy: Option<&str>,
+    cwd: &Path,
+) -> String {
+    let base_policy = base_policy.unwrap_or(MACOS_SEATBELT_BASE_POLICY);
+    let (full_policy, params) = build_seatbelt_policy(base_policy, sandbox_policy, cwd);
+    params.iter().fold(full_policy, |policy, (key, value)| {
+        let escaped = value
+            .to_string_lossy()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        policy.replace(&format!("(param \"{key}\")"), &format!("\"{escaped}\""))
+    })
+}
+
+/// Assembles the Seatbelt policy text, starting with `base_policy`, together
+/// with the `-D` parameter bindings it references, in the order they should
+/// be passed on the command line.
+fn build_seatbelt_policy(
+    base_policy: &str,
+    sandbox_policy: &SandboxPolicy,
+    cwd: &Path,
+) -> (String, Vec<(String, PathBuf)>) {
+    let (file_write_policy, params) = {
         if sandbox_policy.has_full_disk_write_access() {
             // Allegedly, this is more permissive than `(allow file-write*)`.
             (
                 r#"(allow file-write* (regex #"^/"))"#.to_string(),
-                Vec::<String>::new(),
+                Vec::new(),
             )
         } else {
             let writable_roots = sandbox_policy.get_writable_roots_with_cwd(cwd);
 
             let mut writable_folder_policies: Vec<String> = Vec::new();
-            let mut cli_args: Vec<String> = Vec::new();
+            let mut params: Vec<(String, PathBuf)> = Vec::new();
 
             for (index, wr) in writable_roots.iter().enumerate() {
                 // Canonicalize to avoid mismatches like /var vs /private/var on macOS.
                 let canonical_root = wr.root.canonicalize().unwrap_or_else(|_| wr.root.clone());
                 let root_param = format!("WRITABLE_ROOT_{index}");
-                cli_args.push(format!(
-                    "-D{root_param}={}",
-                    canonical_root.to_string_lossy()
-                ));
+                params.push((root_param.clone(), canonical_root));
 
                 if wr.read_only_subpaths.is_empty() {
                     writable_folder_policies.push(format!("(subpath (param \"{root_param}\"))"));
@@ -75,7 +194,7 @@ fn create_seatbelt_command_args(
                     for (subpath_index, ro) in wr.read_only_subpaths.iter().enumerate() {
                         let canonical_ro = ro.canonicalize().unwrap_or_else(|_| ro.clone());
                         let ro_param = format!("WRITABLE_ROOT_{index}_RO_{subpath_index}");
-                        cli_args.push(format!("-D{ro_param}={}", canonical_ro.to_string_lossy()));
+                        params.push((ro_param.clone(), canonical_ro));
                         require_parts
                             .push(format!("(require-not (subpath (param \"{ro_param}\")))"));
                     }
@@ -85,13 +204,13 @@ fn create_seatbelt_command_args(
             }
 
             if writable_folder_policies.is_empty() {
-                ("".to_string(), Vec::<String>::new())
+                ("".to_string(), Vec::new())
             } else {
                 let file_write_policy = format!(
                     "(allow file-write*\n{}\n)",
                     writable_folder_policies.join(" ")
                 );
-                (file_write_policy, cli_args)
+                (file_write_policy, params)
             }
         }
     };
@@ -105,25 +224,32 @@ fn create_seatbelt_command_args(
     // TODO(mbolin): apply_patch calls must also honor the SandboxPolicy.
     let network_policy = if sandbox_policy.has_full_network_access() {
         "(allow network-outbound)\n(allow network-inbound)\n(allow system-socket)"
+    } else if sandbox_policy.has_loopback_only_network_access() {
+        // Seatbelt's `localhost` matches both 127.0.0.1 and ::1.
+        concat!(
+            "(allow network-outbound (remote ip \"localhost:*\"))\n",
+            "(allow network-inbound (local ip \"localhost:*\"))\n",
+            "(allow network-bind (local ip \"localhost:*\"))\n",
+            "(allow system-socket)"
+        )
     } else {
         ""
     };
 
-    let full_policy = format!(
-        "{MACOS_SEATBELT_BASE_POLICY}\n{file_read_policy}\n{file_write_policy}\n{network_policy}"
-    );
+    let full_policy =
+        format!("{base_policy}\n{file_read_policy}\n{file_write_policy}\n{network_policy}");
 
-    let mut seatbelt_args: Vec<String> = vec!["-p".to_string(), full_policy];
-    seatbelt_args.extend(extra_cli_args);
-    seatbelt_args.push("--".to_string());
-    seatbelt_args.extend(command);
-    seatbelt_args
+    (full_policy, params)
 }
 
 #[cfg(test)]
 mod tests {
     use super::MACOS_SEATBELT_BASE_POLICY;
+    use super::SeatbeltCommand;
+    use super::build_seatbelt_policy;
     use super::create_seatbelt_command_args;
+    use super::load_seatbelt_base_policy;
+    use super::resolve_seatbelt_policy;
     use crate::protocol::SandboxPolicy;
     use pretty_assertions::assert_eq;
     use std::fs;
@@ -152,16 +278,15 @@ mod tests {
 
         // Build a policy that only includes the two test roots as writable and
         // does not automatically include defaults TMPDIR or /tmp.
-        let policy = SandboxPolicy::WorkspaceWrite {
-            writable_roots: vec![root_with_git.clone(), root_without_git.clone()],
-            network_access: false,
-            exclude_tmpdir_env_var: true,
-            exclude_slash_tmp: true,
-        };
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![
+            root_with_git.clone(),
+            root_without_git.clone(),
+        ]);
 
-        let args = create_seatbelt_command_args(
+        let seatbelt = create_seatbelt_command_args(
             vec!["/bin/echo".to_string(), "hello".to_string()],
             &policy,
+            None,
             &cwd,
         );
 
@@ -180,31 +305,18 @@ mod tests {
 "#,
         );
 
-        let mut expected_args = vec![
-            "-p".to_string(),
-            expected_policy,
-            format!(
-                "-DWRITABLE_ROOT_0={}",
-                root_with_git_canon.to_string_lossy()
-            ),
-            format!(
-                "-DWRITABLE_ROOT_0_RO_0={}",
-                root_with_git_git_canon.to_string_lossy()
-            ),
-            format!(
-                "-DWRITABLE_ROOT_1={}",
-                root_without_git_canon.to_string_lossy()
-            ),
-            format!("-DWRITABLE_ROOT_2={}", cwd.to_string_lossy()),
-        ];
-
-        expected_args.extend(vec![
-            "--".to_string(),
-            "/bin/echo".to_string(),
-            "hello".to_string(),
-        ]);
+        let expected = SeatbeltCommand {
+            policy: expected_policy,
+            params: vec![
+                ("WRITABLE_ROOT_0".to_string(), root_with_git_canon),
+                ("WRITABLE_ROOT_0_RO_0".to_string(), root_with_git_git_canon),
+                ("WRITABLE_ROOT_1".to_string(), root_without_git_canon),
+                ("WRITABLE_ROOT_2".to_string(), cwd),
+            ],
+            command: vec!["/bin/echo".to_string(), "hello".to_string()],
+        };
 
-        assert_eq!(expected_args, args);
+        assert_eq!(expected, seatbelt);
     }
 
     #[test]
@@ -227,23 +339,26 @@ mod tests {
         // Build a policy that does not specify any writable_roots, but does
         // use the default ones (cwd and TMPDIR) and verifies the `.git` check
         // is done properly for cwd.
-        let policy = SandboxPolicy::WorkspaceWrite {
-            writable_roots: vec![],
-            network_access: false,
-            exclude_tmpdir_env_var: false,
-            exclude_slash_tmp: false,
-        };
+        let policy = SandboxPolicy::new_workspace_write_policy();
 
         let args = create_seatbelt_command_args(
             vec!["/bin/echo".to_string(), "hello".to_string()],
             &policy,
+            None,
             root_with_git.as_path(),
-        );
+        )
+        .into_args();
 
+        let slash_tmp = PathBuf::from("/tmp")
+            .canonicalize()
+            .expect("canonicalize /tmp");
+        // Writable roots are deduped, so a TMPDIR that resolves to /tmp does
+        // not get its own parameter.
         let tmpdir_env_var = std::env::var("TMPDIR")
             .ok()
             .map(PathBuf::from)
             .and_then(|p| p.canonicalize().ok())
+            .filter(|p| *p != slash_tmp)
             .map(|p| p.to_string_lossy().to_string());
 
         let tempdir_policy_entry = if tmpdir_env_var.is_some() {
@@ -278,13 +393,7 @@ mod tests {
                 "-DWRITABLE_ROOT_0_RO_0={}",
                 root_with_git_git_canon.to_string_lossy()
             ),
-            format!(
-                "-DWRITABLE_ROOT_1={}",
-                PathBuf::from("/tmp")
-                    .canonicalize()
-                    .expect("canonicalize /tmp")
-                    .to_string_lossy()
-            ),
+            format!("-DWRITABLE_ROOT_1={}", slash_tmp.to_string_lossy()),
         ];
 
         if let Some(p) = tmpdir_env_var {
@@ -300,6 +409,289 @@ mod tests {
         assert_eq!(expected_args, args);
     }
 
+    #[test]
+    fn resolve_seatbelt_policy_substitutes_writable_root_params() {
+        if cfg!(target_os = "windows") {
+            // /tmp does not exist on Windows, so skip this test.
+            return;
+        }
+
+        let tmp = TempDir::new().expect("tempdir");
+        let PopulatedTmp {
+            root_with_git,
+            root_with_git_canon,
+            root_with_git_git_canon,
+            ..
+        } = populate_tmpdir(tmp.path());
+
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+
+        let resolved = resolve_seatbelt_policy(&policy, None, root_with_git.as_path());
+
+        let expected_policy = format!(
+            r#"{MACOS_SEATBELT_BASE_POLICY}
+; allow read-only file operations
+(allow file-read*)
+(allow file-write*
+(require-all (subpath "{}") (require-not (subpath "{}")) )
+)
+"#,
+            root_with_git_canon.to_string_lossy(),
+            root_with_git_git_canon.to_string_lossy(),
+        );
+
+        assert_eq!(expected_policy, resolved);
+    }
+
+    fn echo_hello() -> Vec<String> {
+        vec!["/bin/echo".to_string(), "hello".to_string()]
+    }
+
+    #[test]
+    fn create_seatbelt_args_for_read_only_policy() {
+        let args = create_seatbelt_command_args(
+            echo_hello(),
+            &SandboxPolicy::new_read_only_policy(),
+            None,
+            Path::new("/does/not/matter"),
+        )
+        .into_args();
+
+        let expected_policy = format!(
+            "{MACOS_SEATBELT_BASE_POLICY}\n; allow read-only file operations\n(allow file-read*)\n\n"
+        );
+        let expected_args = vec![
+            "-p".to_string(),
+            expected_policy,
+            "--".to_string(),
+            "/bin/echo".to_string(),
+            "hello".to_string(),
+        ];
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn create_seatbelt_args_for_full_access_policy() {
+        let args = create_seatbelt_command_args(
+            echo_hello(),
+            &SandboxPolicy::DangerFullAccess,
+            None,
+            Path::new("/does/not/matter"),
+        )
+        .into_args();
+
+        let expected_policy = format!(
+            r#"{MACOS_SEATBELT_BASE_POLICY}
+; allow read-only file operations
+(allow file-read*)
+(allow file-write* (regex #"^/"))
+(allow network-outbound)
+(allow network-inbound)
+(allow system-socket)"#
+        );
+        let expected_args = vec![
+            "-p".to_string(),
+            expected_policy,
+            "--".to_string(),
+            "/bin/echo".to_string(),
+            "hello".to_string(),
+        ];
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn create_seatbelt_args_with_network_access() {
+        if cfg!(target_os = "windows") {
+            // Writable roots are absolute Unix paths, so skip this test.
+            return;
+        }
+
+        let tmp = TempDir::new().expect("tempdir");
+        let PopulatedTmp {
+            root_without_git,
+            root_without_git_canon,
+            ..
+        } = populate_tmpdir(tmp.path());
+
+        let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        if let SandboxPolicy::WorkspaceWrite { network_access, .. } = &mut policy {
+            *network_access = true;
+        }
+
+        let args = create_seatbelt_command_args(echo_hello(), &policy, None, &root_without_git)
+            .into_args();
+
+        let expected_policy = format!(
+            r#"{MACOS_SEATBELT_BASE_POLICY}
+; allow read-only file operations
+(allow file-read*)
+(allow file-write*
+(subpath (param "WRITABLE_ROOT_0"))
+)
+(allow network-outbound)
+(allow network-inbound)
+(allow system-socket)"#
+        );
+        let expected_args = vec![
+            "-p".to_string(),
+            expected_policy,
+            format!(
+                "-DWRITABLE_ROOT_0={}",
+                root_without_git_canon.to_string_lossy()
+            ),
+            "--".to_string(),
+            "/bin/echo".to_string(),
+            "hello".to_string(),
+        ];
+        assert_eq!(expected_args, args);
+    }
+
+    #[test]
+    fn create_seatbelt_args_with_loopback_only_network() {
+        if cfg!(target_os = "windows") {
+            // Writable roots are absolute Unix paths, so skip this test.
+            return;
+        }
+
+        let tmp = TempDir::new().expect("tempdir");
+        let PopulatedTmp {
+            root_without_git, ..
+        } = populate_tmpdir(tmp.path());
+
+        let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        if let SandboxPolicy::WorkspaceWrite {
+            network_loopback_only,
+            ..
+        } = &mut policy
+        {
+            *network_loopback_only = true;
+        }
+
+        let seatbelt = create_seatbelt_command_args(echo_hello(), &policy, None, &root_without_git);
+
+        assert!(seatbelt.policy.ends_with(
+            r#"(allow network-outbound (remote ip "localhost:*"))
+(allow network-inbound (local ip "localhost:*"))
+(allow network-bind (local ip "localhost:*"))
+(allow system-socket)"#
+        ));
+        assert!(!seatbelt.policy.contains("(allow network-outbound)"));
+    }
+
+    #[test]
+    fn create_seatbelt_args_with_writable_root_exclusions() {
+        if cfg!(target_os = "windows") {
+            // Writable roots are absolute Unix paths, so skip this test.
+            return;
+        }
+
+        let tmp = TempDir::new().expect("tempdir");
+        let PopulatedTmp {
+            root_without_git,
+            root_without_git_canon,
+            ..
+        } = populate_tmpdir(tmp.path());
+
+        let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+        if let SandboxPolicy::WorkspaceWrite {
+            writable_root_exclusions,
+            ..
+        } = &mut policy
+        {
+            *writable_root_exclusions = vec![PathBuf::from("node_modules")];
+        }
+
+        let seatbelt = create_seatbelt_command_args(echo_hello(), &policy, None, &root_without_git);
+
+        let expected_policy = format!(
+            r#"{MACOS_SEATBELT_BASE_POLICY}
+; allow read-only file operations
+(allow file-read*)
+(allow file-write*
+(require-all (subpath (param "WRITABLE_ROOT_0")) (require-not (subpath (param "WRITABLE_ROOT_0_RO_0"))) )
+)
+"#
+        );
+        let expected = SeatbeltCommand {
+            policy: expected_policy,
+            params: vec![
+                (
+                    "WRITABLE_ROOT_0".to_string(),
+                    root_without_git_canon.clone(),
+                ),
+                (
+                    "WRITABLE_ROOT_0_RO_0".to_string(),
+                    root_without_git_canon.join("node_modules"),
+                ),
+            ],
+            command: echo_hello(),
+        };
+        assert_eq!(expected, seatbelt);
+    }
+
+    #[test]
+    fn into_args_passes_params_as_defines_before_the_command() {
+        let seatbelt = SeatbeltCommand {
+            policy: "(version 1)".to_string(),
+            params: vec![
+                ("WRITABLE_ROOT_0".to_string(), PathBuf::from("/work")),
+                (
+                    "WRITABLE_ROOT_0_RO_0".to_string(),
+                    PathBuf::from("/work/.git"),
+                ),
+            ],
+            command: vec!["-n".to_string(), "x".to_string()],
+        };
+        assert_eq!(
+            seatbelt.into_args(),
+            [
+                "-p",
+                "(version 1)",
+                "-DWRITABLE_ROOT_0=/work",
+                "-DWRITABLE_ROOT_0_RO_0=/work/.git",
+                "--",
+                "-n",
+                "x",
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_base_policy_replaces_the_embedded_one() {
+        let base = "(version 1)\n(deny default)\n(allow process-exec)";
+        let (policy, params) =
+            build_seatbelt_policy(base, &SandboxPolicy::ReadOnly, Path::new("/"));
+        assert_eq!(
+            policy,
+            format!("{base}\n; allow read-only file operations\n(allow file-read*)\n\n")
+        );
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn load_seatbelt_base_policy_rejects_missing_and_empty_files() {
+        let tmp = TempDir::new().expect("tempdir");
+        let missing = tmp.path().join("missing.sbpl");
+        assert_eq!(
+            load_seatbelt_base_policy(&missing).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        let empty = tmp.path().join("empty.sbpl");
+        fs::write(&empty, "  \n").expect("write empty policy");
+        assert_eq!(
+            load_seatbelt_base_policy(&empty).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        let copy = tmp.path().join("base.sbpl");
+        fs::write(&copy, MACOS_SEATBELT_BASE_POLICY).expect("write base policy");
+        assert_eq!(
+            load_seatbelt_base_policy(&copy).expect("embedded policy is valid"),
+            MACOS_SEATBELT_BASE_POLICY
+        );
+    }
+
     struct PopulatedTmp {
         root_with_git: PathBuf,
         root_without_git: PathBuf,
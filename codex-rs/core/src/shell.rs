@@ -120,6 +120,31 @@ impl Shell {
         }
     }
 
+    /// Like [`Shell::format_default_shell_invocation`], but first runs the
+    /// `prelude` commands (joined with `&&`), so the command only runs if
+    /// they all succeed. Only POSIX shells get the prelude; the returned flag
+    /// says whether it was applied.
+    pub fn format_shell_invocation_with_prelude(
+        &self,
+        command: Vec<String>,
+        prelude: &[String],
+    ) -> Option<(Vec<String>, bool)> {
+        if prelude.is_empty() || !matches!(self, Shell::Posix(_)) {
+            return self
+                .format_default_shell_invocation(command)
+                .map(|command| (command, false));
+        }
+        let script = strip_bash_lc(&command)
+            .or_else(|| shlex::try_join(command.iter().map(|s| s.as_str())).ok())?;
+        let command = vec![
+            "bash".to_string(),
+            "-lc".to_string(),
+            format!("{} && ({script})", prelude.join(" && ")),
+        ];
+        self.format_default_shell_invocation(command)
+            .map(|command| (command, true))
+    }
+
     pub fn name(&self) -> Option<String> {
         match self {
             Shell::Posix(shell) => Path::new(&shell.shell_path)
@@ -409,6 +434,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn prelude_runs_before_the_command() {
+        let shell = Shell::Posix(PosixShell {
+            shell_path: "/bin/bash".to_string(),
+            rc_path: "/does/not/exist/.bashrc".to_string(),
+            shell_snapshot: None,
+        });
+        let prelude = vec![
+            "source .venv/bin/activate".to_string(),
+            "nvm use".to_string(),
+        ];
+        let actual = shell.format_shell_invocation_with_prelude(
+            vec!["echo".to_string(), "hi there".to_string()],
+            &prelude,
+        );
+        assert_eq!(
+            actual,
+            Some((
+                vec![
+                    "/bin/bash".to_string(),
+                    "-lc".to_string(),
+                    "[ -f /does/not/exist/.bashrc ] && . /does/not/exist/.bashrc; \
+                     (source .venv/bin/activate && nvm use && (echo 'hi there'))"
+                        .to_string(),
+                ],
+                true
+            ))
+        );
+
+        let without_prelude =
+            shell.format_shell_invocation_with_prelude(vec!["echo".to_string()], &[]);
+        assert_eq!(
+            without_prelude,
+            shell
+                .format_default_shell_invocation(vec!["echo".to_string()])
+                .map(|command| (command, false))
+        );
+    }
+
     #[tokio::test]
     async fn test_run_with_profile_bash_escaping_and_execution() {
         let shell_path = "/bin/bash";
@@ -476,13 +540,11 @@ mod tests {
                 ExecParams {
                     command: actual_cmd.unwrap(),
                     cwd: PathBuf::from(temp_home.path()),
-                    timeout_ms: None,
                     env: HashMap::from([(
                         "HOME".to_string(),
                         temp_home.path().to_str().unwrap().to_string(),
                     )]),
-                    with_escalated_permissions: None,
-                    justification: None,
+                    ..Default::default()
                 },
                 SandboxType::None,
                 &SandboxPolicy::DangerFullAccess,
@@ -670,13 +732,11 @@ mod macos_tests {
                 ExecParams {
                     command: actual_cmd.unwrap(),
                     cwd: PathBuf::from(temp_home.path()),
-                    timeout_ms: None,
                     env: HashMap::from([(
                         "HOME".to_string(),
                         temp_home.path().to_str().unwrap().to_string(),
                     )]),
-                    with_escalated_permissions: None,
-                    justification: None,
+                    ..Default::default()
                 },
                 SandboxType::None,
                 &SandboxPolicy::DangerFullAccess,
@@ -1,10 +1,22 @@
 use std::collections::HashMap;
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
+#[cfg(unix)]
+use std::os::fd::OwnedFd;
+#[cfg(unix)]
+use std::os::fd::RawFd;
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::io::AsyncRead;
 use tokio::process::Child;
 use tokio::process::Command;
 use tracing::trace;
+use tracing::warn;
 
+#[cfg(target_os = "linux")]
+use crate::flags::CODEX_RS_NO_PDEATHSIG;
 use crate::protocol::SandboxPolicy;
 
 /// Experimental environment variable that will be set to some non-empty value
@@ -22,10 +34,137 @@ pub const CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR: &str = "CODEX_SANDBOX_NETWORK_
 /// accommodate sandboxing configuration and other sandboxing mechanisms.
 pub const CODEX_SANDBOX_ENV_VAR: &str = "CODEX_SANDBOX";
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StdioPolicy {
     RedirectForShellTool,
     Inherit,
+    /// Same pipes as `RedirectForShellTool`, but the consumer of the child
+    /// is expected to echo everything it reads to the parent's stdout/stderr
+    /// so the output is both visible live and captured.
+    TeeToTerminal,
+    /// Same as `RedirectForShellTool`, but the child's stderr is made a
+    /// duplicate of its stdout (`2>&1`), so both are written to the stdout
+    /// pipe in the order the child wrote them. Unix only; elsewhere this
+    /// behaves like `RedirectForShellTool`.
+    CombinedForShellTool,
+    /// `TeeToTerminal` with stderr merged as in `CombinedForShellTool`.
+    TeeCombinedToTerminal,
+    /// For background processes that outlive the tool call: no stdio at all,
+    /// its own process group (so a Ctrl-C in Codex's terminal does not reach
+    /// it), and neither `kill_on_drop` nor the Linux parent-death signal.
+    Detached,
+    /// The child runs in its own session with a pseudo-terminal as its
+    /// controlling terminal and stdio, passed in as
+    /// [`InheritedFd::terminal`]. It writes stdout and stderr to the same
+    /// terminal, which the caller reads from the PTY master. Unix only.
+    Pty,
+}
+
+impl StdioPolicy {
+    pub fn tees_to_terminal(self) -> bool {
+        matches!(
+            self,
+            StdioPolicy::TeeToTerminal | StdioPolicy::TeeCombinedToTerminal
+        )
+    }
+
+    pub fn combines_stderr(self) -> bool {
+        matches!(
+            self,
+            StdioPolicy::CombinedForShellTool | StdioPolicy::TeeCombinedToTerminal
+        )
+    }
+}
+
+/// An open file descriptor (e.g. the read end of a pipe or a FIFO) passed to
+/// the child in addition to stdio, so a command can read input or write
+/// results without a temp file. Unix only; on other platforms none can be
+/// created.
+///
+/// Sandboxes may still restrict what the child can do with it: Landlock only
+/// checks paths when they are opened, so an inherited descriptor keeps the
+/// access it was opened with, but Seatbelt may deny reads or writes to a
+/// file outside the writable roots even through an inherited descriptor.
+#[derive(Debug, Clone)]
+pub struct InheritedFd {
+    #[cfg(unix)]
+    source: std::sync::Arc<OwnedFd>,
+    target: i32,
+}
+
+impl InheritedFd {
+    /// Passes `source` to the child as descriptor number `target`, which must
+    /// not be one of the stdio descriptors (0, 1 or 2).
+    #[cfg(unix)]
+    pub fn new(source: impl Into<OwnedFd>, target: RawFd) -> std::io::Result<Self> {
+        if target <= libc::STDERR_FILENO {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("cannot pass a file descriptor as stdio descriptor {target}"),
+            ));
+        }
+        Ok(Self {
+            source: std::sync::Arc::new(source.into()),
+            target,
+        })
+    }
+
+    /// Makes the slave side of a pseudo-terminal the child's stdin, stdout
+    /// and stderr; see [`StdioPolicy::Pty`].
+    #[cfg(unix)]
+    pub(crate) fn terminal(slave: OwnedFd) -> Vec<Self> {
+        let source = std::sync::Arc::new(slave);
+        (libc::STDIN_FILENO..=libc::STDERR_FILENO)
+            .map(|target| Self {
+                source: source.clone(),
+                target,
+            })
+            .collect()
+    }
+
+    /// Descriptor number the child sees.
+    pub fn target(&self) -> i32 {
+        self.target
+    }
+
+    /// Descriptor number in this process.
+    #[cfg(unix)]
+    pub(crate) fn source(&self) -> RawFd {
+        self.source.as_raw_fd()
+    }
+}
+
+type StdinReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// A stream fed to a command's stdin, e.g. another command's output, so
+/// commands can be piped together without a shell. It is copied to the
+/// child while the child's output is read, and stdin is closed once the
+/// stream ends (or the child stops reading). Only applies to commands whose
+/// output is captured, not to detached or PTY commands.
+///
+/// The stream is used up by the first command it is given to. Clones share
+/// it, so a command that finds it already used up gets an empty stdin.
+#[derive(Clone)]
+pub struct StdinSource {
+    reader: Arc<Mutex<Option<StdinReader>>>,
+}
+
+impl StdinSource {
+    pub fn new(reader: impl AsyncRead + Send + Unpin + 'static) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+        }
+    }
+
+    fn take(&self) -> Option<StdinReader> {
+        self.reader.lock().ok()?.take()
+    }
+}
+
+impl std::fmt::Debug for StdinSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdinSource").finish_non_exhaustive()
+    }
 }
 
 /// Spawns the appropriate child process for the ExecParams and SandboxPolicy,
@@ -35,6 +174,21 @@ pub enum StdioPolicy {
 /// For now, we take `SandboxPolicy` as a parameter to spawn_child() because
 /// we need to determine whether to set the
 /// `CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR` environment variable.
+///
+/// Each of `inherited_fds` is installed in the child as its target
+/// descriptor. Targets must be distinct and must not reuse the number of
+/// another entry's source descriptor.
+///
+/// Runs in a `spawn_child` debug span that records the command and, once
+/// spawned, the child's pid. Only the names of the environment variables are
+/// logged, since their values may be secrets.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    name = "spawn_child",
+    level = "debug",
+    skip_all,
+    fields(program = ?program, args = ?args, cwd = ?cwd, pid = tracing::field::Empty)
+)]
 pub(crate) async fn spawn_child_async(
     program: PathBuf,
     args: Vec<String>,
@@ -43,9 +197,15 @@ pub(crate) async fn spawn_child_async(
     sandbox_policy: &SandboxPolicy,
     stdio_policy: StdioPolicy,
     env: HashMap<String, String>,
+    #[cfg_attr(not(unix), allow(unused_variables))] inherited_fds: &[InheritedFd],
+    stdin_source: Option<StdinSource>,
 ) -> std::io::Result<Child> {
     trace!(
-        "spawn_child_async: {program:?} {args:?} {arg0:?} {cwd:?} {sandbox_policy:?} {stdio_policy:?} {env:?}"
+        ?arg0,
+        ?sandbox_policy,
+        ?stdio_policy,
+        env = ?env.keys().collect::<Vec<_>>(),
+        "spawning child"
     );
 
     let mut cmd = Command::new(&program);
@@ -64,34 +224,127 @@ pub(crate) async fn spawn_child_async(
     // any child processes that were spawned as part of a `"shell"` tool call
     // to also be terminated.
 
-    // This relies on prctl(2), so it only works on Linux.
+    // This relies on prctl(2), so it only works on Linux. It can be turned
+    // off with `CODEX_RS_NO_PDEATHSIG=1` for debugging.
     #[cfg(target_os = "linux")]
-    unsafe {
-        cmd.pre_exec(|| {
-            // This prctl call effectively requests, "deliver SIGTERM when my
-            // current parent dies."
-            if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) == -1 {
-                return Err(std::io::Error::last_os_error());
-            }
+    if stdio_policy != StdioPolicy::Detached && !*CODEX_RS_NO_PDEATHSIG {
+        unsafe {
+            cmd.pre_exec(|| {
+                // This prctl call effectively requests, "deliver SIGTERM when my
+                // current parent dies."
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
 
-            // Though if there was a race condition and this pre_exec() block is
-            // run _after_ the parent (i.e., the Codex process) has already
-            // exited, then the parent is the _init_ process (which will never
-            // die), so we should just terminate the child process now.
-            if libc::getppid() == 1 {
-                libc::raise(libc::SIGTERM);
-            }
-            Ok(())
-        });
+                // Though if there was a race condition and this pre_exec() block is
+                // run _after_ the parent (i.e., the Codex process) has already
+                // exited, then the parent is the _init_ process (which will never
+                // die), so we should just terminate the child process now.
+                if libc::getppid() == 1 {
+                    libc::raise(libc::SIGTERM);
+                }
+                Ok(())
+            });
+        }
+    }
+
+    // Here rather than in the sandboxes so it applies on every Unix platform
+    // and the sandbox helpers inherit it.
+    #[cfg(unix)]
+    if let Some(max_open_files) = sandbox_policy.resource_limits().max_open_files {
+        let rlimit = libc::rlimit {
+            rlim_cur: max_open_files as libc::rlim_t,
+            rlim_max: max_open_files as libc::rlim_t,
+        };
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::setrlimit(libc::RLIMIT_NOFILE, &rlimit) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    // Runs after the stdio pipes are installed in the child, so fd 1 is
+    // already the stdout pipe. The (still created) stderr pipe then simply
+    // never receives any data.
+    #[cfg(unix)]
+    if stdio_policy.combines_stderr() {
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::dup2(libc::STDOUT_FILENO, libc::STDERR_FILENO) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(unix)]
+    if !inherited_fds.is_empty() {
+        let fds: Vec<(RawFd, RawFd)> = inherited_fds
+            .iter()
+            .map(|fd| (fd.source.as_raw_fd(), fd.target))
+            .collect();
+        unsafe {
+            cmd.pre_exec(move || {
+                for &(source, target) in &fds {
+                    if source == target {
+                        // `dup2` would be a no-op, leaving close-on-exec set.
+                        let flags = libc::fcntl(target, libc::F_GETFD);
+                        if flags == -1
+                            || libc::fcntl(target, libc::F_SETFD, flags & !libc::FD_CLOEXEC) == -1
+                        {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    } else if libc::dup2(source, target) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    // After the descriptors above, so stdin is already the terminal.
+    #[cfg(unix)]
+    if stdio_policy == StdioPolicy::Pty {
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setsid() == -1
+                    || libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0) == -1
+                {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
     }
 
+    let stdin_source = stdin_source.filter(|_| {
+        matches!(
+            stdio_policy,
+            StdioPolicy::RedirectForShellTool
+                | StdioPolicy::TeeToTerminal
+                | StdioPolicy::CombinedForShellTool
+                | StdioPolicy::TeeCombinedToTerminal
+        )
+    });
     match stdio_policy {
-        StdioPolicy::RedirectForShellTool => {
-            // Do not create a file descriptor for stdin because otherwise some
-            // commands may hang forever waiting for input. For example, ripgrep has
-            // a heuristic where it may try to read from stdin as explained here:
-            // https://github.com/BurntSushi/ripgrep/blob/e2362d4d5185d02fa857bf381e7bd52e66fafc73/crates/core/flags/hiargs.rs#L1101-L1103
-            cmd.stdin(Stdio::null());
+        StdioPolicy::RedirectForShellTool
+        | StdioPolicy::TeeToTerminal
+        | StdioPolicy::CombinedForShellTool
+        | StdioPolicy::TeeCombinedToTerminal => {
+            if stdin_source.is_some() {
+                cmd.stdin(Stdio::piped());
+            } else {
+                // Do not create a file descriptor for stdin because otherwise some
+                // commands may hang forever waiting for input. For example, ripgrep has
+                // a heuristic where it may try to read from stdin as explained here:
+                // https://github.com/BurntSushi/ripgrep/blob/e2362d4d5185d02fa857bf381e7bd52e66fafc73/crates/core/flags/hiargs.rs#L1101-L1103
+                cmd.stdin(Stdio::null());
+            }
 
             cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
         }
@@ -101,7 +354,46 @@ pub(crate) async fn spawn_child_async(
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit());
         }
+        StdioPolicy::Detached => {
+            // Nobody reads from a detached child, so pipes would eventually
+            // fill up (or break) and stall it.
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            #[cfg(unix)]
+            cmd.process_group(0);
+        }
+        StdioPolicy::Pty => {
+            // Replaced with the terminal by `inherited_fds`.
+            cmd.stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+        }
     }
 
-    cmd.kill_on_drop(true).spawn()
+    let mut child = cmd
+        .kill_on_drop(stdio_policy != StdioPolicy::Detached)
+        .spawn()?;
+    if let Some(pid) = child.id() {
+        tracing::Span::current().record("pid", pid);
+    }
+    if let Some(stdin_source) = stdin_source
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        // Runs alongside whoever reads the output, so a child that only
+        // reads more input after writing output cannot deadlock. Dropping
+        // `stdin` at the end closes it.
+        tokio::spawn(async move {
+            let Some(mut reader) = stdin_source.take() else {
+                return;
+            };
+            match tokio::io::copy(&mut reader, &mut stdin).await {
+                Ok(_) => {}
+                // The child exited or closed its stdin before reading it all.
+                Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+                Err(err) => warn!("failed to feed stdin to the child: {err}"),
+            }
+        });
+    }
+    Ok(child)
 }
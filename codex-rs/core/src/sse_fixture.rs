@@ -0,0 +1,189 @@
+//! Recording Responses API streams and replaying them with their timing.
+//!
+//! A fixture holds one SSE line per line; the blank lines that end events
+//! are implied. A line may start with `@+<ms> `, how long after the previous
+//! line it arrived, and is then replayed after the same delay. Lines without
+//! it are replayed immediately, so untimed fixtures behave as they always
+//! have.
+//!
+//! With `CODEX_RS_SSE_RECORD` set to a directory, every response stream is
+//! recorded there as a timed fixture, which `CODEX_RS_SSE_FIXTURE` can then
+//! replay against the parser, e.g. to exercise the idle timeout with the
+//! cadence of a real session.
+
+use std::fs::File;
+use std::io::BufRead;
+use std::io::LineWriter;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use bytes::Bytes;
+use futures::prelude::*;
+use tracing::warn;
+
+use crate::error::CodexErr;
+use crate::error::Result;
+
+/// Prefix of a line's delay in a timed fixture.
+const DELAY_PREFIX: &str = "@+";
+
+/// Numbers the recordings of this process.
+static RECORDING_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// Splits a fixture line into its delay and the SSE line. A line without a
+/// well-formed delay is returned whole, with no delay.
+pub(crate) fn parse_fixture_line(line: &str) -> (Duration, &str) {
+    line.strip_prefix(DELAY_PREFIX)
+        .and_then(|rest| rest.split_once(' '))
+        .and_then(|(ms, rest)| Some((Duration::from_millis(ms.parse().ok()?), rest)))
+        .unwrap_or((Duration::ZERO, line))
+}
+
+/// Streams the fixture at `path` as SSE bytes, waiting out each line's delay
+/// before yielding it. Lines are read as the parser asks for them rather
+/// than all up front.
+pub(crate) fn fixture_stream(
+    path: impl AsRef<Path>,
+) -> std::io::Result<impl Stream<Item = Result<Bytes>> + Unpin + Send> {
+    let lines = std::io::BufReader::new(File::open(path)?).lines();
+    Ok(Box::pin(stream::iter(lines).then(|line| async move {
+        let line = line.map_err(CodexErr::Io)?;
+        let (delay, line) = parse_fixture_line(&line);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        // insert \n\n after each line for proper SSE parsing
+        Ok(Bytes::from(format!("{line}\n\n")))
+    })))
+}
+
+/// Passes `stream` through, recording it as a timed fixture in a new file
+/// in `dir` if one is given. A recording that fails is abandoned with a
+/// warning; the stream itself is unaffected.
+pub(crate) fn record_sse<S>(
+    stream: S,
+    dir: Option<&Path>,
+) -> impl Stream<Item = Result<Bytes>> + Unpin
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    let mut recorder = dir.and_then(|dir| match SseRecorder::create(dir) {
+        Ok(recorder) => Some(recorder),
+        Err(err) => {
+            warn!(
+                "failed to start recording the SSE stream in {}: {err}",
+                dir.display()
+            );
+            None
+        }
+    });
+    stream.inspect(move |chunk| {
+        if let (Some(active), Ok(chunk)) = (recorder.as_mut(), chunk)
+            && let Err(err) = active.record(chunk)
+        {
+            warn!("failed to record the SSE stream: {err}");
+            recorder = None;
+        }
+    })
+}
+
+/// Writes the lines of an SSE stream, as they complete, to a timed fixture.
+struct SseRecorder {
+    out: LineWriter<File>,
+    /// Bytes of a line that has not completed yet.
+    partial: Vec<u8>,
+    /// When the last line completed, or the recording started.
+    last_line_at: Instant,
+}
+
+impl SseRecorder {
+    fn create(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let seq = RECORDING_SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("responses-{}-{seq}.sse", std::process::id()));
+        Ok(Self::new(File::create(path)?))
+    }
+
+    fn new(file: File) -> Self {
+        Self {
+            out: LineWriter::new(file),
+            partial: Vec::new(),
+            last_line_at: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        let now = Instant::now();
+        self.partial.extend_from_slice(chunk);
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.partial.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                continue;
+            }
+            // Every line after the first of a chunk arrived with it.
+            let delay = now.saturating_duration_since(self.last_line_at);
+            self.last_line_at = now;
+            writeln!(self.out, "{DELAY_PREFIX}{} {line}", delay.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_timed_and_untimed_lines() {
+        assert_eq!(
+            (Duration::from_millis(250), "data: {}"),
+            parse_fixture_line("@+250 data: {}")
+        );
+        assert_eq!((Duration::ZERO, "data: {}"), parse_fixture_line("data: {}"));
+        assert_eq!(
+            (Duration::ZERO, "@+soon data: {}"),
+            parse_fixture_line("@+soon data: {}")
+        );
+    }
+
+    #[tokio::test]
+    async fn recordings_replay_as_the_original_lines() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("recording.sse");
+        let mut recorder = SseRecorder::new(File::create(&path).expect("create recording"));
+        recorder
+            .record(b"event: response.created\r\ndata: {\"a\"")
+            .expect("record");
+        recorder.record(b":1}\r\n\r\n").expect("record");
+        recorder
+            .record(b"event: response.completed\ndata: {}\n\n")
+            .expect("record");
+        drop(recorder);
+
+        let recorded = std::fs::read_to_string(&path).expect("read recording");
+        assert_eq!(4, recorded.lines().count());
+        assert!(recorded.lines().all(|line| line.starts_with(DELAY_PREFIX)));
+
+        let replayed: Vec<Bytes> = fixture_stream(&path)
+            .expect("fixture")
+            .try_collect()
+            .await
+            .expect("replay");
+        assert_eq!(
+            vec![
+                Bytes::from("event: response.created\n\n"),
+                Bytes::from("data: {\"a\":1}\n\n"),
+                Bytes::from("event: response.completed\n\n"),
+                Bytes::from("data: {}\n\n"),
+            ],
+            replayed
+        );
+    }
+}
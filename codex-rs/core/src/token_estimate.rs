@@ -0,0 +1,196 @@
+//! Offline token count estimates for budgeting against a model's context
+//! window (see `ModelInfo::context_window`).
+//!
+//! We do not ship BPE vocabularies, so counts are approximate. For models we
+//! know to use one of OpenAI's byte-level BPE tokenizers (see [`Tokenizer`]),
+//! text is split the way their pre-tokenizer splits it (words with their
+//! leading space, digit groups of at most three, punctuation runs,
+//! whitespace) and each piece is costed by length, which tracks real counts
+//! far better than a flat characters-per-token ratio on code and non-English
+//! text. Everything else falls back to [`CHARS_PER_TOKEN_FALLBACK`]
+//! characters per token.
+
+use crate::model_family::find_family_for_model;
+use crate::openai_model_info::get_model_info;
+
+/// Rule of thumb for English text with an unknown tokenizer.
+pub const CHARS_PER_TOKEN_FALLBACK: usize = 4;
+
+/// ASCII letters that typically fit in a single BPE token.
+const ASCII_LETTERS_PER_TOKEN: usize = 6;
+
+/// UTF-8 bytes per token for non-ASCII text with `o200k_base`; most CJK
+/// characters are three bytes and usually a token each.
+const O200K_NON_ASCII_BYTES_PER_TOKEN: usize = 3;
+
+/// `cl100k_base` has far fewer non-English merges, so a CJK character often
+/// takes two tokens.
+const CL100K_NON_ASCII_BYTES_PER_TOKEN: usize = 2;
+
+/// Maximum number of digits the pre-tokenizer groups into one piece.
+const MAX_DIGITS_PER_PIECE: usize = 3;
+
+/// The BPE vocabulary a model tokenizes its input with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// `cl100k_base`: gpt-4 and gpt-3.5-turbo.
+    Cl100kBase,
+    /// `o200k_base`: gpt-4o, gpt-4.1, gpt-5 and the o-series.
+    O200kBase,
+    /// Not one we know; estimated with [`CHARS_PER_TOKEN_FALLBACK`].
+    Unknown,
+}
+
+impl Tokenizer {
+    /// The tokenizer used by `model`, or [`Tokenizer::Unknown`] for models
+    /// that are not OpenAI models we know about.
+    pub fn for_model(model: &str) -> Self {
+        find_family_for_model(model)
+            .and_then(|family| get_model_info(&family))
+            .map_or(Tokenizer::Unknown, |found| found.info().tokenizer)
+    }
+}
+
+/// Estimates how many tokens `text` occupies for `model`. Never returns 0 for
+/// non-empty text.
+pub fn estimate_tokens(model: &str, text: &str) -> usize {
+    estimate_tokens_with(Tokenizer::for_model(model), text)
+}
+
+/// Like [`estimate_tokens`], for a known tokenizer.
+pub fn estimate_tokens_with(tokenizer: Tokenizer, text: &str) -> usize {
+    if text.is_empty() {
+        return 0;
+    }
+    let non_ascii_bytes_per_token = match tokenizer {
+        Tokenizer::O200kBase => O200K_NON_ASCII_BYTES_PER_TOKEN,
+        Tokenizer::Cl100kBase => CL100K_NON_ASCII_BYTES_PER_TOKEN,
+        // A plain character ratio is the best we can do.
+        Tokenizer::Unknown => return text.chars().count().div_ceil(CHARS_PER_TOKEN_FALLBACK),
+    };
+    estimate_bpe_tokens(text, non_ascii_bytes_per_token)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Letter,
+    Digit,
+    Whitespace,
+    Other,
+}
+
+fn classify(c: char) -> CharClass {
+    if c.is_alphabetic() || c == '\'' {
+        CharClass::Letter
+    } else if c.is_numeric() {
+        CharClass::Digit
+    } else if c.is_whitespace() {
+        CharClass::Whitespace
+    } else {
+        CharClass::Other
+    }
+}
+
+fn estimate_bpe_tokens(text: &str, non_ascii_bytes_per_token: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let mut start = i;
+        // A single space is merged into the word or punctuation run after it.
+        if chars[i] == ' '
+            && chars
+                .get(i + 1)
+                .is_some_and(|next| matches!(classify(*next), CharClass::Letter | CharClass::Other))
+        {
+            i += 1;
+            start = i;
+        }
+        let class = classify(chars[start]);
+        let mut end = start + 1;
+        while end < chars.len() && classify(chars[end]) == class {
+            if class == CharClass::Digit && end - start == MAX_DIGITS_PER_PIECE {
+                break;
+            }
+            end += 1;
+        }
+        tokens += piece_cost(class, &chars[start..end], non_ascii_bytes_per_token);
+        i = end;
+    }
+    tokens
+}
+
+fn piece_cost(class: CharClass, piece: &[char], non_ascii_bytes_per_token: usize) -> usize {
+    match class {
+        CharClass::Letter => {
+            let non_ascii_bytes: usize = piece
+                .iter()
+                .filter(|c| !c.is_ascii())
+                .map(|c| c.len_utf8())
+                .sum();
+            let ascii = piece.iter().filter(|c| c.is_ascii()).count();
+            (ascii.div_ceil(ASCII_LETTERS_PER_TOKEN)
+                + non_ascii_bytes.div_ceil(non_ascii_bytes_per_token))
+            .max(1)
+        }
+        CharClass::Digit => 1,
+        // Runs of spaces/newlines usually collapse into a single token.
+        CharClass::Whitespace => 1,
+        // Common operators like `->` or `::` are single tokens; longer runs of
+        // symbols split roughly in pairs.
+        CharClass::Other => piece.len().div_ceil(2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn empty_text_has_no_tokens() {
+        assert_eq!(0, estimate_tokens("gpt-4o", ""));
+        assert_eq!(0, estimate_tokens("llama3", ""));
+    }
+
+    #[test]
+    fn english_words_are_about_one_token_each() {
+        // "Hello", " world", "!" per the OpenAI tokenizers.
+        assert_eq!(3, estimate_tokens("gpt-4o", "Hello world!"));
+        assert_eq!(
+            9,
+            estimate_tokens("o3", "The quick brown fox jumps over the lazy dog")
+        );
+    }
+
+    #[test]
+    fn digits_are_grouped_in_threes() {
+        assert_eq!(3, estimate_tokens("gpt-4.1", "1234567"));
+    }
+
+    #[test]
+    fn models_map_to_their_tokenizer() {
+        assert_eq!(Tokenizer::O200kBase, Tokenizer::for_model("gpt-4o"));
+        assert_eq!(Tokenizer::O200kBase, Tokenizer::for_model("o3"));
+        assert_eq!(Tokenizer::Cl100kBase, Tokenizer::for_model("gpt-3.5-turbo"));
+        assert_eq!(Tokenizer::Unknown, Tokenizer::for_model("llama3"));
+    }
+
+    #[test]
+    fn cl100k_spends_more_tokens_on_cjk_text() {
+        let text = "你好世界";
+        assert_eq!(4, estimate_tokens_with(Tokenizer::O200kBase, text));
+        assert_eq!(6, estimate_tokens_with(Tokenizer::Cl100kBase, text));
+        // English is about the same with both.
+        assert_eq!(
+            estimate_tokens_with(Tokenizer::O200kBase, "Hello world!"),
+            estimate_tokens_with(Tokenizer::Cl100kBase, "Hello world!")
+        );
+    }
+
+    #[test]
+    fn unknown_models_use_chars_per_token() {
+        assert_eq!(3, estimate_tokens("llama3", "Hello world!"));
+        assert_eq!(1, estimate_tokens("llama3", "a"));
+    }
+}
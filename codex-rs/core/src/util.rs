@@ -1,13 +1,168 @@
 use std::time::Duration;
+use std::time::Instant;
 
+use chrono::DateTime;
+use chrono::Utc;
 use rand::Rng;
 
-const INITIAL_DELAY_MS: u64 = 200;
+pub(crate) const DEFAULT_RETRY_INITIAL_DELAY_MS: u64 = 200;
+pub(crate) const DEFAULT_RETRY_MAX_DELAY_MS: u64 = 60_000;
 const BACKOFF_FACTOR: f64 = 2.0;
 
-pub(crate) fn backoff(attempt: u64) -> Duration {
-    let exp = BACKOFF_FACTOR.powi(attempt.saturating_sub(1) as i32);
-    let base = (INITIAL_DELAY_MS as f64 * exp) as u64;
-    let jitter = rand::rng().random_range(0.9..1.1);
-    Duration::from_millis((base as f64 * jitter) as u64)
+/// Exponential backoff for one sequence of retries (of a request, or of a
+/// dropped stream), configured per provider via `retry_initial_delay_ms`,
+/// `retry_max_delay_ms` and `retry_max_elapsed_ms`.
+#[derive(Debug, Clone)]
+pub(crate) struct RetryBackoff {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_elapsed: Option<Duration>,
+    first_failure: Option<Instant>,
+}
+
+impl RetryBackoff {
+    pub(crate) fn new(
+        initial_delay: Duration,
+        max_delay: Duration,
+        max_elapsed: Option<Duration>,
+    ) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            max_elapsed,
+            first_failure: None,
+        }
+    }
+
+    /// Delay before retry number `attempt` (1-based): `initial_delay` doubled
+    /// per attempt and capped at `max_delay`, of which a random half is
+    /// dropped so that clients failing at the same moment spread out their
+    /// retries instead of hitting the provider in lockstep.
+    pub(crate) fn delay(&self, attempt: u64) -> Duration {
+        // Cap before converting back: the uncapped value overflows `Duration`
+        // after a few dozen attempts.
+        let exp = BACKOFF_FACTOR.powi(attempt.saturating_sub(1).min(64) as i32);
+        let capped_ms =
+            (self.initial_delay.as_millis() as f64 * exp).min(self.max_delay.as_millis() as f64);
+        let capped = Duration::from_millis(capped_ms as u64);
+        let half = capped / 2;
+        half + half.mul_f64(rand::rng().random_range(0.0..=1.0))
+    }
+
+    /// Clamps a server-requested wait (e.g. from `Retry-After`) to
+    /// `max_delay`, so a misbehaving provider cannot stall us indefinitely.
+    pub(crate) fn cap(&self, delay: Duration) -> Duration {
+        delay.min(self.max_delay)
+    }
+
+    /// Records a failure and reports whether waiting `delay` before the next
+    /// attempt keeps the whole sequence, measured from its first failure,
+    /// within `max_elapsed`.
+    pub(crate) fn may_retry_after(&mut self, delay: Duration) -> bool {
+        let first_failure = *self.first_failure.get_or_insert_with(Instant::now);
+        match self.max_elapsed {
+            Some(max_elapsed) => first_failure.elapsed() + delay <= max_elapsed,
+            None => true,
+        }
+    }
+}
+
+/// Parses a `Retry-After` header value, which is either a number of seconds
+/// or an HTTP-date (RFC 9110 §10.2.3). Dates in the past yield a zero wait.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    parse_retry_after_at(value, Utc::now())
+}
+
+fn parse_retry_after_at(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    // IMF-fixdate ("Sun, 06 Nov 1994 08:49:37 GMT") is valid RFC 2822.
+    let at = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+impl Default for RetryBackoff {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_millis(DEFAULT_RETRY_INITIAL_DELAY_MS),
+            Duration::from_millis(DEFAULT_RETRY_MAX_DELAY_MS),
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn delay_grows_exponentially_with_jitter() {
+        let backoff = RetryBackoff::default();
+        for attempt in 1..=4 {
+            let full = Duration::from_millis(DEFAULT_RETRY_INITIAL_DELAY_MS * (1 << (attempt - 1)));
+            let delay = backoff.delay(attempt);
+            assert!(
+                delay >= full / 2 && delay <= full,
+                "attempt {attempt}: {delay:?} not within [{:?}, {full:?}]",
+                full / 2
+            );
+        }
+    }
+
+    #[test]
+    fn delay_is_capped_at_max_delay() {
+        let backoff =
+            RetryBackoff::new(Duration::from_millis(100), Duration::from_millis(500), None);
+        assert!(backoff.delay(10) <= Duration::from_millis(500));
+        assert!(backoff.delay(u64::MAX) <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn cap_clamps_server_requested_delay() {
+        let backoff = RetryBackoff::new(Duration::from_millis(100), Duration::from_secs(5), None);
+        assert_eq!(backoff.cap(Duration::from_secs(2)), Duration::from_secs(2));
+        assert_eq!(
+            backoff.cap(Duration::from_secs(3600)),
+            Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn retry_after_accepts_delta_seconds_and_http_dates() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after_at("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after_at("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after_at("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after_at("soon", now), None);
+    }
+
+    #[test]
+    fn max_elapsed_bounds_the_retry_sequence() {
+        let mut backoff = RetryBackoff::new(
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+            Some(Duration::from_secs(1)),
+        );
+        assert!(backoff.may_retry_after(Duration::from_millis(500)));
+        assert!(!backoff.may_retry_after(Duration::from_secs(2)));
+
+        let mut unbounded = RetryBackoff::default();
+        assert!(unbounded.may_retry_after(Duration::from_secs(3600)));
+    }
 }
@@ -10,7 +10,9 @@ use codex_core::Prompt;
 use codex_core::ReasoningItemContent;
 use codex_core::ResponseItem;
 use codex_core::WireApi;
+use codex_core::model_family::find_family_for_model;
 use codex_core::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use codex_protocol::config_types::ReasoningEffort;
 use codex_protocol::mcp_protocol::ConversationId;
 use core_test_support::load_default_config_for_test;
 use futures::StreamExt;
@@ -27,6 +29,16 @@ fn network_disabled() -> bool {
 }
 
 async fn run_request(input: Vec<ResponseItem>) -> Value {
+    run_request_with(input, None, |_| {}).await
+}
+
+/// Like [`run_request`], optionally switching to `model` and adjusting the
+/// provider before the request is made.
+async fn run_request_with(
+    input: Vec<ResponseItem>,
+    model: Option<&str>,
+    configure_provider: impl FnOnce(&mut ModelProviderInfo),
+) -> Value {
     let server = MockServer::start().await;
 
     let template = ResponseTemplate::new(200)
@@ -43,7 +55,7 @@ async fn run_request(input: Vec<ResponseItem>) -> Value {
         .mount(&server)
         .await;
 
-    let provider = ModelProviderInfo {
+    let mut provider = ModelProviderInfo {
         name: "mock".into(),
         base_url: Some(format!("{}/v1", server.uri())),
         env_key: None,
@@ -56,7 +68,9 @@ async fn run_request(input: Vec<ResponseItem>) -> Value {
         stream_max_retries: Some(0),
         stream_idle_timeout_ms: Some(5_000),
         requires_openai_auth: false,
+        ..Default::default()
     };
+    configure_provider(&mut provider);
 
     let codex_home = match TempDir::new() {
         Ok(dir) => dir,
@@ -66,6 +80,13 @@ async fn run_request(input: Vec<ResponseItem>) -> Value {
     config.model_provider_id = provider.name.clone();
     config.model_provider = provider.clone();
     config.show_raw_agent_reasoning = true;
+    if let Some(model) = model {
+        config.model = model.to_string();
+        config.model_family = match find_family_for_model(model) {
+            Some(family) => family,
+            None => panic!("unknown model {model}"),
+        };
+    }
     let effort = config.model_reasoning_effort;
     let summary = config.model_reasoning_summary;
     let config = Arc::new(config);
@@ -343,3 +364,76 @@ async fn suppresses_duplicate_assistant_messages() {
         Value::String("dup".into())
     );
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sends_reasoning_effort_only_to_reasoning_models() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let set_high = |provider: &mut ModelProviderInfo| {
+        provider.reasoning_effort = Some(ReasoningEffort::High);
+    };
+
+    let body = run_request_with(vec![user_message("u1")], Some("o3"), set_high).await;
+    assert_eq!(body["reasoning_effort"], Value::String("high".into()));
+
+    let body = run_request_with(vec![user_message("u1")], Some("gpt-4o"), set_high).await;
+    assert!(body.get("reasoning_effort").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sends_temperature_only_to_models_that_accept_it() {
+    let body = run_request_with(vec![user_message("u1")], Some("o3"), |_| {}).await;
+    assert!(body.get("temperature").is_none());
+    assert!(body.get("top_p").is_none());
+
+    let body = run_request_with(vec![user_message("u1")], Some("gpt-4o"), |_| {}).await;
+    assert_eq!(body["temperature"], serde_json::json!(1.0));
+    assert!(body.get("top_p").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn sends_mapped_model_id_but_keeps_the_configured_models_capabilities() {
+    let map_o3 = |provider: &mut ModelProviderInfo| {
+        provider.model_map = Some(
+            [("o3".to_string(), "o3-deployment".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        provider.reasoning_effort = Some(ReasoningEffort::High);
+    };
+
+    let body = run_request_with(vec![user_message("u1")], Some("o3"), map_o3).await;
+    assert_eq!(body["model"], Value::String("o3-deployment".into()));
+    // Still treated as o3, not as an unknown model.
+    assert_eq!(body["reasoning_effort"], Value::String("high".into()));
+
+    let body = run_request_with(vec![user_message("u1")], Some("gpt-4o"), map_o3).await;
+    assert_eq!(body["model"], Value::String("gpt-4o".into()));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn requests_usage_only_when_the_provider_opts_in() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let body = run_request(vec![user_message("u1")]).await;
+    assert!(body.get("stream_options").is_none());
+
+    let body = run_request_with(vec![user_message("u1")], None, |provider| {
+        provider.stream_include_usage = true;
+    })
+    .await;
+    assert_eq!(
+        body["stream_options"],
+        serde_json::json!({"include_usage": true})
+    );
+}
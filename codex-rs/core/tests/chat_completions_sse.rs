@@ -23,6 +23,14 @@ fn network_disabled() -> bool {
 }
 
 async fn run_stream(sse_body: &str) -> Vec<ResponseEvent> {
+    run_stream_with(sse_body, |_| {}).await
+}
+
+/// Like [`run_stream`], adjusting the provider before the request is made.
+async fn run_stream_with(
+    sse_body: &str,
+    configure_provider: impl FnOnce(&mut ModelProviderInfo),
+) -> Vec<ResponseEvent> {
     let server = MockServer::start().await;
 
     let template = ResponseTemplate::new(200)
@@ -36,7 +44,7 @@ async fn run_stream(sse_body: &str) -> Vec<ResponseEvent> {
         .mount(&server)
         .await;
 
-    let provider = ModelProviderInfo {
+    let mut provider = ModelProviderInfo {
         name: "mock".into(),
         base_url: Some(format!("{}/v1", server.uri())),
         env_key: None,
@@ -49,7 +57,9 @@ async fn run_stream(sse_body: &str) -> Vec<ResponseEvent> {
         stream_max_retries: Some(0),
         stream_idle_timeout_ms: Some(5_000),
         requires_openai_auth: false,
+        ..Default::default()
     };
+    configure_provider(&mut provider);
 
     let codex_home = match TempDir::new() {
         Ok(dir) => dir,
@@ -318,3 +328,212 @@ async fn streams_reasoning_before_tool_call() {
 
     assert!(matches!(events[3], ResponseEvent::Completed { .. }));
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn reassembles_tool_calls_streamed_in_fragments() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    // Two parallel calls whose arguments arrive interleaved, told apart only
+    // by `index` after their first fragment.
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_a\",\"type\":\"function\",\"function\":{\"name\":\"shell\",\"arguments\":\"\"}}]}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"command\\\":\"}}]}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":1,\"id\":\"call_b\",\"type\":\"function\",\"function\":{\"name\":\"update_plan\",\"arguments\":\"{\\\"plan\\\"\"}}]}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"[\\\"ls\\\"]}\"}},{\"index\":1,\"function\":{\"arguments\":\":[]}\"}}]}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+    );
+
+    let events = run_stream(sse).await;
+    assert_eq!(events.len(), 3, "unexpected events: {events:?}");
+
+    let calls: Vec<(&str, &str, &str)> = events[..2]
+        .iter()
+        .map(|event| match event {
+            ResponseEvent::OutputItemDone(ResponseItem::FunctionCall {
+                name,
+                arguments,
+                call_id,
+                ..
+            }) => (call_id.as_str(), name.as_str(), arguments.as_str()),
+            other => panic!("expected function call, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        calls,
+        vec![
+            ("call_a", "shell", "{\"command\":[\"ls\"]}"),
+            ("call_b", "update_plan", "{\"plan\":[]}"),
+        ]
+    );
+    assert!(matches!(events[2], ResponseEvent::Completed { .. }));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn tool_call_is_emitted_when_stream_ends_without_finish_reason() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"id\":\"call_1\",\"function\":{\"name\":\"run\",\"arguments\":\"{\\\"a\\\"\"}}]}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"function\":{\"arguments\":\":1}\"}}]}}]}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    let events = run_stream(sse).await;
+    assert_eq!(events.len(), 2, "unexpected events: {events:?}");
+    match &events[0] {
+        ResponseEvent::OutputItemDone(ResponseItem::FunctionCall {
+            name,
+            arguments,
+            call_id,
+            ..
+        }) => {
+            assert_eq!(name, "run");
+            assert_eq!(arguments, "{\"a\":1}");
+            assert_eq!(call_id, "call_1");
+        }
+        other => panic!("expected function call, got {other:?}"),
+    }
+    assert!(matches!(events[1], ResponseEvent::Completed { .. }));
+}
+
+async fn assert_text_stream_terminated(sse: &str) {
+    let events = run_stream(sse).await;
+    assert_eq!(events.len(), 3, "unexpected events: {events:?}");
+
+    match &events[0] {
+        ResponseEvent::OutputTextDelta(text) => assert_eq!(text, "hi"),
+        other => panic!("expected text delta, got {other:?}"),
+    }
+
+    match &events[1] {
+        ResponseEvent::OutputItemDone(item) => assert_message(item, "hi"),
+        other => panic!("expected terminal message, got {other:?}"),
+    }
+
+    assert!(matches!(events[2], ResponseEvent::Completed { .. }));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn stream_without_done_sentinel_completes() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n",
+        "data: {\"choices\":[{\"delta\":{}}]}\n\n",
+    );
+
+    assert_text_stream_terminated(sse).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn done_sentinel_without_space_completes() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n",
+        "data:[DONE]\n\n",
+    );
+
+    assert_text_stream_terminated(sse).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn lowercase_padded_done_sentinel_completes() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n",
+        "data:  [done]  \n\n",
+    );
+
+    assert_text_stream_terminated(sse).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn events_after_done_sentinel_are_ignored() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n",
+        "data: [DONE]\n\n",
+        "data: {\"choices\":[{\"delta\":{\"content\":\"late\"}}]}\n\n",
+    );
+
+    assert_text_stream_terminated(sse).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn usage_chunk_after_finish_reason_is_reported_on_completed() {
+    if network_disabled() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let sse = concat!(
+        "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}],\"usage\":null}\n\n",
+        "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":null}\n\n",
+        "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":3,\"total_tokens\":15,",
+        "\"prompt_tokens_details\":{\"cached_tokens\":8}}}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    let events = run_stream_with(sse, |provider| provider.stream_include_usage = true).await;
+    assert_eq!(events.len(), 3, "unexpected events: {events:?}");
+    match &events[1] {
+        ResponseEvent::OutputItemDone(item) => assert_message(item, "hi"),
+        other => panic!("expected terminal message, got {other:?}"),
+    }
+    let ResponseEvent::Completed {
+        token_usage: Some(usage),
+        ..
+    } = &events[2]
+    else {
+        panic!("expected completed with usage, got {:?}", events[2]);
+    };
+    assert_eq!(usage.input_tokens, 12);
+    assert_eq!(usage.cached_input_tokens, 8);
+    assert_eq!(usage.output_tokens, 3);
+    assert_eq!(usage.total_tokens, 15);
+
+    // Without `stream_include_usage` the turn completes at `finish_reason`.
+    let events = run_stream(sse).await;
+    assert!(matches!(
+        events.last(),
+        Some(ResponseEvent::Completed {
+            token_usage: None,
+            ..
+        })
+    ));
+}
@@ -1,5 +1,7 @@
 use assert_cmd::Command as AssertCommand;
 use codex_core::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 use tempfile::TempDir;
@@ -7,6 +9,8 @@ use uuid::Uuid;
 use walkdir::WalkDir;
 use wiremock::Mock;
 use wiremock::MockServer;
+use wiremock::Request;
+use wiremock::Respond;
 use wiremock::ResponseTemplate;
 use wiremock::matchers::method;
 use wiremock::matchers::path;
@@ -82,6 +86,145 @@ async fn chat_mode_stream_cli() {
 /// Verify that passing `-c experimental_instructions_file=...` to the CLI
 /// overrides the built-in base instructions by inspecting the request body
 /// received by a mock OpenAI Responses endpoint.
+/// Answers the first chat request with `status` and a `Retry-After` value
+/// built when that request arrives, and every later one with a short successful stream, recording when
+/// each request arrived.
+struct RetryAfterResponder {
+    status: u16,
+    retry_after: fn() -> String,
+    arrivals: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl Respond for RetryAfterResponder {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        let mut arrivals = self.arrivals.lock().unwrap();
+        arrivals.push(Instant::now());
+        if arrivals.len() == 1 {
+            return ResponseTemplate::new(self.status)
+                .insert_header("retry-after", (self.retry_after)().as_str());
+        }
+        let sse = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{}}]}\n\n",
+            "data: [DONE]\n\n"
+        );
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "text/event-stream")
+            .set_body_raw(sse, "text/event-stream")
+    }
+}
+
+/// Runs `codex exec` against a chat provider whose first response is `status`
+/// with a `Retry-After` header built by `retry_after`, and returns how long
+/// codex waited before retrying.
+async fn wait_after_retry_after(
+    status: u16,
+    retry_after: fn() -> String,
+    max_delay_ms: u64,
+) -> Duration {
+    let server = MockServer::start().await;
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(RetryAfterResponder {
+            status,
+            retry_after,
+            arrivals: arrivals.clone(),
+        })
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let home = TempDir::new().unwrap();
+    let provider_override = format!(
+        "model_providers.mock={{ name = \"mock\", base_url = \"{}/v1\", env_key = \"PATH\", wire_api = \"chat\", request_max_retries = 1, retry_max_delay_ms = {max_delay_ms} }}",
+        server.uri()
+    );
+    let mut cmd = AssertCommand::new("cargo");
+    cmd.arg("run")
+        .arg("-p")
+        .arg("codex-cli")
+        .arg("--quiet")
+        .arg("--")
+        .arg("exec")
+        .arg("--skip-git-repo-check")
+        .arg("-c")
+        .arg(&provider_override)
+        .arg("-c")
+        .arg("model_provider=\"mock\"")
+        .arg("-C")
+        .arg(env!("CARGO_MANIFEST_DIR"))
+        .arg("hello?");
+    cmd.env("CODEX_HOME", home.path())
+        .env("OPENAI_API_KEY", "dummy");
+
+    let output = cmd.output().unwrap();
+    println!("Stderr:\n{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.status.success());
+    server.verify().await;
+
+    let arrivals = arrivals.lock().unwrap();
+    arrivals[1].duration_since(arrivals[0])
+}
+
+/// A 429 with `Retry-After: <seconds>` delays the retry by that long instead
+/// of the (much shorter) default backoff.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn chat_retry_honors_retry_after_seconds() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let waited = wait_after_retry_after(429, || "2".to_string(), 60_000).await;
+    assert!(
+        waited >= Duration::from_millis(1_900) && waited < Duration::from_secs(5),
+        "waited {waited:?}"
+    );
+}
+
+/// A 503 with an HTTP-date `Retry-After` waits until that date.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn chat_retry_honors_retry_after_http_date() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    // HTTP-dates have one-second resolution, so a date 3s out means a wait
+    // of somewhere between 2s and 3s.
+    let retry_after = || {
+        let retry_at = chrono::Utc::now() + chrono::Duration::seconds(3);
+        retry_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    };
+    let waited = wait_after_retry_after(503, retry_after, 60_000).await;
+    assert!(
+        waited >= Duration::from_millis(1_500) && waited < Duration::from_secs(5),
+        "waited {waited:?}"
+    );
+}
+
+/// Server-requested waits are capped by `retry_max_delay_ms`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn chat_retry_after_is_capped_by_max_delay() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let waited = wait_after_retry_after(429, || "3600".to_string(), 1_000).await;
+    assert!(
+        waited >= Duration::from_millis(900) && waited < Duration::from_secs(5),
+        "waited {waited:?}"
+    );
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn exec_cli_applies_experimental_instructions_file() {
     if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
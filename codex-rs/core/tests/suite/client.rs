@@ -241,6 +241,133 @@ async fn resume_includes_initial_messages_and_sends_prior_items() {
     assert_eq!(request_body["input"], expected_input);
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn resume_from_branches_into_new_rollout() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    // Parent rollout with two user/assistant exchanges.
+    let tmpdir = TempDir::new().unwrap();
+    let session_path = tmpdir.path().join("parent-session.jsonl");
+    let parent_id = Uuid::new_v4();
+    let mut f = std::fs::File::create(&session_path).unwrap();
+    writeln!(
+        f,
+        "{}",
+        json!({"id": parent_id, "timestamp": "2024-01-01T00:00:00Z"})
+    )
+    .unwrap();
+    for (role, text) in [
+        ("user", "first question"),
+        ("assistant", "first answer"),
+        ("user", "second question"),
+        ("assistant", "bad answer"),
+    ] {
+        let content = if role == "user" {
+            codex_protocol::models::ContentItem::InputText {
+                text: text.to_string(),
+            }
+        } else {
+            codex_protocol::models::ContentItem::OutputText {
+                text: text.to_string(),
+            }
+        };
+        let item = codex_protocol::models::ResponseItem::Message {
+            id: None,
+            role: role.to_string(),
+            content: vec![content],
+        };
+        writeln!(f, "{}", serde_json::to_string(&item).unwrap()).unwrap();
+    }
+    drop(f);
+    let parent_contents = std::fs::read_to_string(&session_path).unwrap();
+
+    let server = MockServer::start().await;
+    let first = ResponseTemplate::new(200)
+        .insert_header("content-type", "text/event-stream")
+        .set_body_raw(sse_completed("resp1"), "text/event-stream");
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(first)
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model_provider = ModelProviderInfo {
+        base_url: Some(format!("{}/v1", server.uri())),
+        ..built_in_model_providers()["openai"].clone()
+    };
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = model_provider;
+
+    let conversation_manager =
+        ConversationManager::with_auth(CodexAuth::from_api_key("Test API Key"));
+    let NewConversation {
+        conversation: codex,
+        conversation_id,
+        session_configured,
+    } = conversation_manager
+        .resume_from(config, session_path.clone(), 2)
+        .await
+        .expect("branch conversation");
+    assert_ne!(conversation_id.to_string(), parent_id.to_string());
+    assert_ne!(session_configured.rollout_path, session_path);
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "second question, again".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    wait_for_event(&codex, |ev| matches!(ev, EventMsg::TaskComplete(_))).await;
+
+    // Only the items before the branch point are replayed.
+    let request = &server.received_requests().await.unwrap()[0];
+    let request_body = request.body_json::<serde_json::Value>().unwrap();
+    let expected_input = json!([
+        {
+            "type": "message",
+            "role": "user",
+            "content": [{ "type": "input_text", "text": "first question" }]
+        },
+        {
+            "type": "message",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": "first answer" }]
+        },
+        {
+            "type": "message",
+            "role": "user",
+            "content": [{ "type": "input_text", "text": "second question, again" }]
+        }
+    ]);
+    assert_eq!(request_body["input"], expected_input);
+
+    // The parent is untouched and the branch's meta line points back at it.
+    assert_eq!(
+        std::fs::read_to_string(&session_path).unwrap(),
+        parent_contents
+    );
+    let branch_contents = std::fs::read_to_string(&session_configured.rollout_path).unwrap();
+    let branch_meta: serde_json::Value =
+        serde_json::from_str(branch_contents.lines().next().unwrap()).unwrap();
+    assert_eq!(
+        branch_meta["parent"],
+        json!({
+            "conversation_id": parent_id,
+            "path": session_path,
+            "item_index": 2,
+        })
+    );
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn includes_conversation_id_and_model_headers_in_request() {
     if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
@@ -763,6 +890,7 @@ async fn azure_overrides_assign_properties_used_for_responses_url() {
         stream_max_retries: None,
         stream_idle_timeout_ms: None,
         requires_openai_auth: false,
+        ..Default::default()
     };
 
     // Init session
@@ -839,6 +967,7 @@ async fn env_var_overrides_loaded_auth() {
         stream_max_retries: None,
         stream_idle_timeout_ms: None,
         requires_openai_auth: false,
+        ..Default::default()
     };
 
     // Init session
@@ -1,10 +1,11 @@
 #![cfg(target_os = "macos")]
 
-use std::collections::HashMap;
+use std::time::Duration;
 
 use codex_core::exec::ExecParams;
 use codex_core::exec::ExecToolCallOutput;
 use codex_core::exec::SandboxType;
+use codex_core::exec::Timeout;
 use codex_core::exec::process_exec_tool_call;
 use codex_core::protocol::SandboxPolicy;
 use codex_core::spawn::CODEX_SANDBOX_ENV_VAR;
@@ -31,10 +32,8 @@ async fn run_test_cmd(tmp: TempDir, cmd: Vec<&str>) -> Result<ExecToolCallOutput
     let params = ExecParams {
         command: cmd.iter().map(|s| s.to_string()).collect(),
         cwd: tmp.path().to_path_buf(),
-        timeout_ms: Some(1000),
-        env: HashMap::new(),
-        with_escalated_permissions: None,
-        justification: None,
+        timeout: Timeout::Duration(Duration::from_millis(1000)),
+        ..Default::default()
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -58,6 +57,23 @@ async fn exit_code_0_succeeds() {
     assert_eq!(output.stdout.truncated_after_lines, None);
 }
 
+/// Invalid UTF-8 is replaced in the text, and flagged per stream
+#[tokio::test]
+async fn reports_invalid_utf8_output() {
+    if skip_test() {
+        return;
+    }
+
+    let tmp = TempDir::new().expect("should be able to create temp dir");
+    let cmd = vec!["bash", "-c", "printf 'caf\\xe9'; printf 'ok' >&2"];
+
+    let output = run_test_cmd(tmp, cmd).await.unwrap();
+    assert_eq!(output.stdout.text, "caf\u{FFFD}");
+    assert!(!output.stdout_was_valid_utf8);
+    assert_eq!(output.stderr.text, "ok");
+    assert!(output.stderr_was_valid_utf8);
+}
+
 /// Command succeeds with exit code 0 normally
 #[tokio::test]
 async fn truncates_output_lines() {
@@ -0,0 +1,145 @@
+//! Verifies that shell tool calls are routed through the `ExecBackend`
+//! installed on the `ConversationManager`.
+
+use std::sync::Arc;
+
+use codex_core::CodexAuth;
+use codex_core::ConversationManager;
+use codex_core::ModelProviderInfo;
+use codex_core::NewConversation;
+use codex_core::built_in_model_providers;
+use codex_core::exec_backend::FakeExecBackend;
+use codex_core::protocol::AskForApproval;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol::SandboxPolicy;
+use codex_core::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use core_test_support::load_default_config_for_test;
+use core_test_support::load_sse_fixture_with_id;
+use core_test_support::load_sse_fixture_with_id_from_str;
+use core_test_support::wait_for_event;
+use tempfile::TempDir;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::Request;
+use wiremock::Respond;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+fn sse_completed(id: &str) -> String {
+    load_sse_fixture_with_id("tests/fixtures/completed_template.json", id)
+}
+
+fn sse_shell_call(id: &str) -> String {
+    let raw = r#"[
+        {
+            "type": "response.output_item.done",
+            "item": {
+                "type": "function_call",
+                "name": "shell",
+                "arguments": "{\"command\":[\"cargo\",\"test\"]}",
+                "call_id": "call-1"
+            }
+        },
+        {
+            "type": "response.completed",
+            "response": {"id": "__ID__", "output": []}
+        }
+    ]"#;
+    load_sse_fixture_with_id_from_str(raw, id)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn shell_call_uses_injected_exec_backend() {
+    if std::env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return;
+    }
+
+    let server = MockServer::start().await;
+
+    struct SeqResponder;
+    impl Respond for SeqResponder {
+        fn respond(&self, _: &Request) -> ResponseTemplate {
+            use std::sync::atomic::AtomicUsize;
+            use std::sync::atomic::Ordering;
+            static CALLS: AtomicUsize = AtomicUsize::new(0);
+            let body = if CALLS.fetch_add(1, Ordering::SeqCst) == 0 {
+                sse_shell_call("resp1")
+            } else {
+                sse_completed("resp2")
+            };
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(body, "text/event-stream")
+        }
+    }
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(SeqResponder {})
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let model_provider = ModelProviderInfo {
+        base_url: Some(format!("{}/v1", server.uri())),
+        ..built_in_model_providers()["openai"].clone()
+    };
+    let codex_home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&codex_home);
+    config.model_provider = model_provider;
+    config.approval_policy = AskForApproval::Never;
+    config.sandbox_policy = SandboxPolicy::DangerFullAccess;
+
+    let backend = Arc::new(FakeExecBackend::new().with_stdout(
+        vec!["cargo".to_string(), "test".to_string()],
+        101,
+        "test result: FAILED. 0 passed; 1 failed",
+    ));
+    let conversation_manager =
+        ConversationManager::with_auth(CodexAuth::from_api_key("Test API Key"))
+            .with_exec_backend(backend.clone());
+    let NewConversation {
+        conversation: codex,
+        ..
+    } = conversation_manager
+        .new_conversation(config)
+        .await
+        .expect("create new conversation");
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "run the tests".into(),
+            }],
+        })
+        .await
+        .unwrap();
+    wait_for_event(&codex, |ev| matches!(ev, EventMsg::TaskComplete(_))).await;
+
+    assert_eq!(
+        backend.calls(),
+        vec![vec!["cargo".to_string(), "test".to_string()]]
+    );
+
+    // The canned output is what the model sees as the tool result.
+    let requests = server.received_requests().await.unwrap();
+    let request_body = requests[1].body_json::<serde_json::Value>().unwrap();
+    let tool_output = request_body["input"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["type"] == "function_call_output")
+        .expect("function_call_output in follow-up request");
+    assert_eq!(tool_output["call_id"], "call-1");
+    let output = tool_output["output"].as_str().unwrap();
+    assert!(
+        output.contains("test result: FAILED. 0 passed; 1 failed"),
+        "unexpected tool output: {output}"
+    );
+}
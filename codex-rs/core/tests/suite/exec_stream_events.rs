@@ -2,17 +2,26 @@
 
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_channel::Receiver;
+use codex_core::error::CodexErr;
+use codex_core::exec::CaptureStrategy;
+use codex_core::exec::ExecHeartbeat;
 use codex_core::exec::ExecParams;
+use codex_core::exec::ExecProgress;
 use codex_core::exec::SandboxType;
 use codex_core::exec::StdoutStream;
+use codex_core::exec::Timeout;
+use codex_core::exec::process_exec_batch;
 use codex_core::exec::process_exec_tool_call;
 use codex_core::protocol::Event;
 use codex_core::protocol::EventMsg;
 use codex_core::protocol::ExecCommandOutputDeltaEvent;
 use codex_core::protocol::ExecOutputStream;
 use codex_core::protocol::SandboxPolicy;
+use tokio::sync::Notify;
 
 fn collect_stdout_events(rx: Receiver<Event>) -> Vec<u8> {
     let mut out = Vec::new();
@@ -37,6 +46,7 @@ async fn test_exec_stdout_stream_events_echo() {
         sub_id: "test-sub".to_string(),
         call_id: "call-1".to_string(),
         tx_event: tx,
+        max_bytes_per_sec: None,
     };
 
     let cmd = vec![
@@ -49,10 +59,8 @@ async fn test_exec_stdout_stream_events_echo() {
     let params = ExecParams {
         command: cmd,
         cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-        timeout_ms: Some(5_000),
-        env: HashMap::new(),
-        with_escalated_permissions: None,
-        justification: None,
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        ..Default::default()
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -87,6 +95,7 @@ async fn test_exec_stderr_stream_events_echo() {
         sub_id: "test-sub".to_string(),
         call_id: "call-2".to_string(),
         tx_event: tx,
+        max_bytes_per_sec: None,
     };
 
     let cmd = vec![
@@ -99,10 +108,8 @@ async fn test_exec_stderr_stream_events_echo() {
     let params = ExecParams {
         command: cmd,
         cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-        timeout_ms: Some(5_000),
-        env: HashMap::new(),
-        with_escalated_permissions: None,
-        justification: None,
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        ..Default::default()
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -152,10 +159,8 @@ async fn test_aggregated_output_interleaves_in_order() {
     let params = ExecParams {
         command: cmd,
         cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-        timeout_ms: Some(5_000),
-        env: HashMap::new(),
-        with_escalated_permissions: None,
-        justification: None,
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        ..Default::default()
     };
 
     let policy = SandboxPolicy::new_read_only_policy();
@@ -170,3 +175,341 @@ async fn test_aggregated_output_interleaves_in_order() {
     assert_eq!(result.aggregated_output.text, "O1\nE1\nO2\nE2\n");
     assert_eq!(result.aggregated_output.truncated_after_lines, None);
 }
+
+#[tokio::test]
+async fn test_single_task_capture_keeps_stream_order() {
+    let (tx, rx) = async_channel::unbounded::<Event>();
+    let stdout_stream = StdoutStream {
+        sub_id: "test-sub".to_string(),
+        call_id: "call-1".to_string(),
+        tx_event: tx,
+        max_bytes_per_sec: None,
+    };
+
+    let cmd = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "printf 'O1\\n'; sleep 0.01; printf 'E1\\n' 1>&2; sleep 0.01; printf 'O2\\n'; sleep 0.01; printf 'E2\\n' 1>&2".to_string(),
+    ];
+
+    let params = ExecParams {
+        command: cmd,
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        capture_strategy: CaptureStrategy::SingleTask,
+        ..Default::default()
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(
+        params,
+        SandboxType::None,
+        &policy,
+        &None,
+        Some(stdout_stream),
+    )
+    .await
+    .expect("process_exec_tool_call");
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout.text, "O1\nO2\n");
+    assert_eq!(result.stderr.text, "E1\nE2\n");
+    assert_eq!(result.aggregated_output.text, "O1\nE1\nO2\nE2\n");
+
+    // Deltas are emitted from the same task, in read order.
+    let mut streamed = Vec::new();
+    while let Ok(ev) = rx.try_recv() {
+        if let EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent { chunk, .. }) = ev.msg
+        {
+            streamed.extend_from_slice(&chunk);
+        }
+    }
+    assert_eq!(String::from_utf8_lossy(&streamed), "O1\nE1\nO2\nE2\n");
+}
+
+#[tokio::test]
+async fn test_combine_stderr_redirects_stderr_into_stdout() {
+    // No sleeps: with a single pipe, ordering comes from the kernel, not
+    // from how the reader tasks are scheduled.
+    let cmd = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "printf 'O1\\n'; printf 'E1\\n' 1>&2; printf 'O2\\n'; printf 'E2\\n' 1>&2".to_string(),
+    ];
+
+    let params = ExecParams {
+        command: cmd,
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        combine_stderr: true,
+        ..Default::default()
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(params, SandboxType::None, &policy, &None, None)
+        .await
+        .expect("process_exec_tool_call");
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout.text, "O1\nE1\nO2\nE2\n");
+    assert_eq!(result.stderr.text, "");
+    assert_eq!(result.aggregated_output.text, "O1\nE1\nO2\nE2\n");
+}
+
+#[tokio::test]
+async fn test_tee_to_terminal_still_captures_output() {
+    let cmd = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "printf 'to-stdout\n'; printf 'to-stderr\n' 1>&2".to_string(),
+    ];
+
+    let params = ExecParams {
+        command: cmd,
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        tee_to_terminal: true,
+        ..Default::default()
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(params, SandboxType::None, &policy, &None, None)
+        .await
+        .expect("process_exec_tool_call");
+
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(result.stdout.text, "to-stdout\n");
+    assert_eq!(result.stderr.text, "to-stderr\n");
+}
+
+#[tokio::test]
+async fn test_heartbeat_reports_progress_without_output() {
+    let (tx, rx) = async_channel::unbounded::<ExecProgress>();
+
+    let cmd = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "printf 'abc'; sleep 0.5".to_string(),
+    ];
+
+    let params = ExecParams {
+        command: cmd,
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        heartbeat: Some(ExecHeartbeat {
+            interval: Duration::from_millis(100),
+            tx,
+        }),
+        ..Default::default()
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(params, SandboxType::None, &policy, &None, None)
+        .await
+        .expect("process_exec_tool_call");
+    assert_eq!(result.exit_code, 0);
+
+    let mut progress = Vec::new();
+    while let Ok(p) = rx.try_recv() {
+        progress.push(p);
+    }
+    assert!(!progress.is_empty(), "expected at least one heartbeat");
+    let last = progress.last().expect("heartbeat");
+    assert_eq!(last.bytes_so_far, 3);
+    assert!(last.elapsed >= Duration::from_millis(100));
+
+    // The heartbeat task is cancelled once the child exits.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(rx.try_recv().is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_arg0_override_is_visible_to_child() {
+    let cmd = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        "tr '\\0' ' ' < /proc/$$/cmdline".to_string(),
+    ];
+
+    let params = ExecParams {
+        command: cmd,
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        arg0: Some("-custom-sh".to_string()),
+        ..Default::default()
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(params, SandboxType::None, &policy, &None, None)
+        .await
+        .expect("process_exec_tool_call");
+
+    assert_eq!(result.exit_code, 0);
+    assert!(
+        result.stdout.text.starts_with("-custom-sh "),
+        "unexpected cmdline: {}",
+        result.stdout.text
+    );
+}
+
+#[tokio::test]
+async fn test_resolved_program_is_recorded() {
+    let params = ExecParams {
+        command: vec!["sh".to_string(), "-c".to_string(), "true".to_string()],
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(5_000)),
+        env: HashMap::from([("PATH".to_string(), "/usr/bin:/bin".to_string())]),
+        ..Default::default()
+    };
+
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result = process_exec_tool_call(params, SandboxType::None, &policy, &None, None)
+        .await
+        .expect("process_exec_tool_call");
+
+    let resolved = result.resolved_program.expect("sh should resolve via PATH");
+    assert!(resolved.is_absolute(), "not absolute: {resolved:?}");
+    assert_eq!(resolved.file_name(), Some(std::ffi::OsStr::new("sh")));
+}
+
+#[tokio::test]
+async fn test_duration_is_split_into_spawn_and_run() {
+    let policy = SandboxPolicy::new_read_only_policy();
+
+    let result =
+        process_exec_tool_call(sleep_params("0.2"), SandboxType::None, &policy, &None, None)
+            .await
+            .expect("process_exec_tool_call");
+
+    assert!(
+        result.run_duration >= Duration::from_millis(200),
+        "run_duration too short: {:?}",
+        result.run_duration
+    );
+    assert!(
+        result.duration >= result.spawn_latency + result.run_duration,
+        "total {:?} < spawn {:?} + run {:?}",
+        result.duration,
+        result.spawn_latency,
+        result.run_duration
+    );
+}
+
+fn sleep_params(seconds: &str) -> ExecParams {
+    ExecParams {
+        command: vec!["/bin/sleep".to_string(), seconds.to_string()],
+        cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        timeout: Timeout::Duration(Duration::from_millis(10_000)),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_exec_batch_runs_commands_concurrently() {
+    let policy = SandboxPolicy::new_read_only_policy();
+    let batch = vec![
+        sleep_params("0.5"),
+        sleep_params("0.5"),
+        sleep_params("0.5"),
+    ];
+
+    let start = std::time::Instant::now();
+    let results = process_exec_batch(
+        batch,
+        3,
+        SandboxType::None,
+        &policy,
+        &None,
+        Arc::new(Notify::new()),
+    )
+    .await;
+
+    assert_eq!(results.len(), 3);
+    for result in results {
+        assert_eq!(result.expect("sleep should succeed").exit_code, 0);
+    }
+    assert!(
+        start.elapsed() < Duration::from_millis(1_400),
+        "commands did not run in parallel: {:?}",
+        start.elapsed()
+    );
+}
+
+#[tokio::test]
+async fn test_exec_batch_cancellation_interrupts_all() {
+    let policy = SandboxPolicy::new_read_only_policy();
+    let cancel = Arc::new(Notify::new());
+    let batch = vec![sleep_params("5"), sleep_params("5"), sleep_params("5")];
+
+    let canceller = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            cancel.notify_waiters();
+        })
+    };
+
+    let start = std::time::Instant::now();
+    let results = process_exec_batch(batch, 1, SandboxType::None, &policy, &None, cancel).await;
+    canceller.await.expect("canceller task");
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    for result in results {
+        assert!(matches!(result, Err(CodexErr::Interrupted)));
+    }
+}
+
+#[tokio::test]
+async fn test_exec_call_id_tags_output_and_stream_events() {
+    let (tx, rx) = async_channel::unbounded::<Event>();
+    let stdout_stream = StdoutStream {
+        sub_id: "test-sub".to_string(),
+        call_id: "call-1".to_string(),
+        tx_event: tx,
+        max_bytes_per_sec: None,
+    };
+    let params = ExecParams::builder(["/bin/sh", "-c", "printf 'tagged\\n'"])
+        .call_id("tagged-call")
+        .build();
+
+    let policy = SandboxPolicy::new_read_only_policy();
+    let output = process_exec_tool_call(
+        params,
+        SandboxType::None,
+        &policy,
+        &None,
+        Some(stdout_stream),
+    )
+    .await
+    .expect("printf runs");
+    assert_eq!(output.call_id, "tagged-call");
+
+    let mut deltas = 0;
+    while let Ok(ev) = rx.try_recv() {
+        if let EventMsg::ExecCommandOutputDelta(ExecCommandOutputDeltaEvent { call_id, .. }) =
+            ev.msg
+        {
+            assert_eq!(call_id, "tagged-call");
+            deltas += 1;
+        }
+    }
+    assert!(deltas > 0);
+
+    // Without a call ID or a stream, one is generated per call.
+    let untagged = || ExecParams::builder(["/bin/sh", "-c", "true"]).build();
+    let first = process_exec_tool_call(untagged(), SandboxType::None, &policy, &None, None)
+        .await
+        .expect("true runs");
+    let second = process_exec_tool_call(untagged(), SandboxType::None, &policy, &None, None)
+        .await
+        .expect("true runs");
+    assert!(!first.call_id.is_empty());
+    assert_ne!(first.call_id, second.call_id);
+}
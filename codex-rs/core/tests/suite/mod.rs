@@ -4,10 +4,14 @@ mod cli_stream;
 mod client;
 mod compact;
 mod exec;
+mod exec_backend;
 mod exec_stream_events;
 mod fork_conversation;
 mod live_cli;
 mod prompt_caching;
+mod request_timeout;
+mod retry_classification;
 mod seatbelt;
+mod stream_cancellation;
 mod stream_error_allows_next_turn;
 mod stream_no_completed;
@@ -377,16 +377,16 @@ async fn overrides_turn_context_but_keeps_cached_prefix_and_key_constant() {
     wait_for_event(&codex, |ev| matches!(ev, EventMsg::TaskComplete(_))).await;
 
     let writable = TempDir::new().unwrap();
+    let mut sandbox_policy =
+        SandboxPolicy::new_workspace_write_policy_limited_to(vec![writable.path().to_path_buf()]);
+    if let SandboxPolicy::WorkspaceWrite { network_access, .. } = &mut sandbox_policy {
+        *network_access = true;
+    }
     codex
         .submit(Op::OverrideTurnContext {
             cwd: None,
             approval_policy: Some(AskForApproval::Never),
-            sandbox_policy: Some(SandboxPolicy::WorkspaceWrite {
-                writable_roots: vec![writable.path().to_path_buf()],
-                network_access: true,
-                exclude_tmpdir_env_var: true,
-                exclude_slash_tmp: true,
-            }),
+            sandbox_policy: Some(sandbox_policy),
             model: Some("o3".to_string()),
             effort: Some(ReasoningEffort::High),
             summary: Some(ReasoningSummary::Detailed),
@@ -500,6 +500,11 @@ async fn per_turn_overrides_keep_cached_prefix_and_key_constant() {
     // Second turn using per-turn overrides via UserTurn
     let new_cwd = TempDir::new().unwrap();
     let writable = TempDir::new().unwrap();
+    let mut sandbox_policy =
+        SandboxPolicy::new_workspace_write_policy_limited_to(vec![writable.path().to_path_buf()]);
+    if let SandboxPolicy::WorkspaceWrite { network_access, .. } = &mut sandbox_policy {
+        *network_access = true;
+    }
     codex
         .submit(Op::UserTurn {
             items: vec![InputItem::Text {
@@ -507,12 +512,7 @@ async fn per_turn_overrides_keep_cached_prefix_and_key_constant() {
             }],
             cwd: new_cwd.path().to_path_buf(),
             approval_policy: AskForApproval::Never,
-            sandbox_policy: SandboxPolicy::WorkspaceWrite {
-                writable_roots: vec![writable.path().to_path_buf()],
-                network_access: true,
-                exclude_tmpdir_env_var: true,
-                exclude_slash_tmp: true,
-            },
+            sandbox_policy,
             model: "o3".to_string(),
             effort: ReasoningEffort::High,
             summary: ReasoningSummary::Detailed,
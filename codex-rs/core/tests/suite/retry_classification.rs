@@ -0,0 +1,238 @@
+//! Verifies that only failures which are transient and safe to repeat are
+//! retried: connection errors, 429s and 5xxs are, other 4xxs are not, and
+//! neither is a response that broke off after it already ran a tool call.
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use codex_core::CodexAuth;
+use codex_core::ConversationManager;
+use codex_core::ModelClient;
+use codex_core::ModelProviderInfo;
+use codex_core::Prompt;
+use codex_core::WireApi;
+use codex_core::error::CodexErr;
+use codex_core::protocol::ErrorEvent;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_protocol::mcp_protocol::ConversationId;
+use core_test_support::load_default_config_for_test;
+use core_test_support::load_sse_fixture_with_id;
+use core_test_support::load_sse_fixture_with_id_from_str;
+use core_test_support::wait_for_event_with_timeout;
+use pretty_assertions::assert_eq;
+use reqwest::StatusCode;
+use tempfile::TempDir;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::Request;
+use wiremock::Respond;
+use wiremock::ResponseTemplate;
+use wiremock::matchers::method;
+use wiremock::matchers::path;
+
+fn sse_completed(id: &str) -> String {
+    load_sse_fixture_with_id("tests/fixtures/completed_template.json", id)
+}
+
+fn provider(uri: &str) -> ModelProviderInfo {
+    ModelProviderInfo {
+        name: "mock-openai".into(),
+        base_url: Some(format!("{uri}/v1")),
+        env_key: None,
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(2),
+        stream_max_retries: Some(2),
+        stream_idle_timeout_ms: Some(2_000),
+        retry_initial_delay_ms: Some(1),
+        retry_max_delay_ms: Some(10),
+        requires_openai_auth: false,
+        ..Default::default()
+    }
+}
+
+fn client(uri: &str, home: &TempDir) -> ModelClient {
+    let provider = provider(uri);
+    let mut config = load_default_config_for_test(home);
+    config.model_provider_id = provider.name.clone();
+    config.model_provider = provider.clone();
+    let effort = config.model_reasoning_effort;
+    let summary = config.model_reasoning_summary;
+    ModelClient::new(
+        Arc::new(config),
+        None,
+        provider,
+        effort,
+        summary,
+        ConversationId::new(),
+    )
+}
+
+/// Fails the first request with `status`, then answers with a completed
+/// response.
+struct FailOnce {
+    status: u16,
+    calls: AtomicUsize,
+}
+
+impl Respond for FailOnce {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+            ResponseTemplate::new(self.status).set_body_string("{}")
+        } else {
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(sse_completed("resp_ok"), "text/event-stream")
+        }
+    }
+}
+
+/// Sends one request against a server whose first answer is `status` and
+/// returns the result along with how many requests the server saw.
+async fn request_after_status(status: u16) -> (codex_core::error::Result<()>, usize) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(FailOnce {
+            status,
+            calls: AtomicUsize::new(0),
+        })
+        .mount(&server)
+        .await;
+
+    let home = TempDir::new().unwrap();
+    let result = client(&server.uri(), &home)
+        .stream(&Prompt::default())
+        .await
+        .map(drop);
+    let requests = server.received_requests().await.unwrap().len();
+    (result, requests)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn server_errors_and_rate_limits_are_retried() {
+    for status in [500, 502, 503, 429] {
+        let (result, requests) = request_after_status(status).await;
+        assert!(result.is_ok(), "{status}: {result:?}");
+        assert_eq!(requests, 2, "{status}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn client_errors_are_not_retried() {
+    for status in [400, 401, 403, 404] {
+        let (result, requests) = request_after_status(status).await;
+        match result {
+            Err(CodexErr::UnexpectedStatus(got, _)) => {
+                assert_eq!(got, StatusCode::from_u16(status).unwrap());
+            }
+            other => panic!("{status}: unexpected result {other:?}"),
+        }
+        assert_eq!(requests, 1, "{status}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn dropped_connections_are_retried() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        // Hang up on the first request before answering it.
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 64 * 1024];
+        let _ = socket.read(&mut buf).await.unwrap();
+        drop(socket);
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let _ = socket.read(&mut buf).await.unwrap();
+        let body = sse_completed("resp_ok");
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+    });
+
+    let home = TempDir::new().unwrap();
+    let result = client(&uri, &home).stream(&Prompt::default()).await;
+    assert!(result.is_ok(), "{:?}", result.err());
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("second request never arrived")
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn stream_that_ran_a_tool_call_is_not_retried() {
+    let server = MockServer::start().await;
+
+    // A tool call, then the stream ends without `response.completed`.
+    let body = load_sse_fixture_with_id_from_str(
+        r#"[
+            {"type": "response.output_item.done", "item": {
+                "type": "function_call",
+                "name": "unknown_tool",
+                "arguments": "{}",
+                "call_id": "__ID__"
+            }}
+        ]"#,
+        "call_1",
+    );
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_raw(body, "text/event-stream"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&home);
+    config.model_provider = ModelProviderInfo {
+        env_key: Some("PATH".into()),
+        ..provider(&server.uri())
+    };
+    let conversation_manager =
+        ConversationManager::with_auth(CodexAuth::from_api_key("Test API Key"));
+    let codex = conversation_manager
+        .new_conversation(config)
+        .await
+        .unwrap()
+        .conversation;
+
+    codex
+        .submit(Op::UserInput {
+            items: vec![InputItem::Text {
+                text: "hello".into(),
+            }],
+        })
+        .await
+        .unwrap();
+
+    let ev = wait_for_event_with_timeout(
+        &codex,
+        |ev| matches!(ev, EventMsg::Error(_) | EventMsg::StreamError(_)),
+        Duration::from_secs(5),
+    )
+    .await;
+    let EventMsg::Error(ErrorEvent { message }) = ev else {
+        panic!("turn was retried: {ev:?}");
+    };
+    assert!(
+        message.contains("stream disconnected"),
+        "unexpected error message: {message}"
+    );
+}
@@ -75,12 +75,9 @@ impl TestScenario {
 async fn if_parent_of_repo_is_writable_then_dot_git_folder_is_writable() {
     let tmp = TempDir::new().expect("should be able to create temp dir");
     let test_scenario = create_test_scenario(&tmp);
-    let policy = SandboxPolicy::WorkspaceWrite {
-        writable_roots: vec![test_scenario.repo_parent.clone()],
-        network_access: false,
-        exclude_tmpdir_env_var: true,
-        exclude_slash_tmp: true,
-    };
+    let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![
+        test_scenario.repo_parent.clone(),
+    ]);
 
     test_scenario
         .run_test(
@@ -101,12 +98,8 @@ async fn if_parent_of_repo_is_writable_then_dot_git_folder_is_writable() {
 async fn if_git_repo_is_writable_root_then_dot_git_folder_is_read_only() {
     let tmp = TempDir::new().expect("should be able to create temp dir");
     let test_scenario = create_test_scenario(&tmp);
-    let policy = SandboxPolicy::WorkspaceWrite {
-        writable_roots: vec![test_scenario.repo_root.clone()],
-        network_access: false,
-        exclude_tmpdir_env_var: true,
-        exclude_slash_tmp: true,
-    };
+    let policy =
+        SandboxPolicy::new_workspace_write_policy_limited_to(vec![test_scenario.repo_root.clone()]);
 
     test_scenario
         .run_test(
@@ -180,9 +173,12 @@ async fn python_getpwuid_works_under_seatbelt() {
             "import pwd, os; print(pwd.getpwuid(os.getuid()))".to_string(),
         ],
         &policy,
+        None,
         std::env::current_dir().expect("should be able to get current dir"),
         StdioPolicy::RedirectForShellTool,
         HashMap::new(),
+        &[],
+        None,
     )
     .await
     .expect("should be able to spawn python under seatbelt");
@@ -222,9 +218,12 @@ async fn touch(path: &Path, policy: &SandboxPolicy) -> bool {
             path.to_string_lossy().to_string(),
         ],
         policy,
+        None,
         std::env::current_dir().expect("should be able to get current dir"),
         StdioPolicy::RedirectForShellTool,
         HashMap::new(),
+        &[],
+        None,
     )
     .await
     .expect("should be able to spawn command under seatbelt");
@@ -0,0 +1,104 @@
+//! Verifies that cancelling a streaming model request drops the connection to
+//! the provider instead of reading the rest of the response.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use codex_core::ModelClient;
+use codex_core::ModelProviderInfo;
+use codex_core::Prompt;
+use codex_core::ResponseEvent;
+use codex_core::WireApi;
+use codex_core::error::CodexErr;
+use codex_protocol::mcp_protocol::ConversationId;
+use core_test_support::load_default_config_for_test;
+use futures::StreamExt;
+use tempfile::TempDir;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+/// Answers a single request with the start of an SSE response that never
+/// completes, then resolves once the client closes the connection.
+async fn start_stalling_server() -> (String, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+    let closed = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 64 * 1024];
+        let _ = socket.read(&mut buf).await.unwrap();
+        socket
+            .write_all(
+                b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n\
+                  event: response.created\n\
+                  data: {\"type\":\"response.created\",\"response\":{}}\n\n",
+            )
+            .await
+            .unwrap();
+        // Drain whatever is left of the request until the client hangs up.
+        while socket.read(&mut buf).await.unwrap_or(0) > 0 {}
+    });
+    (uri, closed)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn cancelling_a_stream_drops_the_connection() {
+    let (uri, closed) = start_stalling_server().await;
+
+    let provider = ModelProviderInfo {
+        name: "mock-openai".into(),
+        base_url: Some(format!("{uri}/v1")),
+        env_key: None,
+        env_key_instructions: None,
+        wire_api: WireApi::Responses,
+        query_params: None,
+        http_headers: None,
+        env_http_headers: None,
+        request_max_retries: Some(0),
+        stream_max_retries: Some(0),
+        stream_idle_timeout_ms: Some(30_000),
+        requires_openai_auth: false,
+        ..Default::default()
+    };
+
+    let home = TempDir::new().unwrap();
+    let mut config = load_default_config_for_test(&home);
+    config.model_provider_id = provider.name.clone();
+    config.model_provider = provider.clone();
+    let effort = config.model_reasoning_effort;
+    let summary = config.model_reasoning_summary;
+    let client = ModelClient::new(
+        Arc::new(config),
+        None,
+        provider,
+        effort,
+        summary,
+        ConversationId::new(),
+    );
+
+    let cancel = CancellationToken::new();
+    let mut stream = client
+        .stream_with_cancellation(&Prompt::default(), &cancel)
+        .await
+        .unwrap();
+    assert!(matches!(
+        stream.next().await,
+        Some(Ok(ResponseEvent::Created))
+    ));
+
+    cancel.cancel();
+    assert!(matches!(
+        stream.next().await,
+        Some(Err(CodexErr::Interrupted))
+    ));
+    assert!(stream.next().await.is_none());
+
+    // The stream is still alive here, so it is the cancellation (not a drop)
+    // that closed the connection.
+    tokio::time::timeout(Duration::from_secs(5), closed)
+        .await
+        .expect("connection was not dropped after cancel")
+        .unwrap();
+    drop(stream);
+}
@@ -0,0 +1,57 @@
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How long streamed text may sit in [`DeltaWriter`] before it is flushed.
+/// Short enough that the stream still looks live.
+pub(crate) const DELTA_FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Prints streamed deltas (answer and reasoning tokens) to stdout without a
+/// write and flush per token: deltas are collected and written together once
+/// one contains a newline or [`DELTA_FLUSH_INTERVAL`] has passed since the
+/// last write. Anything else printed in between must call
+/// [`DeltaWriter::flush`] first so the output stays in order.
+pub(crate) struct DeltaWriter {
+    pending: String,
+    last_flush: Instant,
+}
+
+impl DeltaWriter {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: String::new(),
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, delta: &str) {
+        self.pending.push_str(delta);
+        if delta.contains('\n') || self.last_flush.elapsed() >= DELTA_FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// Writes out everything collected so far.
+    pub(crate) fn flush(&mut self) {
+        #[expect(clippy::expect_used)]
+        self.write_pending().expect("could not flush stdout");
+    }
+
+    fn write_pending(&mut self) -> std::io::Result<()> {
+        self.last_flush = Instant::now();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(self.pending.as_bytes())?;
+        self.pending.clear();
+        stdout.flush()
+    }
+}
+
+impl Drop for DeltaWriter {
+    fn drop(&mut self) {
+        // Never panic here; stdout may already be closed.
+        let _ = self.write_pending();
+    }
+}
@@ -15,6 +15,10 @@ pub(crate) trait EventProcessor {
 
     /// Handle a single event emitted by the agent.
     fn process_event(&mut self, event: Event) -> CodexStatus;
+
+    /// Write out output held back to be printed together, e.g. streamed
+    /// text. Called periodically while waiting for events.
+    fn flush_pending(&mut self) {}
 }
 
 pub(crate) fn handle_last_message(last_agent_message: Option<&str>, output_file: &Path) {
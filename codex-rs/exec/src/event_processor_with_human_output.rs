@@ -35,6 +35,7 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::time::Instant;
 
+use crate::delta_writer::DeltaWriter;
 use crate::event_processor::CodexStatus;
 use crate::event_processor::EventProcessor;
 use crate::event_processor::handle_last_message;
@@ -66,6 +67,7 @@ pub(crate) struct EventProcessorWithHumanOutput {
     reasoning_started: bool,
     raw_reasoning_started: bool,
     last_message_path: Option<PathBuf>,
+    deltas: DeltaWriter,
 }
 
 impl EventProcessorWithHumanOutput {
@@ -94,6 +96,7 @@ impl EventProcessorWithHumanOutput {
                 reasoning_started: false,
                 raw_reasoning_started: false,
                 last_message_path,
+                deltas: DeltaWriter::new(),
             }
         } else {
             Self {
@@ -112,6 +115,7 @@ impl EventProcessorWithHumanOutput {
                 reasoning_started: false,
                 raw_reasoning_started: false,
                 last_message_path,
+                deltas: DeltaWriter::new(),
             }
         }
     }
@@ -170,6 +174,15 @@ impl EventProcessor for EventProcessorWithHumanOutput {
 
     fn process_event(&mut self, event: Event) -> CodexStatus {
         let Event { id: _, msg } = event;
+        // Anything else printed must come after the text streamed so far.
+        if !matches!(
+            msg,
+            EventMsg::AgentMessageDelta(_)
+                | EventMsg::AgentReasoningDelta(_)
+                | EventMsg::AgentReasoningRawContentDelta(_)
+        ) {
+            self.deltas.flush();
+        }
         match msg {
             EventMsg::Error(ErrorEvent { message }) => {
                 let prefix = "ERROR:".style(self.red);
@@ -204,9 +217,7 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                     ts_println!(self, "{}\n", "codex".style(self.italic).style(self.magenta));
                     self.answer_started = true;
                 }
-                print!("{delta}");
-                #[expect(clippy::expect_used)]
-                std::io::stdout().flush().expect("could not flush stdout");
+                self.deltas.push(&delta);
             }
             EventMsg::AgentReasoningDelta(AgentReasoningDeltaEvent { delta }) => {
                 if !self.show_agent_reasoning {
@@ -220,9 +231,7 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                     );
                     self.reasoning_started = true;
                 }
-                print!("{delta}");
-                #[expect(clippy::expect_used)]
-                std::io::stdout().flush().expect("could not flush stdout");
+                self.deltas.push(&delta);
             }
             EventMsg::AgentReasoningSectionBreak(_) => {
                 if !self.show_agent_reasoning {
@@ -254,9 +263,7 @@ impl EventProcessor for EventProcessorWithHumanOutput {
                 if !self.raw_reasoning_started {
                     self.raw_reasoning_started = true;
                 }
-                print!("{delta}");
-                #[expect(clippy::expect_used)]
-                std::io::stdout().flush().expect("could not flush stdout");
+                self.deltas.push(&delta);
             }
             EventMsg::AgentMessage(AgentMessageEvent { message }) => {
                 // if answer_started is false, this means we haven't received any
@@ -564,6 +571,10 @@ impl EventProcessor for EventProcessorWithHumanOutput {
         }
         CodexStatus::Running
     }
+
+    fn flush_pending(&mut self) {
+        self.deltas.flush();
+    }
 }
 
 fn escape_command(command: &[String]) -> String {
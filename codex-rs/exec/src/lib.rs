@@ -1,4 +1,5 @@
 mod cli;
+mod delta_writer;
 mod event_processor;
 mod event_processor_with_human_output;
 mod event_processor_with_json_output;
@@ -30,6 +31,7 @@ use tracing::error;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
+use crate::delta_writer::DELTA_FLUSH_INTERVAL;
 use crate::event_processor::CodexStatus;
 use crate::event_processor::EventProcessor;
 
@@ -151,6 +153,7 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
         include_view_image_tool: None,
         show_raw_agent_reasoning: oss.then_some(true),
         tools_web_search_request: None,
+        denied_programs: None,
     };
     // Parse `-c` overrides.
     let cli_kv_overrides = match config_overrides.parse_overrides() {
@@ -265,8 +268,22 @@ pub async fn run_main(cli: Cli, codex_linux_sandbox_exe: Option<PathBuf>) -> any
     let initial_prompt_task_id = conversation.submit(Op::UserInput { items }).await?;
     info!("Sent prompt with event ID: {initial_prompt_task_id}");
 
-    // Run the loop until the task is complete.
-    while let Some(event) = rx.recv().await {
+    // Run the loop until the task is complete. Streamed text the event
+    // processor holds back is flushed on every tick, so it never waits much
+    // longer than `DELTA_FLUSH_INTERVAL` to appear.
+    let mut flush_interval = tokio::time::interval(DELTA_FLUSH_INTERVAL);
+    flush_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            _ = flush_interval.tick() => {
+                event_processor.flush_pending();
+                continue;
+            }
+        };
         let shutdown: CodexStatus = event_processor.process_event(event);
         match shutdown {
             CodexStatus::Running => continue,
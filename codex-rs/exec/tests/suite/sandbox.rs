@@ -17,7 +17,17 @@ async fn spawn_command_under_sandbox(
     env: HashMap<String, String>,
 ) -> std::io::Result<Child> {
     use codex_core::seatbelt::spawn_command_under_seatbelt;
-    spawn_command_under_seatbelt(command, sandbox_policy, cwd, stdio_policy, env).await
+    spawn_command_under_seatbelt(
+        command,
+        sandbox_policy,
+        None,
+        cwd,
+        stdio_policy,
+        env,
+        &[],
+        None,
+    )
+    .await
 }
 
 #[cfg(target_os = "linux")]
@@ -37,6 +47,8 @@ async fn spawn_command_under_sandbox(
         cwd,
         stdio_policy,
         env,
+        &[],
+        None,
     )
     .await
 }
@@ -53,12 +65,14 @@ async fn python_multiprocessing_lock_works_under_sandbox() {
     #[cfg(target_os = "linux")]
     let writable_roots = vec![PathBuf::from("/dev/shm")];
 
-    let policy = SandboxPolicy::WorkspaceWrite {
-        writable_roots,
-        network_access: false,
-        exclude_tmpdir_env_var: false,
-        exclude_slash_tmp: false,
-    };
+    let mut policy = SandboxPolicy::new_workspace_write_policy();
+    if let SandboxPolicy::WorkspaceWrite {
+        writable_roots: roots,
+        ..
+    } = &mut policy
+    {
+        *roots = writable_roots;
+    }
 
     let python_code = r#"import multiprocessing
 from multiprocessing import Lock, Process
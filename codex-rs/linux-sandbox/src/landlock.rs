@@ -1,10 +1,16 @@
 use std::collections::BTreeMap;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
 use std::path::Path;
 use std::path::PathBuf;
 
 use codex_core::error::CodexErr;
 use codex_core::error::Result;
 use codex_core::error::SandboxErr;
+use codex_core::protocol::ResourceLimits;
+use codex_core::protocol::RunAsUser;
+use codex_core::protocol::SandboxFeature;
 use codex_core::protocol::SandboxPolicy;
 
 use landlock::ABI;
@@ -31,11 +37,44 @@ pub(crate) fn apply_sandbox_policy_to_current_thread(
     sandbox_policy: &SandboxPolicy,
     cwd: &Path,
 ) -> Result<()> {
-    if !sandbox_policy.has_full_network_access() {
-        install_network_seccomp_filter_on_current_thread()?;
+    // Make sure neither the command nor anything it spawns can regain
+    // privileges through setuid/setgid binaries or file capabilities.
+    set_no_new_privs()?;
+    apply_resource_limits(sandbox_policy.resource_limits())?;
+
+    // Each feature is applied independently, so disabling one that the
+    // kernel does not support leaves the others in place.
+    if sandbox_policy.is_audit_only() {
+        // Landlock cannot log instead of deny, so only the network syscalls
+        // are audited; see `SandboxPolicy::WorkspaceWrite::audit_only`.
+        if sandbox_policy.enforces_sandbox_feature(SandboxFeature::Network) {
+            install_network_seccomp_filter_on_current_thread(SeccompAction::Log)?;
+        }
+    } else if sandbox_policy.enforces_sandbox_feature(SandboxFeature::Network) {
+        if sandbox_policy.has_loopback_only_network_access() {
+            // seccomp cannot see the address passed to `connect`, so instead
+            // of filtering sockets give the command a network of its own.
+            // This must happen before Landlock, which would deny the writes
+            // to `/proc/self` it may need.
+            isolate_network_to_loopback()?;
+        } else {
+            install_network_seccomp_filter_on_current_thread(SeccompAction::Errno(
+                libc::EPERM as u32,
+            ))?;
+        }
     }
 
-    if !sandbox_policy.has_full_disk_write_access() {
+    if sandbox_policy.enforces_sandbox_feature(SandboxFeature::Filesystem)
+        && !sandbox_policy.is_audit_only()
+    {
+        // Landlock rules only ever grant access, and to a whole subtree, so
+        // the read-only subpaths of a writable root (its `.git`,
+        // `writable_root_exclusions`) cannot be carved back out here and are
+        // only enforced for Codex's own edits. Granting each of the root's
+        // other children instead would still leave the root itself without
+        // the rights to create or remove entries in it, since those would
+        // extend to the excluded subtrees again. Codex warns about explicit
+        // exclusions when the session starts instead.
         let writable_roots = sandbox_policy
             .get_writable_roots_with_cwd(cwd)
             .into_iter()
@@ -47,6 +86,156 @@ pub(crate) fn apply_sandbox_policy_to_current_thread(
     // TODO(ragona): Add appropriate restrictions if
     // `sandbox_policy.has_full_disk_read_access()` is `false`.
 
+    // Last, since nothing above needs Codex's privileges but this takes them
+    // away for good.
+    if let Some(run_as) = sandbox_policy.run_as() {
+        switch_user(run_as)?;
+    }
+
+    Ok(())
+}
+
+/// Drops to `uid`/`gid`, with `gid` as the only supplementary group, and
+/// makes sure the switch cannot be undone. A no-op when this process already
+/// runs as that user and group; otherwise it needs root.
+fn switch_user(RunAsUser { uid, gid }: RunAsUser) -> Result<()> {
+    // SAFETY: these calls have no preconditions and cannot fail.
+    let (ruid, euid, rgid, egid) = unsafe {
+        (
+            libc::getuid(),
+            libc::geteuid(),
+            libc::getgid(),
+            libc::getegid(),
+        )
+    };
+    if ruid == uid && euid == uid && rgid == gid && egid == gid {
+        return Ok(());
+    }
+
+    let failed = |step: &str| {
+        let err = std::io::Error::last_os_error();
+        std::io::Error::new(
+            err.kind(),
+            format!("failed to switch to uid {uid}, gid {gid}: {step}: {err}"),
+        )
+    };
+    // Groups first: once the uid is dropped they can no longer be changed.
+    let groups: [libc::gid_t; 1] = [gid];
+    if unsafe { libc::setgroups(groups.len(), groups.as_ptr()) } == -1 {
+        return Err(failed("setgroups").into());
+    }
+    if unsafe { libc::setgid(gid) } == -1 {
+        return Err(failed("setgid").into());
+    }
+    if unsafe { libc::setuid(uid) } == -1 {
+        return Err(failed("setuid").into());
+    }
+    if uid != 0 && unsafe { libc::setuid(0) } != -1 {
+        return Err(std::io::Error::other(format!(
+            "switched to uid {uid}, but root privileges could be regained"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Moves this process into a new network namespace in which only the
+/// loopback interface exists and is up, so the command can use `127.0.0.1`
+/// and `::1` among its own processes but reach nothing else, including
+/// servers on the host's loopback. Without `CAP_SYS_ADMIN` the namespace is
+/// created inside a new user namespace that maps just the current uid and
+/// gid.
+fn isolate_network_to_loopback() -> Result<()> {
+    let failed = |step: &str, err: std::io::Error| {
+        std::io::Error::new(
+            err.kind(),
+            format!("failed to restrict the network to loopback: {step}: {err}"),
+        )
+    };
+
+    if unsafe { libc::unshare(libc::CLONE_NEWNET) } == -1 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EPERM) {
+            return Err(failed("unshare", err).into());
+        }
+        // SAFETY: these calls have no preconditions and cannot fail.
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        // Only allowed while single-threaded, which the helper still is.
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } == -1 {
+            return Err(failed("unshare", std::io::Error::last_os_error()).into());
+        }
+        for (path, contents) in [
+            ("/proc/self/setgroups", "deny".to_string()),
+            ("/proc/self/uid_map", format!("{uid} {uid} 1")),
+            ("/proc/self/gid_map", format!("{gid} {gid} 1")),
+        ] {
+            std::fs::write(path, contents).map_err(|e| failed(path, e))?;
+        }
+    }
+
+    bring_up_loopback().map_err(|e| failed("bringing up lo", e))?;
+    Ok(())
+}
+
+/// A new network namespace starts with `lo` down.
+fn bring_up_loopback() -> std::io::Result<()> {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `fd` was just opened and is owned by nothing else.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: `ifreq` is plain old data, for which all zeroes is valid.
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in ifr.ifr_name.iter_mut().zip(b"lo\0") {
+        *dst = *src as libc::c_char;
+    }
+    if unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS as _, &mut ifr) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: SIOCGIFFLAGS filled in the flags member of the union.
+    unsafe { ifr.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short };
+    if unsafe { libc::ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS as _, &ifr) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn set_no_new_privs() -> Result<()> {
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } == -1 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Lowers the soft and hard rlimits for every limit that is set. Unlike the
+/// Landlock and seccomp rules these apply to the whole process, which is fine
+/// because the helper execs into the command right after.
+fn apply_resource_limits(limits: ResourceLimits) -> Result<()> {
+    let ResourceLimits {
+        max_processes,
+        max_file_size_bytes,
+        cpu_time_limit_secs,
+        // Already set by Codex when it spawned this helper.
+        max_open_files: _,
+    } = limits;
+    for (resource, limit) in [
+        (libc::RLIMIT_NPROC, max_processes),
+        (libc::RLIMIT_FSIZE, max_file_size_bytes),
+        (libc::RLIMIT_CPU, cpu_time_limit_secs),
+    ] {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let rlimit = libc::rlimit {
+            rlim_cur: limit as libc::rlim_t,
+            rlim_max: limit as libc::rlim_t,
+        };
+        if unsafe { libc::setrlimit(resource, &rlimit) } == -1 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
     Ok(())
 }
 
@@ -82,9 +271,12 @@ fn install_filesystem_landlock_rules_on_current_thread(writable_roots: Vec<PathB
     Ok(())
 }
 
-/// Installs a seccomp filter that blocks outbound network access except for
-/// AF_UNIX domain sockets.
-fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(), SandboxErr> {
+/// Installs a seccomp filter that takes `on_match` (normally `EPERM`, or
+/// `Log` to only audit) for outbound network access except for AF_UNIX
+/// domain sockets.
+fn install_network_seccomp_filter_on_current_thread(
+    on_match: SeccompAction,
+) -> std::result::Result<(), SandboxErr> {
     // Build rule map.
     let mut rules: BTreeMap<i64, Vec<SeccompRule>> = BTreeMap::new();
 
@@ -126,8 +318,8 @@ fn install_network_seccomp_filter_on_current_thread() -> std::result::Result<(),
 
     let filter = SeccompFilter::new(
         rules,
-        SeccompAction::Allow,                     // default – allow
-        SeccompAction::Errno(libc::EPERM as u32), // when rule matches – return EPERM
+        SeccompAction::Allow, // default – allow
+        on_match,
         if cfg!(target_arch = "x86_64") {
             TargetArch::x86_64
         } else if cfg!(target_arch = "aarch64") {
@@ -1,29 +1,61 @@
 use clap::Parser;
+use std::collections::HashMap;
 use std::ffi::CString;
+use std::fs::File;
+use std::os::fd::FromRawFd;
+use std::os::fd::RawFd;
 use std::path::PathBuf;
 
+use codex_core::landlock::LinuxSandboxPayload;
+use codex_core::protocol::SandboxPolicy;
+
 use crate::landlock::apply_sandbox_policy_to_current_thread;
 
 #[derive(Debug, Parser)]
 pub struct LandlockCommand {
+    /// Read the sandbox policy and environment (a JSON
+    /// `LinuxSandboxPayload`) from this inherited file descriptor instead of
+    /// the `SANDBOX_POLICY` argument, which would show up in the process
+    /// table.
+    #[arg(long = "policy-fd", value_name = "FD")]
+    pub policy_fd: Option<RawFd>,
+
     /// It is possible that the cwd used in the context of the sandbox policy
     /// is different from the cwd of the process to spawn.
     pub sandbox_policy_cwd: PathBuf,
 
-    pub sandbox_policy: codex_core::protocol::SandboxPolicy,
+    /// Without `--policy-fd`, the command runs with this process's own
+    /// environment. It is never taken as an argument, where any user could
+    /// read it.
+    #[arg(required_unless_present = "policy_fd")]
+    pub sandbox_policy: Option<SandboxPolicy>,
 
-    /// Full command args to run under landlock.
-    #[arg(trailing_var_arg = true)]
+    /// Full command args to run under landlock, after `--`.
+    #[arg(last = true)]
     pub command: Vec<String>,
 }
 
 pub fn run_main() -> ! {
     let LandlockCommand {
+        policy_fd,
         sandbox_policy_cwd,
         sandbox_policy,
         command,
     } = LandlockCommand::parse();
 
+    let (sandbox_policy, sandbox_env) = match (policy_fd, sandbox_policy) {
+        (Some(fd), _) => match read_payload(fd) {
+            Ok(LinuxSandboxPayload {
+                sandbox_policy,
+                sandbox_env,
+            }) => (sandbox_policy, sandbox_env),
+            Err(e) => panic!("error reading the sandbox policy from fd {fd}: {e}"),
+        },
+        (None, Some(sandbox_policy)) => (sandbox_policy, HashMap::new()),
+        // Enforced by clap.
+        (None, None) => panic!("No sandbox policy specified."),
+    };
+
     if let Err(e) = apply_sandbox_policy_to_current_thread(&sandbox_policy, &sandbox_policy_cwd) {
         panic!("error running landlock: {e:?}");
     }
@@ -32,6 +64,12 @@ pub fn run_main() -> ! {
         panic!("No command specified to execute.");
     }
 
+    for (key, value) in sandbox_env {
+        // It is safe to call set_var() because this process is still
+        // single-threaded and is about to exec into the command.
+        unsafe { std::env::set_var(&key, &value) };
+    }
+
     #[expect(clippy::expect_used)]
     let c_command =
         CString::new(command[0].as_str()).expect("Failed to convert command to CString");
@@ -52,3 +90,12 @@ pub fn run_main() -> ! {
     let err = std::io::Error::last_os_error();
     panic!("Failed to execvp {}: {err}", command[0].as_str());
 }
+
+/// Reads the payload from `fd` until EOF, closing it so the command does
+/// not inherit it.
+fn read_payload(fd: RawFd) -> std::io::Result<LinuxSandboxPayload> {
+    // SAFETY: the caller handed this descriptor to us and nothing else in
+    // this process uses it.
+    let file = unsafe { File::from_raw_fd(fd) };
+    Ok(serde_json::from_reader(file)?)
+}
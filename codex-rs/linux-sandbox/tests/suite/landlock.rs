@@ -4,11 +4,14 @@ use codex_core::error::CodexErr;
 use codex_core::error::SandboxErr;
 use codex_core::exec::ExecParams;
 use codex_core::exec::SandboxType;
+use codex_core::exec::Timeout;
 use codex_core::exec::process_exec_tool_call;
 use codex_core::exec_env::create_env;
+use codex_core::protocol::ResourceLimits;
 use codex_core::protocol::SandboxPolicy;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 // At least on GitHub CI, the arm64 tests appear to need longer timeouts.
@@ -38,21 +41,16 @@ async fn run_cmd(cmd: &[&str], writable_roots: &[PathBuf], timeout_ms: u64) {
     let params = ExecParams {
         command: cmd.iter().map(|elm| elm.to_string()).collect(),
         cwd: std::env::current_dir().expect("cwd should exist"),
-        timeout_ms: Some(timeout_ms),
+        timeout: Timeout::Duration(Duration::from_millis(timeout_ms)),
         env: create_env_from_core_vars(),
-        with_escalated_permissions: None,
-        justification: None,
+        ..Default::default()
     };
 
-    let sandbox_policy = SandboxPolicy::WorkspaceWrite {
-        writable_roots: writable_roots.to_vec(),
-        network_access: false,
-        // Exclude tmp-related folders from writable roots because we need a
-        // folder that is writable by tests but that we intentionally disallow
-        // writing to in the sandbox.
-        exclude_tmpdir_env_var: true,
-        exclude_slash_tmp: true,
-    };
+    // Exclude tmp-related folders from writable roots because we need a
+    // folder that is writable by tests but that we intentionally disallow
+    // writing to in the sandbox.
+    let sandbox_policy =
+        SandboxPolicy::new_workspace_write_policy_limited_to(writable_roots.to_vec());
     let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
     let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
     let res = process_exec_tool_call(
@@ -126,6 +124,182 @@ async fn test_timeout() {
     run_cmd(&["sleep", "2"], &[], 50).await;
 }
 
+#[tokio::test]
+#[expect(clippy::expect_used)]
+async fn test_exec_params_env_is_visible_to_command() {
+    let mut env = create_env_from_core_vars();
+    env.insert(
+        "CODEX_TEST_SANDBOX_ENV".to_string(),
+        "visible in sandbox".to_string(),
+    );
+    let params = ExecParams {
+        command: vec!["printenv".to_string(), "CODEX_TEST_SANDBOX_ENV".to_string()],
+        cwd: std::env::current_dir().expect("cwd should exist"),
+        timeout: Timeout::Duration(Duration::from_millis(LONG_TIMEOUT_MS)),
+        env,
+        ..Default::default()
+    };
+
+    let sandbox_policy = SandboxPolicy::new_read_only_policy();
+    let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
+    let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
+    let output = process_exec_tool_call(
+        params,
+        SandboxType::LinuxSeccomp,
+        &sandbox_policy,
+        &codex_linux_sandbox_exe,
+        None,
+    )
+    .await
+    .expect("printenv should succeed");
+
+    assert_eq!(output.exit_code, 0);
+    assert_eq!(output.stdout.text, "visible in sandbox\n");
+}
+
+#[tokio::test]
+#[expect(clippy::expect_used)]
+async fn test_max_file_size_is_enforced() {
+    let tmpdir = tempfile::tempdir().expect("tempdir");
+    let file_path = tmpdir.path().join("big");
+    let params = ExecParams {
+        command: vec![
+            "bash".to_string(),
+            "-c".to_string(),
+            format!("head -c 4096 /dev/zero > {}", file_path.to_string_lossy()),
+        ],
+        cwd: std::env::current_dir().expect("cwd should exist"),
+        timeout: Timeout::Duration(Duration::from_millis(LONG_TIMEOUT_MS)),
+        env: create_env_from_core_vars(),
+        ..Default::default()
+    };
+
+    let mut sandbox_policy =
+        SandboxPolicy::new_workspace_write_policy_limited_to(vec![tmpdir.path().to_path_buf()]);
+    if let SandboxPolicy::WorkspaceWrite {
+        resource_limits, ..
+    } = &mut sandbox_policy
+    {
+        *resource_limits = ResourceLimits {
+            max_file_size_bytes: Some(1024),
+            ..Default::default()
+        };
+    }
+    let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
+    let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
+    let result = process_exec_tool_call(
+        params,
+        SandboxType::LinuxSeccomp,
+        &sandbox_policy,
+        &codex_linux_sandbox_exe,
+        None,
+    )
+    .await;
+
+    // Exceeding RLIMIT_FSIZE raises SIGXFSZ, which kills `head`.
+    assert!(
+        !matches!(result, Ok(ref output) if output.exit_code == 0),
+        "write past the file size limit succeeded: {result:?}"
+    );
+    let written = std::fs::metadata(&file_path).expect("file exists").len();
+    assert!(written <= 1024, "wrote {written} bytes");
+}
+
+#[tokio::test]
+#[expect(clippy::expect_used)]
+async fn test_audit_only_allows_what_it_would_deny() {
+    let tmpfile = NamedTempFile::new().expect("tempfile");
+    let params = ExecParams {
+        command: vec![
+            "bash".to_string(),
+            "-c".to_string(),
+            format!(
+                "echo blah > {} && python3 -c 'import socket; socket.socket()'",
+                tmpfile.path().to_string_lossy()
+            ),
+        ],
+        cwd: std::env::current_dir().expect("cwd should exist"),
+        timeout: Timeout::Duration(Duration::from_millis(LONG_TIMEOUT_MS)),
+        env: create_env_from_core_vars(),
+        ..Default::default()
+    };
+
+    let mut sandbox_policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+    if let SandboxPolicy::WorkspaceWrite { audit_only, .. } = &mut sandbox_policy {
+        *audit_only = true;
+    }
+    let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
+    let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
+    let output = process_exec_tool_call(
+        params,
+        SandboxType::LinuxSeccomp,
+        &sandbox_policy,
+        &codex_linux_sandbox_exe,
+        None,
+    )
+    .await
+    .expect("nothing is denied in audit mode");
+
+    assert_eq!(output.exit_code, 0, "stderr: {}", output.stderr.text);
+    let written = std::fs::read_to_string(tmpfile.path()).expect("read tmpfile");
+    assert_eq!(written, "blah\n");
+}
+
+#[tokio::test]
+#[expect(clippy::expect_used)]
+async fn test_loopback_only_network_reaches_localhost_only() {
+    let script = "
+import socket
+server = socket.socket()
+server.bind(('127.0.0.1', 0))
+server.listen()
+socket.create_connection(server.getsockname(), timeout=1).close()
+print('loopback ok')
+try:
+    socket.create_connection(('1.1.1.1', 80), timeout=1)
+    print('external reachable')
+except OSError:
+    print('external blocked')
+";
+    let params = ExecParams {
+        command: vec!["python3".to_string(), "-c".to_string(), script.to_string()],
+        cwd: std::env::current_dir().expect("cwd should exist"),
+        timeout: Timeout::Duration(Duration::from_millis(NETWORK_TIMEOUT_MS)),
+        env: create_env_from_core_vars(),
+        ..Default::default()
+    };
+
+    let mut sandbox_policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![]);
+    if let SandboxPolicy::WorkspaceWrite {
+        network_loopback_only,
+        ..
+    } = &mut sandbox_policy
+    {
+        *network_loopback_only = true;
+    }
+    let sandbox_program = env!("CARGO_BIN_EXE_codex-linux-sandbox");
+    let codex_linux_sandbox_exe = Some(PathBuf::from(sandbox_program));
+    let output = process_exec_tool_call(
+        params,
+        SandboxType::LinuxSeccomp,
+        &sandbox_policy,
+        &codex_linux_sandbox_exe,
+        None,
+    )
+    .await
+    .expect("command should run");
+
+    // Leaner CI images may not ship python3.
+    if output.exit_code == 127 {
+        return;
+    }
+    assert_eq!(
+        "loopback ok\nexternal blocked\n", output.stdout.text,
+        "stderr: {}",
+        output.stderr.text
+    );
+}
+
 /// Helper that runs `cmd` under the Linux sandbox and asserts that the command
 /// does NOT succeed (i.e. returns a non‑zero exit code) **unless** the binary
 /// is missing in which case we silently treat it as an accepted skip so the
@@ -138,10 +312,9 @@ async fn assert_network_blocked(cmd: &[&str]) {
         cwd,
         // Give the tool a generous 2-second timeout so even slow DNS timeouts
         // do not stall the suite.
-        timeout_ms: Some(NETWORK_TIMEOUT_MS),
+        timeout: Timeout::Duration(Duration::from_millis(NETWORK_TIMEOUT_MS)),
         env: create_env_from_core_vars(),
-        with_escalated_permissions: None,
-        justification: None,
+        ..Default::default()
     };
 
     let sandbox_policy = SandboxPolicy::new_read_only_policy();
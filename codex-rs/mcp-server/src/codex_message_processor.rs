@@ -17,6 +17,7 @@ use codex_core::config::ConfigToml;
 use codex_core::config::load_config_as_toml;
 use codex_core::default_client::get_codex_user_agent;
 use codex_core::exec::ExecParams;
+use codex_core::exec::Timeout;
 use codex_core::exec_env::create_env;
 use codex_core::get_platform_sandbox;
 use codex_core::git_info::git_diff_to_remote;
@@ -449,14 +450,12 @@ impl CodexMessageProcessor {
 
         let cwd = params.cwd.unwrap_or_else(|| self.config.cwd.clone());
         let env = create_env(&self.config.shell_environment_policy);
-        let timeout_ms = params.timeout_ms;
         let exec_params = ExecParams {
             command: params.command,
             cwd,
-            timeout_ms,
+            timeout: Timeout::from_ms(params.timeout_ms),
             env,
-            with_escalated_permissions: None,
-            justification: None,
+            ..Default::default()
         };
 
         let effective_policy = params
@@ -1017,6 +1016,7 @@ fn derive_config_from_params(
         include_view_image_tool: None,
         show_raw_agent_reasoning: None,
         tools_web_search_request: None,
+        denied_programs: None,
     };
 
     let cli_overrides = cli_overrides
@@ -88,9 +88,20 @@ async fn get_config_toml_parses_all_fields() {
             sandbox_mode: Some(SandboxMode::WorkspaceWrite),
             sandbox_settings: Some(SandboxSettings {
                 writable_roots: vec!["/tmp".into()],
+                writable_root_exclusions: vec![],
                 network_access: Some(true),
                 exclude_tmpdir_env_var: Some(true),
                 exclude_slash_tmp: Some(true),
+                denied_programs: vec![],
+                max_processes: None,
+                max_file_size_bytes: None,
+                cpu_time_limit_secs: None,
+                max_open_files: None,
+                disabled_sandbox_features: vec![],
+                scratch_dir: None,
+                run_as: None,
+                network_loopback_only: Some(false),
+                audit_only: Some(false),
             }),
             model: Some("gpt-5".into()),
             model_reasoning_effort: Some(ReasoningEffort::High),
@@ -10,6 +10,8 @@ use crate::protocol::AskForApproval;
 use crate::protocol::EventMsg;
 use crate::protocol::FileChange;
 use crate::protocol::ReviewDecision;
+use crate::protocol::RunAsUser;
+use crate::protocol::SandboxFeature;
 use crate::protocol::SandboxPolicy;
 use crate::protocol::TurnAbortReason;
 use mcp_types::RequestId;
@@ -428,12 +430,34 @@ pub struct Tools {
 pub struct SandboxSettings {
     #[serde(default)]
     pub writable_roots: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writable_root_exclusions: Vec<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub network_access: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exclude_tmpdir_env_var: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exclude_slash_tmp: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_programs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_processes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_time_limit_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_open_files: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_sandbox_features: Vec<SandboxFeature>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scratch_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_as: Option<RunAsUser>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_loopback_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit_only: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
@@ -5,6 +5,7 @@
 
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -216,6 +217,19 @@ pub enum SandboxPolicy {
         #[serde(default, skip_serializing_if = "Vec::is_empty")]
         writable_roots: Vec<PathBuf>,
 
+        /// Subtrees of the writable roots that stay read-only, e.g.
+        /// `node_modules` or a secrets directory. Relative paths are resolved
+        /// against every writable root; absolute paths only apply to the
+        /// root they are under. The top-level `.git` of each root is always
+        /// read-only and need not be listed.
+        ///
+        /// Only Seatbelt (macOS) enforces these for commands. Landlock
+        /// (Linux) can only grant access to whole subtrees, so there they,
+        /// and `.git`, bind Codex's own edits but not what commands write;
+        /// a session started with exclusions on Linux warns about this.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        writable_root_exclusions: Vec<PathBuf>,
+
         /// When set to `true`, outbound network access is allowed. `false` by
         /// default.
         #[serde(default)]
@@ -231,9 +245,110 @@ pub enum SandboxPolicy {
         /// writable roots on UNIX. Defaults to `false`.
         #[serde(default)]
         exclude_slash_tmp: bool,
+
+        /// Programs the model may not run, matched on the basename of
+        /// `command[0]` (after resolving it through `PATH`), of the commands
+        /// in a `bash -c` style script and of the command behind wrappers
+        /// such as `env` or `xargs`. Enforced before spawning, so it also
+        /// applies where no OS sandbox is available.
+        ///
+        /// This is a convenience filter, not a security boundary: a program
+        /// can still be reached less directly, e.g. through `python -c`, a
+        /// script file or a copy under another name.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        denied_programs: Vec<String>,
+
+        /// Process, file size, CPU time and open file limits for the
+        /// sandboxed command. Flattened, so they are set as top-level keys.
+        #[serde(flatten)]
+        resource_limits: ResourceLimits,
+
+        /// Parts of the Linux sandbox to leave out, e.g. `filesystem` on a
+        /// kernel without Landlock, while still enforcing the others. Ignored
+        /// by the macOS sandbox, which always enforces everything.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        disabled_sandbox_features: Vec<SandboxFeature>,
+
+        /// Writable directory for temporary files, for systems where `/tmp`
+        /// is `noexec`, too small or excluded. It is added to the writable
+        /// roots and sandboxed commands get it as `TMPDIR`. Must exist.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scratch_dir: Option<PathBuf>,
+
+        /// Run sandboxed commands as this user and group instead of Codex's
+        /// own, e.g. an unprivileged account on a CI host. Codex must run as
+        /// root to switch; otherwise the command fails before it is spawned.
+        /// Only supported by the Linux sandbox.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run_as: Option<RunAsUser>,
+
+        /// When `network_access` is `false`, still allow connections to and
+        /// from loopback addresses (`127.0.0.1`, `::1`) so commands can reach
+        /// a local dev server or database. Ignored when `network_access` is
+        /// `true`. On Linux the command gets its own network namespace with
+        /// only `lo`, so it cannot reach servers listening on the host's
+        /// loopback either.
+        #[serde(default)]
+        network_loopback_only: bool,
+
+        /// Log what the Linux sandbox would deny instead of denying it, like
+        /// SELinux's permissive mode, to find out what a project needs before
+        /// enforcing the sandbox. Network syscalls the seccomp filter would
+        /// reject are allowed and logged by the kernel; Landlock has no such
+        /// mode, so writes are neither restricted nor reported. Ignored by
+        /// the macOS sandbox, which keeps enforcing.
+        #[serde(default)]
+        audit_only: bool,
     },
 }
 
+/// The user and group a sandboxed command runs as; see
+/// [`SandboxPolicy::run_as`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct RunAsUser {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// An independently enforced part of the Linux sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display, TS)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum SandboxFeature {
+    /// Write access confined to the writable roots (Landlock).
+    Filesystem,
+    /// Outbound network access blocked (seccomp).
+    Network,
+}
+
+/// Resource limits applied to sandboxed commands; `None` leaves the limit
+/// inherited from Codex unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+pub struct ResourceLimits {
+    /// Maximum number of processes (`RLIMIT_NPROC`) for the sandboxed
+    /// command. Note that the kernel counts every process of the user, not
+    /// just the command's descendants. Only enforced on Linux.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_processes: Option<u64>,
+
+    /// Largest file, in bytes, the sandboxed command may write
+    /// (`RLIMIT_FSIZE`). Only enforced on Linux.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_size_bytes: Option<u64>,
+
+    /// CPU time, in seconds, the sandboxed command may consume
+    /// (`RLIMIT_CPU`). Only enforced on Linux.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_limit_secs: Option<u64>,
+
+    /// Most file descriptors the command may have open at once
+    /// (`RLIMIT_NOFILE`), so a runaway command cannot leak enough of them to
+    /// affect Codex. Set by Codex when it starts the command, so it is
+    /// enforced on every Unix platform. Unset keeps the inherited limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_open_files: Option<u64>,
+}
+
 /// A writable root path accompanied by a list of subpaths that should remain
 /// read‑only even when the root is writable. This is primarily used to ensure
 /// top‑level VCS metadata directories (e.g. `.git`) under a writable root are
@@ -288,9 +403,113 @@ impl SandboxPolicy {
             network_access: false,
             exclude_tmpdir_env_var: false,
             exclude_slash_tmp: false,
+            denied_programs: vec![],
+            resource_limits: ResourceLimits::default(),
+            writable_root_exclusions: vec![],
+            disabled_sandbox_features: vec![],
+            scratch_dir: None,
+            run_as: None,
+            network_loopback_only: false,
+            audit_only: false,
+        }
+    }
+
+    /// Returns a policy like [`SandboxPolicy::new_workspace_write_policy`]
+    /// that can only write to the current working directory and
+    /// `writable_roots`: neither `TMPDIR` nor `/tmp` is writable.
+    pub fn new_workspace_write_policy_limited_to(writable_roots: Vec<PathBuf>) -> Self {
+        SandboxPolicy::WorkspaceWrite {
+            writable_roots,
+            network_access: false,
+            exclude_tmpdir_env_var: true,
+            exclude_slash_tmp: true,
+            denied_programs: vec![],
+            resource_limits: ResourceLimits::default(),
+            writable_root_exclusions: vec![],
+            disabled_sandbox_features: vec![],
+            scratch_dir: None,
+            run_as: None,
+            network_loopback_only: false,
+            audit_only: false,
+        }
+    }
+
+    /// Programs that must not be executed under this policy.
+    pub fn denied_programs(&self) -> &[String] {
+        match self {
+            SandboxPolicy::DangerFullAccess => &[],
+            SandboxPolicy::ReadOnly => &[],
+            SandboxPolicy::WorkspaceWrite {
+                denied_programs, ..
+            } => denied_programs,
+        }
+    }
+
+    /// Subtrees of the writable roots to keep read-only, as configured; see
+    /// [`SandboxPolicy::get_writable_roots_with_cwd`] for the resolved paths.
+    pub fn writable_root_exclusions(&self) -> &[PathBuf] {
+        match self {
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => &[],
+            SandboxPolicy::WorkspaceWrite {
+                writable_root_exclusions,
+                ..
+            } => writable_root_exclusions,
         }
     }
 
+    /// Directory sandboxed commands use for temporary files, if configured.
+    pub fn scratch_dir(&self) -> Option<&Path> {
+        match self {
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => None,
+            SandboxPolicy::WorkspaceWrite { scratch_dir, .. } => scratch_dir.as_deref(),
+        }
+    }
+
+    /// User and group sandboxed commands run as, if not Codex's own.
+    pub fn run_as(&self) -> Option<RunAsUser> {
+        match self {
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => None,
+            SandboxPolicy::WorkspaceWrite { run_as, .. } => *run_as,
+        }
+    }
+
+    /// Resource limits for commands run under this policy.
+    pub fn resource_limits(&self) -> ResourceLimits {
+        match self {
+            SandboxPolicy::DangerFullAccess => ResourceLimits::default(),
+            SandboxPolicy::ReadOnly => ResourceLimits::default(),
+            SandboxPolicy::WorkspaceWrite {
+                resource_limits, ..
+            } => *resource_limits,
+        }
+    }
+
+    /// Whether the Linux sandbox only logs what it would deny; see
+    /// `audit_only`.
+    pub fn is_audit_only(&self) -> bool {
+        match self {
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => false,
+            SandboxPolicy::WorkspaceWrite { audit_only, .. } => *audit_only,
+        }
+    }
+
+    /// Whether the Linux sandbox enforces `feature` under this policy, i.e.
+    /// the policy restricts what it covers and does not disable it.
+    pub fn enforces_sandbox_feature(&self, feature: SandboxFeature) -> bool {
+        let restricted = match feature {
+            SandboxFeature::Filesystem => !self.has_full_disk_write_access(),
+            SandboxFeature::Network => !self.has_full_network_access(),
+        };
+        let disabled = match self {
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => false,
+            SandboxPolicy::WorkspaceWrite {
+                disabled_sandbox_features,
+                ..
+            } => disabled_sandbox_features.contains(&feature),
+        };
+        restricted && !disabled
+    }
+
     /// Always returns `true`; restricting read access is not supported.
     pub fn has_full_disk_read_access(&self) -> bool {
         true
@@ -312,6 +531,19 @@ impl SandboxPolicy {
         }
     }
 
+    /// Whether commands may connect to loopback addresses even though
+    /// [`Self::has_full_network_access`] is `false`.
+    pub fn has_loopback_only_network_access(&self) -> bool {
+        match self {
+            SandboxPolicy::DangerFullAccess | SandboxPolicy::ReadOnly => false,
+            SandboxPolicy::WorkspaceWrite {
+                network_access,
+                network_loopback_only,
+                ..
+            } => !*network_access && *network_loopback_only,
+        }
+    }
+
     /// Returns the list of writable roots (tailored to the current working
     /// directory) together with subpaths that should remain read‑only under
     /// each writable root.
@@ -321,9 +553,17 @@ impl SandboxPolicy {
             SandboxPolicy::ReadOnly => Vec::new(),
             SandboxPolicy::WorkspaceWrite {
                 writable_roots,
+                writable_root_exclusions,
                 exclude_tmpdir_env_var,
                 exclude_slash_tmp,
                 network_access: _,
+                denied_programs: _,
+                resource_limits: _,
+                disabled_sandbox_features: _,
+                scratch_dir,
+                run_as: _,
+                network_loopback_only: _,
+                audit_only: _,
             } => {
                 // Start from explicitly configured writable roots.
                 let mut roots: Vec<PathBuf> = writable_roots.clone();
@@ -355,8 +595,45 @@ impl SandboxPolicy {
                     roots.push(PathBuf::from(tmpdir));
                 }
 
+                if let Some(scratch_dir) = scratch_dir {
+                    roots.push(scratch_dir.clone());
+                }
+
+                // Sandboxes match on resolved paths, so resolve symlinks up
+                // front: a symlinked workspace must behave like the real
+                // directory. Roots that do not exist yet are kept verbatim.
+                // Several entries (e.g. cwd and TMPDIR) may resolve to the
+                // same directory, so dedupe while preserving order.
+                let mut canonical_roots: Vec<PathBuf> = Vec::with_capacity(roots.len());
+                for root in roots {
+                    let root = match root.canonicalize() {
+                        Ok(canonical) => canonical,
+                        Err(e) => {
+                            tracing::warn!(
+                                "failed to canonicalize writable root {root:?}, using it as-is: {e}"
+                            );
+                            root
+                        }
+                    };
+                    if !canonical_roots.contains(&root) {
+                        canonical_roots.push(root);
+                    }
+                }
+
+                // Exclusions are matched against the canonical roots, so
+                // resolve absolute ones the same way.
+                let absolute_exclusions: Vec<PathBuf> = writable_root_exclusions
+                    .iter()
+                    .filter(|exclusion| exclusion.is_absolute())
+                    .map(|exclusion| {
+                        exclusion
+                            .canonicalize()
+                            .unwrap_or_else(|_| exclusion.clone())
+                    })
+                    .collect();
+
                 // For each root, compute subpaths that should remain read-only.
-                roots
+                canonical_roots
                     .into_iter()
                     .map(|writable_root| {
                         let mut subpaths = Vec::new();
@@ -364,6 +641,19 @@ impl SandboxPolicy {
                         if top_level_git.is_dir() {
                             subpaths.push(top_level_git);
                         }
+                        let relative_exclusions = writable_root_exclusions
+                            .iter()
+                            .filter(|exclusion| exclusion.is_relative())
+                            .map(|exclusion| writable_root.join(exclusion));
+                        let exclusions_under_root = absolute_exclusions
+                            .iter()
+                            .filter(|exclusion| exclusion.starts_with(&writable_root))
+                            .cloned();
+                        for exclusion in relative_exclusions.chain(exclusions_under_root) {
+                            if !subpaths.contains(&exclusion) {
+                                subpaths.push(exclusion);
+                            }
+                        }
                         WritableRoot {
                             root: writable_root,
                             read_only_subpaths: subpaths,
@@ -373,6 +663,73 @@ impl SandboxPolicy {
             }
         }
     }
+
+    /// Whether a write to `path` (relative paths are resolved against `cwd`)
+    /// would be allowed under this policy, without running anything. The path
+    /// does not need to exist; `.` and `..` are resolved lexically and
+    /// symlinks in its existing ancestors are followed. Only where the path
+    /// resolves to counts, so a symlink inside a writable root that points
+    /// outside it, or into `.git`, does not make its target writable.
+    pub fn is_path_writable(&self, path: &Path, cwd: &Path) -> bool {
+        if self.has_full_disk_write_access() {
+            return true;
+        }
+        let writable_roots = self.get_writable_roots_with_cwd(cwd);
+        if writable_roots.is_empty() {
+            return false;
+        }
+
+        let resolved = resolve_existing_prefix(&normalize_path(&cwd.join(path)));
+        writable_roots.iter().any(|writable_root| {
+            let writable_root = WritableRoot {
+                root: resolve_existing_prefix(&writable_root.root),
+                read_only_subpaths: writable_root
+                    .read_only_subpaths
+                    .iter()
+                    .map(|subpath| resolve_existing_prefix(subpath))
+                    .collect(),
+            };
+            writable_root.is_path_writable(&resolved)
+        })
+    }
+}
+
+/// Removes `.` and resolves `..` without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+/// Canonicalizes the longest existing prefix of `path` and re-appends the
+/// remaining components, so paths to files that do not exist yet can still be
+/// compared against canonical directories.
+fn resolve_existing_prefix(path: &Path) -> PathBuf {
+    let mut suffix = Vec::new();
+    let mut prefix = path;
+    loop {
+        if let Ok(canonical) = prefix.canonicalize() {
+            return suffix
+                .iter()
+                .rev()
+                .fold(canonical, |acc: PathBuf, component| acc.join(component));
+        }
+        match (prefix.parent(), prefix.file_name()) {
+            (Some(parent), Some(name)) => {
+                suffix.push(name.to_os_string());
+                prefix = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
 }
 
 /// User input
@@ -1022,8 +1379,193 @@ pub enum TurnAbortReason {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pretty_assertions::assert_eq;
     use serde_json::json;
     use tempfile::NamedTempFile;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[test]
+    fn writable_roots_are_canonicalized_and_deduped() {
+        let tmp = TempDir::new().unwrap();
+        let real = tmp.path().join("real");
+        std::fs::create_dir_all(real.join(".git")).unwrap();
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let missing = tmp.path().join("missing");
+        let real_canon = real.canonicalize().unwrap();
+
+        let policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![
+            link,
+            PathBuf::from(format!("{}/", real.display())),
+            missing.clone(),
+        ]);
+
+        assert_eq!(
+            policy.get_writable_roots_with_cwd(&real),
+            vec![
+                WritableRoot {
+                    root: real_canon.clone(),
+                    read_only_subpaths: vec![real_canon.join(".git")],
+                },
+                WritableRoot {
+                    root: missing,
+                    read_only_subpaths: vec![],
+                },
+            ]
+        );
+    }
+
+    fn workspace_only_policy() -> SandboxPolicy {
+        SandboxPolicy::new_workspace_write_policy_limited_to(vec![])
+    }
+
+    #[test]
+    fn is_path_writable_handles_nested_and_escaping_paths() {
+        let tmp = TempDir::new().unwrap();
+        let workspace = tmp.path().join("workspace");
+        std::fs::create_dir_all(workspace.join(".git")).unwrap();
+        let policy = workspace_only_policy();
+
+        assert!(policy.is_path_writable(Path::new("src/deeply/nested/new.rs"), &workspace));
+        assert!(policy.is_path_writable(&workspace.join("a/../b.txt"), &workspace));
+        assert!(!policy.is_path_writable(Path::new("../outside.txt"), &workspace));
+        assert!(!policy.is_path_writable(Path::new("src/../../outside.txt"), &workspace));
+        assert!(!policy.is_path_writable(Path::new(".git/config"), &workspace));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn is_path_writable_follows_symlinks() {
+        let tmp = TempDir::new().unwrap();
+        let real = tmp.path().join("real");
+        std::fs::create_dir_all(&real).unwrap();
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let policy = workspace_only_policy();
+
+        // A workspace reached through a symlink behaves like the real one.
+        assert!(policy.is_path_writable(&link.join("new.txt"), &real));
+        assert!(policy.is_path_writable(&real.join("new.txt"), &link));
+        assert!(!policy.is_path_writable(&tmp.path().join("new.txt"), &link));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn is_path_writable_rejects_symlinks_out_of_the_workspace() {
+        let tmp = TempDir::new().unwrap();
+        let workspace = tmp.path().join("workspace");
+        let outside = tmp.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::os::unix::fs::symlink(&outside, workspace.join("link")).unwrap();
+        let policy = workspace_only_policy();
+
+        assert!(!policy.is_path_writable(Path::new("link/new.txt"), &workspace));
+        assert!(!policy.is_path_writable(&workspace.join("link"), &workspace));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn is_path_writable_rejects_symlinks_into_git() {
+        let tmp = TempDir::new().unwrap();
+        let workspace = tmp.path().join("workspace");
+        std::fs::create_dir_all(workspace.join(".git")).unwrap();
+        std::os::unix::fs::symlink(workspace.join(".git"), workspace.join("git")).unwrap();
+        let policy = workspace_only_policy();
+
+        assert!(!policy.is_path_writable(Path::new("git/config"), &workspace));
+        assert!(!policy.is_path_writable(Path::new("git/hooks/pre-commit"), &workspace));
+    }
+
+    #[test]
+    fn is_path_writable_depends_on_full_disk_access() {
+        let cwd = Path::new("/workspace");
+        assert!(SandboxPolicy::DangerFullAccess.is_path_writable(Path::new("/etc/hosts"), cwd));
+        assert!(!SandboxPolicy::ReadOnly.is_path_writable(Path::new("file.txt"), cwd));
+    }
+
+    #[test]
+    fn writable_root_exclusions_become_read_only_subpaths() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("workspace/.git")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("other")).unwrap();
+        let workspace = tmp.path().join("workspace").canonicalize().unwrap();
+        let other = tmp.path().join("other").canonicalize().unwrap();
+
+        let mut policy = SandboxPolicy::new_workspace_write_policy_limited_to(vec![other.clone()]);
+        if let SandboxPolicy::WorkspaceWrite {
+            writable_root_exclusions,
+            ..
+        } = &mut policy
+        {
+            *writable_root_exclusions = vec![
+                PathBuf::from("node_modules"),
+                PathBuf::from(".git"),
+                workspace.join("secrets"),
+            ];
+        }
+
+        let roots = policy.get_writable_roots_with_cwd(&workspace);
+        assert_eq!(
+            roots,
+            vec![
+                WritableRoot {
+                    root: other.clone(),
+                    read_only_subpaths: vec![other.join("node_modules"), other.join(".git")],
+                },
+                WritableRoot {
+                    root: workspace.clone(),
+                    read_only_subpaths: vec![
+                        workspace.join(".git"),
+                        workspace.join("node_modules"),
+                        workspace.join("secrets"),
+                    ],
+                },
+            ]
+        );
+        assert!(!roots[1].is_path_writable(&workspace.join("secrets/key.pem")));
+        assert!(roots[1].is_path_writable(&workspace.join("src/main.rs")));
+    }
+
+    #[test]
+    fn disabled_sandbox_features_are_not_enforced() {
+        let policy: SandboxPolicy = serde_json::from_value(json!({
+            "mode": "workspace-write",
+            "disabled_sandbox_features": ["filesystem"],
+        }))
+        .unwrap();
+        assert!(!policy.enforces_sandbox_feature(SandboxFeature::Filesystem));
+        assert!(policy.enforces_sandbox_feature(SandboxFeature::Network));
+
+        // Nothing to enforce when the policy does not restrict it anyway.
+        assert!(!SandboxPolicy::DangerFullAccess.enforces_sandbox_feature(SandboxFeature::Network));
+        assert!(SandboxPolicy::ReadOnly.enforces_sandbox_feature(SandboxFeature::Filesystem));
+    }
+
+    #[test]
+    fn resource_limits_are_top_level_keys() {
+        let json = json!({
+            "mode": "workspace-write",
+            "network_access": false,
+            "exclude_tmpdir_env_var": false,
+            "exclude_slash_tmp": false,
+            "max_processes": 64,
+            "max_open_files": 256,
+            "network_loopback_only": false,
+            "audit_only": false,
+        });
+        let policy: SandboxPolicy = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            ResourceLimits {
+                max_processes: Some(64),
+                max_open_files: Some(256),
+                ..Default::default()
+            },
+            policy.resource_limits()
+        );
+        assert_eq!(json, serde_json::to_value(&policy).unwrap());
+    }
 
     /// Serialize Event to verify that its JSON representation has the expected
     /// amount of nesting.